@@ -0,0 +1,10 @@
+//! Fuzzes the parser, which should report an error rather than panicking on any input.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let _ = starling::parser::parse(input);
+    let _ = starling::parser::parse_recovering(input);
+});
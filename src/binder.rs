@@ -1,2 +1,483 @@
 //! Semantic binder for the PVC language.
-mod var;
\ No newline at end of file
+//!
+//! The binder lowers a parsed program into the symbol tables of a [`cfg::Program`].  In doing so, it
+//! resolves every identifier into a [`cfg::var::Symbol`] that records the block in which the
+//! identifier's variable was declared.
+//!
+//! Binding happens in two passes, after we declare every named type: first, we declare every
+//! global variable, view, and procedure (so that declarations can appear in any order); then, we
+//! bind the bodies of procedures and constraints.
+
+use egg::Symbol;
+use pest::Span;
+
+use super::language::{
+    ast::{self, Identifier},
+    cfg::{self, Error, Origin, Result},
+    tagged::{Spanned, Tagged},
+    typing,
+    var::Scope,
+};
+
+mod constraint;
+mod stm;
+mod typedef;
+mod var;
+
+/// Shorthand for the type of metadata the binder consumes.
+pub type Meta<'inp> = Option<Span<'inp>>;
+
+/// Shorthand for the type of program the binder consumes.
+pub type Program<'inp> = ast::Program<'inp, Meta<'inp>, Identifier<'inp>>;
+
+/// Shorthand for the type of program the binder produces.
+pub type Bound<'inp> = cfg::Program<'inp, Meta<'inp>>;
+
+/// Binds `program`, producing its control-flow graph symbol tables.
+///
+/// # Errors
+///
+/// Fails if `program` declares something twice in the same scope, or refers to a variable that
+/// has not been declared.
+pub fn bind<'inp>(program: &'inp Program<'inp>) -> Result<'inp, Bound<'inp>> {
+    let mut binder = Binder::default();
+    binder.declare_types(program)?;
+    for decl in &program.decls {
+        binder.declare(decl)?;
+    }
+    for decl in &program.decls {
+        binder.define(decl)?;
+    }
+    Ok(binder.program)
+}
+
+/// The state of the binder.
+#[derive(Default)]
+struct Binder<'inp> {
+    /// The program being built.
+    program: Bound<'inp>,
+}
+
+impl<'inp> Binder<'inp> {
+    /// Declares the globally visible parts of `decl`.
+    fn declare(
+        &mut self,
+        decl: &'inp Spanned<'inp, ast::Decl<'inp, Meta<'inp>, Identifier<'inp>>>,
+    ) -> Result<'inp, ()> {
+        match &decl.item {
+            ast::Decl::Var(v) => v
+                .vars
+                .iter()
+                .try_for_each(|p| self.declare_var(&cfg::BlockRef::Global, v.scope.item, p)),
+            ast::Decl::View(v) => v.contents.iter().try_for_each(|p| self.declare_view(p)),
+            ast::Decl::Procedure(p) => self.declare_procedure(&p.prototype),
+            ast::Decl::Constraint(_) | ast::Decl::Type(_) => Ok(()),
+        }
+    }
+
+    /// Binds the bodies of `decl`, if it has any.
+    fn define(
+        &mut self,
+        decl: &'inp Spanned<'inp, ast::Decl<'inp, Meta<'inp>, Identifier<'inp>>>,
+    ) -> Result<'inp, ()> {
+        match &decl.item {
+            ast::Decl::Procedure(p) => self.define_procedure(p),
+            ast::Decl::Constraint(c) => self.define_constraint(decl.meta, c),
+            ast::Decl::Type(_) | ast::Decl::Var(_) | ast::Decl::View(_) => Ok(()),
+        }
+    }
+
+    /// Declares the view with prototype `proto`.
+    fn declare_view(
+        &mut self,
+        proto: &'inp Spanned<'inp, ast::call::Prototype<'inp, Meta<'inp>, Identifier<'inp>>>,
+    ) -> Result<'inp, ()> {
+        let name = &proto.item.name;
+        let sym = Symbol::new(&name.item);
+        if let Some(existing) = self.program.views.get(&sym) {
+            return Err(Error::DuplicateView {
+                name: &name.item,
+                first_loc: existing.origin.meta().copied().flatten(),
+                second_loc: name.meta,
+            });
+        }
+
+        let parameters = proto
+            .item
+            .args
+            .iter()
+            .map(|p| {
+                let ty = self.ty(&p.item.ty, || format!("{}.{}", name.item, p.item.name.item))?;
+                Ok(Tagged::new(p.item.ty.meta, ty))
+            })
+            .collect::<Result<_>>()?;
+        self.program.views.insert(
+            sym,
+            cfg::View {
+                origin: Origin::Script(proto.meta),
+                parameters,
+            },
+        );
+        Ok(())
+    }
+
+    /// Declares the procedure with prototype `proto`, including its parameters.
+    fn declare_procedure(
+        &mut self,
+        proto: &'inp Spanned<'inp, ast::call::Prototype<'inp, Meta<'inp>, Identifier<'inp>>>,
+    ) -> Result<'inp, ()> {
+        let name = &proto.item.name;
+        let sym = Symbol::new(&name.item);
+        if let Some(existing) = self.program.procedure(sym) {
+            return Err(Error::DuplicateProcedure {
+                name: &name.item,
+                first_loc: existing.origin.meta().copied().flatten(),
+                second_loc: name.meta,
+            });
+        }
+
+        let block = cfg::BlockRef::Proc(sym, vec![]);
+        let parameters = proto
+            .item
+            .args
+            .iter()
+            .map(|p| {
+                self.declare_var(&block, Scope::Thread, p)?;
+                Ok(cfg::var::Symbol {
+                    block: block.clone(),
+                    name: Symbol::new(&p.item.name.item),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        self.program.procedures.push(cfg::Procedure {
+            origin: Origin::Script(proto.meta),
+            name: sym,
+            parameters,
+            locals: vec![],
+            requires: None,
+            ensures: None,
+            body: vec![],
+        });
+        Ok(())
+    }
+
+    /// Binds the specification, local variables, and body of procedure `proc`, which must have
+    /// been declared already.
+    ///
+    /// We bind the specification before declaring the local variables, so that it can't see them.
+    fn define_procedure(
+        &mut self,
+        proc: &'inp ast::Procedure<'inp, Meta<'inp>, Identifier<'inp>>,
+    ) -> Result<'inp, ()> {
+        let sym = Symbol::new(&proc.prototype.item.name.item);
+        let block = cfg::BlockRef::Proc(sym, vec![]);
+        let requires = self.triple_assertion(&proc.requires, &block)?;
+        let ensures = self.triple_assertion(&proc.ensures, &block)?;
+
+        let locals = proc
+            .locals
+            .iter()
+            .map(|p| {
+                self.declare_var(&block, Scope::Thread, p)?;
+                Ok(cfg::var::Symbol {
+                    block: block.clone(),
+                    name: Symbol::new(&p.item.name.item),
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let mut scope = var::Scope::new(block);
+        let body = self.list(&proc.body, &mut scope)?;
+
+        if let Some(p) = self.program.procedures.iter_mut().find(|p| p.name == sym) {
+            p.locals = locals;
+            p.requires = requires;
+            p.ensures = ensures;
+            p.body = body;
+        }
+        Ok(())
+    }
+
+    /// Interns the type `ty` into the typing table, returning its index.
+    ///
+    /// Primitive types map onto their intrinsic entries, and named types onto their declared
+    /// entries; all other types are synthesised, using `name` to generate a name for them.
+    fn ty(
+        &mut self,
+        ty: &'inp Spanned<'inp, typing::Type<Meta<'inp>, Identifier<'inp>>>,
+        name: impl FnOnce() -> String,
+    ) -> Result<'inp, Symbol> {
+        use super::language::expr::map::HasVars;
+
+        self.check_type(&ty.item)?;
+        match &ty.item {
+            typing::Type::Prim(p) => return Ok(p.to_symbol()),
+            typing::Type::Named(n) => return Ok(Symbol::new(&n.item)),
+            _ => (),
+        }
+
+        let sym = Symbol::new(format!("{}:type", name()));
+        let record = cfg::Type {
+            origin: Origin::Script(ty.meta),
+            ty: ty
+                .item
+                .clone()
+                .map_var(super::language::var::Variable::into_symbol),
+        };
+        self.program.types.insert(sym, record);
+        Ok(sym)
+    }
+}
+
+impl<'inp> Binder<'inp> {
+    /// Checks that `ty` refers only to declared named types, and that its refinement predicates
+    /// mention only their refinement variables.
+    fn check_type(&self, ty: &'inp typing::Type<Meta<'inp>, Identifier<'inp>>) -> Result<'inp, ()> {
+        check_refinements(ty)?;
+
+        let mut err = None;
+        ty.for_each_name(&mut |n| {
+            if err.is_none() && !self.program.types.contains_key(&Symbol::new(&n.item)) {
+                err = Some(Error::UnresolvedType {
+                    name: &n.item,
+                    loc: n.meta,
+                });
+            }
+        });
+        err.map_or(Ok(()), Err)
+    }
+}
+
+/// Checks that the refinement predicates of `ty` mention only their refinement variables.
+///
+/// Refinements can't refer to program variables, as the refinement would then change with the
+/// program state.
+fn check_refinements<'inp>(
+    ty: &'inp typing::Type<Meta<'inp>, Identifier<'inp>>,
+) -> Result<'inp, ()> {
+    for refined in ty.refinements() {
+        let mut err = None;
+        refined.refinement().for_each_tagged_var(&mut |v| {
+            if err.is_none() && v.item != refined.var() {
+                err = Some(Error::UnresolvedVar {
+                    name: &v.item,
+                    loc: v.meta,
+                });
+            }
+        });
+        if let Some(err) = err {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Constructs a duplicate-variable error given the existing record and the new declaration.
+fn duplicate_var<'inp>(
+    existing: &cfg::var::Record<Meta<'inp>>,
+    name: &'inp Spanned<'inp, Identifier<'inp>>,
+) -> Error<'inp> {
+    Error::DuplicateVar {
+        name: &name.item,
+        first_loc: existing.origin.meta().copied().flatten(),
+        second_loc: name.meta,
+    }
+}
+
+/// Shorthand for a tagged symbol.
+type TaggedSym<'inp> = Tagged<Meta<'inp>, cfg::var::Symbol>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::var::Variable;
+
+    const ARC: &str = include_str!("../examples/arc.pvc");
+
+    /// Tests that the binder accepts the ARC example, and resolves its globals and parameters.
+    #[test]
+    fn bind_arc() {
+        let ast = crate::parser::parse(ARC).unwrap();
+        let program = bind(&ast.item).unwrap();
+
+        let count = program
+            .variables
+            .get(&cfg::var::Symbol::global("count"))
+            .expect("count should be declared");
+        assert_eq!(Scope::Shared, count.1.scope);
+        assert_eq!(typing::Prim::Int.to_symbol(), count.1.ty);
+
+        assert_eq!(3, program.procedures.len());
+        assert_eq!(3, program.constraints.len());
+        assert_eq!(2, program.views.len());
+    }
+
+    /// Tests that pattern variables are bound in their constraint's block.
+    #[test]
+    fn bind_pattern_vars() {
+        let ast = crate::parser::parse(ARC).unwrap();
+        let program = bind(&ast.item).unwrap();
+
+        // `constraint CountWas(m) * CountWas(n) entails (m <> 1) or ...`: `m` is bound by the
+        // pattern.
+        let constraint = &program.constraints[2].item;
+        let ast::constraint::Constraint::Entails(e) = &constraint.body.item else {
+            panic!("expected entailment");
+        };
+        let crate::language::expr::Expr::Bop { lhs, .. } = &e.entails.item else {
+            panic!("expected disjunction");
+        };
+        let crate::language::expr::Expr::Bop { lhs, .. } = lhs.as_ref() else {
+            panic!("expected inequality");
+        };
+        let crate::language::expr::Expr::Var(v) = lhs.as_ref() else {
+            panic!("expected variable");
+        };
+        assert_eq!(cfg::BlockRef::Constraint(2), v.item.block);
+        assert_eq!("constraint2.m", v.item.to_symbol().as_str());
+    }
+
+    /// Tests that the binder reports duplicate variables.
+    #[test]
+    fn bind_duplicate_var() {
+        let ast =
+            crate::parser::parse("program Dup; var x : integer; threadvar x : boolean; begin end.")
+                .unwrap();
+        let err = bind(&ast.item).unwrap_err();
+        assert!(matches!(err, Error::DuplicateVar { name: "x", .. }));
+    }
+
+    /// Tests that calls resolve to procedures outside atomic blocks and to primitives inside them,
+    /// whatever the case of the primitive's name.
+    #[test]
+    fn bind_primitives() {
+        use crate::language::{ast::stm::Stm, primitive::Primitive};
+
+        let ast = crate::parser::parse(
+            "program L; var x : integer;
+procedure Inc(n : integer); begin nop end;
+procedure P; begin Inc(x); <| inc(x) |>; Dec(x) end;
+begin end.",
+        )
+        .unwrap();
+        let program = bind(&ast.item).unwrap();
+        let stms: Vec<_> = program.procedures[1]
+            .body
+            .iter()
+            .map(|t| &t.item.stm.item)
+            .collect();
+        let [Stm::Call(_), Stm::Atomic(a), Stm::Primitive(dec)] = &stms[..] else {
+            panic!("expected a call, an atomic block, and a primitive, got {stms:?}");
+        };
+        let Stm::Primitive(inc) = &a.body[0].item.stm.item else {
+            panic!("expected a primitive inside the atomic block");
+        };
+        assert_eq!(Primitive::Inc, inc.primitive.item);
+        assert_eq!(Primitive::Dec, dec.primitive.item);
+
+        let ast = crate::parser::parse(
+            "program L; procedure Q; begin nop end; procedure P; begin <| Q |> end; begin end.",
+        )
+        .unwrap();
+        let err = bind(&ast.item).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnresolvedCall {
+                name: "Q",
+                atomic: true,
+                ..
+            }
+        ));
+    }
+
+    /// Tests that the binder reports unresolved variables.
+    #[test]
+    fn bind_unresolved_var() {
+        let ast = crate::parser::parse(
+            "program Unres; var x : integer; procedure P; begin <| x := y |> end; begin end.",
+        )
+        .unwrap();
+        let err = bind(&ast.item).unwrap_err();
+        assert!(matches!(err, Error::UnresolvedVar { name: "y", .. }));
+    }
+
+    /// Tests that procedure locals are bound in the procedure's block, and that specifications
+    /// can see parameters but not locals.
+    #[test]
+    fn bind_procedure_locals() {
+        let ast = crate::parser::parse(
+            "program L; view V(n : integer);
+procedure P(x : integer) requires { V(x) } ensures { emp };
+var y : integer;
+begin <| y := x |> end;
+begin end.",
+        )
+        .unwrap();
+        let program = bind(&ast.item).unwrap();
+        let proc = &program.procedures[0];
+        let y = cfg::var::Symbol {
+            block: cfg::BlockRef::Proc(Symbol::new("P"), vec![]),
+            name: Symbol::new("y"),
+        };
+        assert_eq!(vec![y.clone()], proc.locals);
+        assert_eq!(
+            Scope::Thread,
+            program.variables.get_exact(&y).unwrap().scope
+        );
+        assert!(proc.requires.is_some());
+
+        let ast = crate::parser::parse(
+            "program L; view V(n : integer);
+procedure P requires { V(y) }; var y : integer; begin nop end;
+begin end.",
+        )
+        .unwrap();
+        let err = bind(&ast.item).unwrap_err();
+        assert!(matches!(err, Error::UnresolvedVar { name: "y", .. }));
+
+        let ast = crate::parser::parse(
+            "program L; procedure P(x : integer); var x : integer; begin nop end; begin end.",
+        )
+        .unwrap();
+        let err = bind(&ast.item).unwrap_err();
+        assert!(matches!(err, Error::DuplicateVar { name: "x", .. }));
+    }
+
+    /// Tests that refinement predicates can't mention variables other than their own.
+    #[test]
+    fn bind_refinement_free_var() {
+        let ast = crate::parser::parse(
+            "program Ref; var y : integer; var x : {v : integer | v < y}; begin end.",
+        )
+        .unwrap();
+        let err = bind(&ast.item).unwrap_err();
+        assert!(matches!(err, Error::UnresolvedVar { name: "y", .. }));
+    }
+
+    /// Tests that variables can have named types declared after them.
+    #[test]
+    fn bind_named_type() {
+        let ast = crate::parser::parse(
+            "program T; var x : Nat; type Nat = {v : integer | v >= 0}; begin end.",
+        )
+        .unwrap();
+        let program = bind(&ast.item).unwrap();
+        let record = program
+            .variables
+            .get_exact(&cfg::var::Symbol::global("x"))
+            .unwrap();
+        assert_eq!(Symbol::new("Nat"), record.ty);
+    }
+
+    /// Tests that named types can't be defined in terms of themselves, even indirectly.
+    #[test]
+    fn bind_cyclic_type() {
+        let ast = crate::parser::parse(
+            "program T; type A = array[0..1] of B; B = {v : A | true}; begin end.",
+        )
+        .unwrap();
+        let err = bind(&ast.item).unwrap_err();
+        assert!(matches!(err, Error::CyclicType { name: "A", .. }));
+    }
+}
@@ -0,0 +1,162 @@
+//! Binding of constraints and view patterns.
+//!
+//! Each constraint has its own block, in which the variables bound by its view patterns live.
+//! A pattern argument (or iterator) that is a bare identifier binds a fresh variable, shadowing any
+//! global variable with the same name; any other pattern argument is an expression that the
+//! binder resolves as normal.
+
+use egg::Symbol;
+
+use super::{
+    super::language::{
+        ast::{
+            constraint,
+            view::pattern::{self, Argument},
+            Identifier,
+        },
+        cfg::{self, BlockRef, Result},
+        expr::Expr,
+        tagged::Tagged,
+        typing,
+        var::Scope,
+    },
+    Binder, Meta,
+};
+
+/// Shorthand for the type of constraint consumed by the binder.
+type Constraint<'inp> = constraint::Decl<Meta<'inp>, Identifier<'inp>>;
+
+/// Shorthand for the type of pattern argument consumed by the binder.
+type TaggedArgument<'inp> = Tagged<Meta<'inp>, Argument<Meta<'inp>, Identifier<'inp>>>;
+
+impl<'inp> Binder<'inp> {
+    /// Binds a constraint, adding it to the constraint table.
+    pub(super) fn define_constraint(
+        &mut self,
+        meta: Meta<'inp>,
+        decl: &'inp Constraint<'inp>,
+    ) -> Result<'inp, ()> {
+        let block = BlockRef::Constraint(self.program.constraints.len());
+
+        // Pattern variables must all be in scope before we bind any pattern expressions, as
+        // expressions in one atom can refer to variables bound in another.
+        for pat in &decl.views {
+            for atom in &pat.item.contents {
+                self.declare_pattern_vars(&atom.item, &block)?;
+            }
+        }
+
+        let views = decl
+            .views
+            .iter()
+            .map(|pat| {
+                Ok(Tagged::new(
+                    pat.meta,
+                    pattern::Pattern {
+                        contents: pat
+                            .item
+                            .contents
+                            .iter()
+                            .map(|a| Ok(Tagged::new(a.meta, self.pattern_atom(&a.item, &block)?)))
+                            .collect::<Result<_>>()?,
+                    },
+                ))
+            })
+            .collect::<Result<_>>()?;
+
+        let body = Tagged::new(
+            decl.body.meta,
+            match &decl.body.item {
+                constraint::Constraint::Entails(e) => {
+                    constraint::Constraint::Entails(constraint::Entailment {
+                        entails: self.tagged_expr(&e.entails, &block)?,
+                    })
+                }
+                constraint::Constraint::Exact(d) => {
+                    constraint::Constraint::Exact(constraint::Definition {
+                        definition: self.tagged_expr(&d.definition, &block)?,
+                    })
+                }
+                constraint::Constraint::Indefinite => constraint::Constraint::Indefinite,
+                constraint::Constraint::Fails => constraint::Constraint::Fails,
+            },
+        );
+
+        self.program
+            .constraints
+            .push(Tagged::new(meta, constraint::Decl { views, body }));
+        Ok(())
+    }
+
+    /// Declares the variables bound by the arguments and iterator of `atom`.
+    fn declare_pattern_vars(
+        &mut self,
+        atom: &'inp pattern::Atom<Meta<'inp>, Identifier<'inp>>,
+        block: &BlockRef,
+    ) -> Result<'inp, ()> {
+        // Ill-formed patterns (unknown views and arity mismatches) are reported when checking
+        // constraints; here, we just fall back to typing such arguments as integers.
+        let params = self
+            .program
+            .views
+            .get(&Symbol::new(&atom.name.item))
+            .map(|v| v.parameters.iter().map(|p| p.item).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let int = typing::Prim::Int.to_symbol();
+
+        for (i, arg) in atom.args.iter().enumerate() {
+            let ty = params.get(i).copied().unwrap_or(int);
+            self.declare_pattern_var(arg, block, ty)?;
+        }
+        self.declare_pattern_var(&atom.iterator, block, int)
+    }
+
+    /// Declares `arg` as a pattern variable of type `ty` if it is a bare identifier.
+    fn declare_pattern_var(
+        &mut self,
+        arg: &'inp TaggedArgument<'inp>,
+        block: &BlockRef,
+        ty: Symbol,
+    ) -> Result<'inp, ()> {
+        if let Argument::Expr(Expr::Var(v)) = &arg.item {
+            let sym = cfg::var::Symbol {
+                block: block.clone(),
+                name: Symbol::new(&v.item),
+            };
+            self.insert_var(sym, v, Scope::Bound, ty)?;
+        }
+        Ok(())
+    }
+
+    /// Binds a pattern atom.
+    fn pattern_atom(
+        &self,
+        atom: &'inp pattern::Atom<Meta<'inp>, Identifier<'inp>>,
+        block: &BlockRef,
+    ) -> Result<'inp, pattern::Atom<Meta<'inp>, cfg::var::Symbol>> {
+        Ok(pattern::Atom {
+            name: Tagged::new(atom.name.meta, cfg::var::Symbol::global(&*atom.name.item)),
+            args: atom
+                .args
+                .iter()
+                .map(|a| self.pattern_argument(a, block))
+                .collect::<Result<_>>()?,
+            iterator: self.pattern_argument(&atom.iterator, block)?,
+        })
+    }
+
+    /// Binds a pattern argument.
+    fn pattern_argument(
+        &self,
+        arg: &'inp TaggedArgument<'inp>,
+        block: &BlockRef,
+    ) -> Result<'inp, Tagged<Meta<'inp>, Argument<Meta<'inp>, cfg::var::Symbol>>> {
+        Ok(Tagged::new(
+            arg.meta,
+            match &arg.item {
+                Argument::Wildcard => Argument::Wildcard,
+                Argument::Expr(e) => Argument::Expr(self.expr(e, block)?),
+            },
+        ))
+    }
+}
@@ -0,0 +1,228 @@
+//! Binding of statements and view assertions.
+
+use egg::Symbol;
+
+use super::{
+    super::language::{
+        ast::{call, ite, stm, view, Identifier},
+        cfg::{self, BlockRef, Result},
+        primitive::Primitive,
+        tagged::{Spanned, Tagged},
+    },
+    var::Scope,
+    Binder, Meta,
+};
+
+/// Shorthand for the type of statement list consumed by the binder.
+type List<'inp> = stm::List<'inp, Meta<'inp>, Identifier<'inp>>;
+
+/// Shorthand for the type of statement list produced by the binder.
+type BoundList<'inp> = stm::List<'inp, Meta<'inp>, cfg::var::Symbol>;
+
+/// Shorthand for the type of statement produced by the binder.
+type BoundStm<'inp> = stm::Stm<'inp, Meta<'inp>, cfg::var::Symbol>;
+
+/// Shorthand for the type of view assertion produced by the binder.
+type BoundAssertion<'inp> = view::Assertion<'inp, Meta<'inp>, cfg::var::Symbol>;
+
+impl<'inp> Binder<'inp> {
+    /// Binds a list of statement triples in `scope`.
+    pub(super) fn list(
+        &self,
+        list: &'inp List<'inp>,
+        scope: &mut Scope,
+    ) -> Result<'inp, BoundList<'inp>> {
+        list.iter()
+            .map(|t| Ok(Tagged::new(t.meta, self.triple(&t.item, scope)?)))
+            .collect()
+    }
+
+    /// Binds a statement triple in `scope`.
+    fn triple(
+        &self,
+        triple: &'inp stm::Triple<'inp, Meta<'inp>, Identifier<'inp>>,
+        scope: &mut Scope,
+    ) -> Result<'inp, stm::Triple<'inp, Meta<'inp>, cfg::var::Symbol>> {
+        Ok(stm::Triple {
+            pre: self.triple_assertion(&triple.pre, &scope.block)?,
+            stm: Tagged::new(triple.stm.meta, self.stm(&triple.stm.item, scope)?),
+            post: self.triple_assertion(&triple.post, &scope.block)?,
+        })
+    }
+
+    /// Binds an optional assertion in a triple, or in a procedure specification.
+    pub(super) fn triple_assertion(
+        &self,
+        assertion: &'inp stm::TripleAssertion<'inp, Meta<'inp>, Identifier<'inp>>,
+        block: &BlockRef,
+    ) -> Result<'inp, stm::TripleAssertion<'inp, Meta<'inp>, cfg::var::Symbol>> {
+        assertion
+            .as_ref()
+            .map(|a| Ok(Tagged::new(a.meta, self.assertion(&a.item, block)?)))
+            .transpose()
+    }
+
+    /// Binds a statement in `scope`.
+    fn stm(
+        &self,
+        stm: &'inp stm::Stm<'inp, Meta<'inp>, Identifier<'inp>>,
+        scope: &mut Scope,
+    ) -> Result<'inp, BoundStm<'inp>> {
+        Ok(match stm {
+            stm::Stm::Assign(a) => stm::Stm::Assign(stm::Assign {
+                lvalue: a
+                    .lvalue
+                    .as_ref()
+                    .map(|l| self.tagged_expr(l, &scope.block))
+                    .transpose()?,
+                rvalue: self.tagged_expr(&a.rvalue, &scope.block)?,
+            }),
+            stm::Stm::Atomic(a) => stm::Stm::Atomic(stm::Atomic {
+                syntax: a.syntax,
+                body: self.list(&a.body, &mut scope.enter_atomic())?,
+            }),
+            stm::Stm::Block(l) => stm::Stm::Block(self.list(l, &mut scope.enter())?),
+            stm::Stm::Call(c) => self.call_stm(c, scope)?,
+            stm::Stm::Ite(i) => stm::Stm::Ite(ite::Ite {
+                cond: self.condition(&i.cond, &scope.block)?,
+                true_branch: self.branch(&i.true_branch, scope)?,
+                false_branch: self.branch(&i.false_branch, scope)?,
+            }),
+            stm::Stm::Nop => stm::Stm::Nop,
+            stm::Stm::Primitive(p) => stm::Stm::Primitive(stm::Primitive {
+                primitive: p.primitive.clone(),
+                args: p
+                    .args
+                    .iter()
+                    .map(|a| self.tagged_expr(a, &scope.block))
+                    .collect::<Result<_>>()?,
+            }),
+            stm::Stm::Repeat(r) => stm::Stm::Repeat(stm::Repeat {
+                invariant: self.invariant(&r.invariant, &scope.block)?,
+                body: self.list(&r.body, &mut scope.enter())?,
+                cond: self.condition(&r.cond, &scope.block)?,
+            }),
+            stm::Stm::While(w) => stm::Stm::While(stm::While {
+                cond: self.condition(&w.cond, &scope.block)?,
+                invariant: self.invariant(&w.invariant, &scope.block)?,
+                body: self.branch(&w.body, scope)?,
+            }),
+        })
+    }
+
+    /// Binds a loop invariant.
+    fn invariant(
+        &self,
+        invariant: &'inp stm::Invariant<'inp, Meta<'inp>, Identifier<'inp>>,
+        block: &BlockRef,
+    ) -> Result<'inp, stm::Invariant<'inp, Meta<'inp>, cfg::var::Symbol>> {
+        Ok(Tagged::new(
+            invariant.meta,
+            self.assertion(&invariant.item, block)?,
+        ))
+    }
+
+    /// Binds a branch of an if-then-else statement, or the body of a while loop.
+    fn branch(
+        &self,
+        branch: &'inp Spanned<'inp, Box<stm::Stm<'inp, Meta<'inp>, Identifier<'inp>>>>,
+        scope: &mut Scope,
+    ) -> Result<'inp, Tagged<Meta<'inp>, Box<BoundStm<'inp>>>> {
+        Ok(Tagged::new(
+            branch.meta,
+            Box::new(self.stm(&branch.item, scope)?),
+        ))
+    }
+
+    /// Binds an if-then-else condition.
+    fn condition(
+        &self,
+        cond: &'inp Spanned<'inp, ite::Condition<Meta<'inp>, Identifier<'inp>>>,
+        block: &BlockRef,
+    ) -> Result<'inp, Tagged<Meta<'inp>, ite::Condition<Meta<'inp>, cfg::var::Symbol>>> {
+        Ok(Tagged::new(
+            cond.meta,
+            match &cond.item {
+                ite::Condition::Nondeterministic => ite::Condition::Nondeterministic,
+                ite::Condition::Deterministic(c) => {
+                    ite::Condition::Deterministic(self.expr(c, block)?)
+                }
+            },
+        ))
+    }
+
+    /// Binds the arguments of a call or view atom.
+    ///
+    /// The name of the call is left as-is.
+    pub(super) fn call(
+        &self,
+        call: &'inp call::Call<'inp, Meta<'inp>, Identifier<'inp>>,
+        block: &BlockRef,
+    ) -> Result<'inp, call::Call<'inp, Meta<'inp>, cfg::var::Symbol>> {
+        Ok(call::Generic {
+            name: call.name.clone(),
+            args: call
+                .args
+                .iter()
+                .map(|a| self.tagged_expr(a, block))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    /// Binds a call statement in `scope`, resolving it to a procedure or a primitive.
+    ///
+    /// Procedures take priority over primitives of the same name, except inside atomic blocks,
+    /// where procedures can't be called.
+    fn call_stm(
+        &self,
+        call: &'inp call::Call<'inp, Meta<'inp>, Identifier<'inp>>,
+        scope: &Scope,
+    ) -> Result<'inp, BoundStm<'inp>> {
+        let name = &call.name.item;
+        if !scope.atomic && self.program.procedure(Symbol::new(name)).is_some() {
+            return Ok(stm::Stm::Call(self.call(call, &scope.block)?));
+        }
+
+        let primitive = Primitive::from_name(name).ok_or(cfg::Error::UnresolvedCall {
+            name,
+            loc: call.name.meta,
+            atomic: scope.atomic,
+        })?;
+        Ok(stm::Stm::Primitive(stm::Primitive {
+            primitive: Tagged::new(call.name.meta, primitive),
+            args: self.call(call, &scope.block)?.args,
+        }))
+    }
+
+    /// Binds a view assertion.
+    pub(super) fn assertion(
+        &self,
+        assertion: &'inp view::Assertion<'inp, Meta<'inp>, Identifier<'inp>>,
+        block: &BlockRef,
+    ) -> Result<'inp, BoundAssertion<'inp>> {
+        use view::Assertion;
+
+        Ok(match assertion {
+            Assertion::Atom(a) => Assertion::Atom(Tagged::new(a.meta, self.call(&a.item, block)?)),
+            Assertion::Emp => Assertion::Emp,
+            Assertion::Guarded(g) => Assertion::Guarded(view::assertion::Guarded {
+                guard: self.expr(&g.guard, block)?,
+                view: Box::new(self.assertion(&g.view, block)?),
+            }),
+            Assertion::Ite(i) => Assertion::Ite(ite::Ite {
+                cond: self.condition(&i.cond, block)?,
+                true_branch: Box::new(self.assertion(&i.true_branch, block)?),
+                false_branch: Box::new(self.assertion(&i.false_branch, block)?),
+            }),
+            Assertion::Iterated(i) => Assertion::Iterated(view::Iterated {
+                item: Box::new(self.assertion(&i.item, block)?),
+                iterator: self.tagged_expr(&i.iterator, block)?,
+            }),
+            Assertion::Join(l, r) => Assertion::Join(
+                Box::new(self.assertion(l, block)?),
+                Box::new(self.assertion(r, block)?),
+            ),
+            Assertion::Local(l) => Assertion::Local(self.tagged_expr(l, block)?),
+        })
+    }
+}
@@ -0,0 +1,114 @@
+//! Binding of type declarations.
+//!
+//! Named types are declared before anything else, so that any declaration can refer to them
+//! regardless of order.  Type definitions may refer to other named types, but not (directly or
+//! indirectly) to themselves.
+
+use std::collections::{HashMap, HashSet};
+
+use egg::Symbol;
+
+use super::{
+    super::language::{
+        ast::{typedef, Identifier},
+        cfg::{self, Error, Origin, Result},
+        expr::map::HasVars,
+        tagged::Spanned,
+        typing,
+        var::Variable,
+    },
+    Binder, Meta, Program,
+};
+
+/// Shorthand for the type of type definition consumed by the binder.
+type Def<'inp> = Spanned<'inp, typedef::Def<'inp, Meta<'inp>, Identifier<'inp>>>;
+
+/// Shorthand for the type of type consumed by the binder.
+type Type<'inp> = typing::Type<Meta<'inp>, Identifier<'inp>>;
+
+impl<'inp> Binder<'inp> {
+    /// Declares every named type in `program`.
+    pub(super) fn declare_types(&mut self, program: &'inp Program<'inp>) -> Result<'inp, ()> {
+        let defs: Vec<&Def> = program
+            .decls
+            .iter()
+            .filter_map(|d| match &d.item {
+                super::ast::Decl::Type(t) => Some(&t.defs),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+
+        for def in &defs {
+            let name = &def.item.name;
+            let sym = Symbol::new(&name.item);
+            if let Some(existing) = self.program.types.get(&sym) {
+                return Err(Error::DuplicateType {
+                    name: &name.item,
+                    first_loc: existing.origin.meta().copied().flatten(),
+                    second_loc: name.meta,
+                });
+            }
+            let record = cfg::Type {
+                origin: Origin::Script(def.meta),
+                ty: def.item.ty.item.clone().map_var(Variable::into_symbol),
+            };
+            self.program.types.insert(sym, record);
+        }
+
+        for def in &defs {
+            self.check_type(&def.item.ty.item)?;
+        }
+        check_cycles(&defs)
+    }
+}
+
+/// Checks that no type in `defs` is defined in terms of itself.
+fn check_cycles<'inp>(defs: &[&'inp Def<'inp>]) -> Result<'inp, ()> {
+    let by_name: HashMap<&str, &Type> = defs
+        .iter()
+        .map(|d| (d.item.name.item.as_ref(), &d.item.ty.item))
+        .collect();
+    let mut done = HashSet::new();
+    for def in defs {
+        let name = def.item.name.item.as_ref();
+        visit(&by_name, &mut vec![name], &mut done, &def.item.ty.item)?;
+        done.insert(name);
+    }
+    Ok(())
+}
+
+/// Visits the named types that `ty` refers to, failing if any of them is on `path`.
+///
+/// `path` is the chain of definitions that led us to `ty`; `done` records the named types whose
+/// definitions we already know to be acyclic.
+fn visit<'inp>(
+    by_name: &HashMap<&'inp str, &'inp Type<'inp>>,
+    path: &mut Vec<&'inp str>,
+    done: &mut HashSet<&'inp str>,
+    ty: &'inp Type<'inp>,
+) -> Result<'inp, ()> {
+    let mut names = vec![];
+    ty.for_each_name(&mut |n| names.push(n));
+
+    for name in names {
+        let key = name.item.as_ref();
+        if path.contains(&key) {
+            return Err(Error::CyclicType {
+                name: key,
+                loc: name.meta,
+            });
+        }
+        let Some(def) = by_name.get(key) else {
+            continue;
+        };
+        if done.contains(key) {
+            continue;
+        }
+        path.push(key);
+        visit(by_name, path, done, def)?;
+        path.pop();
+        done.insert(key);
+    }
+    Ok(())
+}
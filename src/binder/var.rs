@@ -1,5 +1,150 @@
 //! Variable binding.
 
-enum Error {
-    MissingVar,
+use egg::Symbol;
+
+use super::{
+    super::language::{
+        ast::{call::Parameter, Identifier},
+        cfg::{self, BlockRef, Error, Origin, Result},
+        expr::Expr,
+        tagged::{Spanned, Tagged},
+        var,
+    },
+    Binder, Meta, TaggedSym,
+};
+
+/// The block scope in which the binder is currently resolving variables.
+pub struct Scope {
+    /// The current block.
+    pub block: BlockRef,
+    /// Whether this scope is inside an atomic block.
+    pub atomic: bool,
+    /// The number of child blocks already entered from this block.
+    children: usize,
+}
+
+impl Scope {
+    /// Constructs a scope at the top of `block`.
+    #[must_use]
+    pub fn new(block: BlockRef) -> Self {
+        Self {
+            block,
+            atomic: false,
+            children: 0,
+        }
+    }
+
+    /// Constructs the scope of the next child block of this scope.
+    #[must_use]
+    pub fn enter(&mut self) -> Self {
+        let child = self.block.child(self.children);
+        self.children += 1;
+        Self {
+            atomic: self.atomic,
+            ..Self::new(child)
+        }
+    }
+
+    /// As [`Self::enter`], but marks the child scope as being inside an atomic block.
+    #[must_use]
+    pub fn enter_atomic(&mut self) -> Self {
+        Self {
+            atomic: true,
+            ..self.enter()
+        }
+    }
+}
+
+impl<'inp> Binder<'inp> {
+    /// Declares the variable described by `param` in `block`, with scope `scope`.
+    pub(super) fn declare_var(
+        &mut self,
+        block: &BlockRef,
+        scope: var::Scope,
+        param: &'inp Spanned<'inp, Parameter<'inp, Meta<'inp>, Identifier<'inp>>>,
+    ) -> Result<'inp, ()> {
+        let name = &param.item.name;
+        let sym = cfg::var::Symbol {
+            block: block.clone(),
+            name: Symbol::new(&name.item),
+        };
+        let ty = self.ty(&param.item.ty, || sym.to_string())?;
+        self.insert_var(sym, name, scope, ty)
+    }
+
+    /// Inserts a variable record for the variable `sym` named by `name`.
+    pub(super) fn insert_var(
+        &mut self,
+        sym: cfg::var::Symbol,
+        name: &'inp Spanned<'inp, Identifier<'inp>>,
+        scope: var::Scope,
+        ty: Symbol,
+    ) -> Result<'inp, ()> {
+        let record = cfg::var::Record {
+            origin: Origin::Script(name.meta),
+            scope,
+            ty,
+        };
+        self.program
+            .variables
+            .insert(sym, record)
+            .map_err(|existing| super::duplicate_var(existing, name))
+    }
+
+    /// Resolves the variable named by `name` as seen from `block`.
+    pub(super) fn resolve(
+        &self,
+        name: &'inp Spanned<'inp, Identifier<'inp>>,
+        block: &BlockRef,
+    ) -> Result<'inp, TaggedSym<'inp>> {
+        let sym = cfg::var::Symbol {
+            block: block.clone(),
+            name: Symbol::new(&name.item),
+        };
+        self.program
+            .variables
+            .get(&sym)
+            .map(|(sym, _)| Tagged::new(name.meta, sym))
+            .ok_or(Error::UnresolvedVar {
+                name: &name.item,
+                loc: name.meta,
+            })
+    }
+
+    /// Binds all variables in `expr` as seen from `block`.
+    pub(super) fn expr(
+        &self,
+        expr: &'inp Expr<Meta<'inp>, Identifier<'inp>>,
+        block: &BlockRef,
+    ) -> Result<'inp, Expr<Meta<'inp>, cfg::var::Symbol>> {
+        Ok(match expr {
+            Expr::Literal(l) => Expr::Literal(l.clone()),
+            Expr::Var(v) => Expr::Var(self.resolve(v, block)?),
+            Expr::Bop { op, lhs, rhs } => {
+                Expr::bop(self.expr(lhs, block)?, *op, self.expr(rhs, block)?)
+            }
+            Expr::Uop { op, expr } => Expr::uop(*op, self.expr(expr, block)?),
+            Expr::Index { array, index } => {
+                Expr::index(self.expr(array, block)?, self.expr(index, block)?)
+            }
+            Expr::Update {
+                array,
+                index,
+                value,
+            } => Expr::update(
+                self.expr(array, block)?,
+                self.expr(index, block)?,
+                self.expr(value, block)?,
+            ),
+        })
+    }
+
+    /// Binds all variables in the tagged expression `expr` as seen from `block`.
+    pub(super) fn tagged_expr(
+        &self,
+        expr: &'inp Spanned<'inp, Expr<Meta<'inp>, Identifier<'inp>>>,
+        block: &BlockRef,
+    ) -> Result<'inp, Tagged<Meta<'inp>, Expr<Meta<'inp>, cfg::var::Symbol>>> {
+        Ok(Tagged::new(expr.meta, self.expr(&expr.item, block)?))
+    }
 }
@@ -0,0 +1,199 @@
+//! Diagnostics for PVC scripts.
+//!
+//! A [Diagnostic] is a message about a program, with a severity, an optional error code, labels
+//! pointing at spans of source, and free-form notes.  Every phase of the pipeline (the parser, the
+//! binder, the type checker, and so on) converts its errors into diagnostics through
+//! [`ToDiagnostic`], so that they can all be rendered in the same way: either for humans, in the
+//! style of `rustc` (see [human]), or as JSON for editors (see [json]).
+//!
+//! Labels refer to source by byte ranges into files registered in a [Files] database, so that
+//! diagnostics can outlive the source they were built from, and refer to more than one file.
+
+use std::{
+    fmt::{Display, Formatter},
+    ops::Range,
+};
+
+pub mod code;
+pub mod files;
+pub mod human;
+pub mod json;
+
+pub use code::Code;
+pub use files::{FileId, Files};
+
+/// A diagnostic message.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// The severity of the diagnostic.
+    pub severity: Severity,
+    /// The error code of the diagnostic, if it has one.
+    pub code: Option<Code>,
+    /// The main message of the diagnostic.
+    pub message: String,
+    /// Labels pointing at the source the diagnostic concerns.
+    pub labels: Vec<Label>,
+    /// Notes to display after the labels.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Constructs a diagnostic with the given severity and message, and nothing else.
+    #[must_use]
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    /// Constructs an error diagnostic.
+    #[must_use]
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    /// Constructs a warning diagnostic.
+    #[must_use]
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    /// Sets the error code of this diagnostic.
+    #[must_use]
+    pub fn with_code(mut self, code: Code) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Adds a label to this diagnostic.
+    #[must_use]
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    /// Adds a label to this diagnostic if `label` is present.
+    ///
+    /// This is useful for labelling optional spans.
+    #[must_use]
+    pub fn with_label_opt(self, label: Option<Label>) -> Self {
+        match label {
+            Some(l) => self.with_label(l),
+            None => self,
+        }
+    }
+
+    /// Adds a note to this diagnostic.
+    #[must_use]
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+}
+
+/// The severity of a diagnostic.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// A hint on how to fix something.
+    Help,
+    /// Additional information.
+    Note,
+    /// Something suspicious, but not fatal.
+    Warning,
+    /// Something that stops the script from being processed further.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Help => "help",
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A label pointing at a span of source.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Label {
+    /// Whether this is a primary label (the site of the problem) or a secondary one (context).
+    pub primary: bool,
+    /// The file containing the span.
+    pub file: FileId,
+    /// The byte range of the span.
+    pub range: Range<usize>,
+    /// The message to display next to the span.
+    pub message: String,
+}
+
+impl Label {
+    /// Constructs a primary label with no message.
+    #[must_use]
+    pub fn primary(file: FileId, range: Range<usize>) -> Self {
+        Self {
+            primary: true,
+            file,
+            range,
+            message: String::new(),
+        }
+    }
+
+    /// Constructs a secondary label with no message.
+    #[must_use]
+    pub fn secondary(file: FileId, range: Range<usize>) -> Self {
+        Self {
+            primary: false,
+            ..Self::primary(file, range)
+        }
+    }
+
+    /// Sets the message of this label.
+    #[must_use]
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+}
+
+/// Gets the byte range of a Pest span.
+///
+/// Spans of non-atomic rules include any whitespace after the rule, which we don't want to
+/// underline, so we trim it off.
+#[must_use]
+pub fn range(span: &pest::Span) -> Range<usize> {
+    span.start()..span.start() + span.as_str().trim_end().len()
+}
+
+/// Labels the span `loc` in `file` with `message`, if there is a span.
+///
+/// The label is primary if `primary` is set, and secondary otherwise.
+#[must_use]
+pub fn label(
+    file: FileId,
+    loc: &Option<pest::Span>,
+    primary: bool,
+    message: impl Into<String>,
+) -> Option<Label> {
+    loc.map(|l| {
+        let range = range(&l);
+        let label = if primary {
+            Label::primary(file, range)
+        } else {
+            Label::secondary(file, range)
+        };
+        label.with_message(message)
+    })
+}
+
+/// Trait of things, usually errors, that can be reported as diagnostics.
+pub trait ToDiagnostic {
+    /// Converts this item to a diagnostic, taking any spans to be in `file`.
+    fn to_diagnostic(&self, file: FileId) -> Diagnostic;
+}
@@ -0,0 +1,80 @@
+//! Diagnostic error codes.
+//!
+//! Every code used anywhere in Starling is listed here, so that codes stay unique.  Codes are
+//! grouped by the phase that raises them: `E00xx` for parsing, `E01xx` for binding, `E02xx` for
+//! type and constraint checking, `E03xx` for verification condition generation, and `E04xx` for
+//! verification.
+
+use std::fmt::{Display, Formatter};
+
+/// A diagnostic error code.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, serde::Serialize)]
+#[serde(transparent)]
+pub struct Code(pub &'static str);
+
+impl Display for Code {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+/// The script does not match the PVC grammar.
+pub const SYNTAX: Code = Code("E0001");
+/// The parser accepted something it couldn't turn into a syntax tree; this is a bug in Starling.
+pub const INTERNAL: Code = Code("E0002");
+/// The script uses syntax that the grammar accepts, but Starling doesn't support yet.
+pub const UNSUPPORTED_SYNTAX: Code = Code("E0003");
+
+/// A variable is declared twice in the same scope.
+pub const DUPLICATE_VAR: Code = Code("E0101");
+/// A view is declared twice.
+pub const DUPLICATE_VIEW: Code = Code("E0102");
+/// A procedure is declared twice.
+pub const DUPLICATE_PROCEDURE: Code = Code("E0103");
+/// A variable is used but not declared.
+pub const UNRESOLVED_VAR: Code = Code("E0104");
+/// A named type is declared twice.
+pub const DUPLICATE_TYPE: Code = Code("E0105");
+/// A named type is used but not declared.
+pub const UNRESOLVED_TYPE: Code = Code("E0106");
+/// A named type is defined in terms of itself.
+pub const CYCLIC_TYPE: Code = Code("E0107");
+/// A call names neither a procedure nor a primitive that can be called in its context.
+pub const UNRESOLVED_CALL: Code = Code("E0108");
+
+/// An expression does not have the type its context expects.
+pub const TYPE_MISMATCH: Code = Code("E0201");
+/// A call or view atom has the wrong number of arguments.
+pub const ARITY_MISMATCH: Code = Code("E0202");
+/// A view atom refers to an undeclared view.
+pub const UNKNOWN_VIEW: Code = Code("E0203");
+/// An expression that is not a pointer is dereferenced.
+pub const BAD_DEREF: Code = Code("E0204");
+/// An expression that is not an array is subscripted.
+pub const BAD_INDEX: Code = Code("E0205");
+/// A constraint mentions a pattern variable that one of its patterns does not bind.
+pub const UNBOUND_PATTERN_VAR: Code = Code("E0206");
+/// A view is declared but never constrained.
+pub const UNCONSTRAINED_VIEW: Code = Code("E0207");
+/// A constraint mentions a thread-local variable.
+pub const THREAD_IN_CONSTRAINT: Code = Code("E0208");
+/// A local assertion mentions a shared variable.
+pub const SHARED_IN_LOCAL: Code = Code("E0209");
+/// A statement reads a shared variable outside an atomic block.
+pub const SHARED_OUTSIDE_ATOMIC: Code = Code("E0210");
+
+/// A command is not surrounded by view assertions.
+pub const MISSING_ASSERTION: Code = Code("E0301");
+/// Two atomic commands are not separated by a view assertion.
+pub const ADJACENT_ATOMICS: Code = Code("E0302");
+/// A view assertion appears inside an atomic block.
+pub const ASSERTION_IN_ATOMIC: Code = Code("E0303");
+/// An assignment has a left-hand side that is not assignable.
+pub const BAD_LVALUE: Code = Code("E0304");
+/// The program uses a construct the generator does not yet support.
+pub const UNSUPPORTED: Code = Code("E0399");
+
+/// A verification condition does not hold.
+pub const FAILED_CONDITION: Code = Code("E0401");
+/// The solver could not decide a verification condition.
+pub const UNKNOWN_CONDITION: Code = Code("E0402");
@@ -0,0 +1,125 @@
+//! The database of source files that diagnostics refer to.
+
+/// Identifies a file in a [Files] database.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub struct FileId(usize);
+
+/// A database of source files.
+#[derive(Debug, Clone, Default)]
+pub struct Files {
+    files: Vec<File>,
+}
+
+impl Files {
+    /// Adds a file with the given name and contents, returning its ID.
+    pub fn add(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        self.files.push(File::new(name.into(), source.into()));
+        FileId(self.files.len() - 1)
+    }
+
+    /// Borrows the file with ID `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` did not come from this database.
+    #[must_use]
+    pub fn get(&self, id: FileId) -> &File {
+        &self.files[id.0]
+    }
+}
+
+/// A source file.
+#[derive(Debug, Clone)]
+pub struct File {
+    name: String,
+    source: String,
+    /// Byte offsets of the start of each line.
+    line_starts: Vec<usize>,
+}
+
+/// A position in a file, with 1-based line and column numbers.
+///
+/// Columns count characters, not bytes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Location {
+    /// The line number.
+    pub line: usize,
+    /// The column number.
+    pub column: usize,
+}
+
+impl File {
+    fn new(name: String, source: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            name,
+            source,
+            line_starts,
+        }
+    }
+
+    /// Borrows the name of the file.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Borrows the contents of the file.
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Gets the location of byte offset `offset`, clamping it to the end of the file.
+    #[must_use]
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = self.clamp(offset);
+        let index = self.line_starts.partition_point(|&s| s <= offset) - 1;
+        let start = self.line_starts[index];
+        Location {
+            line: index + 1,
+            column: self.source[start..offset].chars().count() + 1,
+        }
+    }
+
+    /// Borrows line number `line` (1-based), without its line terminator.
+    #[must_use]
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.line_starts.get(line - 1).copied().unwrap_or(0);
+        let end = self
+            .line_starts
+            .get(line)
+            .copied()
+            .unwrap_or(self.source.len());
+        self.source[start..end].trim_end_matches(['\n', '\r'])
+    }
+
+    /// Clamps `offset` into the file, moving it back onto a character boundary.
+    fn clamp(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests line and column lookup, including across multi-byte characters.
+    #[test]
+    fn location_lines() {
+        let mut files = Files::default();
+        let id = files.add("test.pvc", "ab\nλx\n");
+        let file = files.get(id);
+
+        assert_eq!(Location { line: 1, column: 1 }, file.location(0));
+        assert_eq!(Location { line: 2, column: 1 }, file.location(3));
+        assert_eq!(Location { line: 2, column: 2 }, file.location(5));
+        assert_eq!("λx", file.line(2));
+    }
+}
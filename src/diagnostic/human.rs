@@ -0,0 +1,213 @@
+//! Rendering of diagnostics for humans, in the style of `rustc`.
+//!
+//! Each diagnostic renders as a header line, then an excerpt of every file it has labels in, with
+//! primary labels underlined by `^` and secondary labels by `-`, then its notes.
+
+use std::fmt::Write as _;
+
+use super::{files::File, Diagnostic, FileId, Files, Label};
+
+/// Renders `diagnostic`, looking up its files in `files`.
+#[must_use]
+pub fn render(files: &Files, diagnostic: &Diagnostic) -> String {
+    let mut out = match diagnostic.code {
+        Some(code) => format!("{}[{code}]: {}\n", diagnostic.severity, diagnostic.message),
+        None => format!("{}: {}\n", diagnostic.severity, diagnostic.message),
+    };
+
+    let width = diagnostic
+        .labels
+        .iter()
+        .map(|l| {
+            let file = files.get(l.file);
+            file.location(l.range.end).line.to_string().len()
+        })
+        .max()
+        .unwrap_or(0);
+    let gutter = " ".repeat(width);
+
+    for (i, file) in file_order(diagnostic).into_iter().enumerate() {
+        let mut labels: Vec<&Label> = diagnostic
+            .labels
+            .iter()
+            .filter(|l| l.file == file)
+            .collect();
+        let anchor = labels
+            .iter()
+            .find(|l| l.primary)
+            .unwrap_or(&labels[0])
+            .range
+            .start;
+        labels.sort_by_key(|l| (l.range.start, l.range.end));
+
+        let file = files.get(file);
+        let loc = file.location(anchor);
+        let arrow = if i == 0 { "-->" } else { ":::" };
+        let _ = writeln!(
+            out,
+            "{gutter}{arrow} {}:{}:{}",
+            file.name(),
+            loc.line,
+            loc.column
+        );
+        let _ = writeln!(out, "{gutter} |");
+        Excerpt {
+            out: &mut out,
+            file,
+            width,
+            last_line: None,
+        }
+        .labels(&labels);
+    }
+
+    if !diagnostic.notes.is_empty() && !diagnostic.labels.is_empty() {
+        let _ = writeln!(out, "{gutter} |");
+    }
+    for note in &diagnostic.notes {
+        let _ = writeln!(out, "{gutter} = note: {note}");
+    }
+    out
+}
+
+/// Gets the files a diagnostic has labels in, starting with the file of its first primary label.
+fn file_order(diagnostic: &Diagnostic) -> Vec<FileId> {
+    let mut order = vec![];
+    let primary = diagnostic.labels.iter().filter(|l| l.primary);
+    for label in primary.chain(&diagnostic.labels) {
+        if !order.contains(&label.file) {
+            order.push(label.file);
+        }
+    }
+    order
+}
+
+/// State for rendering the labels of one file.
+struct Excerpt<'a> {
+    out: &'a mut String,
+    file: &'a File,
+    width: usize,
+    /// The last source line printed, if any.
+    last_line: Option<usize>,
+}
+
+impl Excerpt<'_> {
+    fn labels(&mut self, labels: &[&Label]) {
+        for label in labels {
+            self.label(label);
+        }
+    }
+
+    fn label(&mut self, label: &Label) {
+        let start = self.file.location(label.range.start);
+        let end = self.file.location(label.range.end);
+        // A span ending in a line break ends at column 1 of the next line, which it doesn't cover.
+        let last = if end.line > start.line && end.column == 1 {
+            end.line - 1
+        } else {
+            end.line
+        };
+        let marker = if label.primary { "^" } else { "-" };
+
+        for line in start.line..=last {
+            let text = self.file.line(line);
+            let len = text.chars().count();
+            let from = if line == start.line {
+                start.column
+            } else {
+                text.chars().take_while(|c| c.is_whitespace()).count() + 1
+            };
+            let to = if line == end.line {
+                end.column
+            } else {
+                len + 1
+            };
+
+            self.source_line(line, text);
+            let pad = " ".repeat(from - 1);
+            let marks = marker.repeat(to.saturating_sub(from).max(1));
+            let message = if line == last && !label.message.is_empty() {
+                format!(" {}", label.message)
+            } else {
+                String::new()
+            };
+            let _ = writeln!(
+                self.out,
+                "{:w$} | {pad}{marks}{message}",
+                "",
+                w = self.width
+            );
+        }
+    }
+
+    /// Prints source line `line`, unless we printed it last, eliding any gap since the last line.
+    fn source_line(&mut self, line: usize, text: &str) {
+        match self.last_line {
+            Some(l) if l == line => return,
+            Some(l) if l + 1 < line => {
+                let _ = writeln!(self.out, "...");
+            }
+            _ => (),
+        }
+        let _ = writeln!(self.out, "{line:>w$} | {text}", w = self.width);
+        self.last_line = Some(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::code, *};
+
+    /// Tests rendering two labels on the same line, with a note.
+    #[test]
+    fn render_same_line() {
+        let mut files = Files::default();
+        let file = files.add("p.pvc", "program P;\n  { emp } <| x := 1 |> { emp }\n");
+        let diagnostic = Diagnostic::error("something is wrong")
+            .with_code(code::UNSUPPORTED)
+            .with_label(Label::secondary(file, 13..20).with_message("pre-condition"))
+            .with_label(Label::primary(file, 21..33).with_message("command"))
+            .with_note("a note");
+
+        assert_eq!(
+            "error[E0399]: something is wrong
+ --> p.pvc:2:11
+  |
+2 |   { emp } <| x := 1 |> { emp }
+  |   ------- pre-condition
+  |           ^^^^^^^^^^^^ command
+  |
+  = note: a note
+",
+            render(&files, &diagnostic)
+        );
+    }
+
+    /// Tests rendering labels in two files, and eliding lines between labels.
+    #[test]
+    fn render_multiple_files() {
+        let mut files = Files::default();
+        let a = files.add("a.pvc", "x\n\n\ny\n");
+        let b = files.add("b.pvc", "z\n");
+        let diagnostic = Diagnostic::error("oops")
+            .with_label(Label::primary(a, 0..1))
+            .with_label(Label::secondary(a, 4..5).with_message("here"))
+            .with_label(Label::secondary(b, 0..1).with_message("there"));
+
+        assert_eq!(
+            "error: oops
+ --> a.pvc:1:1
+  |
+1 | x
+  | ^
+...
+4 | y
+  | - here
+ ::: b.pvc:1:1
+  |
+1 | z
+  | - there
+",
+            render(&files, &diagnostic)
+        );
+    }
+}
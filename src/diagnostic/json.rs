@@ -0,0 +1,90 @@
+//! Rendering of diagnostics as JSON, for editors and other tools.
+//!
+//! Each diagnostic becomes a single JSON object, in which labels carry both byte offsets and
+//! 1-based line and column numbers.
+
+use serde::Serialize;
+
+use super::{Code, Diagnostic, Files, Severity};
+
+/// Renders `diagnostic` as a single line of JSON, looking up its files in `files`.
+///
+/// # Panics
+///
+/// Panics if serialisation fails, which should never happen.
+#[must_use]
+pub fn render(files: &Files, diagnostic: &Diagnostic) -> String {
+    let labels = diagnostic
+        .labels
+        .iter()
+        .map(|l| {
+            let file = files.get(l.file);
+            let start = file.location(l.range.start);
+            let end = file.location(l.range.end);
+            Label {
+                file: file.name(),
+                byte_start: l.range.start,
+                byte_end: l.range.end,
+                line_start: start.line,
+                column_start: start.column,
+                line_end: end.line,
+                column_end: end.column,
+                primary: l.primary,
+                message: &l.message,
+            }
+        })
+        .collect();
+
+    let json = Json {
+        severity: diagnostic.severity,
+        code: diagnostic.code,
+        message: &diagnostic.message,
+        labels,
+        notes: &diagnostic.notes,
+    };
+    serde_json::to_string(&json).expect("diagnostics should always serialise")
+}
+
+/// The JSON form of a diagnostic.
+#[derive(Serialize)]
+struct Json<'a> {
+    severity: Severity,
+    code: Option<Code>,
+    message: &'a str,
+    labels: Vec<Label<'a>>,
+    notes: &'a [String],
+}
+
+/// The JSON form of a label.
+#[derive(Serialize)]
+struct Label<'a> {
+    file: &'a str,
+    byte_start: usize,
+    byte_end: usize,
+    line_start: usize,
+    column_start: usize,
+    line_end: usize,
+    column_end: usize,
+    primary: bool,
+    message: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::code, *};
+
+    /// Tests the JSON rendering of a diagnostic with one label.
+    #[test]
+    fn render_label() {
+        let mut files = Files::default();
+        let file = files.add("p.pvc", "program P;\nx := y\n");
+        let diagnostic = Diagnostic::error("unresolved variable `y`")
+            .with_code(code::UNRESOLVED_VAR)
+            .with_label(super::super::Label::primary(file, 16..17));
+
+        assert_eq!(
+            r#"{"severity":"error","code":"E0104","message":"unresolved variable `y`","labels":[{"file":"p.pvc","byte_start":16,"byte_end":17,"line_start":2,"column_start":6,"line_end":2,"column_end":7,"primary":true,"message":""}],"notes":[]}"#,
+            render(&files, &diagnostic)
+        );
+    }
+}
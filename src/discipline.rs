@@ -0,0 +1,354 @@
+//! Checker for the thread-local and shared variable discipline of bound PVC programs.
+//!
+//! Shared variables (declared with `var`) are visible to every thread, while thread-local
+//! variables (declared with `threadvar`, and procedure parameters and locals) belong to one
+//! thread.  The binder resolves both kinds of variable anywhere they are in scope, so this checker
+//! rejects the uses that the verification methodology can't give a meaning to:
+//!
+//! - constraints relate views to the shared state, so they must not mention thread-local
+//!   variables;
+//! - local assertions (`@(...)`) hold without regard to interference, so they must not mention
+//!   shared variables;
+//! - other threads can change shared variables at any time, so statements must only read them
+//!   inside atomic blocks.
+//!
+//! Like the type checker, this checker reports every problem it finds.
+
+use egg::Symbol;
+
+use super::{
+    binder::{Bound, Meta},
+    diagnostic::{self, code, Diagnostic, FileId, ToDiagnostic},
+    language::{
+        ast::{ite, stm, view},
+        cfg,
+        expr::{self, Expr},
+        tagged::Tagged,
+        var::Scope,
+    },
+};
+
+/// A use of a variable in a scope that forbids it.
+#[derive(Debug, Clone, thiserror::Error, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Error<M> {
+    /// A constraint mentions a thread-local variable.
+    #[error("constraint mentions thread-local variable `{name}`")]
+    ThreadInConstraint { name: Symbol, loc: M, decl: M },
+    /// A local assertion mentions a shared variable.
+    #[error("local assertion mentions shared variable `{name}`")]
+    SharedInLocal { name: Symbol, loc: M, decl: M },
+    /// A statement reads a shared variable outside of an atomic block.
+    #[error("shared variable `{name}` is read outside an atomic block")]
+    SharedOutsideAtomic { name: Symbol, loc: M, decl: M },
+}
+
+impl ToDiagnostic for Error<Option<pest::Span<'_>>> {
+    fn to_diagnostic(&self, file: FileId) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.to_string());
+        let (code, loc, decl, used, declared) = match self {
+            Self::ThreadInConstraint { loc, decl, .. } => (
+                code::THREAD_IN_CONSTRAINT,
+                loc,
+                decl,
+                "constraints may only mention shared variables",
+                "declared thread-local here",
+            ),
+            Self::SharedInLocal { loc, decl, .. } => (
+                code::SHARED_IN_LOCAL,
+                loc,
+                decl,
+                "local assertions may only mention thread-local variables",
+                "declared shared here",
+            ),
+            Self::SharedOutsideAtomic { loc, decl, .. } => (
+                code::SHARED_OUTSIDE_ATOMIC,
+                loc,
+                decl,
+                "move this read into an atomic block",
+                "declared shared here",
+            ),
+        };
+        diagnostic
+            .with_code(code)
+            .with_label_opt(diagnostic::label(file, loc, true, used))
+            .with_label_opt(diagnostic::label(file, decl, false, declared))
+    }
+}
+
+/// Checks the variable discipline of `program`, returning every problem found.
+///
+/// An empty result means that `program` uses its thread-local and shared variables properly.
+#[must_use]
+pub fn check<'inp>(program: &Bound<'inp>) -> Vec<Error<Meta<'inp>>> {
+    let mut checker = Checker {
+        program,
+        atomic: false,
+        errors: vec![],
+    };
+
+    for proc in &program.procedures {
+        for spec in proc.requires.iter().chain(&proc.ensures) {
+            checker.assertion(&spec.item);
+        }
+        checker.list(&proc.body);
+    }
+    for decl in &program.constraints {
+        checker.constraint(&decl.item);
+    }
+
+    checker.errors
+}
+
+/// The state of the discipline checker.
+struct Checker<'a, 'inp> {
+    /// The program being checked.
+    program: &'a Bound<'inp>,
+    /// Whether we are inside an atomic block, where statements may read shared variables.
+    atomic: bool,
+    /// The errors found so far.
+    errors: Vec<Error<Meta<'inp>>>,
+}
+
+impl<'inp> Checker<'_, 'inp> {
+    /// Checks that a constraint mentions no thread-local variables.
+    fn constraint(&mut self, decl: &cfg::Constraint<Meta<'inp>>) {
+        let mut mention = |expr: &Expr<Meta<'inp>, cfg::var::Symbol>| {
+            self.scan(expr, Scope::Thread, |name, loc, decl| {
+                Error::ThreadInConstraint { name, loc, decl }
+            });
+        };
+        for pattern in &decl.views {
+            for atom in &pattern.item.contents {
+                for arg in atom.item.args.iter().chain([&atom.item.iterator]) {
+                    if let view::pattern::Argument::Expr(e) = &arg.item {
+                        mention(e);
+                    }
+                }
+            }
+        }
+        if let Some(body) = decl.body.item.expr() {
+            mention(&body.item);
+        }
+    }
+
+    /// Checks that the local assertions in `assertion` mention no shared variables.
+    fn assertion(&mut self, assertion: &view::Assertion<'inp, Meta<'inp>, cfg::var::Symbol>) {
+        use view::Assertion;
+
+        match assertion {
+            Assertion::Atom(_) | Assertion::Emp => (),
+            Assertion::Guarded(g) => self.assertion(&g.view),
+            Assertion::Ite(i) => {
+                self.assertion(&i.true_branch);
+                self.assertion(&i.false_branch);
+            }
+            Assertion::Iterated(i) => self.assertion(&i.item),
+            Assertion::Join(l, r) => {
+                self.assertion(l);
+                self.assertion(r);
+            }
+            Assertion::Local(l) => self.scan(&l.item, Scope::Shared, |name, loc, decl| {
+                Error::SharedInLocal { name, loc, decl }
+            }),
+        }
+    }
+
+    /// Checks a list of statement triples.
+    fn list(&mut self, list: &stm::List<'inp, Meta<'inp>, cfg::var::Symbol>) {
+        for triple in list {
+            for assertion in triple.item.pre.iter().chain(&triple.item.post) {
+                self.assertion(&assertion.item);
+            }
+            self.stm(&triple.item.stm.item);
+        }
+    }
+
+    /// Checks a statement.
+    fn stm(&mut self, stm: &stm::Stm<'inp, Meta<'inp>, cfg::var::Symbol>) {
+        match stm {
+            stm::Stm::Assign(a) => {
+                if let Some(lvalue) = &a.lvalue {
+                    self.lvalue(&lvalue.item);
+                }
+                self.read(&a.rvalue.item);
+            }
+            // Atomic blocks may read shared variables, but local assertions inside them still
+            // mustn't mention any.
+            stm::Stm::Atomic(a) => {
+                let outer = std::mem::replace(&mut self.atomic, true);
+                self.list(&a.body);
+                self.atomic = outer;
+            }
+            stm::Stm::Nop => (),
+            stm::Stm::Block(l) => self.list(l),
+            // Procedures take their arguments by value, so calls only read them.
+            stm::Stm::Call(c) => {
+                for arg in &c.args {
+                    self.read(&arg.item);
+                }
+            }
+            stm::Stm::Ite(i) => {
+                self.condition(&i.cond.item);
+                for branch in [&i.true_branch, &i.false_branch] {
+                    self.stm(&branch.item);
+                }
+            }
+            stm::Stm::Primitive(p) => {
+                for arg in &p.args {
+                    self.read(&arg.item);
+                }
+            }
+            stm::Stm::Repeat(r) => {
+                self.assertion(&r.invariant.item);
+                self.list(&r.body);
+                self.condition(&r.cond.item);
+            }
+            stm::Stm::While(w) => {
+                self.condition(&w.cond.item);
+                self.assertion(&w.invariant.item);
+                self.stm(&w.body.item);
+            }
+        }
+    }
+
+    /// Checks the condition of an if-then-else or loop.
+    fn condition(&mut self, cond: &ite::Condition<Meta<'inp>, cfg::var::Symbol>) {
+        if let ite::Condition::Deterministic(c) = cond {
+            self.read(c);
+        }
+    }
+
+    /// Checks the left-hand side of an assignment.
+    ///
+    /// Writing to the assigned variable doesn't read it, but evaluating any subscripts does.
+    fn lvalue(&mut self, lvalue: &Expr<Meta<'inp>, cfg::var::Symbol>) {
+        match lvalue {
+            Expr::Var(_) => (),
+            Expr::Index { array, index } => {
+                self.lvalue(array);
+                self.read(index);
+            }
+            // Dereferences read their operand to find the location they write.
+            Expr::Uop {
+                op: expr::Uop::Deref,
+                expr,
+            } => self.read(expr),
+            e => self.read(e),
+        }
+    }
+
+    /// Checks that `expr` reads no shared variables, unless it is inside an atomic block.
+    fn read(&mut self, expr: &Expr<Meta<'inp>, cfg::var::Symbol>) {
+        if self.atomic {
+            return;
+        }
+        self.scan(expr, Scope::Shared, |name, loc, decl| {
+            Error::SharedOutsideAtomic { name, loc, decl }
+        });
+    }
+
+    /// Reports every variable in `expr` with scope `forbidden`, using `error` to build the error.
+    fn scan(
+        &mut self,
+        expr: &Expr<Meta<'inp>, cfg::var::Symbol>,
+        forbidden: Scope,
+        error: impl Fn(Symbol, Meta<'inp>, Meta<'inp>) -> Error<Meta<'inp>>,
+    ) {
+        let variables = &self.program.variables;
+        expr.for_each_tagged_var(&mut |v: &Tagged<Meta<'inp>, cfg::var::Symbol>| {
+            if let Some(record) = variables.get_exact(&v.item) {
+                if record.scope == forbidden {
+                    let decl = record.origin.meta().copied().flatten();
+                    self.errors.push(error(v.item.name, v.meta, decl));
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARC: &str = include_str!("../examples/arc.pvc");
+
+    /// Checks the discipline of the program with the given declarations.
+    fn checked(decls: &str) -> Vec<String> {
+        let source =
+            format!("program T; var x : integer; threadvar t : integer; {decls} begin end.");
+        let ast = crate::parser::parse(&source).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        check(&program).iter().map(ToString::to_string).collect()
+    }
+
+    /// Tests that the ARC example follows the discipline.
+    #[test]
+    fn check_arc() {
+        let ast = crate::parser::parse(ARC).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        assert_eq!(Vec::<Error<Meta>>::new(), check(&program));
+    }
+
+    /// Tests that constraints can't mention thread-local variables, in patterns or bodies.
+    #[test]
+    fn check_thread_in_constraint() {
+        assert_eq!(
+            vec![
+                "constraint mentions thread-local variable `t`",
+                "constraint mentions thread-local variable `t`",
+            ],
+            checked("view V(n : integer); constraint V(t + 1) entails x = t;")
+        );
+    }
+
+    /// Tests that local assertions can't mention shared variables, however deeply nested.
+    #[test]
+    fn check_shared_in_local() {
+        assert_eq!(
+            vec![
+                "local assertion mentions shared variable `x`",
+                "local assertion mentions shared variable `x`",
+            ],
+            checked(
+                "view V; procedure P; begin
+  { V * @(t = 1) } <| t := x |> { [t > 0] (V * @(x > 0)) };
+  { V * (@(x = t) <[t > 0]> emp) } <| t := 0 |> { V }
+end; constraint V entails true;"
+            )
+        );
+    }
+
+    /// Tests that shared variables can only be read inside atomic blocks.
+    #[test]
+    fn check_shared_outside_atomic() {
+        assert_eq!(
+            vec![
+                "shared variable `x` is read outside an atomic block",
+                "shared variable `x` is read outside an atomic block",
+            ],
+            checked(
+                "view V; procedure P; begin
+  { V } t := x + 1 { V };
+  { V } if x > 0 then <| x := t |> { V };
+  { V } <| t := x; x := 0 |> { V };
+  { V } x := t { V }
+end; constraint V entails true;"
+            )
+        );
+    }
+
+    /// Tests that atomic blocks may read shared variables, however deeply nested, but that local
+    /// assertions inside them still can't mention any.
+    #[test]
+    fn check_inside_atomic() {
+        assert_eq!(
+            vec!["local assertion mentions shared variable `x`"],
+            checked(
+                "view V; procedure P; begin
+  { V } <| if x > 0 then t := x else begin { @(x = 0) } t := x { @(t = 0) } end |> { V }
+end; constraint V entails true;"
+            )
+        );
+    }
+}
@@ -7,12 +7,14 @@
 //! - the _control flow graph_, which is the intermediate representation used for PVC proof
 //!   scripts during lowering and emission;
 //! - the Starling _type system_;
+//! - the built-in atomic _primitives_;
 //! - metadata tagging and other utilities.
 
 pub mod ast;
 pub mod cfg;
 pub mod expr;
 pub mod ite;
+pub mod primitive;
 pub mod tagged;
 pub mod typing;
 pub mod var;
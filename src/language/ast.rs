@@ -13,7 +13,9 @@ pub use var::Identifier;
 pub mod call;
 pub mod constraint;
 pub mod ite;
+pub mod map;
 pub mod program;
 pub mod stm;
+pub mod typedef;
 pub mod var;
 pub mod view;
@@ -17,7 +17,7 @@ pub struct Generic<'inp, M, Arg> {
 ///
 /// An empty name is syntactically invalid, but we assume it will be replaced with something
 /// non-empty if we are emitting syntax.
-impl<'inp, M: Default, Arg> Default for Generic<'inp, M, Arg> {
+impl<M: Default, Arg> Default for Generic<'_, M, Arg> {
     fn default() -> Self {
         Self {
             name: Tagged::default(),
@@ -43,7 +43,7 @@ pub struct Parameter<'inp, M, V> {
 }
 
 /// The default parameter has integer type and a default name.
-impl<'inp, M: Default, V: Default> Default for Parameter<'inp, M, V> {
+impl<M: Default, V: Default> Default for Parameter<'_, M, V> {
     fn default() -> Self {
         Parameter {
             name: Tagged::default(),
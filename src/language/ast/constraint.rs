@@ -30,6 +30,24 @@ impl<M: Default, V> Default for Decl<M, V> {
 pub enum Constraint<M, V> {
     /// An entailment constraint body.
     Entails(Entailment<M, V>),
+    /// An exact definition of the view pattern.
+    Exact(Definition<M, V>),
+    /// An indefinite constraint body, whose definition is left to be inferred.
+    Indefinite,
+    /// A constraint body stating that the view pattern can never hold.
+    Fails,
+}
+
+impl<M, V> Constraint<M, V> {
+    /// Borrows the expression in this constraint body, if it has one.
+    #[must_use]
+    pub fn expr(&self) -> Option<&Tagged<M, expr::Expr<M, V>>> {
+        match self {
+            Self::Entails(e) => Some(&e.entails),
+            Self::Exact(d) => Some(&d.definition),
+            Self::Indefinite | Self::Fails => None,
+        }
+    }
 }
 
 /// A constraint body that maps a view pattern to a Boolean expression.
@@ -48,3 +66,11 @@ impl<M: Default, V> Default for Entailment<M, V> {
         }
     }
 }
+
+/// A constraint body that defines a view pattern as exactly a Boolean expression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Definition<M, V> {
+    /// The expression defining the view pattern.
+    pub definition: Tagged<M, expr::Expr<M, V>>,
+}
@@ -0,0 +1,330 @@
+//! Mapping over the metadata of ASTs.
+//!
+//! Statements and view assertions are recursive, so their implementations erase the mapping
+//! function into a trait object before recursing; otherwise, we would instantiate an infinite
+//! tower of closure references.
+
+use super::{
+    super::{expr::map::HasMeta, ite},
+    call, constraint, program, stm, typedef, var,
+    view::{self, assertion, pattern},
+};
+
+impl<'inp, M, V> HasMeta<M> for program::Program<'inp, M, V> {
+    type Output<N> = program::Program<'inp, N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(program::Program {
+            name: self.name.try_map_direct_meta(&mut f)?,
+            decls: self.decls.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<'inp, M, V> HasMeta<M> for program::Decl<'inp, M, V> {
+    type Output<N> = program::Decl<'inp, N, V>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(match self {
+            Self::Constraint(c) => program::Decl::Constraint(c.try_map_meta(f)?),
+            Self::Procedure(p) => program::Decl::Procedure(p.try_map_meta(f)?),
+            Self::Type(t) => program::Decl::Type(t.try_map_meta(f)?),
+            Self::Var(v) => program::Decl::Var(v.try_map_meta(f)?),
+            Self::View(v) => program::Decl::View(v.try_map_meta(f)?),
+        })
+    }
+}
+
+impl<'inp, M, V> HasMeta<M> for program::Procedure<'inp, M, V> {
+    type Output<N> = program::Procedure<'inp, N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(program::Procedure {
+            prototype: self.prototype.try_map_meta(&mut f)?,
+            requires: self.requires.try_map_meta(&mut f)?,
+            ensures: self.ensures.try_map_meta(&mut f)?,
+            locals: self.locals.try_map_meta(&mut f)?,
+            body: self.body.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<'inp, M, Arg: HasMeta<M>> HasMeta<M> for call::Generic<'inp, M, Arg> {
+    type Output<N> = call::Generic<'inp, N, Arg::Output<N>>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(call::Generic {
+            name: self.name.try_map_direct_meta(&mut f)?,
+            args: self.args.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<'inp, M, V> HasMeta<M> for call::Parameter<'inp, M, V> {
+    type Output<N> = call::Parameter<'inp, N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(call::Parameter {
+            name: self.name.try_map_direct_meta(&mut f)?,
+            ty: self.ty.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<'inp, M, V> HasMeta<M> for typedef::Decl<'inp, M, V> {
+    type Output<N> = typedef::Decl<'inp, N, V>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(typedef::Decl {
+            defs: self.defs.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<'inp, M, V> HasMeta<M> for typedef::Def<'inp, M, V> {
+    type Output<N> = typedef::Def<'inp, N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(typedef::Def {
+            name: self.name.try_map_direct_meta(&mut f)?,
+            ty: self.ty.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<'inp, M, V> HasMeta<M> for var::Decl<'inp, M, V> {
+    type Output<N> = var::Decl<'inp, N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(var::Decl {
+            scope: self.scope.try_map_direct_meta(&mut f)?,
+            vars: self.vars.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<'inp, M, V> HasMeta<M> for view::Decl<'inp, M, V> {
+    type Output<N> = view::Decl<'inp, N, V>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(view::Decl {
+            contents: self.contents.try_map_meta(f)?,
+        })
+    }
+}
+
+//
+// Constraints
+//
+
+impl<M, V> HasMeta<M> for constraint::Decl<M, V> {
+    type Output<N> = constraint::Decl<N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(constraint::Decl {
+            views: self.views.try_map_meta(&mut f)?,
+            body: self.body.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<M, V> HasMeta<M> for constraint::Constraint<M, V> {
+    type Output<N> = constraint::Constraint<N, V>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(match self {
+            Self::Entails(e) => constraint::Constraint::Entails(constraint::Entailment {
+                entails: e.entails.try_map_meta(f)?,
+            }),
+            Self::Exact(d) => constraint::Constraint::Exact(constraint::Definition {
+                definition: d.definition.try_map_meta(f)?,
+            }),
+            Self::Indefinite => constraint::Constraint::Indefinite,
+            Self::Fails => constraint::Constraint::Fails,
+        })
+    }
+}
+
+impl<M, V> HasMeta<M> for pattern::Pattern<M, V> {
+    type Output<N> = pattern::Pattern<N, V>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(pattern::Pattern {
+            contents: self.contents.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<M, V> HasMeta<M> for pattern::Atom<M, V> {
+    type Output<N> = pattern::Atom<N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(pattern::Atom {
+            name: self.name.try_map_direct_meta(&mut f)?,
+            args: self.args.try_map_meta(&mut f)?,
+            iterator: self.iterator.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<M, V> HasMeta<M> for pattern::Argument<M, V> {
+    type Output<N> = pattern::Argument<N, V>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(match self {
+            Self::Wildcard => pattern::Argument::Wildcard,
+            Self::Expr(e) => pattern::Argument::Expr(e.try_map_meta(f)?),
+        })
+    }
+}
+
+//
+// Statements
+//
+
+impl<'inp, M, V> HasMeta<M> for stm::Triple<'inp, M, V> {
+    type Output<N> = stm::Triple<'inp, N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(stm::Triple {
+            pre: self.pre.try_map_meta(&mut f)?,
+            stm: self.stm.try_map_meta(&mut f)?,
+            post: self.post.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<'inp, M, V> HasMeta<M> for stm::Stm<'inp, M, V> {
+    type Output<N> = stm::Stm<'inp, N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        let f: &mut dyn FnMut(M) -> Result<N, E> = &mut f;
+        Ok(match self {
+            Self::Assign(a) => stm::Stm::Assign(stm::Assign {
+                lvalue: a.lvalue.try_map_meta(&mut *f)?,
+                rvalue: a.rvalue.try_map_meta(f)?,
+            }),
+            Self::Atomic(a) => stm::Stm::Atomic(stm::Atomic {
+                syntax: a.syntax,
+                body: a.body.try_map_meta(f)?,
+            }),
+            Self::Block(l) => stm::Stm::Block(l.try_map_meta(f)?),
+            Self::Call(c) => stm::Stm::Call(c.try_map_meta(f)?),
+            Self::Ite(i) => stm::Stm::Ite(i.try_map_meta(f)?),
+            Self::Nop => stm::Stm::Nop,
+            Self::Primitive(p) => stm::Stm::Primitive(stm::Primitive {
+                primitive: p.primitive.try_map_direct_meta(&mut *f)?,
+                args: p.args.try_map_meta(f)?,
+            }),
+            Self::Repeat(r) => stm::Stm::Repeat(stm::Repeat {
+                invariant: r.invariant.try_map_meta(&mut *f)?,
+                body: r.body.try_map_meta(&mut *f)?,
+                cond: r.cond.try_map_meta(f)?,
+            }),
+            Self::While(w) => stm::Stm::While(stm::While {
+                cond: w.cond.try_map_meta(&mut *f)?,
+                invariant: w.invariant.try_map_meta(&mut *f)?,
+                body: w.body.try_map_meta(f)?,
+            }),
+        })
+    }
+}
+
+impl<M, B: HasMeta<M>, C: HasMeta<M>> HasMeta<M> for ite::Ite<M, B, C> {
+    type Output<N> = ite::Ite<N, B::Output<N>, C::Output<N>>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(ite::Ite {
+            true_branch: self.true_branch.try_map_meta(&mut f)?,
+            cond: self.cond.try_map_meta(&mut f)?,
+            false_branch: self.false_branch.try_map_meta(f)?,
+        })
+    }
+}
+
+impl<M, C: HasMeta<M>> HasMeta<M> for ite::Condition<C> {
+    type Output<N> = ite::Condition<C::Output<N>>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(match self {
+            Self::Nondeterministic => ite::Condition::Nondeterministic,
+            Self::Deterministic(c) => ite::Condition::Deterministic(c.try_map_meta(f)?),
+        })
+    }
+}
+
+//
+// View assertions
+//
+
+impl<'inp, M, V> HasMeta<M> for assertion::Assertion<'inp, M, V> {
+    type Output<N> = assertion::Assertion<'inp, N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        use assertion::Assertion;
+
+        let f: &mut dyn FnMut(M) -> Result<N, E> = &mut f;
+        Ok(match self {
+            Self::Atom(a) => Assertion::Atom(a.try_map_meta(f)?),
+            Self::Emp => Assertion::Emp,
+            Self::Guarded(g) => Assertion::Guarded(assertion::Guarded {
+                guard: g.guard.try_map_meta(&mut *f)?,
+                view: g.view.try_map_meta(f)?,
+            }),
+            Self::Ite(i) => Assertion::Ite(i.try_map_meta(f)?),
+            Self::Iterated(i) => Assertion::Iterated(i.try_map_meta(f)?),
+            Self::Join(l, r) => Assertion::Join(l.try_map_meta(&mut *f)?, r.try_map_meta(f)?),
+            Self::Local(l) => Assertion::Local(l.try_map_meta(f)?),
+        })
+    }
+}
+
+impl<M, V, T: HasMeta<M>> HasMeta<M> for view::Iterated<M, V, T> {
+    type Output<N> = view::Iterated<N, V, T::Output<N>>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(view::Iterated {
+            item: self.item.try_map_meta(&mut f)?,
+            iterator: self.iterator.try_map_meta(f)?,
+        })
+    }
+}
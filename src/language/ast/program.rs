@@ -1,6 +1,10 @@
 //! Programs, procedures, and declarations.
 
-use super::{super::tagged::Tagged, call::Prototype, constraint, stm, var, view};
+use super::{
+    super::tagged::Tagged,
+    call::{Parameter, Prototype},
+    constraint, stm, typedef, var, view,
+};
 
 /// A program.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -16,7 +20,7 @@ pub struct Program<'inp, M, V> {
 ///
 /// An empty name is not syntactically valid, but we assume that users of the default program will
 /// replace it.
-impl<'inp, M: Default, V> Default for Program<'inp, M, V> {
+impl<M: Default, V> Default for Program<'_, M, V> {
     fn default() -> Self {
         Self {
             name: Tagged::default(),
@@ -33,6 +37,8 @@ pub enum Decl<'inp, M, V> {
     Constraint(constraint::Decl<M, V>),
     /// A procedure declaration.
     Procedure(Procedure<'inp, M, V>),
+    /// A type declaration.
+    Type(typedef::Decl<'inp, M, V>),
     /// A variable declaration.
     Var(var::Decl<'inp, M, V>),
     /// A view declaration.
@@ -45,14 +51,23 @@ pub enum Decl<'inp, M, V> {
 pub struct Procedure<'inp, M, V> {
     /// The procedure prototype.
     pub prototype: Tagged<M, Prototype<'inp, M, V>>,
+    /// The pre-condition that every call must satisfy, if any.
+    pub requires: stm::TripleAssertion<'inp, M, V>,
+    /// The post-condition that every call may assume, if any.
+    pub ensures: stm::TripleAssertion<'inp, M, V>,
+    /// The local variables of the procedure, modelled as parameters.
+    pub locals: Vec<Tagged<M, Parameter<'inp, M, V>>>,
     /// The body, as a list of tagged statement triples.
     pub body: stm::List<'inp, M, V>,
 }
 
-impl<'inp, M: Default, V> Default for Procedure<'inp, M, V> {
+impl<M: Default, V> Default for Procedure<'_, M, V> {
     fn default() -> Self {
         Self {
             prototype: Tagged::default(),
+            requires: None,
+            ensures: None,
+            locals: vec![],
             body: vec![],
         }
     }
@@ -1,7 +1,7 @@
 //! Statements.
 
 use super::{
-    super::{expr, tagged::Tagged},
+    super::{expr, primitive, tagged::Tagged},
     call, ite, view,
 };
 
@@ -22,24 +22,8 @@ pub struct Triple<'inp, M, V> {
     pub post: TripleAssertion<'inp, M, V>,
 }
 
-impl<'inp, M, V> Triple<'inp, M, V> {
-    /// Mutably borrows the first assertion in the triple that is not currently populated.
-    ///
-    /// The main use of this is to fill assertions while parsing.
-    #[must_use]
-    pub fn first_empty_assertion_mut(&mut self) -> Option<&mut TripleAssertion<'inp, M, V>> {
-        if self.pre.is_none() {
-            Some(&mut self.pre)
-        } else if self.post.is_none() {
-            Some(&mut self.post)
-        } else {
-            None
-        }
-    }
-}
-
 /// The default triple is a no-op with no pre- or post-condition.
-impl<'inp, M: Default, V> Default for Triple<'inp, M, V> {
+impl<M: Default, V> Default for Triple<'_, M, V> {
     fn default() -> Self {
         Self {
             pre: None,
@@ -60,7 +44,7 @@ pub enum Stm<'inp, M, V> {
     /// The semantics of an atomic statement is that all of the statements contained within are
     /// combined into one effective statement for the purposes of verification.  In other words,
     /// there is no interference permitted at any point within.
-    Atomic(List<'inp, M, V>),
+    Atomic(Atomic<'inp, M, V>),
     /// A non-atomic block statement.
     ///
     /// The semantics of a block statement is that all of the statements contained within are
@@ -77,6 +61,53 @@ pub enum Stm<'inp, M, V> {
     /// A no-operation statement.
     #[default]
     Nop,
+    /// A call to a built-in atomic primitive.
+    ///
+    /// The parser reads these as ordinary calls; the binder recognises them by name.  The
+    /// semantics of a primitive call is given by the primitive.
+    Primitive(Primitive<M, V>),
+    /// A repeat-until loop.
+    ///
+    /// The semantics of a repeat-until loop is that its invariant holds before every iteration of
+    /// its body: on entry to the loop, and whenever the condition is false after an iteration.
+    Repeat(Repeat<'inp, M, V>),
+    /// A while loop.
+    ///
+    /// The semantics of a while loop is that its invariant holds whenever the loop evaluates its
+    /// condition: on entry to the loop, and after every iteration of its body.
+    While(While<'inp, M, V>),
+}
+
+/// An atomic block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Atomic<'inp, M, V> {
+    /// The syntax used to write the block.
+    pub syntax: AtomicSyntax,
+    /// The statements inside the block.
+    pub body: List<'inp, M, V>,
+}
+
+/// The default atomic block is empty, and uses angle brackets.
+impl<M, V> Default for Atomic<'_, M, V> {
+    fn default() -> Self {
+        Self {
+            syntax: AtomicSyntax::default(),
+            body: vec![],
+        }
+    }
+}
+
+/// The two syntaxes for atomic blocks.
+///
+/// Both syntaxes have the same meaning; we record which one was used so that we can print the
+/// block back in the same way.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AtomicSyntax {
+    /// Angle brackets, as in `<| x := 1 |>`.
+    #[default]
+    Brackets,
+    /// Keywords, as in `atomic x := 1 end`.
+    Keywords,
 }
 
 /// An assignment.
@@ -104,5 +135,61 @@ impl<M: Default, V> Default for Assign<M, V> {
     }
 }
 
+/// A call to a built-in atomic primitive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Primitive<M, V> {
+    /// The primitive being called.
+    pub primitive: Tagged<M, primitive::Primitive>,
+    /// The arguments of the call.
+    pub args: Vec<expr::Tagged<M, V>>,
+}
+
+/// Type of loop invariants.
+pub type Invariant<'inp, M, V> = Tagged<M, view::Assertion<'inp, M, V>>;
+
+/// A while loop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct While<'inp, M, V> {
+    /// The condition under which the loop runs its body.
+    pub cond: Tagged<M, ite::Condition<M, V>>,
+    /// The loop invariant.
+    pub invariant: Invariant<'inp, M, V>,
+    /// The body of the loop.
+    pub body: Tagged<M, Box<Stm<'inp, M, V>>>,
+}
+
+/// The default while loop is a nondeterministic loop with an empty invariant and body.
+impl<M: Default, V> Default for While<'_, M, V> {
+    fn default() -> Self {
+        Self {
+            cond: Tagged::default(),
+            invariant: Tagged::with_default(view::Assertion::Emp),
+            body: Tagged::default(),
+        }
+    }
+}
+
+/// A repeat-until loop.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repeat<'inp, M, V> {
+    /// The loop invariant.
+    pub invariant: Invariant<'inp, M, V>,
+    /// The body of the loop.
+    pub body: List<'inp, M, V>,
+    /// The condition under which the loop exits.
+    pub cond: Tagged<M, ite::Condition<M, V>>,
+}
+
+/// The default repeat-until loop is a nondeterministic loop with an empty invariant and body.
+impl<M: Default, V> Default for Repeat<'_, M, V> {
+    fn default() -> Self {
+        Self {
+            invariant: Tagged::with_default(view::Assertion::Emp),
+            body: vec![],
+            cond: Tagged::default(),
+        }
+    }
+}
+
 /// Type of if-then-else statements.
 pub type Ite<'inp, M, V> = ite::Ite<M, V, Tagged<M, Box<Stm<'inp, M, V>>>>;
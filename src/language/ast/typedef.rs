@@ -0,0 +1,41 @@
+//! AST nodes for type definitions.
+
+use super::{
+    super::{tagged::Tagged, typing::Type},
+    var::Identifier,
+};
+
+/// A type declaration, defining one or more named types.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Decl<'inp, M, V> {
+    /// Type definitions in the declaration.
+    pub defs: Vec<Tagged<M, Def<'inp, M, V>>>,
+}
+
+/// The default decl is empty.
+///
+/// We hand-roll this implementation to avoid unnecessary bounds on `M` and `V`.
+impl<M, V> Default for Decl<'_, M, V> {
+    fn default() -> Self {
+        Self { defs: vec![] }
+    }
+}
+
+/// A definition of a named type, in the form `Name = type`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Def<'inp, M, V> {
+    /// Name of the type.
+    pub name: Tagged<M, Identifier<'inp>>,
+    /// Definition of the type.
+    pub ty: Tagged<M, Type<M, V>>,
+}
+
+/// The default definition names the integer type with a default name.
+impl<M: Default, V> Default for Def<'_, M, V> {
+    fn default() -> Self {
+        Self {
+            name: Tagged::default(),
+            ty: Tagged::with_default(Type::INT),
+        }
+    }
+}
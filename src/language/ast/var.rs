@@ -21,7 +21,7 @@ pub struct Decl<'inp, M, V> {
 /// The default decl is an empty shared-scope decl.
 ///
 /// We hand-roll this implementation to avoid unnecessary bounds on `V`.
-impl<'inp, M: Default, V> Default for Decl<'inp, M, V> {
+impl<M: Default, V> Default for Decl<'_, M, V> {
     fn default() -> Self {
         Self {
             scope: Tagged::default(),
@@ -34,7 +34,7 @@ impl<'inp, M: Default, V> Default for Decl<'inp, M, V> {
 pub type Identifier<'inp> = std::borrow::Cow<'inp, str>;
 
 /// Identifiers are variable references.
-impl<'inp> Variable for Identifier<'inp> {
+impl Variable for Identifier<'_> {
     fn to_symbol(&self) -> Symbol {
         Symbol::new(self)
     }
@@ -8,6 +8,7 @@ use super::super::{expr::Expr, tagged::Tagged};
 
 pub mod assertion;
 pub mod decl;
+pub mod normal;
 pub mod pattern;
 
 /// Wraps a view component with an iterator.
@@ -2,7 +2,7 @@
 
 use super::{
     super::{
-        super::{expr, tagged::Tagged},
+        super::{expr, ite::Condition, tagged::Tagged},
         call, ite,
     },
     Iterated,
@@ -23,12 +23,13 @@ pub enum Assertion<'inp, M, V> {
     /// An if-then-else construct.
     ///
     /// This is semantically equivalent to the join of `true_view` guarded by `cond`, and
-    /// `false_view` guarded by the negation of `cond`.  Normalising
+    /// `false_view` guarded by the negation of `cond`.  Normalising (see [`super::normal`]) turns it
+    /// into that join.
     Ite(Ite<'inp, M, V>),
     /// An iteration.
     ///
     /// Entire atoms can be iterated, with the result being flattened into individual guarded
-    /// iterated views later on.
+    /// iterated views on normalisation (see [`super::normal`]).
     Iterated(Iterated<M, V, Box<Assertion<'inp, M, V>>>),
     /// A join of two assertions.
     Join(Box<Assertion<'inp, M, V>>, Box<Assertion<'inp, M, V>>),
@@ -38,6 +39,73 @@ pub enum Assertion<'inp, M, V> {
     Local(expr::Tagged<M, V>),
 }
 
+impl<'inp, M, V> Assertion<'inp, M, V> {
+    /// Substitutes every variable in this assertion with the expression `f` returns for it.
+    #[must_use]
+    pub fn subst<U>(
+        self,
+        f: &mut impl FnMut(Tagged<M, V>) -> expr::Expr<M, U>,
+    ) -> Assertion<'inp, M, U> {
+        match self {
+            Self::Atom(a) => Assertion::Atom(a.map(|a| call::Generic {
+                name: a.name,
+                args: a.args.into_iter().map(|e| e.map(|e| e.subst(f))).collect(),
+            })),
+            Self::Emp => Assertion::Emp,
+            Self::Guarded(g) => Assertion::Guarded(Guarded {
+                guard: g.guard.subst(f),
+                view: Box::new(g.view.subst(f)),
+            }),
+            Self::Ite(i) => Assertion::Ite(ite::Ite {
+                true_branch: Box::new(i.true_branch.subst(f)),
+                cond: i.cond.map(|c| match c {
+                    Condition::Deterministic(c) => Condition::Deterministic(c.subst(f)),
+                    Condition::Nondeterministic => Condition::Nondeterministic,
+                }),
+                false_branch: Box::new(i.false_branch.subst(f)),
+            }),
+            Self::Iterated(i) => Assertion::Iterated(Iterated {
+                item: Box::new(i.item.subst(f)),
+                iterator: i.iterator.map(|e| e.subst(f)),
+            }),
+            Self::Join(l, r) => Assertion::Join(Box::new(l.subst(f)), Box::new(r.subst(f))),
+            Self::Local(l) => Assertion::Local(l.map(|e| e.subst(f))),
+        }
+    }
+
+    /// Visits every variable in this assertion, with its tag, in left-to-right order.
+    pub fn for_each_tagged_var<'a>(&'a self, f: &mut impl FnMut(&'a Tagged<M, V>)) {
+        match self {
+            Self::Atom(a) => {
+                for arg in &a.item.args {
+                    arg.item.for_each_tagged_var(f);
+                }
+            }
+            Self::Emp => (),
+            Self::Guarded(g) => {
+                g.guard.for_each_tagged_var(f);
+                g.view.for_each_tagged_var(f);
+            }
+            Self::Ite(i) => {
+                i.true_branch.for_each_tagged_var(f);
+                if let Condition::Deterministic(c) = &i.cond.item {
+                    c.for_each_tagged_var(f);
+                }
+                i.false_branch.for_each_tagged_var(f);
+            }
+            Self::Iterated(i) => {
+                i.item.for_each_tagged_var(f);
+                i.iterator.item.for_each_tagged_var(f);
+            }
+            Self::Join(l, r) => {
+                l.for_each_tagged_var(f);
+                r.for_each_tagged_var(f);
+            }
+            Self::Local(l) => l.item.for_each_tagged_var(f),
+        }
+    }
+}
+
 /// A guarded view.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Guarded<'inp, M, V> {
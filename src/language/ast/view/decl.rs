@@ -15,7 +15,7 @@ pub struct Decl<'inp, M, V> {
 /// The default declaration is an empty one.
 ///
 /// We can't derive this because that would introduce unnecessary constraints on `M` and `V`.
-impl<'inp, M, V> Default for Decl<'inp, M, V> {
+impl<M, V> Default for Decl<'_, M, V> {
     fn default() -> Self {
         Self { contents: vec![] }
     }
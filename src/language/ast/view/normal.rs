@@ -0,0 +1,306 @@
+//! Normal forms of view assertions.
+//!
+//! A view assertion in normal form is a list of guarded, iterated atoms, together with one local
+//! assertion.  Normalisation pushes guards through joins, conditionals, and iterations, multiplies
+//! nested iterators, and merges atoms that differ only in their iterators by summing those
+//! iterators.  This only preserves the meaning of the assertion if its iterators are non-negative,
+//! so the normal form also records the iterators that might not be, for later checking.
+//!
+//! For example, `[g] (A * (B <[c]> emp)[2]) * [g] A` normalises to `[g] A[2] * [g and c] B[2]`.
+
+use num_bigint::{BigInt, Sign};
+
+use super::{
+    super::super::{
+        expr::{bop, map::HasMeta, Constant, Expr},
+        ite::Condition,
+        tagged::Tagged,
+    },
+    assertion::{Assertion, Atom},
+    Iterated,
+};
+
+/// Something present under a guard.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Guarded<M, V, T> {
+    /// The guard.
+    pub guard: Expr<M, V>,
+    /// The guarded item.
+    pub item: T,
+}
+
+/// A guarded, iterated atom.
+pub type GuardedAtom<'inp, M, V> = Guarded<M, V, Iterated<M, V, Tagged<M, Atom<'inp, M, V>>>>;
+
+/// A guarded iterator.
+pub type GuardedIterator<M, V> = Guarded<M, V, Tagged<M, Expr<M, V>>>;
+
+/// A view assertion in normal form.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Normal<'inp, M, V> {
+    /// The guarded, iterated atoms, in order of first appearance.
+    pub atoms: Vec<GuardedAtom<'inp, M, V>>,
+    /// The conjunction of the local assertions, each already implied by its guard.
+    pub local: Expr<M, V>,
+    /// The iterators of the assertion that aren't non-negative literals, as written, with their
+    /// guards.
+    ///
+    /// Normalisation multiplies and sums iterators, which only agrees with the assertion if these
+    /// iterators are non-negative.
+    pub iterators: Vec<GuardedIterator<M, V>>,
+}
+
+/// The default normal form is `emp`.
+impl<M: Default, V> Default for Normal<'_, M, V> {
+    fn default() -> Self {
+        Self {
+            atoms: vec![],
+            local: Expr::bool(true),
+            iterators: vec![],
+        }
+    }
+}
+
+/// An error that prevents normalisation.
+#[derive(Debug, Clone, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error<M> {
+    /// The assertion contains a conditional with a nondeterministic condition.
+    #[error("views with nondeterministic conditions have no normal form")]
+    Nondeterministic { loc: M },
+}
+
+/// Normalises `assertion`.
+///
+/// Atoms merge if their names, arguments, and guards are equal, ignoring metadata.  Atoms whose
+/// iterators are literally zero, or whose guards are literally false, disappear.
+///
+/// # Errors
+///
+/// Fails if `assertion` contains a conditional with a nondeterministic condition.
+pub fn normalise<'inp, M: Clone + Default, V: Clone + Eq>(
+    assertion: &Assertion<'inp, M, V>,
+) -> Result<Normal<'inp, M, V>, Error<M>> {
+    let mut result = Normal::default();
+    let once = Tagged::with_default(Expr::i64(1));
+    normalise_into(&mut result, assertion, &Expr::bool(true), &once)?;
+    Ok(result)
+}
+
+fn normalise_into<'inp, M: Clone + Default, V: Clone + Eq>(
+    result: &mut Normal<'inp, M, V>,
+    assertion: &Assertion<'inp, M, V>,
+    guard: &Expr<M, V>,
+    iterator: &Tagged<M, Expr<M, V>>,
+) -> Result<(), Error<M>> {
+    match assertion {
+        Assertion::Atom(a) => result.add(Guarded {
+            guard: guard.clone(),
+            item: Iterated {
+                item: a.clone(),
+                iterator: iterator.clone(),
+            },
+        }),
+        Assertion::Emp => (),
+        Assertion::Guarded(g) => {
+            let guard = Expr::conjunction([guard.clone(), g.guard.clone()]);
+            normalise_into(result, &g.view, &guard, iterator)?;
+        }
+        Assertion::Ite(i) => {
+            let Condition::Deterministic(cond) = &i.cond.item else {
+                return Err(Error::Nondeterministic {
+                    loc: i.cond.meta.clone(),
+                });
+            };
+            let true_guard = Expr::conjunction([guard.clone(), cond.clone()]);
+            let false_guard = Expr::conjunction([guard.clone(), Expr::not(cond.clone())]);
+            normalise_into(result, &i.true_branch, &true_guard, iterator)?;
+            normalise_into(result, &i.false_branch, &false_guard, iterator)?;
+        }
+        Assertion::Iterated(i) => {
+            if int(&i.iterator.item).is_none_or(|n| n.sign() == Sign::Minus) {
+                result.iterators.push(Guarded {
+                    guard: guard.clone(),
+                    item: i.iterator.clone(),
+                });
+            }
+            let iterator = arith(iterator, bop::Arith::Mul, &i.iterator);
+            normalise_into(result, &i.item, guard, &iterator)?;
+        }
+        Assertion::Join(l, r) => {
+            normalise_into(result, l, guard, iterator)?;
+            normalise_into(result, r, guard, iterator)?;
+        }
+        Assertion::Local(l) => {
+            let local = if is_true(guard) {
+                l.item.clone()
+            } else {
+                Expr::bop(guard.clone(), bop::Bool::Implies, l.item.clone())
+            };
+            result.local = Expr::conjunction([result.local.clone(), local]);
+        }
+    }
+    Ok(())
+}
+
+impl<'inp, M: Clone + Default, V: Clone + Eq> Normal<'inp, M, V> {
+    /// Adds `atom` to this normal form, merging it with any equal atom.
+    fn add(&mut self, atom: GuardedAtom<'inp, M, V>) {
+        if is_false(&atom.guard) || is_zero(&atom.item.iterator.item) {
+            return;
+        }
+        let existing = self
+            .atoms
+            .iter_mut()
+            .find(|a| same(&a.guard, &atom.guard) && same(&a.item.item.item, &atom.item.item.item));
+        match existing {
+            Some(a) => {
+                a.item.iterator = arith(&a.item.iterator, bop::Arith::Add, &atom.item.iterator);
+            }
+            None => self.atoms.push(atom),
+        }
+    }
+}
+
+/// Checks whether `x` and `y` are equal, ignoring metadata.
+fn same<M: Clone, T: Clone + HasMeta<M>>(x: &T, y: &T) -> bool
+where
+    T::Output<()>: Eq,
+{
+    x.clone().map_meta(|_| ()) == y.clone().map_meta(|_| ())
+}
+
+/// Applies `op` to `lhs` and `rhs`, evaluating it if both are integer literals and dropping
+/// identities.
+///
+/// The result keeps the metadata of `rhs`, unless `rhs` is a literal and `lhs` is not.
+fn arith<M: Clone + Default, V: Clone>(
+    lhs: &Tagged<M, Expr<M, V>>,
+    op: bop::Arith,
+    rhs: &Tagged<M, Expr<M, V>>,
+) -> Tagged<M, Expr<M, V>> {
+    let unit = BigInt::from(match op {
+        bop::Arith::Mul => 1,
+        _ => 0,
+    });
+    match (int(&lhs.item), int(&rhs.item)) {
+        (Some(l), Some(r)) => Tagged::new(
+            rhs.meta.clone(),
+            Expr::int(match op {
+                bop::Arith::Mul => l * r,
+                _ => l + r,
+            }),
+        ),
+        (Some(l), _) if *l == unit => rhs.clone(),
+        (_, Some(r)) if *r == unit => lhs.clone(),
+        (_, Some(_)) => Tagged::new(
+            lhs.meta.clone(),
+            Expr::bop(lhs.item.clone(), op, rhs.item.clone()),
+        ),
+        _ => Tagged::new(
+            rhs.meta.clone(),
+            Expr::bop(lhs.item.clone(), op, rhs.item.clone()),
+        ),
+    }
+}
+
+/// Gets the value of `expr` if it is an integer literal.
+fn int<M, V>(expr: &Expr<M, V>) -> Option<&BigInt> {
+    match expr.as_literal() {
+        Some(Constant::Int(i)) => Some(i),
+        _ => None,
+    }
+}
+
+fn is_true<M, V>(expr: &Expr<M, V>) -> bool {
+    expr.as_literal() == Some(&Constant::Bool(true))
+}
+
+fn is_false<M, V>(expr: &Expr<M, V>) -> bool {
+    expr.as_literal() == Some(&Constant::Bool(false))
+}
+
+fn is_zero<M, V>(expr: &Expr<M, V>) -> bool {
+    expr.as_literal().is_some_and(Constant::is_zero)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::super::{program::Decl, var::Identifier},
+        *,
+    };
+
+    /// Normalises the precondition of the procedure in `view`, and applies `f` to the result.
+    fn with_normal<T>(
+        view: &str,
+        f: impl FnOnce(&Normal<'_, Option<pest::Span<'_>>, Identifier>) -> T,
+    ) -> T {
+        let source =
+            format!("program T; procedure P requires {{ {view} }}; begin nop end; begin end.");
+        let ast = crate::parser::parse(&source).unwrap();
+        let Some(Decl::Procedure(proc)) = ast.item.decls.iter().map(|d| &d.item).next() else {
+            panic!("expected a procedure");
+        };
+        let requires = &proc.requires.as_ref().unwrap().item;
+        f(&normalise(requires).unwrap())
+    }
+
+    /// Normalises the precondition of the procedure in `view`, rendering each atom as
+    /// `guard: name(args)[iterator]`, followed by the local assertion.
+    fn normalised(view: &str) -> Vec<String> {
+        with_normal(view, |normal| {
+            let mut result: Vec<String> = (normal.atoms.iter())
+                .map(|a| {
+                    let atom = &a.item.item.item;
+                    let args: Vec<String> = atom.args.iter().map(|e| e.item.to_string()).collect();
+                    format!(
+                        "{}: {}({})[{}]",
+                        a.guard,
+                        atom.name.item,
+                        args.join(", "),
+                        a.item.iterator.item
+                    )
+                })
+                .collect();
+            result.push(normal.local.to_string());
+            result
+        })
+    }
+
+    /// Tests that guards distribute over joins and conditionals, that iterators multiply, and
+    /// that equal atoms merge.
+    #[test]
+    fn normalise_guards() {
+        assert_eq!(
+            vec![
+                "x: V(1)[3]",
+                "x and y: W()[2]",
+                "x and not y: W()[2 * n]",
+                "true"
+            ],
+            normalised("[x] (V(1) * (W <[y]> W[n]))[2] * [x] V(1)")
+        );
+    }
+
+    /// Tests that locals become implications from their guards, and that atoms that are absent
+    /// disappear.
+    #[test]
+    fn normalise_locals() {
+        assert_eq!(
+            vec!["true: V(x)[n + 1]", "x implies y"],
+            normalised("V(x)[n] * [false] W * V(x) * W[0] * [x] @(y)")
+        );
+    }
+
+    /// Tests that iterators that might be negative are recorded with their guards, as written.
+    #[test]
+    fn normalise_iterators() {
+        let iterators = with_normal("[x] (V[n] * W[2])[m - 1] * V * V[-1]", |normal| {
+            (normal.iterators.iter())
+                .map(|i| format!("{}: {}", i.guard, i.item.item))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(vec!["x: m - 1", "x: n", "true: -1"], iterators);
+    }
+}
@@ -5,19 +5,76 @@ use std::collections::HashMap;
 use egg::Symbol;
 use pest::Span;
 
-use super::typing;
+use super::{
+    super::diagnostic::{self, code, Diagnostic, FileId, ToDiagnostic},
+    ast::{constraint, stm},
+    tagged::Tagged,
+    typing,
+};
 
-mod var;
+pub mod var;
 
 /// A full PVC program in control-flow graph form.
 ///
 /// This structure contains an entire program as a series of symbol tables.
 #[derive(Debug, Clone, Eq, PartialEq)]
-pub struct Program<M> {
+pub struct Program<'inp, M> {
     /// Named types defined in this program.
     pub types: HashMap<Symbol, Type<M>>,
     /// Variables defined in this program.
     pub variables: var::Map<M>,
+    /// Views declared in this program.
+    pub views: HashMap<Symbol, View<M>>,
+    /// Procedures defined in this program, in declaration order.
+    pub procedures: Vec<Procedure<'inp, M>>,
+    /// Constraints defined in this program, in declaration order.
+    pub constraints: Vec<Tagged<M, Constraint<M>>>,
+}
+
+/// The default program contains only the intrinsic types.
+impl<M> Default for Program<'_, M> {
+    fn default() -> Self {
+        let types = [typing::Prim::Int, typing::Prim::Bool]
+            .into_iter()
+            .map(|p| (p.to_symbol(), Type::intrinsic(typing::Type::Prim(p))))
+            .collect();
+
+        Self {
+            types,
+            variables: var::Map::default(),
+            views: HashMap::new(),
+            procedures: vec![],
+            constraints: vec![],
+        }
+    }
+}
+
+impl<'inp, M> Program<'inp, M> {
+    /// Expands every reference to a named type in `ty` into its definition in the typing table.
+    ///
+    /// The binder rejects cyclic type definitions, so expansion terminates on bound programs.
+    #[must_use]
+    pub fn expand(&self, ty: &typing::Type<M, Symbol>) -> typing::Type<M, Symbol>
+    where
+        M: Clone,
+    {
+        ty.expand(&|name| self.types.get(name).map(|t| &t.ty))
+    }
+
+    /// Looks up the type with symbol `sym` in the typing table, expanding any named types in it.
+    #[must_use]
+    pub fn type_of(&self, sym: &Symbol) -> Option<typing::Type<M, Symbol>>
+    where
+        M: Clone,
+    {
+        self.types.get(sym).map(|t| self.expand(&t.ty))
+    }
+
+    /// Looks up the procedure with name `name`.
+    #[must_use]
+    pub fn procedure(&self, name: Symbol) -> Option<&Procedure<'inp, M>> {
+        self.procedures.iter().find(|p| p.name == name)
+    }
 }
 
 /// An unambiguous path to a particular block in a program.
@@ -27,22 +84,43 @@ pub enum BlockRef {
     Global,
     /// Local to the procedure with the given name, and indirect through the given block path.
     Proc(Symbol, Vec<usize>),
+    /// Local to the constraint with the given index.
+    ///
+    /// Constraint blocks contain the variables bound by view patterns.
+    Constraint(usize),
 }
 
 impl BlockRef {
-    /// Gets the parent scope of the
+    /// Gets the parent scope of the block, if any.
+    #[must_use]
     pub fn parent(&self) -> Option<BlockRef> {
         match self {
             Self::Global => None,
-            Self::Proc(proc, blocks) => Some(proc_parent(proc, blocks)),
+            Self::Proc(proc, blocks) => Some(proc_parent(*proc, blocks)),
+            Self::Constraint(_) => Some(Self::Global),
+        }
+    }
+
+    /// Gets the block reference of the `index`-th child block of this block.
+    ///
+    /// Only procedure blocks have children; other blocks return themselves.
+    #[must_use]
+    pub fn child(&self, index: usize) -> BlockRef {
+        match self {
+            Self::Proc(proc, blocks) => {
+                let mut blocks = blocks.clone();
+                blocks.push(index);
+                Self::Proc(*proc, blocks)
+            }
+            b => b.clone(),
         }
     }
 }
 
-fn proc_parent(proc: &Symbol, blocks: &[usize]) -> BlockRef {
+fn proc_parent(proc: Symbol, blocks: &[usize]) -> BlockRef {
     let mut blocks = blocks.to_vec();
     if blocks.pop().is_some() {
-        BlockRef::Proc(*proc, blocks)
+        BlockRef::Proc(proc, blocks)
     } else {
         BlockRef::Global
     }
@@ -57,6 +135,57 @@ pub struct Type<M> {
     pub ty: typing::Type<M, Symbol>,
 }
 
+impl<M> Type<M> {
+    /// Constructs a record for a type intrinsic to the language.
+    #[must_use]
+    pub fn intrinsic(ty: typing::Type<M, Symbol>) -> Self {
+        Self {
+            origin: Origin::Intrinsic,
+            ty,
+        }
+    }
+}
+
+/// A view record.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct View<M> {
+    /// The origin of the view.
+    pub origin: Origin<M>,
+    /// The types of each parameter of the view, as indices into the typing table.
+    ///
+    /// Each type is tagged with the metadata of its appearance in the view's prototype.
+    pub parameters: Vec<Tagged<M, Symbol>>,
+}
+
+/// A procedure record.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Procedure<'inp, M> {
+    /// The origin of the procedure.
+    pub origin: Origin<M>,
+    /// The name of the procedure.
+    pub name: Symbol,
+    /// The parameters of the procedure, as references into the variable table.
+    pub parameters: Vec<var::Symbol>,
+    /// The local variables of the procedure, as references into the variable table.
+    pub locals: Vec<var::Symbol>,
+    /// The pre-condition of the procedure, with all variables resolved.
+    ///
+    /// Pre-conditions see the parameters of the procedure, but not its local variables.
+    pub requires: stm::TripleAssertion<'inp, M, var::Symbol>,
+    /// The post-condition of the procedure, with all variables resolved.
+    ///
+    /// As with pre-conditions, post-conditions don't see local variables.
+    pub ensures: stm::TripleAssertion<'inp, M, var::Symbol>,
+    /// The body of the procedure, with all variables resolved.
+    pub body: stm::List<'inp, M, var::Symbol>,
+}
+
+/// A constraint with all variables resolved.
+///
+/// The names of the views in the constraint's patterns are resolved to symbols in the global
+/// block.
+pub type Constraint<M> = constraint::Decl<M, var::Symbol>;
+
 /// Information about where some semantic element originated from.
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
 pub enum Origin<M> {
@@ -69,12 +198,176 @@ pub enum Origin<M> {
     Script(M),
 }
 
+impl<M> Origin<M> {
+    /// Borrows the script metadata of this origin, if any.
+    #[must_use]
+    pub fn meta(&self) -> Option<&M> {
+        if let Self::Script(m) = self {
+            Some(m)
+        } else {
+            None
+        }
+    }
+}
+
+/// Errors raised while constructing a control-flow graph.
 #[derive(Debug, Clone, thiserror::Error, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Error<'ast> {
-    #[error("Duplicate variable")]
+    /// A variable was declared twice in the same block.
+    #[error("duplicate variable `{name}`")]
     DuplicateVar {
         name: &'ast str,
-        first_loc: Span<'ast>,
-        second_loc: Span<'ast>,
+        first_loc: Option<Span<'ast>>,
+        second_loc: Option<Span<'ast>>,
+    },
+    /// A view was declared twice.
+    #[error("duplicate view `{name}`")]
+    DuplicateView {
+        name: &'ast str,
+        first_loc: Option<Span<'ast>>,
+        second_loc: Option<Span<'ast>>,
+    },
+    /// A procedure was declared twice.
+    #[error("duplicate procedure `{name}`")]
+    DuplicateProcedure {
+        name: &'ast str,
+        first_loc: Option<Span<'ast>>,
+        second_loc: Option<Span<'ast>>,
+    },
+    /// A named type was declared twice.
+    #[error("duplicate type `{name}`")]
+    DuplicateType {
+        name: &'ast str,
+        first_loc: Option<Span<'ast>>,
+        second_loc: Option<Span<'ast>>,
+    },
+    /// A variable was used without being declared in any enclosing block.
+    #[error("unresolved variable `{name}`")]
+    UnresolvedVar {
+        name: &'ast str,
+        loc: Option<Span<'ast>>,
+    },
+    /// A named type was used without being declared.
+    #[error("unresolved type `{name}`")]
+    UnresolvedType {
+        name: &'ast str,
+        loc: Option<Span<'ast>>,
+    },
+    /// A named type was defined in terms of itself.
+    #[error("type `{name}` is defined in terms of itself")]
+    CyclicType {
+        name: &'ast str,
+        loc: Option<Span<'ast>>,
+    },
+    /// A call named neither a procedure nor a primitive.
+    ///
+    /// Inside atomic blocks, only primitives can be called.
+    #[error("unresolved call `{name}`")]
+    UnresolvedCall {
+        name: &'ast str,
+        loc: Option<Span<'ast>>,
+        atomic: bool,
     },
 }
+
+impl<'ast> Error<'ast> {
+    /// Gets the primary location of this error, if any.
+    #[must_use]
+    pub fn loc(&self) -> Option<Span<'ast>> {
+        match self {
+            Self::DuplicateVar { second_loc, .. }
+            | Self::DuplicateView { second_loc, .. }
+            | Self::DuplicateProcedure { second_loc, .. }
+            | Self::DuplicateType { second_loc, .. } => *second_loc,
+            Self::UnresolvedVar { loc, .. }
+            | Self::UnresolvedType { loc, .. }
+            | Self::CyclicType { loc, .. }
+            | Self::UnresolvedCall { loc, .. } => *loc,
+        }
+    }
+}
+
+impl ToDiagnostic for Error<'_> {
+    fn to_diagnostic(&self, file: FileId) -> Diagnostic {
+        let code = match self {
+            Self::DuplicateVar { .. } => code::DUPLICATE_VAR,
+            Self::DuplicateView { .. } => code::DUPLICATE_VIEW,
+            Self::DuplicateProcedure { .. } => code::DUPLICATE_PROCEDURE,
+            Self::DuplicateType { .. } => code::DUPLICATE_TYPE,
+            Self::UnresolvedVar { .. } => code::UNRESOLVED_VAR,
+            Self::UnresolvedType { .. } => code::UNRESOLVED_TYPE,
+            Self::CyclicType { .. } => code::CYCLIC_TYPE,
+            Self::UnresolvedCall { .. } => code::UNRESOLVED_CALL,
+        };
+        let diagnostic = Diagnostic::error(self.to_string()).with_code(code);
+
+        match self {
+            Self::DuplicateVar {
+                first_loc,
+                second_loc,
+                ..
+            }
+            | Self::DuplicateView {
+                first_loc,
+                second_loc,
+                ..
+            }
+            | Self::DuplicateProcedure {
+                first_loc,
+                second_loc,
+                ..
+            }
+            | Self::DuplicateType {
+                first_loc,
+                second_loc,
+                ..
+            } => diagnostic
+                .with_label_opt(diagnostic::label(
+                    file,
+                    second_loc,
+                    true,
+                    "declared again here",
+                ))
+                .with_label_opt(diagnostic::label(
+                    file,
+                    first_loc,
+                    false,
+                    "first declared here",
+                )),
+            Self::UnresolvedVar { loc, .. } => diagnostic.with_label_opt(diagnostic::label(
+                file,
+                loc,
+                true,
+                "not declared in any enclosing scope",
+            )),
+            Self::UnresolvedType { loc, .. } => diagnostic.with_label_opt(diagnostic::label(
+                file,
+                loc,
+                true,
+                "not declared in any type declaration",
+            )),
+            Self::CyclicType { loc, .. } => diagnostic.with_label_opt(diagnostic::label(
+                file,
+                loc,
+                true,
+                "refers back to the type being defined",
+            )),
+            Self::UnresolvedCall { loc, atomic, .. } => {
+                diagnostic.with_label_opt(diagnostic::label(
+                    file,
+                    loc,
+                    true,
+                    if *atomic {
+                        "not a primitive; only primitives can be called inside atomic blocks"
+                    } else {
+                        "not a declared procedure or a primitive"
+                    },
+                ))
+            }
+        }
+    }
+}
+
+/// Shorthand for results over [Error].
+pub type Result<'ast, T> = std::result::Result<T, Error<'ast>>;
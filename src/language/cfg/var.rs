@@ -2,7 +2,7 @@
 //!
 //! Variables form a global symbol table resolved in one of the early binding passes.
 use super::{BlockRef, Origin};
-use std::collections::HashMap;
+use std::collections::{hash_map, HashMap};
 
 /// A variable map.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -11,11 +11,21 @@ pub struct Map<M> {
     contents: HashMap<Symbol, Record<M>>,
 }
 
+/// The default map is empty.
+impl<M> Default for Map<M> {
+    fn default() -> Self {
+        Self {
+            contents: HashMap::new(),
+        }
+    }
+}
+
 impl<M> Map<M> {
     /// Resolves `sym` to a variable.
     ///
     /// If `sym` cannot be found in the requested block, we recursively ascend in scope until we
     /// either find it or we reach the global variables and still fail to find it.
+    #[must_use]
     pub fn get(&self, sym: &Symbol) -> Option<(Symbol, &Record<M>)> {
         let mut sym = Some(sym.clone());
 
@@ -29,6 +39,32 @@ impl<M> Map<M> {
 
         None
     }
+
+    /// Gets the record for exactly `sym`, without ascending in scope.
+    #[must_use]
+    pub fn get_exact(&self, sym: &Symbol) -> Option<&Record<M>> {
+        self.contents.get(sym)
+    }
+
+    /// Declares `sym` as the variable described by `record`.
+    ///
+    /// # Errors
+    ///
+    /// Fails, returning the existing record, if `sym` has already been declared in the same block.
+    pub fn insert(&mut self, sym: Symbol, record: Record<M>) -> Result<(), &Record<M>> {
+        match self.contents.entry(sym) {
+            hash_map::Entry::Occupied(e) => Err(e.into_mut()),
+            hash_map::Entry::Vacant(e) => {
+                e.insert(record);
+                Ok(())
+            }
+        }
+    }
+
+    /// Iterates over all variables in the map, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Symbol, &Record<M>)> {
+        self.contents.iter()
+    }
 }
 
 /// A variable symbol.
@@ -45,7 +81,17 @@ pub struct Symbol {
 }
 
 impl Symbol {
+    /// Constructs a symbol for a variable named `name` in the global block.
+    #[must_use]
+    pub fn global(name: impl Into<egg::Symbol>) -> Self {
+        Self {
+            block: BlockRef::Global,
+            name: name.into(),
+        }
+    }
+
     /// Gets a symbol with the same name as this one, but at the parent block level (if any).
+    #[must_use]
     pub fn parent(&self) -> Option<Symbol> {
         self.block.parent().map(|block| Self {
             block,
@@ -54,6 +100,37 @@ impl Symbol {
     }
 }
 
+/// Symbols are displayed in their mangled form.
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.block {
+            BlockRef::Global => write!(f, "{}", self.name),
+            BlockRef::Proc(proc, blocks) => {
+                write!(f, "{proc}")?;
+                for b in blocks {
+                    write!(f, ".{b}")?;
+                }
+                write!(f, ".{}", self.name)
+            }
+            BlockRef::Constraint(index) => write!(f, "constraint{index}.{}", self.name),
+        }
+    }
+}
+
+/// Symbols are variables, mangled according to their block.
+impl super::super::var::Variable for Symbol {
+    fn to_symbol(&self) -> egg::Symbol {
+        match self.block {
+            BlockRef::Global => self.name,
+            _ => egg::Symbol::new(self.to_string()),
+        }
+    }
+
+    fn into_symbol(self) -> egg::Symbol {
+        self.to_symbol()
+    }
+}
+
 /// A variable record.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Record<M> {
@@ -61,7 +138,8 @@ pub struct Record<M> {
     pub origin: Origin<M>,
     /// The scope of the variable (thread-local or shared).
     ///
-    /// It is ill-formed for local variables to be shared.
+    /// It is ill-formed for local variables to be shared; the binder declares them thread-local.
+    /// The [discipline checker](crate::discipline) enforces where each scope may be used.
     pub scope: super::super::var::Scope,
     /// The type of the variable, as an index into the typing table.
     pub ty: egg::Symbol,
@@ -32,6 +32,21 @@ pub enum Expr<M, V> {
     },
     /// Unary (prefix or postfix) operation.
     Uop { op: Uop, expr: Box<Expr<M, V>> },
+    /// Array subscript.
+    Index {
+        array: Box<Expr<M, V>>,
+        index: Box<Expr<M, V>>,
+    },
+    /// Functional array update: the array equal to `array`, except that it maps `index` to
+    /// `value`.
+    ///
+    /// Updates have no concrete syntax; they arise from assignments to array elements during
+    /// verification condition generation.
+    Update {
+        array: Box<Expr<M, V>>,
+        index: Box<Expr<M, V>>,
+        value: Box<Expr<M, V>>,
+    },
 }
 
 impl<M, V> Expr<M, V> {
@@ -59,6 +74,93 @@ impl<M, V> Expr<M, V> {
             expr: expr.into(),
         }
     }
+
+    /// Convenience constructor for an array subscript.
+    #[must_use]
+    pub fn index(array: impl Into<Box<Self>>, index: impl Into<Box<Self>>) -> Self {
+        Self::Index {
+            array: array.into(),
+            index: index.into(),
+        }
+    }
+
+    /// Convenience constructor for a functional array update.
+    #[must_use]
+    pub fn update(
+        array: impl Into<Box<Self>>,
+        index: impl Into<Box<Self>>,
+        value: impl Into<Box<Self>>,
+    ) -> Self {
+        Self::Update {
+            array: array.into(),
+            index: index.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Convenience constructor for a logical negation.
+    #[must_use]
+    pub fn not(expr: impl Into<Box<Self>>) -> Self {
+        Self::uop(Uop::Not, expr)
+    }
+
+    /// Substitutes every variable in this expression with the expression `f` returns for it.
+    #[must_use]
+    pub fn subst<U>(self, f: &mut impl FnMut(tagged::Tagged<M, V>) -> Expr<M, U>) -> Expr<M, U> {
+        match self {
+            Self::Literal(l) => Expr::Literal(l),
+            Self::Var(v) => f(v),
+            Self::Bop { op, lhs, rhs } => Expr::bop(lhs.subst(f), op, rhs.subst(f)),
+            Self::Uop { op, expr } => Expr::uop(op, expr.subst(f)),
+            Self::Index { array, index } => Expr::index(array.subst(f), index.subst(f)),
+            Self::Update {
+                array,
+                index,
+                value,
+            } => Expr::update(array.subst(f), index.subst(f), value.subst(f)),
+        }
+    }
+
+    /// Visits every variable in this expression, in left-to-right order.
+    pub fn for_each_var<'a>(&'a self, f: &mut impl FnMut(&'a V)) {
+        self.for_each_tagged_var(&mut |v| f(&v.item));
+    }
+
+    /// Visits every variable in this expression, with its tag, in left-to-right order.
+    pub fn for_each_tagged_var<'a>(&'a self, f: &mut impl FnMut(&'a tagged::Tagged<M, V>)) {
+        match self {
+            Self::Literal(_) => (),
+            Self::Var(v) => f(v),
+            Self::Bop { lhs, rhs, .. }
+            | Self::Index {
+                array: lhs,
+                index: rhs,
+            } => {
+                lhs.for_each_tagged_var(f);
+                rhs.for_each_tagged_var(f);
+            }
+            Self::Uop { expr, .. } => expr.for_each_tagged_var(f),
+            Self::Update {
+                array,
+                index,
+                value,
+            } => {
+                array.for_each_tagged_var(f);
+                index.for_each_tagged_var(f);
+                value.for_each_tagged_var(f);
+            }
+        }
+    }
+
+    /// Gets the constant this expression denotes, if it is a literal.
+    #[must_use]
+    pub fn as_literal(&self) -> Option<&Constant> {
+        if let Self::Literal(l) = self {
+            Some(&l.item)
+        } else {
+            None
+        }
+    }
 }
 
 impl<M: Default, V> Expr<M, V> {
@@ -75,19 +177,106 @@ impl<M: Default, V> Expr<M, V> {
             num_bigint::BigInt::from(value),
         )))
     }
+
+    /// Convenience constructor for an arbitrary-precision integer literal with no tag.
+    #[must_use]
+    pub fn int(value: num_bigint::BigInt) -> Self {
+        Self::Literal(tagged::Tagged::with_default(Constant::Int(value)))
+    }
+
+    /// Constructs the conjunction of `exprs`.
+    ///
+    /// Literal `true` conjuncts are dropped, and the empty conjunction is `true`.
+    #[must_use]
+    pub fn conjunction(exprs: impl IntoIterator<Item = Self>) -> Self {
+        exprs
+            .into_iter()
+            .filter(|e| e.as_literal() != Some(&Constant::Bool(true)))
+            .reduce(|l, r| Self::bop(l, bop::Bool::And, r))
+            .unwrap_or_else(|| Self::bool(true))
+    }
+}
+
+/// Precedence of prefix operators (and negative literals, which print like them).
+const PREFIX_PRECEDENCE: u8 = 7;
+/// Precedence of postfix operators and subscripts.
+const POSTFIX_PRECEDENCE: u8 = 8;
+/// Precedence of expressions that never need parentheses.
+const ATOM_PRECEDENCE: u8 = 9;
+
+impl<M, V> Expr<M, V> {
+    /// Gets the binding strength of the outermost construct of this expression.
+    ///
+    /// This extends [`Bop::precedence`] to every kind of expression, following the parser.
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Literal(l) => match &l.item {
+                Constant::Int(i) if num_traits::Signed::is_negative(i) => PREFIX_PRECEDENCE,
+                _ => ATOM_PRECEDENCE,
+            },
+            Self::Var(_) => ATOM_PRECEDENCE,
+            Self::Bop { op, .. } => op.precedence(),
+            Self::Uop { op, .. } => match op.fixity() {
+                uop::Fixity::Prefix => PREFIX_PRECEDENCE,
+                uop::Fixity::Postfix => POSTFIX_PRECEDENCE,
+            },
+            Self::Index { .. } | Self::Update { .. } => POSTFIX_PRECEDENCE,
+        }
+    }
 }
 
+/// Expressions are displayed in PVC syntax, with only the parentheses needed to parse them back.
+///
+/// Functional array updates have no PVC syntax, and display as `a[i := v]`.
 impl<M, V: Display> Display for Expr<M, V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Literal(l) => l.fmt(f),
             Self::Var(v) => v.fmt(f),
-            // TODO: precedence
-            Self::Bop { op, lhs, rhs } => write!(f, "({lhs}) {op} ({rhs})"),
+            Self::Bop { op, lhs, rhs } => {
+                // Binary operators are left-associative.
+                let prec = op.precedence();
+                lhs.fmt_operand(f, prec)?;
+                write!(f, " {op} ")?;
+                rhs.fmt_operand(f, prec + 1)
+            }
             Self::Uop { op, expr } => match op.fixity() {
-                uop::Fixity::Prefix => write!(f, "{op}({expr})"),
-                uop::Fixity::Postfix => write!(f, "({expr}){op}"),
+                uop::Fixity::Prefix => {
+                    write!(f, "{op}")?;
+                    if *op == Uop::Not {
+                        f.write_str(" ")?;
+                    }
+                    expr.fmt_operand(f, PREFIX_PRECEDENCE)
+                }
+                uop::Fixity::Postfix => {
+                    expr.fmt_operand(f, POSTFIX_PRECEDENCE)?;
+                    write!(f, "{op}")
+                }
             },
+            Self::Index { array, index } => {
+                array.fmt_operand(f, POSTFIX_PRECEDENCE)?;
+                write!(f, "[{index}]")
+            }
+            Self::Update {
+                array,
+                index,
+                value,
+            } => {
+                array.fmt_operand(f, POSTFIX_PRECEDENCE)?;
+                write!(f, "[{index} := {value}]")
+            }
+        }
+    }
+}
+
+impl<M, V: Display> Expr<M, V> {
+    /// Displays this expression as an operand of a construct of precedence `prec`, bracketing it
+    /// if it binds more loosely.
+    fn fmt_operand(&self, f: &mut Formatter<'_>, prec: u8) -> std::fmt::Result {
+        if self.precedence() < prec {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
         }
     }
 }
@@ -98,8 +287,8 @@ impl<M, V: Variable> HasVars<V> for Expr<M, V> {
     fn try_map_var<U, E>(self, f: impl FnMut(V) -> Result<U, E>) -> Result<Self::Output<U>, E> {
         VarMapper {
             f,
-            v: PhantomData::default(),
-            e: PhantomData::default(),
+            v: PhantomData,
+            e: PhantomData,
         }
         .try_map(self)
     }
@@ -126,6 +315,21 @@ impl<V: Variable, U, E, F: FnMut(V) -> Result<U, E>> VarMapper<V, U, E, F> {
                 let expr: Expr<M, U> = self.try_map(*expr)?;
                 Ok(Expr::uop(op, expr))
             }
+            Expr::Index { array, index } => {
+                let array: Expr<M, U> = self.try_map(*array)?;
+                let index: Expr<M, U> = self.try_map(*index)?;
+                Ok(Expr::index(array, index))
+            }
+            Expr::Update {
+                array,
+                index,
+                value,
+            } => {
+                let array: Expr<M, U> = self.try_map(*array)?;
+                let index: Expr<M, U> = self.try_map(*index)?;
+                let value: Expr<M, U> = self.try_map(*value)?;
+                Ok(Expr::update(array, index, value))
+            }
         }
     }
 }
@@ -136,8 +340,8 @@ impl<M, V> HasMeta<M> for Expr<M, V> {
     fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
         MetaMapper {
             f,
-            v: PhantomData::default(),
-            e: PhantomData::default(),
+            v: PhantomData,
+            e: PhantomData,
         }
         .try_map(self)
     }
@@ -164,6 +368,21 @@ impl<M, N, E, F: FnMut(M) -> Result<N, E>> MetaMapper<M, N, E, F> {
                 let expr = self.try_map(*expr)?;
                 Ok(Expr::uop(op, expr))
             }
+            Expr::Index { array, index } => {
+                let array = self.try_map(*array)?;
+                let index = self.try_map(*index)?;
+                Ok(Expr::index(array, index))
+            }
+            Expr::Update {
+                array,
+                index,
+                value,
+            } => {
+                let array = self.try_map(*array)?;
+                let index = self.try_map(*index)?;
+                let value = self.try_map(*value)?;
+                Ok(Expr::update(array, index, value))
+            }
         }
     }
 }
@@ -16,6 +16,24 @@ pub enum Bop {
     Rel(Rel),
 }
 
+impl Bop {
+    /// Gets the binding strength of this operator, as used by the parser.
+    ///
+    /// Operators with higher precedence bind more tightly; all binary operators are
+    /// left-associative.
+    #[must_use]
+    pub fn precedence(self) -> u8 {
+        match self {
+            Self::Bool(Bool::Implies | Bool::Iff) => 1,
+            Self::Bool(Bool::Or) => 2,
+            Self::Bool(Bool::And) => 3,
+            Self::Rel(_) => 4,
+            Self::Arith(Arith::Add | Arith::Sub) => 5,
+            Self::Arith(Arith::Mul | Arith::Div | Arith::Modulus) => 6,
+        }
+    }
+}
+
 /// Outputs a human-readable rendering of a binary operator.
 impl Display for Bop {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
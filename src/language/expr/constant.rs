@@ -64,10 +64,12 @@ impl TryFrom<Constant> for BigInt {
     }
 }
 
-fn convert_error(got: typing::Prim, value: &Constant) -> ConvertError {
+fn convert_error(want: typing::Prim, value: &Constant) -> ConvertError {
     ConvertError::Mismatch {
-        got: typing::Type::Prim(got),
-        want: typing::Type::Prim(value.prim_type()),
+        want: typing::Type::Prim(want),
+        got: typing::Type::Prim(value.prim_type()),
+        want_loc: (),
+        got_loc: (),
     }
 }
 
@@ -39,6 +39,9 @@ egg::define_language! {
         // Postfix operations
         "^" = Deref(Id),
         "not" = Not(Id),
+        // Arrays
+        "select" = Index([Id; 2]),
+        "store" = Update([Id; 3]),
         // Terminals
         Constant(super::constant::Constant),
         Var(Symbol),
@@ -59,6 +62,8 @@ fn init() -> Vec<Rewrite> {
         rw!("mul-1"; "(* ?x 1)" => "?x"),
         // Zeroes
         rw!("mul-0"; "(* ?x 0)" => "0"),
+        // Arrays
+        rw!("select-store"; "(select (store ?a ?i ?v) ?i)" => "?v"),
         // Symmetry on equalities
         rw!("gt-lt"; "(> ?x ?y)" => "(< ?y ?x)"),
         rw!("ge-le"; "(>= ?x ?y)" => "(<= ?y ?x)"),
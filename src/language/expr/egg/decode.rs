@@ -33,6 +33,8 @@ pub fn expr(e: &super::Expr, top_id: Id) -> Expr {
         Term::Plus(x) => unary_op(e, uop::Uop::Plus, *x),
         Term::Deref(x) => unary_op(e, uop::Uop::Deref, *x),
         Term::Not(x) => unary_op(e, uop::Uop::Not, *x),
+        Term::Index([a, i]) => Expr::index(expr(e, *a), expr(e, *i)),
+        Term::Update([a, i, v]) => Expr::update(expr(e, *a), expr(e, *i), expr(e, *v)),
         Term::Constant(k) => Expr::Literal(Tagged::with_default(k.clone())),
         Term::Var(v) => Expr::Var(Tagged::with_default(*v)),
     }
@@ -18,6 +18,12 @@ pub fn expr<M, V: Clone + Into<Symbol>>(dest: &mut super::Expr, expr: &Expr<M, V
         Expr::Var(v) => dest.add(Term::Var(v.item.clone().into())),
         Expr::Bop { lhs, op, rhs } => bop_expr(dest, lhs, *op, rhs),
         Expr::Uop { op, expr } => uop_expr(dest, *op, expr),
+        Expr::Index { array, index } => index_expr(dest, array, index),
+        Expr::Update {
+            array,
+            index,
+            value,
+        } => update_expr(dest, array, index, value),
     }
 }
 
@@ -96,3 +102,24 @@ fn uop(op: Uop) -> fn(Id) -> Term {
         Uop::Not => Term::Not,
     }
 }
+
+/// Adds a PVC array subscript to an egg rec-expr.
+fn index_expr<M, V: Clone + Into<Symbol>>(
+    dest: &mut super::Expr,
+    array: &Expr<M, V>,
+    index: &Expr<M, V>,
+) -> Id {
+    let ids = [expr(dest, array), expr(dest, index)];
+    dest.add(Term::Index(ids))
+}
+
+/// Adds a PVC array update to an egg rec-expr.
+fn update_expr<M, V: Clone + Into<Symbol>>(
+    dest: &mut super::Expr,
+    array: &Expr<M, V>,
+    index: &Expr<M, V>,
+    value: &Expr<M, V>,
+) -> Id {
+    let ids = [expr(dest, array), expr(dest, index), expr(dest, value)];
+    dest.add(Term::Update(ids))
+}
@@ -87,3 +87,33 @@ impl<M, T: HasMeta<M>> HasMeta<M> for Tagged<M, T> {
         Ok(Tagged::new(f(self.meta)?, self.item.try_map_meta(f)?))
     }
 }
+
+/// We can map over the metadata of every item in a vector.
+impl<M, T: HasMeta<M>> HasMeta<M> for Vec<T> {
+    type Output<N> = Vec<T::Output<N>>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        self.into_iter().map(|x| x.try_map_meta(&mut f)).collect()
+    }
+}
+
+/// We can map over the metadata of an optional item.
+impl<M, T: HasMeta<M>> HasMeta<M> for Option<T> {
+    type Output<N> = Option<T::Output<N>>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        self.map(|x| x.try_map_meta(f)).transpose()
+    }
+}
+
+/// We can map over the metadata of a boxed item.
+impl<M, T: HasMeta<M>> HasMeta<M> for Box<T> {
+    type Output<N> = Box<T::Output<N>>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(Box::new((*self).try_map_meta(f)?))
+    }
+}
@@ -0,0 +1,164 @@
+//! Built-in atomic primitives.
+//!
+//! Primitives are commands with a fixed meaning, such as compare-and-swap, that PVC scripts write
+//! as calls inside atomic blocks (`<| CAS(x, e, n, ok) |>`).  Their names are case-insensitive,
+//! like PVC keywords.
+
+use std::fmt::{Display, Formatter};
+
+/// A built-in atomic primitive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Primitive {
+    /// Compare-and-swap.
+    ///
+    /// `CAS(x, e, n, ok)` sets `x` to `n` if `x` equals `e`, and sets `ok` to whether it did.
+    Cas,
+    /// Decrement.
+    ///
+    /// `Dec(x)` subtracts one from `x`.
+    Dec,
+    /// Failure.
+    ///
+    /// `error` does nothing, but verification fails if it can ever run.
+    Error,
+    /// Exchange.
+    ///
+    /// `Xchg(x, n, old)` sets `x` to `n`, and `old` to the previous value of `x`.
+    Exchange,
+    /// Fetch-and-add.
+    ///
+    /// `FAA(x, d, old)` adds `d` to `x`, and sets `old` to the previous value of `x`.
+    FetchAdd,
+    /// Increment.
+    ///
+    /// `Inc(x)` adds one to `x`.
+    Inc,
+    /// Test-and-set.
+    ///
+    /// `TAS(x, old)` sets `x` to true, and `old` to the previous value of `x`.
+    TestAndSet,
+}
+
+/// All primitives, in the order in which they are declared.
+pub const ALL: [Primitive; 7] = [
+    Primitive::Cas,
+    Primitive::Dec,
+    Primitive::Error,
+    Primitive::Exchange,
+    Primitive::FetchAdd,
+    Primitive::Inc,
+    Primitive::TestAndSet,
+];
+
+impl Primitive {
+    /// Looks up the primitive with the given name, ignoring case.
+    ///
+    /// ```
+    /// use starling::language::primitive::Primitive;
+    ///
+    /// assert_eq!(Primitive::from_name("cas"), Some(Primitive::Cas));
+    /// assert_eq!(Primitive::from_name("XCHG"), Some(Primitive::Exchange));
+    /// assert_eq!(Primitive::from_name("swap"), None);
+    /// ```
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL.into_iter()
+            .find(|p| p.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Gets the canonical name of this primitive.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cas => "CAS",
+            Self::Dec => "Dec",
+            Self::Error => "error",
+            Self::Exchange => "Xchg",
+            Self::FetchAdd => "FAA",
+            Self::Inc => "Inc",
+            Self::TestAndSet => "TAS",
+        }
+    }
+
+    /// Gets the parameters of this primitive.
+    #[must_use]
+    pub fn parameters(self) -> &'static [Parameter] {
+        const fn param(name: &'static str, ty: ParamType, assigned: bool) -> Parameter {
+            Parameter { name, ty, assigned }
+        }
+        const CAS: [Parameter; 4] = [
+            param("x", ParamType::Scalar, true),
+            param("e", ParamType::Same, false),
+            param("n", ParamType::Same, false),
+            param("ok", ParamType::Bool, true),
+        ];
+        const COUNTER: [Parameter; 1] = [param("x", ParamType::Int, true)];
+        const EXCHANGE: [Parameter; 3] = [
+            param("x", ParamType::Scalar, true),
+            param("n", ParamType::Same, false),
+            param("old", ParamType::Same, true),
+        ];
+        const FETCH_ADD: [Parameter; 3] = [
+            param("x", ParamType::Int, true),
+            param("d", ParamType::Int, false),
+            param("old", ParamType::Int, true),
+        ];
+        const TEST_AND_SET: [Parameter; 2] = [
+            param("x", ParamType::Bool, true),
+            param("old", ParamType::Bool, true),
+        ];
+
+        match self {
+            Self::Cas => &CAS,
+            Self::Dec | Self::Inc => &COUNTER,
+            Self::Error => &[],
+            Self::Exchange => &EXCHANGE,
+            Self::FetchAdd => &FETCH_ADD,
+            Self::TestAndSet => &TEST_AND_SET,
+        }
+    }
+}
+
+impl Display for Primitive {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A parameter of a primitive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Parameter {
+    /// The name used for the parameter in documentation and diagnostics.
+    pub name: &'static str,
+    /// The type the argument must have.
+    pub ty: ParamType,
+    /// Whether the primitive assigns to the argument, which must then be an lvalue.
+    pub assigned: bool,
+}
+
+/// The type of a primitive parameter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParamType {
+    /// The argument must be an integer.
+    Int,
+    /// The argument must be a Boolean.
+    Bool,
+    /// The argument may be an integer or a Boolean.
+    Scalar,
+    /// The argument must have the same type as the first argument.
+    Same,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every primitive can be found by its canonical name.
+    #[test]
+    fn names_round_trip() {
+        for p in ALL {
+            assert_eq!(Primitive::from_name(p.name()), Some(p));
+        }
+    }
+}
@@ -1,6 +1,9 @@
 //! Starling's type system.
 //!
 //! Starling has a refinement type system based on a handful of primitive types.
+//!
+//! Types can also refer to named types by name.  Names are resolved against a typing table (see
+//! [`super::cfg::Program::expand`]); the methods on [Type] do not look through names.
 
 pub mod array;
 pub mod error;
@@ -14,6 +17,12 @@ pub use error::{Error, Result};
 pub use prim::Prim;
 pub use refined::Refined;
 
+use super::{
+    expr::map::{HasMeta, HasVars},
+    tagged::Tagged,
+    var::Variable,
+};
+
 /// Enumeration of all types in the Starling type system.
 ///
 /// This enumeration predicates over `P`, the type of predicate expressions.
@@ -25,6 +34,8 @@ pub enum Type<M, V> {
     Array(Array<M, V>),
     /// A refined type.
     Refined(Refined<M, V>),
+    /// A reference to a named type.
+    Named(Tagged<M, V>),
     /// A primitive type.
     Prim(Prim),
 }
@@ -34,6 +45,129 @@ impl<M, V> Type<M, V> {
     pub const BOOL: Self = Type::Prim(Prim::Bool);
     /// The integer primitive type.
     pub const INT: Self = Type::Prim(Prim::Int);
+
+    /// Gets the type underlying any refinements on this type.
+    ///
+    /// Two types with the same base type have the same values, up to refinement predicates.
+    #[must_use]
+    pub fn base(&self) -> &Self {
+        match self {
+            Self::Refined(r) => r.element().base(),
+            t => t,
+        }
+    }
+
+    /// Checks whether this type has the same values as `other`, up to refinement.
+    ///
+    /// This is the notion of type equality used by the type checker; refinements are checked
+    /// separately, through verification conditions.
+    #[must_use]
+    pub fn compatible(&self, other: &Self) -> bool {
+        match (self.base(), other.base()) {
+            (Self::Prim(p), Self::Prim(q)) => p == q,
+            (Self::Array(a), Self::Array(b)) => a.compatible(b),
+            _ => false,
+        }
+    }
+
+    /// Visits every reference to a named type in this type, including those in element types.
+    pub fn for_each_name<'a>(&'a self, f: &mut impl FnMut(&'a Tagged<M, V>)) {
+        match self {
+            Self::Array(a) => a.base().for_each_name(f),
+            Self::Refined(r) => r.element().for_each_name(f),
+            Self::Named(n) => f(n),
+            Self::Prim(_) => (),
+        }
+    }
+
+    /// Replaces every reference to a named type in this type with its definition, as given by
+    /// `lookup`, recursively expanding references in that definition.
+    ///
+    /// References that `lookup` does not know are left as they are.  If `lookup` gives cyclic
+    /// definitions, expansion does not terminate.
+    #[must_use]
+    pub fn expand<'a>(&'a self, lookup: &impl Fn(&V) -> Option<&'a Self>) -> Self
+    where
+        M: Clone,
+        V: Clone,
+    {
+        match self {
+            Self::Array(a) => Self::Array(Array::new(
+                a.base().expand(lookup),
+                a.low().clone(),
+                a.high().clone(),
+            )),
+            Self::Refined(r) => Self::Refined(Refined::new(
+                r.var(),
+                r.element().expand(lookup),
+                r.refinement().clone(),
+            )),
+            Self::Named(n) => lookup(&n.item).map_or_else(|| self.clone(), |t| t.expand(lookup)),
+            Self::Prim(p) => Self::Prim(*p),
+        }
+    }
+
+    /// Iterates over the refinements applied directly to values of this type, outermost first.
+    ///
+    /// Refinements on the elements of array types are not included.
+    pub fn refinements(&self) -> impl Iterator<Item = &Refined<M, V>> {
+        std::iter::successors(Some(self), |t| match t {
+            Self::Refined(r) => Some(r.element()),
+            _ => None,
+        })
+        .filter_map(|t| match t {
+            Self::Refined(r) => Some(r),
+            _ => None,
+        })
+    }
+}
+
+impl<M, V: Variable> HasVars<V> for Type<M, V> {
+    type Output<U> = Type<M, U>;
+
+    fn try_map_var<U, E>(
+        self,
+        mut f: impl FnMut(V) -> std::result::Result<U, E>,
+    ) -> std::result::Result<Self::Output<U>, E> {
+        self.try_map_var_with(&mut f)
+    }
+}
+
+impl<M, V: Variable> Type<M, V> {
+    /// Maps `f` over the variables of this type, retaining ownership of `f`.
+    ///
+    /// Types are recursive, so we cannot use `try_map_var` directly without instantiating an
+    /// infinite tower of closure references.
+    fn try_map_var_with<U, E, F: FnMut(V) -> std::result::Result<U, E>>(
+        self,
+        f: &mut F,
+    ) -> std::result::Result<Type<M, U>, E> {
+        Ok(match self {
+            Self::Array(a) => Type::Array(a.try_map_var_with(f)?),
+            Self::Refined(r) => Type::Refined(r.try_map_var_with(f)?),
+            Self::Named(n) => Type::Named(Tagged::new(n.meta, f(n.item)?)),
+            Self::Prim(p) => Type::Prim(p),
+        })
+    }
+}
+
+impl<M, V> HasMeta<M> for Type<M, V> {
+    type Output<N> = Type<N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> std::result::Result<N, E>,
+    ) -> std::result::Result<Self::Output<N>, E> {
+        // Types are recursive, so we erase `f` to avoid instantiating an infinite tower of closure
+        // references.
+        let f: &mut dyn FnMut(M) -> std::result::Result<N, E> = &mut f;
+        Ok(match self {
+            Self::Array(a) => Type::Array(a.try_map_meta(f)?),
+            Self::Refined(r) => Type::Refined(r.try_map_meta(f)?),
+            Self::Named(n) => Type::Named(n.try_map_direct_meta(f)?),
+            Self::Prim(p) => Type::Prim(p),
+        })
+    }
 }
 
 impl<M, V: Display> Display for Type<M, V> {
@@ -41,6 +175,7 @@ impl<M, V: Display> Display for Type<M, V> {
         match self {
             Self::Array(a) => a.fmt(f),
             Self::Refined(r) => r.fmt(f),
+            Self::Named(n) => n.item.fmt(f),
             Self::Prim(p) => p.fmt(f),
         }
     }
@@ -2,21 +2,88 @@
 
 use std::fmt::{Display, Formatter};
 
+use num_bigint::BigInt;
+
+use super::super::{expr::map::HasMeta, var::Variable};
+
 /// An array type.
 ///
-/// Arrays in Starling are homogeneous, and have explicit low and high bound types.
+/// Arrays in Starling are homogeneous, and have explicit, inclusive low and high bounds.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Array<M, V> {
     /// The base type of the array.
     base: Box<super::Type<M, V>>,
     /// The low bound of the array.
-    low: usize,
+    low: BigInt,
     /// The high bound of the array.
-    high: usize,
+    high: BigInt,
+}
+
+impl<M, V> Array<M, V> {
+    /// Constructs an array type over `base`, with indices from `low` to `high` inclusive.
+    #[must_use]
+    pub fn new(base: super::Type<M, V>, low: BigInt, high: BigInt) -> Self {
+        Self {
+            base: Box::new(base),
+            low,
+            high,
+        }
+    }
+
+    /// Borrows the base (element) type of the array.
+    #[must_use]
+    pub fn base(&self) -> &super::Type<M, V> {
+        &self.base
+    }
+
+    /// Borrows the low bound of the array.
+    #[must_use]
+    pub fn low(&self) -> &BigInt {
+        &self.low
+    }
+
+    /// Borrows the high bound of the array.
+    #[must_use]
+    pub fn high(&self) -> &BigInt {
+        &self.high
+    }
+
+    /// Checks whether this array type has the same values as `other`, up to refinement of their
+    /// base types.
+    #[must_use]
+    pub fn compatible(&self, other: &Self) -> bool {
+        self.low == other.low && self.high == other.high && self.base.compatible(&other.base)
+    }
+}
+
+impl<M, V: Variable> Array<M, V> {
+    /// Maps `f` over the variables of the array's base type, retaining ownership of `f`.
+    pub(super) fn try_map_var_with<U, E, F: FnMut(V) -> Result<U, E>>(
+        self,
+        f: &mut F,
+    ) -> Result<Array<M, U>, E> {
+        Ok(Array {
+            base: Box::new(self.base.try_map_var_with(f)?),
+            low: self.low,
+            high: self.high,
+        })
+    }
+}
+
+impl<M, V> HasMeta<M> for Array<M, V> {
+    type Output<N> = Array<N, V>;
+
+    fn try_map_meta<N, E>(self, f: impl FnMut(M) -> Result<N, E>) -> Result<Self::Output<N>, E> {
+        Ok(Array {
+            base: self.base.try_map_meta(f)?,
+            low: self.low,
+            high: self.high,
+        })
+    }
 }
 
 impl<M, V: Display> Display for Array<M, V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}[{},{}]", self.base, self.low, self.high)
+        write!(f, "array[{}..{}] of {}", self.low, self.high, self.base)
     }
 }
@@ -1,15 +1,105 @@
 //! Errors relating to the type system.
 
+use std::fmt::Display;
+
+use super::super::super::diagnostic::{self, code, Diagnostic, FileId, Label, ToDiagnostic};
+
 /// A PVC typing error.
+///
+/// Errors carry metadata for the site that determined the expected type (`want_loc`) as well as
+/// for the site that had the wrong type (`got_loc`).
 #[derive(Debug, Clone, thiserror::Error)]
+#[non_exhaustive]
 pub enum Error<M, V> {
     /// A typing mismatch.
     #[error("expected type {want}, got type {got}")]
     Mismatch {
         want: super::Type<M, V>,
         got: super::Type<M, V>,
+        want_loc: M,
+        got_loc: M,
+    },
+    /// A call or view atom has the wrong number of arguments.
+    #[error("`{name}` takes {want} argument(s), but {got} were supplied")]
+    Arity {
+        name: String,
+        want: usize,
+        got: usize,
+        want_loc: M,
+        got_loc: M,
     },
+    /// A view atom refers to a view that has not been declared.
+    #[error("unknown view `{name}`")]
+    UnknownView { name: String, loc: M },
+    /// An expression that is not a pointer was dereferenced.
+    #[error("cannot dereference an expression of type {got}")]
+    Deref { got: super::Type<M, V>, loc: M },
+    /// An expression that is not an array was subscripted.
+    #[error("cannot subscript an expression of type {got}")]
+    Index { got: super::Type<M, V>, loc: M },
 }
 
 /// Shorthand for results over [Error].
 pub type Result<T, M, V> = std::result::Result<T, Error<M, V>>;
+
+impl<V: Display> ToDiagnostic for Error<Option<pest::Span<'_>>, V> {
+    fn to_diagnostic(&self, file: FileId) -> Diagnostic {
+        let primary = |loc: &Option<pest::Span>, message: String| {
+            loc.map(|l| Label::primary(file, diagnostic::range(&l)).with_message(message))
+        };
+        let secondary = |loc: &Option<pest::Span>, message: String| {
+            loc.map(|l| Label::secondary(file, diagnostic::range(&l)).with_message(message))
+        };
+
+        let diagnostic = Diagnostic::error(self.to_string());
+        match self {
+            Self::Mismatch {
+                want,
+                got,
+                want_loc,
+                got_loc,
+            } => diagnostic
+                .with_code(code::TYPE_MISMATCH)
+                .with_label_opt(primary(got_loc, format!("expected {want}, found {got}")))
+                .with_label_opt(secondary(
+                    want_loc,
+                    format!("{want} expected because of this"),
+                )),
+            Self::Arity {
+                want,
+                got,
+                want_loc,
+                got_loc,
+                ..
+            } => diagnostic
+                .with_code(code::ARITY_MISMATCH)
+                .with_label_opt(primary(got_loc, format!("{got} argument(s) supplied")))
+                .with_label_opt(secondary(
+                    want_loc,
+                    format!("declared with {want} parameter(s) here"),
+                )),
+            Self::UnknownView { loc, .. } => diagnostic
+                .with_code(code::UNKNOWN_VIEW)
+                .with_label_opt(primary(
+                    loc,
+                    "not declared in any view declaration".to_owned(),
+                )),
+            Self::Deref { got, loc } => {
+                diagnostic
+                    .with_code(code::BAD_DEREF)
+                    .with_label_opt(primary(
+                        loc,
+                        format!("has type {got}, which is not a pointer"),
+                    ))
+            }
+            Self::Index { got, loc } => {
+                diagnostic
+                    .with_code(code::BAD_INDEX)
+                    .with_label_opt(primary(
+                        loc,
+                        format!("has type {got}, which is not an array"),
+                    ))
+            }
+        }
+    }
+}
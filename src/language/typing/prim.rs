@@ -20,7 +20,15 @@ impl Display for Prim {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
             Self::Int => "int",
-            Self::Bool => "bool"
+            Self::Bool => "bool",
         })
     }
-}
\ No newline at end of file
+}
+
+impl Prim {
+    /// Gets the symbol under which this primitive type lives in a program's typing table.
+    #[must_use]
+    pub fn to_symbol(self) -> egg::Symbol {
+        egg::Symbol::new(self.to_string())
+    }
+}
@@ -2,6 +2,11 @@
 
 use std::fmt::{Display, Formatter};
 
+use super::super::{
+    expr::map::{HasMeta, HasVars},
+    var::Variable,
+};
+
 /// Base structure of a refined type.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Refined<M, V> {
@@ -13,6 +18,69 @@ pub struct Refined<M, V> {
     refinement: super::super::expr::Expr<M, V>,
 }
 
+impl<M, V> Refined<M, V> {
+    /// Constructs the refinement of `element` by `refinement`, a predicate over `var`.
+    #[must_use]
+    pub fn new(
+        var: impl Into<String>,
+        element: super::Type<M, V>,
+        refinement: super::super::expr::Expr<M, V>,
+    ) -> Self {
+        Self {
+            element: Box::new(element),
+            var: var.into(),
+            refinement,
+        }
+    }
+
+    /// Borrows the type being refined.
+    #[must_use]
+    pub fn element(&self) -> &super::Type<M, V> {
+        &self.element
+    }
+
+    /// Borrows the name of the refinement variable.
+    #[must_use]
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    /// Borrows the refinement predicate.
+    #[must_use]
+    pub fn refinement(&self) -> &super::super::expr::Expr<M, V> {
+        &self.refinement
+    }
+}
+
+impl<M, V: Variable> Refined<M, V> {
+    /// Maps `f` over the variables of the refined type, retaining ownership of `f`.
+    pub(super) fn try_map_var_with<U, E, F: FnMut(V) -> Result<U, E>>(
+        self,
+        f: &mut F,
+    ) -> Result<Refined<M, U>, E> {
+        Ok(Refined {
+            element: Box::new(self.element.try_map_var_with(f)?),
+            var: self.var,
+            refinement: self.refinement.try_map_var(f)?,
+        })
+    }
+}
+
+impl<M, V> HasMeta<M> for Refined<M, V> {
+    type Output<N> = Refined<N, V>;
+
+    fn try_map_meta<N, E>(
+        self,
+        mut f: impl FnMut(M) -> Result<N, E>,
+    ) -> Result<Self::Output<N>, E> {
+        Ok(Refined {
+            element: self.element.try_map_meta(&mut f)?,
+            var: self.var,
+            refinement: self.refinement.try_map_meta(f)?,
+        })
+    }
+}
+
 impl<M, V: Display> Display for Refined<M, V> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let Self {
@@ -11,6 +11,10 @@ pub enum Scope {
     Shared,
     /// Thread-local scope.
     Thread,
+    /// Bound by a view pattern in a constraint.
+    ///
+    /// Bound variables stand for the arguments of a view atom, which may belong to any thread.
+    Bound,
 }
 
 /// A variable reference.
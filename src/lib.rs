@@ -4,11 +4,22 @@
 //!
 //! - the Starling language in its various forms (AST, CFG, and so on);
 //! - the Starling parser;
-//! - Starling semantic analysis and lowering;
-//! - Verification condition generation in various output formats.
+//! - Starling semantic analysis and lowering, and a pipeline running it over scripts;
+//! - Verification condition generation in various output formats;
+//! - pretty-printing of Starling programs back into PVC;
+//! - a language server for editors.
 
 #![warn(clippy::pedantic)]
 
 pub mod binder;
+pub mod diagnostic;
+pub mod discipline;
 pub mod language;
+pub mod lsp;
 pub mod parser;
+pub mod pipeline;
+pub mod printer;
+pub mod solver;
+pub mod typechecker;
+pub mod vcgen;
+pub mod wellformed;
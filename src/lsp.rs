@@ -0,0 +1,494 @@
+//! A language server for PVC scripts.
+//!
+//! The server speaks the Language Server Protocol over standard input and output.  It keeps the
+//! text of every open document, and answers requests by running the analyses in [analysis] over
+//! that text, converting between byte offsets and editor positions as it goes.
+//!
+//! The server supports:
+//!
+//! - diagnostics from the parser, binder, type checker, and condition generator, published
+//!   whenever a document opens or changes;
+//! - hover information for variables and view atoms;
+//! - going to the definitions of variables and view atoms;
+//! - document symbols for procedures, views, and constraints.
+
+use std::collections::HashMap;
+
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{self, Notification as _},
+    request::{self, Request as _},
+    DiagnosticRelatedInformation, DiagnosticSeverity, DocumentSymbol, DocumentSymbolResponse,
+    GotoDefinitionResponse, Hover, HoverContents, HoverProviderCapability, LanguageString,
+    Location, LogMessageParams, MarkedString, MessageType, NumberOrString, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, SymbolKind, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Url,
+};
+use serde::de::DeserializeOwned;
+
+use super::diagnostic::{self, Severity};
+
+pub mod analysis;
+
+/// Runs the language server over standard input and output, until the client shuts it down.
+///
+/// # Errors
+///
+/// Fails if the connection to the client breaks, or the client breaks protocol.
+pub fn run() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        connection: &connection,
+        documents: HashMap::new(),
+    };
+    server.serve()?;
+
+    // The writer thread only stops once every sender has gone.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+/// The state of a running language server.
+struct Server<'a> {
+    /// The connection to the client.
+    connection: &'a Connection,
+    /// The text of each open document.
+    documents: HashMap<Url, String>,
+}
+
+impl Server<'_> {
+    /// Handles messages until the client shuts the server down.
+    fn serve(&mut self) -> Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(req) => {
+                    if self.connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let response = self.request(req)?;
+                    self.send(Message::Response(response))?;
+                }
+                Message::Notification(note) => self.notification(note)?,
+                Message::Response(_) => (),
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles the request `req`, producing a response.
+    ///
+    /// Requests with malformed parameters get an `InvalidParams` error response.
+    fn request(&self, req: Request) -> Result<Response> {
+        let id = req.id.clone();
+        let result = match req.method.as_str() {
+            request::HoverRequest::METHOD => {
+                let params: lsp_types::HoverParams = match request_params(req) {
+                    Ok(params) => params,
+                    Err(response) => return Ok(response),
+                };
+                let pos = params.text_document_position_params;
+                serde_json::to_value(self.hover(&pos.text_document.uri, pos.position))?
+            }
+            request::GotoDefinition::METHOD => {
+                let params: lsp_types::GotoDefinitionParams = match request_params(req) {
+                    Ok(params) => params,
+                    Err(response) => return Ok(response),
+                };
+                let pos = params.text_document_position_params;
+                serde_json::to_value(self.definition(pos.text_document.uri, pos.position))?
+            }
+            request::DocumentSymbolRequest::METHOD => {
+                let params: lsp_types::DocumentSymbolParams = match request_params(req) {
+                    Ok(params) => params,
+                    Err(response) => return Ok(response),
+                };
+                serde_json::to_value(self.symbols(&params.text_document.uri))?
+            }
+            _ => {
+                return Ok(Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request: {}", req.method),
+                ))
+            }
+        };
+        Ok(Response::new_ok(id, result))
+    }
+
+    /// Handles the notification `note`.
+    ///
+    /// Notifications can't be answered, so we log those with malformed parameters to the client,
+    /// and otherwise ignore them.
+    fn notification(&mut self, note: Notification) -> Result<()> {
+        match note.method.as_str() {
+            notification::DidOpenTextDocument::METHOD => {
+                let Some(params) =
+                    self.notification_params::<lsp_types::DidOpenTextDocumentParams>(note)?
+                else {
+                    return Ok(());
+                };
+                let doc = params.text_document;
+                self.documents.insert(doc.uri.clone(), doc.text);
+                self.publish_diagnostics(doc.uri, Some(doc.version))
+            }
+            notification::DidChangeTextDocument::METHOD => {
+                let Some(params) =
+                    self.notification_params::<lsp_types::DidChangeTextDocumentParams>(note)?
+                else {
+                    return Ok(());
+                };
+                let doc = params.text_document;
+                // We only ask for full synchronisation, so the last change has the whole text.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(doc.uri.clone(), change.text);
+                }
+                self.publish_diagnostics(doc.uri, Some(doc.version))
+            }
+            notification::DidCloseTextDocument::METHOD => {
+                let Some(params) =
+                    self.notification_params::<lsp_types::DidCloseTextDocumentParams>(note)?
+                else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+                self.documents.remove(&uri);
+                self.publish(uri, vec![], None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Decodes the parameters of `note`, logging an error to the client if they are malformed.
+    fn notification_params<P: DeserializeOwned>(&self, note: Notification) -> Result<Option<P>> {
+        match serde_json::from_value(note.params) {
+            Ok(params) => Ok(Some(params)),
+            Err(e) => {
+                let params = LogMessageParams {
+                    typ: MessageType::ERROR,
+                    message: format!("invalid parameters for {}: {e}", note.method),
+                };
+                self.send(Message::Notification(Notification::new(
+                    notification::LogMessage::METHOD.to_owned(),
+                    params,
+                )))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Checks the document at `uri`, and publishes its diagnostics to the client.
+    fn publish_diagnostics(&self, uri: Url, version: Option<i32>) -> Result<()> {
+        let Some(source) = self.documents.get(&uri) else {
+            return Ok(());
+        };
+        let diagnostics = analysis::diagnostics(source)
+            .iter()
+            .map(|d| to_lsp_diagnostic(&uri, source, d))
+            .collect();
+        self.publish(uri, diagnostics, version)
+    }
+
+    /// Publishes `diagnostics` for the document at `uri`.
+    fn publish(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+        version: Option<i32>,
+    ) -> Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version,
+        };
+        self.send(Message::Notification(Notification::new(
+            notification::PublishDiagnostics::METHOD.to_owned(),
+            params,
+        )))
+    }
+
+    /// Gets hover information for `position` in the document at `uri`.
+    fn hover(&self, uri: &Url, position: Position) -> Option<Hover> {
+        let source = self.documents.get(uri)?;
+        let hover = analysis::hover(source, offset(source, position))?;
+        Some(Hover {
+            contents: HoverContents::Scalar(MarkedString::LanguageString(LanguageString {
+                language: "pvc".to_owned(),
+                value: hover.text,
+            })),
+            range: Some(range(source, &hover.range)),
+        })
+    }
+
+    /// Gets the definition of the construct at `position` in the document at `uri`.
+    fn definition(&self, uri: Url, position: Position) -> Option<GotoDefinitionResponse> {
+        let source = self.documents.get(&uri)?;
+        let target = analysis::definition(source, offset(source, position))?;
+        let range = range(source, &target);
+        Some(GotoDefinitionResponse::Scalar(Location { uri, range }))
+    }
+
+    /// Gets the outline of the document at `uri`.
+    fn symbols(&self, uri: &Url) -> Option<DocumentSymbolResponse> {
+        let source = self.documents.get(uri)?;
+        let symbols = analysis::symbols(source)
+            .into_iter()
+            .map(|s| {
+                #[allow(deprecated)] // `deprecated` is deprecated, but we still have to set it.
+                DocumentSymbol {
+                    name: s.name,
+                    detail: None,
+                    kind: match s.kind {
+                        analysis::SymbolKind::Procedure => SymbolKind::FUNCTION,
+                        analysis::SymbolKind::View => SymbolKind::INTERFACE,
+                        analysis::SymbolKind::Constraint => SymbolKind::PROPERTY,
+                    },
+                    tags: None,
+                    deprecated: None,
+                    range: range(source, &s.range),
+                    selection_range: range(source, &s.selection),
+                    children: None,
+                }
+            })
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    /// Sends `message` to the client.
+    fn send(&self, message: Message) -> Result<()> {
+        self.connection
+            .sender
+            .send(message)
+            .map_err(|_| Error::Disconnected)
+    }
+}
+
+/// Decodes the parameters of `req`, or makes an `InvalidParams` error response if they are
+/// malformed.
+fn request_params<P: DeserializeOwned>(req: Request) -> std::result::Result<P, Response> {
+    serde_json::from_value(req.params).map_err(|e| {
+        Response::new_err(
+            req.id,
+            lsp_server::ErrorCode::InvalidParams as i32,
+            format!("invalid parameters for {}: {e}", req.method),
+        )
+    })
+}
+
+/// Converts `diagnostic`, from the document at `uri` with text `source`, into an LSP diagnostic.
+///
+/// The primary label becomes the range of the diagnostic, and secondary labels become related
+/// information.
+fn to_lsp_diagnostic(
+    uri: &Url,
+    source: &str,
+    diagnostic: &diagnostic::Diagnostic,
+) -> lsp_types::Diagnostic {
+    let primary = diagnostic
+        .labels
+        .iter()
+        .find(|l| l.primary)
+        .or_else(|| diagnostic.labels.first());
+    let related = diagnostic
+        .labels
+        .iter()
+        .filter(|l| !l.primary && !l.message.is_empty())
+        .map(|l| DiagnosticRelatedInformation {
+            location: Location {
+                uri: uri.clone(),
+                range: range(source, &l.range),
+            },
+            message: l.message.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut message = diagnostic.message.clone();
+    if let Some(label) = primary.filter(|l| !l.message.is_empty()) {
+        message = format!("{message}: {}", label.message);
+    }
+    for note in &diagnostic.notes {
+        message = format!("{message}\n{note}");
+    }
+
+    lsp_types::Diagnostic {
+        range: primary.map_or_else(Range::default, |l| range(source, &l.range)),
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Note => DiagnosticSeverity::INFORMATION,
+            Severity::Help => DiagnosticSeverity::HINT,
+        }),
+        code: diagnostic
+            .code
+            .map(|c| NumberOrString::String(c.to_string())),
+        source: Some("starling".to_owned()),
+        message,
+        related_information: (!related.is_empty()).then_some(related),
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+/// Converts the byte range `bytes` of `source` into an LSP range.
+fn range(source: &str, bytes: &std::ops::Range<usize>) -> Range {
+    Range {
+        start: position(source, bytes.start),
+        end: position(source, bytes.end),
+    }
+}
+
+/// Converts the byte offset `offset` of `source` into an LSP position.
+///
+/// LSP positions count characters in UTF-16 code units, and offsets past the end of the source
+/// clamp to the end.
+fn position(source: &str, offset: usize) -> Position {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map_or(0, |n| n + 1);
+    Position {
+        line: to_u32(before.matches('\n').count()),
+        character: to_u32(before[line_start..].encode_utf16().count()),
+    }
+}
+
+/// Converts the LSP position `position` in `source` into a byte offset.
+///
+/// Positions past the end of a line clamp to the end of that line.
+fn offset(source: &str, position: Position) -> usize {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum();
+    let line = source[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+/// Converts `n` to a `u32`, saturating if it is too large.
+fn to_u32(n: usize) -> u32 {
+    u32::try_from(n).unwrap_or(u32::MAX)
+}
+
+/// Errors that can occur while running the language server.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The client broke protocol.
+    #[error("protocol error: {0}")]
+    Protocol(#[from] lsp_server::ProtocolError),
+    /// We couldn't encode a reply.
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The connection to the client closed unexpectedly.
+    #[error("the client disconnected")]
+    Disconnected,
+    /// The I/O threads failed.
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Shorthand for results of language server operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that positions and offsets convert back and forth, including across multi-byte
+    /// characters.
+    #[test]
+    fn position_round_trip() {
+        let source = "ab\n(* ü𝄞 *) x\n";
+        let x = source.find('x').unwrap();
+        let pos = position(source, x);
+        assert_eq!(Position::new(1, 10), pos);
+        assert_eq!(x, offset(source, pos));
+        assert_eq!(Position::new(2, 0), position(source, source.len()));
+    }
+
+    /// Tests that the server survives requests and notifications with malformed parameters.
+    #[test]
+    fn serve_bad_params() {
+        let (server, client) = Connection::memory();
+        let thread = std::thread::spawn(move || {
+            Server {
+                connection: &server,
+                documents: HashMap::new(),
+            }
+            .serve()
+        });
+        let request = |id: i32, method: &str, params: serde_json::Value| {
+            client
+                .sender
+                .send(Message::Request(Request::new(
+                    id.into(),
+                    method.to_owned(),
+                    params,
+                )))
+                .unwrap();
+        };
+        let receive = || client.receiver.recv().unwrap();
+
+        let bad = serde_json::json!({ "textDocument": 42 });
+        request(1, request::HoverRequest::METHOD, bad.clone());
+        let Message::Response(response) = receive() else {
+            panic!("expected a response");
+        };
+        assert_eq!(
+            Some(lsp_server::ErrorCode::InvalidParams as i32),
+            response.error.map(|e| e.code)
+        );
+
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                notification::DidOpenTextDocument::METHOD.to_owned(),
+                bad,
+            )))
+            .unwrap();
+        let Message::Notification(log) = receive() else {
+            panic!("expected a notification");
+        };
+        assert_eq!(notification::LogMessage::METHOD, log.method);
+
+        let uri = "file:///t.pvc";
+        request(
+            2,
+            request::DocumentSymbolRequest::METHOD,
+            serde_json::json!({ "textDocument": { "uri": uri } }),
+        );
+        let Message::Response(response) = receive() else {
+            panic!("expected a response");
+        };
+        assert_eq!(Some(serde_json::Value::Null), response.result);
+        assert!(response.error.is_none());
+
+        request(3, request::Shutdown::METHOD, serde_json::Value::Null);
+        let Message::Response(response) = receive() else {
+            panic!("expected a response");
+        };
+        assert!(response.error.is_none());
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                notification::Exit::METHOD.to_owned(),
+                (),
+            )))
+            .unwrap();
+        thread.join().unwrap().unwrap();
+    }
+}
@@ -0,0 +1,485 @@
+//! Source analyses backing the language server.
+//!
+//! Each analysis takes the full text of a script and works in byte offsets, leaving conversion to
+//! and from editor positions to the server proper.  Analyses reparse the script on every request;
+//! scripts are small, and this keeps the server stateless apart from the text of open documents.
+//! They parse with error recovery, so that a script being edited still has an outline, and shows
+//! all of its syntax errors at once.
+
+use std::ops::Range;
+
+use pest::Span;
+
+use super::super::{
+    binder,
+    diagnostic::{self, Diagnostic, FileId, ToDiagnostic},
+    language::{
+        ast::{self, constraint, stm, view, Identifier},
+        cfg,
+        expr::Expr,
+        tagged::Tagged,
+        var::Scope,
+    },
+    parser, pipeline, printer, vcgen,
+};
+
+/// Runs the checking pipeline over `source`, returning any diagnostics.
+///
+/// Labels in the diagnostics refer to the default file ID.
+#[must_use]
+pub fn diagnostics(source: &str) -> Vec<Diagnostic> {
+    let file = FileId::default();
+    let ast = match pipeline::parse(source, file) {
+        Ok(ast) => ast,
+        Err(diagnostics) => return diagnostics,
+    };
+    let (program, mut diagnostics) = match pipeline::check(&ast.item, file) {
+        Ok(checked) => checked,
+        Err(diagnostics) => return diagnostics,
+    };
+    if let Err(e) = vcgen::generate(&program) {
+        diagnostics.push(e.to_diagnostic(file));
+    }
+    diagnostics
+}
+
+/// Information to show when hovering over part of a script.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Hover {
+    /// The range of the construct being hovered over.
+    pub range: Range<usize>,
+    /// The information, as PVC source.
+    pub text: String,
+}
+
+/// Gets hover information for the view atom or variable at `offset` in `source`.
+///
+/// View atoms show the prototype of their view, and variables show their declaration.
+#[must_use]
+pub fn hover(source: &str, offset: usize) -> Option<Hover> {
+    let (ast, _) = parser::parse_recovering(source);
+    if let Some((range, name)) = view_at(&ast.item, offset) {
+        let proto = view_prototype(&ast.item, &name)?;
+        return Some(Hover {
+            range,
+            text: format!("view {}", printer::prototype(proto)),
+        });
+    }
+
+    let program = binder::bind(&ast.item).ok()?;
+    let (range, sym) = var_at(&program, offset)?;
+    let (_, record) = program.variables.get(&sym)?;
+    let keyword = match record.scope {
+        Scope::Shared => "var ",
+        Scope::Thread => "threadvar ",
+        Scope::Bound => "",
+    };
+    Some(Hover {
+        range,
+        text: format!("{keyword}{} : {}", sym.name, type_name(&program, record.ty)),
+    })
+}
+
+/// Gets the range of the declaration of the view atom or variable at `offset` in `source`.
+#[must_use]
+pub fn definition(source: &str, offset: usize) -> Option<Range<usize>> {
+    let (ast, _) = parser::parse_recovering(source);
+    if let Some((_, name)) = view_at(&ast.item, offset) {
+        return span_range(view_prototype(&ast.item, &name)?.name.meta);
+    }
+
+    let program = binder::bind(&ast.item).ok()?;
+    let (_, sym) = var_at(&program, offset)?;
+    let (_, record) = program.variables.get(&sym)?;
+    span_range(*record.origin.meta()?)
+}
+
+/// A symbol in the outline of a script.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Symbol {
+    /// The name of the symbol.
+    pub name: String,
+    /// The kind of the symbol.
+    pub kind: SymbolKind,
+    /// The range of the whole declaration of the symbol.
+    pub range: Range<usize>,
+    /// The range of the part of the declaration that names the symbol.
+    pub selection: Range<usize>,
+}
+
+/// Kinds of symbol in the outline of a script.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum SymbolKind {
+    /// A procedure.
+    Procedure,
+    /// A view.
+    View,
+    /// A constraint.
+    Constraint,
+}
+
+/// Gets the outline of the procedures, views, and constraints declared in `source`.
+///
+/// Constraints have no names, so we name them after their view patterns.
+#[must_use]
+pub fn symbols(source: &str) -> Vec<Symbol> {
+    let (ast, _) = parser::parse_recovering(source);
+
+    let mut symbols = vec![];
+    for decl in &ast.item.decls {
+        match &decl.item {
+            ast::Decl::Procedure(p) => symbols.extend(named(
+                SymbolKind::Procedure,
+                decl.meta,
+                &p.prototype.item.name,
+            )),
+            ast::Decl::View(v) => symbols.extend(
+                v.contents
+                    .iter()
+                    .filter_map(|p| named(SymbolKind::View, p.meta, &p.item.name)),
+            ),
+            ast::Decl::Constraint(c) => symbols.extend(constraint_symbol(source, decl.meta, c)),
+            _ => (),
+        }
+    }
+    symbols
+}
+
+/// Makes a symbol named by `name` and declared by the construct with metadata `meta`.
+fn named(
+    kind: SymbolKind,
+    meta: Option<Span>,
+    name: &Tagged<Option<Span>, Identifier>,
+) -> Option<Symbol> {
+    Some(Symbol {
+        name: name.item.to_string(),
+        kind,
+        range: span_range(meta)?,
+        selection: span_range(name.meta)?,
+    })
+}
+
+/// Makes a symbol for the constraint `constraint`, whose declaration has metadata `meta`.
+fn constraint_symbol(
+    source: &str,
+    meta: Option<Span>,
+    constraint: &constraint::Decl<Option<Span>, Identifier>,
+) -> Option<Symbol> {
+    let first = span_range(constraint.views.first()?.meta)?;
+    let last = span_range(constraint.views.last()?.meta)?;
+    let selection = first.start..last.end;
+    Some(Symbol {
+        name: format!("constraint {}", &source[selection.clone()]),
+        kind: SymbolKind::Constraint,
+        range: span_range(meta)?,
+        selection,
+    })
+}
+
+/// Gets the trimmed byte range of the span in `meta`, if any.
+fn span_range(meta: Option<Span>) -> Option<Range<usize>> {
+    meta.map(|s| diagnostic::range(&s))
+}
+
+/// Checks whether the construct with metadata `meta` covers `offset`.
+///
+/// We count the offset just after the construct as covering it, as that is where editors put the
+/// cursor after typing an identifier.
+fn covers(meta: Option<Span>, offset: usize) -> bool {
+    span_range(meta).is_some_and(|r| r.start <= offset && offset <= r.end)
+}
+
+/// Finds the name of the view atom at `offset` in `program`, along with its range.
+fn view_at(
+    program: &ast::Program<Option<Span>, Identifier>,
+    offset: usize,
+) -> Option<(Range<usize>, String)> {
+    let mut found = None;
+    walk_program(&program.decls, &mut |node| {
+        let name = match node {
+            Node::View(name) => name,
+            Node::Expr(_) => return,
+        };
+        if found.is_none() && covers(name.meta, offset) {
+            found = span_range(name.meta).map(|r| (r, name.item.to_string()));
+        }
+    });
+    found
+}
+
+/// Finds the prototype of the view named `name` in `program`.
+fn view_prototype<'a, 'inp>(
+    program: &'a ast::Program<'inp, Option<Span<'inp>>, Identifier<'inp>>,
+    name: &str,
+) -> Option<&'a view::decl::Prototype<'inp, Option<Span<'inp>>, Identifier<'inp>>> {
+    program.decls.iter().find_map(|d| match &d.item {
+        ast::Decl::View(v) => v
+            .contents
+            .iter()
+            .map(|p| &p.item)
+            .find(|p| p.name.item == name),
+        _ => None,
+    })
+}
+
+/// Finds the symbol of the variable at `offset` in `program`, along with its range.
+///
+/// This covers both uses of variables and their declarations.
+fn var_at(program: &binder::Bound, offset: usize) -> Option<(Range<usize>, cfg::var::Symbol)> {
+    let declared = program.variables.iter().find_map(|(sym, record)| {
+        let meta = *record.origin.meta()?;
+        covers(meta, offset).then(|| Some((span_range(meta)?, sym.clone())))?
+    });
+    if declared.is_some() {
+        return declared;
+    }
+
+    let mut found = None;
+    let mut visit = |node: Node<_, _>| {
+        if let Node::Expr(e) = node {
+            e.for_each_tagged_var(&mut |v: &Tagged<Option<Span>, cfg::var::Symbol>| {
+                if found.is_none() && covers(v.meta, offset) {
+                    found = span_range(v.meta).map(|r| (r, v.item.clone()));
+                }
+            });
+        }
+    };
+    for proc in &program.procedures {
+        for spec in proc.requires.iter().chain(&proc.ensures) {
+            walk_assertion(&spec.item, &mut visit);
+        }
+        walk_list(&proc.body, &mut visit);
+    }
+    for constraint in &program.constraints {
+        walk_constraint(&constraint.item, &mut visit);
+    }
+    found
+}
+
+/// Gets a readable name for the type with symbol `ty` in `program`.
+fn type_name(program: &binder::Bound, ty: egg::Symbol) -> String {
+    match program.types.get(&ty) {
+        // Anonymous types have synthesised names that can't be written in PVC, so we show their
+        // definitions instead; we also show intrinsic types by their keywords.
+        Some(t) if ty.as_str().contains(':') || t.origin == cfg::Origin::Intrinsic => {
+            printer::ty(&t.ty)
+        }
+        _ => ty.to_string(),
+    }
+}
+
+//
+// Walking over programs
+//
+
+/// A construct of interest found while walking a program.
+enum Node<'a, M, V> {
+    /// An expression.
+    Expr(&'a Expr<M, V>),
+    /// The name of a view atom in an assertion or pattern.
+    View(&'a Tagged<M, Identifier<'a>>),
+}
+
+/// Walks over the procedure bodies and constraints in `decls`.
+fn walk_program<'a, 'inp, M>(
+    decls: &'a [Tagged<M, ast::Decl<'inp, M, Identifier<'inp>>>],
+    f: &mut impl FnMut(Node<'a, M, Identifier<'a>>),
+) {
+    for decl in decls {
+        match &decl.item {
+            ast::Decl::Procedure(p) => {
+                for spec in p.requires.iter().chain(&p.ensures) {
+                    walk_assertion(&spec.item, f);
+                }
+                walk_list(&p.body, f);
+            }
+            ast::Decl::Constraint(c) => {
+                for pattern in &c.views {
+                    for atom in &pattern.item.contents {
+                        f(Node::View(&atom.item.name));
+                    }
+                }
+                walk_constraint(c, f);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Walks over the expressions in the constraint `constraint`.
+///
+/// The names of views in patterns have a different type in bound programs, so we don't walk them
+/// here.
+fn walk_constraint<'a, M, V>(
+    constraint: &'a constraint::Decl<M, V>,
+    f: &mut impl FnMut(Node<'a, M, V>),
+) {
+    use view::pattern::Argument;
+
+    for pattern in &constraint.views {
+        for atom in &pattern.item.contents {
+            for arg in atom.item.args.iter().chain([&atom.item.iterator]) {
+                if let Argument::Expr(e) = &arg.item {
+                    f(Node::Expr(e));
+                }
+            }
+        }
+    }
+    if let Some(e) = constraint.body.item.expr() {
+        f(Node::Expr(&e.item));
+    }
+}
+
+/// Walks over the triples in `list`.
+fn walk_list<'a, 'inp, M, V>(list: &'a stm::List<'inp, M, V>, f: &mut impl FnMut(Node<'a, M, V>)) {
+    for triple in list {
+        for assertion in triple.item.pre.iter().chain(&triple.item.post) {
+            walk_assertion(&assertion.item, f);
+        }
+        walk_stm(&triple.item.stm.item, f);
+    }
+}
+
+/// Walks over the statement `stm`.
+fn walk_stm<'a, 'inp, M, V>(stm: &'a stm::Stm<'inp, M, V>, f: &mut impl FnMut(Node<'a, M, V>)) {
+    match stm {
+        stm::Stm::Assign(a) => {
+            if let Some(l) = &a.lvalue {
+                f(Node::Expr(&l.item));
+            }
+            f(Node::Expr(&a.rvalue.item));
+        }
+        stm::Stm::Atomic(stm::Atomic { body, .. }) | stm::Stm::Block(body) => walk_list(body, f),
+        stm::Stm::Call(c) => c.args.iter().for_each(|a| f(Node::Expr(&a.item))),
+        stm::Stm::Primitive(p) => p.args.iter().for_each(|a| f(Node::Expr(&a.item))),
+        stm::Stm::Ite(i) => {
+            if let Some(c) = i.cond.item.as_det() {
+                f(Node::Expr(c));
+            }
+            walk_stm(&i.true_branch.item, f);
+            walk_stm(&i.false_branch.item, f);
+        }
+        stm::Stm::Nop => (),
+        stm::Stm::Repeat(r) => {
+            walk_assertion(&r.invariant.item, f);
+            walk_list(&r.body, f);
+            if let Some(c) = r.cond.item.as_det() {
+                f(Node::Expr(c));
+            }
+        }
+        stm::Stm::While(w) => {
+            if let Some(c) = w.cond.item.as_det() {
+                f(Node::Expr(c));
+            }
+            walk_assertion(&w.invariant.item, f);
+            walk_stm(&w.body.item, f);
+        }
+    }
+}
+
+/// Walks over the view assertion `assertion`.
+fn walk_assertion<'a, 'inp, M, V>(
+    assertion: &'a view::Assertion<'inp, M, V>,
+    f: &mut impl FnMut(Node<'a, M, V>),
+) {
+    match assertion {
+        view::Assertion::Atom(a) => {
+            f(Node::View(&a.item.name));
+            a.item.args.iter().for_each(|a| f(Node::Expr(&a.item)));
+        }
+        view::Assertion::Emp => (),
+        view::Assertion::Guarded(g) => {
+            f(Node::Expr(&g.guard));
+            walk_assertion(&g.view, f);
+        }
+        view::Assertion::Ite(i) => {
+            if let Some(c) = i.cond.item.as_det() {
+                f(Node::Expr(c));
+            }
+            walk_assertion(&i.true_branch, f);
+            walk_assertion(&i.false_branch, f);
+        }
+        view::Assertion::Iterated(i) => {
+            walk_assertion(&i.item, f);
+            f(Node::Expr(&i.iterator.item));
+        }
+        view::Assertion::Join(l, r) => {
+            walk_assertion(l, f);
+            walk_assertion(r, f);
+        }
+        view::Assertion::Local(l) => f(Node::Expr(&l.item)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "program P;
+var x : integer;
+threadvar t : boolean;
+view V(n : integer);
+procedure Q;
+begin
+  { V(x) } <| t := true |> { V(1) }
+end;
+constraint V(m) entails m > x;
+begin
+end.";
+
+    /// Gets the offset of the `nth` occurrence of `needle` in the test source.
+    fn at(needle: &str, nth: usize) -> usize {
+        SOURCE.match_indices(needle).nth(nth).unwrap().0
+    }
+
+    /// Tests hovering over a view atom and over variables.
+    #[test]
+    fn hover_atom_and_vars() {
+        let hover = hover(SOURCE, at("V(x)", 0)).unwrap();
+        assert_eq!("view V(n : integer)", hover.text);
+        assert_eq!(at("V(x)", 0)..at("V(x)", 0) + 1, hover.range);
+
+        assert_eq!(
+            "var x : integer",
+            super::hover(SOURCE, at("x)", 0) + 1).unwrap().text
+        );
+        assert_eq!(
+            "threadvar t : boolean",
+            super::hover(SOURCE, at("t :=", 0)).unwrap().text
+        );
+    }
+
+    /// Tests going to the definitions of a view and a variable.
+    #[test]
+    fn definition_of_atom_and_var() {
+        let view = at("V(n", 0);
+        assert_eq!(Some(view..view + 1), definition(SOURCE, at("V(m)", 0)));
+        let var = at("x :", 0);
+        assert_eq!(Some(var..var + 1), definition(SOURCE, at("> x", 0) + 2));
+    }
+
+    /// Tests the outline of the test source.
+    #[test]
+    fn outline() {
+        let names: Vec<_> = symbols(SOURCE)
+            .into_iter()
+            .map(|s| (s.name, s.kind))
+            .collect();
+        assert_eq!(
+            vec![
+                ("V".to_owned(), SymbolKind::View),
+                ("Q".to_owned(), SymbolKind::Procedure),
+                ("constraint V(m)".to_owned(), SymbolKind::Constraint),
+            ],
+            names
+        );
+    }
+
+    /// Tests that parse errors come through as diagnostics.
+    #[test]
+    fn parse_error_diagnostic() {
+        let diagnostics = diagnostics("program P; begin");
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(Some(diagnostic::code::SYNTAX), diagnostics[0].code);
+    }
+}
@@ -7,12 +7,17 @@ use std::{
     process::exit,
 };
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 
-use starling::language::{
-    ast::Identifier,
-    expr::map::{HasMeta, HasVars},
-    var::Variable,
+use starling::{
+    diagnostic::{self, Diagnostic, FileId, Files, ToDiagnostic},
+    language::{
+        ast::Identifier,
+        expr::map::{HasMeta, HasVars},
+        tagged::Spanned,
+        var::Variable,
+    },
+    parser::Program,
 };
 
 /// Automated concurrent algorithm proof checker (second edition).
@@ -20,6 +25,9 @@ use starling::language::{
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 struct Cli {
+    /// Format in which to report errors and other diagnostics.
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
     #[command(subcommand)]
     command: Commands,
 }
@@ -33,6 +41,57 @@ enum Commands {
         #[arg(required = true)]
         path: PathBuf,
     },
+    /// Generates verification conditions for a PVC script.
+    Vcgen {
+        /// Format in which to emit the conditions.
+        #[arg(long, value_enum, default_value_t = Format::Smt2)]
+        format: Format,
+        /// Directory in which to write one file per condition; if absent, conditions are written
+        /// to standard output.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Path to the PVC script to analyse.
+        #[arg(required = true)]
+        path: PathBuf,
+    },
+    /// Verifies a PVC script by discharging its verification conditions with an SMT solver.
+    Verify {
+        /// Solver to use (z3, cvc5, or yices).
+        #[arg(long, default_value = "z3")]
+        solver: starling::solver::Kind,
+        /// Path to the solver binary, if it is not on the path under its usual name.
+        #[arg(long)]
+        solver_path: Option<PathBuf>,
+        /// Path to the PVC script to verify.
+        #[arg(required = true)]
+        path: PathBuf,
+    },
+    /// Infers definitions for the indefinite constraints of a PVC script with a CHC solver.
+    Infer {
+        /// CHC solver to use (z3 or eldarica).
+        #[arg(long, default_value = "z3")]
+        solver: starling::solver::horn::Kind,
+        /// Path to the solver binary, if it is not on the path under its usual name.
+        #[arg(long)]
+        solver_path: Option<PathBuf>,
+        /// Path to the PVC script whose constraints are to be inferred.
+        #[arg(required = true)]
+        path: PathBuf,
+    },
+    /// Formats PVC scripts in place, keeping their comments.
+    Fmt {
+        /// Check that the scripts are formatted, rather than formatting them.
+        #[arg(long)]
+        check: bool,
+        /// Number of spaces in each level of indentation.
+        #[arg(long, default_value_t = starling::printer::Config::default().indent)]
+        indent: usize,
+        /// Paths to the PVC scripts to format.
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Runs a language server for PVC scripts over standard input and output.
+    Lsp,
     /// Simplifies a PVC expression.
     ExprSimp {
         #[arg(required = true)]
@@ -40,38 +99,265 @@ enum Commands {
     },
 }
 
+/// Output formats for diagnostics.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum ErrorFormat {
+    /// Human-readable diagnostics with source excerpts.
+    Human,
+    /// One JSON object per diagnostic, for editors and other tools.
+    Json,
+}
+
+/// Output formats for verification conditions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+enum Format {
+    /// SMT-LIB 2 scripts, one per condition.
+    Smt2,
+    /// One SMT-LIB 2 script of constrained Horn clauses, over the indefinite constraints.
+    Horn,
+}
+
 fn main() -> eyre::Result<()> {
     color_eyre::install()?;
 
     let args = Cli::parse();
-    let (cur_path, err) = match args.command {
-        Commands::Lint { path } => (path.to_string_lossy().into_owned(), lint(&path)),
-        Commands::ExprSimp { expr } => (String::from("(none)"), simplify_expr(&expr)),
+    let mut session = Session {
+        files: Files::default(),
+        error_format: args.error_format,
     };
-
-    match err {
-        Err(Error::Parser(starling::parser::Error::Parse(e))) => {
-            eprintln!("Parse error in file {cur_path}:");
-            eprintln!("{e}");
-            exit(1);
+    let result = match args.command {
+        Commands::Lint { path } => lint(&mut session, &path),
+        Commands::Vcgen {
+            format,
+            output,
+            path,
+        } => vcgen(&mut session, &path, format, output.as_deref()),
+        Commands::Verify {
+            solver,
+            solver_path,
+            path,
+        } => {
+            let config = starling::solver::Config {
+                kind: solver,
+                path: solver_path,
+            };
+            verify(&mut session, &path, &config)
+        }
+        Commands::Infer {
+            solver,
+            solver_path,
+            path,
+        } => {
+            let config = starling::solver::horn::Config {
+                kind: solver,
+                path: solver_path,
+            };
+            infer(&mut session, &path, &config)
         }
-        e => e,
-    }?;
+        Commands::Fmt {
+            check,
+            indent,
+            paths,
+        } => {
+            let config = starling::printer::Config { indent };
+            fmt(&mut session, &paths, config, check)
+        }
+        Commands::Lsp => starling::lsp::run().map_err(Error::from),
+        Commands::ExprSimp { expr } => simplify_expr(&mut session, expr),
+    };
 
+    if let Err(err) = result {
+        for diagnostic in err.into_diagnostics() {
+            session.emit(&diagnostic);
+        }
+        exit(1);
+    }
     Ok(())
 }
 
-fn lint(path: impl AsRef<Path>) -> Result<()> {
-    let contents = std::fs::read_to_string(path)?;
-    let ast = starling::parser::parse(&contents)?;
+/// State shared across a run of the CLI.
+struct Session {
+    /// The files loaded so far, for use in diagnostics.
+    files: Files,
+    /// The format in which to emit diagnostics.
+    error_format: ErrorFormat,
+}
 
-    println!("{ast:#?}");
+impl Session {
+    /// Loads the file at `path` into the session.
+    fn load(&mut self, path: &Path) -> Result<FileId> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(self.files.add(path.to_string_lossy(), contents))
+    }
 
-    Ok(())
+    /// Emits a diagnostic to standard error.
+    fn emit(&self, diagnostic: &Diagnostic) {
+        let rendered = match self.error_format {
+            ErrorFormat::Human => diagnostic::human::render(&self.files, diagnostic),
+            ErrorFormat::Json => diagnostic::json::render(&self.files, diagnostic),
+        };
+        eprintln!("{rendered}");
+    }
+}
+
+/// Converts an error that can be reported as a diagnostic in `file` into a CLI error.
+fn report(err: &impl ToDiagnostic, file: FileId) -> Error {
+    Error::Diagnostics(vec![err.to_diagnostic(file)])
+}
+
+/// Parses `source`, reporting every syntax error as a diagnostic in `file`.
+fn parse(source: &str, file: FileId) -> Result<Spanned<'_, Program<'_>>> {
+    starling::pipeline::parse(source, file).map_err(Error::Diagnostics)
+}
+
+/// Loads, parses, and checks the script at `path`, then passes its bound program to `then`.
+///
+/// Warnings found while checking the script are emitted before calling `then`.
+fn check<T>(
+    session: &mut Session,
+    path: &Path,
+    then: impl FnOnce(&Session, FileId, &starling::binder::Bound) -> Result<T>,
+) -> Result<T> {
+    let file = session.load(path)?;
+    let ast = parse(session.files.get(file).source(), file)?;
+    let (program, warnings) =
+        starling::pipeline::check(&ast.item, file).map_err(Error::Diagnostics)?;
+    for warning in &warnings {
+        session.emit(warning);
+    }
+    then(session, file, &program)
 }
 
-fn simplify_expr(input: &str) -> Result<()> {
-    let ast = starling::parser::expr(input)?;
+fn lint(session: &mut Session, path: &Path) -> Result<()> {
+    check(session, path, |_, _, program| {
+        println!("{program:#?}");
+        Ok(())
+    })
+}
+
+fn vcgen(session: &mut Session, path: &Path, format: Format, output: Option<&Path>) -> Result<()> {
+    use starling::vcgen::smt2;
+    use std::io::Write;
+
+    check(session, path, |_, file, program| {
+        let conditions = starling::vcgen::generate(program).map_err(|e| report(&e, file))?;
+
+        if format == Format::Horn {
+            use starling::vcgen::horn;
+            if let Some(dir) = output {
+                std::fs::create_dir_all(dir)?;
+                let mut file = std::fs::File::create(dir.join("horn.smt2"))?;
+                horn::write(program, &conditions, &mut file)?;
+            } else {
+                horn::write(program, &conditions, &mut std::io::stdout().lock())?;
+            }
+            return Ok(());
+        }
+
+        if let Some(dir) = output {
+            std::fs::create_dir_all(dir)?;
+            for cond in &conditions {
+                let mut file = std::fs::File::create(dir.join(format!("{}.smt2", cond.name())))?;
+                smt2::write(program, cond, &mut file)?;
+            }
+        } else {
+            let mut stdout = std::io::stdout().lock();
+            for (i, cond) in conditions.iter().enumerate() {
+                if i != 0 {
+                    writeln!(stdout, "(reset)")?;
+                }
+                smt2::write(program, cond, &mut stdout)?;
+            }
+        }
+
+        Ok(())
+    })
+}
+
+fn verify(session: &mut Session, path: &Path, config: &starling::solver::Config) -> Result<()> {
+    use starling::solver::Verdict;
+
+    check(session, path, |session, file, program| {
+        let conditions = starling::vcgen::generate(program).map_err(|e| report(&e, file))?;
+
+        let (mut failed, mut unknown) = (0, 0);
+        for outcome in starling::solver::verify(config, program, &conditions)? {
+            println!("{}: {}", outcome.condition.name(), outcome.verdict);
+            match outcome.verdict {
+                Verdict::Valid => (),
+                Verdict::Invalid(_) => failed += 1,
+                Verdict::Unknown => unknown += 1,
+            }
+            if let Some(diagnostic) = outcome.to_diagnostic(program, file) {
+                session.emit(&diagnostic);
+            }
+        }
+
+        if failed + unknown == 0 {
+            Ok(())
+        } else {
+            Err(Error::Unverified { failed, unknown })
+        }
+    })
+}
+
+fn infer(
+    session: &mut Session,
+    path: &Path,
+    config: &starling::solver::horn::Config,
+) -> Result<()> {
+    use starling::solver::horn::Inference;
+
+    check(session, path, |_, file, program| {
+        let conditions = starling::vcgen::generate(program).map_err(|e| report(&e, file))?;
+
+        match config.infer(program, &conditions)? {
+            Inference::Solved(definitions) => {
+                for def in definitions {
+                    println!("{};", starling::printer::constraint(&def.to_decl(program)));
+                }
+                Ok(())
+            }
+            Inference::Refuted => Err(Error::Refuted),
+            Inference::Unknown => Err(Error::Uninferred),
+        }
+    })
+}
+
+fn fmt(
+    session: &mut Session,
+    paths: &[PathBuf],
+    config: starling::printer::Config,
+    check: bool,
+) -> Result<()> {
+    let mut unformatted = 0;
+    for path in paths {
+        let file = session.load(path)?;
+        let source = session.files.get(file).source();
+        let ast = parse(source, file)?;
+        let formatted = starling::printer::format(source, &ast.item, config);
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", path.display());
+            unformatted += 1;
+        } else {
+            std::fs::write(path, formatted)?;
+        }
+    }
+
+    if unformatted == 0 {
+        Ok(())
+    } else {
+        Err(Error::Unformatted(unformatted))
+    }
+}
+
+fn simplify_expr(session: &mut Session, input: String) -> Result<()> {
+    let file = session.files.add("<expr>", input);
+    let ast =
+        starling::parser::expr(session.files.get(file).source()).map_err(|e| report(&e, file))?;
     let symbol_expr = ast.item.map_var(Identifier::into_symbol);
     let no_meta_expr = symbol_expr.map_meta(|_| ());
     let simpl_expr = starling::language::expr::egg::simp(&no_meta_expr);
@@ -84,8 +370,32 @@ fn simplify_expr(input: &str) -> Result<()> {
 enum Error {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
-    #[error("Parser error: {0}")]
-    Parser(#[from] starling::parser::Error),
+    #[error("{} error(s) in the script", .0.len())]
+    Diagnostics(Vec<Diagnostic>),
+    #[error("SMT-LIB error: {0}")]
+    Smt2(#[from] starling::vcgen::smt2::Error),
+    #[error("Language server error: {0}")]
+    Lsp(#[from] starling::lsp::Error),
+    #[error("Solver error: {0}")]
+    Solver(#[from] starling::solver::Error),
+    #[error("{failed} conditions failed and {unknown} were undecided")]
+    Unverified { failed: usize, unknown: usize },
+    #[error("no definitions of the indefinite constraints make every condition hold")]
+    Refuted,
+    #[error("the solver could not infer definitions of the indefinite constraints")]
+    Uninferred,
+    #[error("{0} script(s) are not formatted")]
+    Unformatted(usize),
+}
+
+impl Error {
+    /// Converts this error into diagnostics for reporting.
+    fn into_diagnostics(self) -> Vec<Diagnostic> {
+        match self {
+            Self::Diagnostics(d) => d,
+            e => vec![Diagnostic::error(e.to_string())],
+        }
+    }
 }
 
 type Result<T> = std::result::Result<T, Error>;
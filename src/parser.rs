@@ -3,18 +3,26 @@
 //! The PVC parser is based on `pest`, and has two parts.  First, we use the Pest grammar
 //! (`starling.pest`) to parse PVC scripts into Pest pairs.  Then, we use the recursive descent
 //! functions within the submodules of this module to massage those pairs into AST nodes (this stage
-//! cannot produce further parse errors so long as the Pest grammar is well-formed).
+//! cannot produce further parse errors so long as the Pest grammar and the functions agree on the
+//! shape of the pairs; if they don't, it reports an internal error rather than panicking).
+
+use std::ops::Range;
 
 use pest::Parser;
 
 pub use expr::Expr;
+pub use program::Program;
 
-use super::language::{ast, tagged::Spanned};
+use super::{
+    diagnostic::{self, Diagnostic, FileId, Label, ToDiagnostic},
+    language::{ast, tagged::Spanned},
+};
 
 mod call;
 mod constraint;
 mod expr;
 mod program;
+mod recovery;
 mod stm;
 mod typing;
 mod utils;
@@ -31,10 +39,47 @@ struct Pvc;
 /// # Errors
 ///
 /// Fails if `input` could not be parsed correctly.
-pub fn parse(input: &str) -> Result<Spanned<program::Program>> {
+pub fn parse(input: &str) -> Result<Spanned<'_, Program<'_>>> {
     let pairs = Pvc::parse(Rule::program, input).map_err(Box::new)?;
-    let pair = utils::one(pairs);
-    Ok(utils::lift_many(pair, program::parse))
+    build(pairs, Rule::program, input, program::parse)
+}
+
+/// Parses a program, recovering from syntax errors.
+///
+/// Where [parse] stops at the first syntax error, this function skips over any declaration or
+/// statement it can't parse, up to the next `;` or `end`, and carries on.  It returns the program
+/// made from whatever it could parse, along with the errors in the skipped parts; the first error
+/// is always the one that [parse] would have returned.
+#[must_use]
+pub fn parse_recovering(input: &str) -> (Spanned<'_, Program<'_>>, Vec<Error>) {
+    let first = match Pvc::parse(Rule::program, input) {
+        Ok(pairs) => {
+            return match build(pairs, Rule::program, input, program::parse) {
+                Ok(program) => (program, vec![]),
+                Err(e) => (Spanned::default(), vec![e]),
+            }
+        }
+        Err(e) => e,
+    };
+
+    let mut skipped = vec![];
+    // The recovering grammar accepts any input, but we fall back on an empty program just in case.
+    let recovered = Pvc::parse(Rule::recovering_program, input)
+        .map_err(|e| Error::Parse(Box::new(e)))
+        .and_then(|pairs| {
+            build(pairs, Rule::recovering_program, input, |p| {
+                program::recovering(p, &mut skipped)
+            })
+        });
+    let mut errors: Vec<_> = recovery::errors(input, first, &skipped)
+        .into_iter()
+        .map(|e| Error::Parse(Box::new(e)))
+        .collect();
+    let program = recovered.unwrap_or_else(|e| {
+        errors.push(e);
+        Spanned::default()
+    });
+    (program, errors)
 }
 
 /// Parses a standalone expression.
@@ -42,10 +87,23 @@ pub fn parse(input: &str) -> Result<Spanned<program::Program>> {
 /// # Errors
 ///
 /// Fails if `input` could not be parsed correctly.
-pub fn expr(input: &str) -> Result<Spanned<Expr>> {
+pub fn expr(input: &str) -> Result<Spanned<'_, Expr<'_>>> {
     let pairs = Pvc::parse(Rule::expr, input).map_err(Box::new)?;
-    let pair = utils::one(pairs);
-    Ok(utils::lift_many(pair, expr::parse))
+    build(pairs, Rule::expr, input, expr::parse)
+}
+
+/// Builds an AST node using `builder` from the `pairs` resulting from parsing `input` as `rule`.
+fn build<'inp, T>(
+    pairs: pest::iterators::Pairs<'inp, Rule>,
+    rule: Rule,
+    input: &'inp str,
+    builder: impl FnOnce(pest::iterators::Pairs<'inp, Rule>) -> Result<T>,
+) -> Result<Spanned<'inp, T>> {
+    let whole = pest::Span::new(input, 0, input.len()).ok_or(Error::Internal {
+        rule,
+        span: 0..input.len(),
+    })?;
+    utils::lift_many(utils::one(pairs, rule, whole)?, builder)
 }
 
 /// Errors returned by the Starling parser.
@@ -54,6 +112,62 @@ pub fn expr(input: &str) -> Result<Spanned<Expr>> {
 pub enum Error {
     #[error("Parser error: {0}")]
     Parse(#[from] Box<pest::error::Error<Rule>>),
+    /// The script uses syntax that the grammar accepts, but that Starling doesn't support yet.
+    #[error("{what} are not supported")]
+    Unsupported {
+        /// A description of the unsupported syntax, in the plural.
+        what: &'static str,
+        /// A hint about what to write instead.
+        hint: &'static str,
+        /// The byte range covered by the syntax.
+        span: Range<usize>,
+    },
+    /// The grammar accepted something that the AST builder doesn't understand.
+    ///
+    /// This is always a bug in Starling, rather than in the script.
+    #[error("Internal parser error: unexpected {rule:?} at {span:?}")]
+    Internal {
+        /// The rule of the pair that the AST builder didn't expect.
+        rule: Rule,
+        /// The byte range covered by the pair.
+        span: Range<usize>,
+    },
+}
+
+impl Error {
+    /// Constructs an internal error for a pair with rule `rule` spanning `span`.
+    #[must_use]
+    pub fn internal(rule: Rule, span: pest::Span) -> Self {
+        Self::Internal {
+            rule,
+            span: span.start()..span.end(),
+        }
+    }
+}
+
+impl ToDiagnostic for Error {
+    fn to_diagnostic(&self, file: FileId) -> Diagnostic {
+        match self {
+            Self::Parse(e) => {
+                let range = match e.location {
+                    pest::error::InputLocation::Pos(p) => p..p,
+                    pest::error::InputLocation::Span((start, end)) => start..end,
+                };
+                Diagnostic::error(e.variant.message())
+                    .with_code(diagnostic::code::SYNTAX)
+                    .with_label(Label::primary(file, range))
+            }
+            Self::Unsupported { hint, span, .. } => Diagnostic::error(self.to_string())
+                .with_code(diagnostic::code::UNSUPPORTED_SYNTAX)
+                .with_label(Label::primary(file, span.clone()).with_message(*hint)),
+            Self::Internal { rule, span } => {
+                Diagnostic::error(format!("internal parser error: unexpected {rule:?}"))
+                    .with_code(diagnostic::code::INTERNAL)
+                    .with_label(Label::primary(file, span.clone()))
+                    .with_note("this is a bug in Starling, not in the script")
+            }
+        }
+    }
 }
 
 /// Shorthand for a result over [Error].
@@ -7,7 +7,7 @@ use super::{
         ast::{call, Identifier},
         tagged::Spanned,
     },
-    expr, typing, utils, Rule,
+    expr, typing, utils, Result, Rule,
 };
 
 //
@@ -21,20 +21,18 @@ pub type Prototype<'inp> = call::Prototype<'inp, Option<Span<'inp>>, Identifier<
 pub type Parameter<'inp> = call::Parameter<'inp, Option<Span<'inp>>, Identifier<'inp>>;
 
 /// Parses `pairs` representing a function or view prototype.
-#[must_use]
-pub fn prototype(pairs: Pairs<Rule>) -> Prototype {
+pub fn prototype(pairs: Pairs<Rule>) -> Result<Prototype> {
     utils::match_rules!(pair in pairs, proto: Prototype {
         identifier => proto.name = utils::spanned_id(&pair),
-        parameter => proto.args.push(utils::lift_many(pair, parameter))
+        parameter => proto.args.push(utils::lift_many(pair, parameter)?)
     })
 }
 
 /// Parses `pairs` representing a formal parameter.
-#[must_use]
-pub fn parameter(pairs: Pairs<Rule>) -> Parameter {
+pub fn parameter(pairs: Pairs<Rule>) -> Result<Parameter> {
     utils::match_rules!(pair in pairs, param: Parameter {
         identifier => param.name = utils::spanned_id(&pair),
-        starling_type => param.ty = utils::lift_one(pair, typing::starling_type)
+        starling_type => param.ty = utils::lift_one(pair, typing::starling_type)?
     })
 }
 
@@ -49,18 +47,16 @@ pub type Call<'inp> = call::Call<'inp, Option<Span<'inp>>, Identifier<'inp>>;
 pub type ArgumentList<'inp> = Vec<Spanned<'inp, expr::Expr<'inp>>>;
 
 /// Parses a call.
-#[must_use]
-pub fn parse(pairs: Pairs<Rule>) -> Call {
+pub fn parse(pairs: Pairs<Rule>) -> Result<Call> {
     utils::match_rules!(pair in pairs, call: Call {
         identifier => call.name = utils::spanned_id(&pair),
-        argument_list => call.args = argument_list(pair.into_inner())
+        argument_list => call.args = argument_list(pair.into_inner())?
     })
 }
 
 /// Parses an argument list.
-#[must_use]
-pub fn argument_list(pairs: Pairs<Rule>) -> ArgumentList {
+pub fn argument_list(pairs: Pairs<Rule>) -> Result<ArgumentList> {
     utils::match_rules!(pair in pairs, args: ArgumentList {
-        expr => args.push(utils::lift_many(pair, expr::parse))
+        expr => args.push(utils::lift_many(pair, expr::parse)?)
     })
 }
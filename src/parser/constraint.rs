@@ -7,7 +7,7 @@ use pest::{
 
 use super::{
     super::language::ast::{constraint, Identifier},
-    utils, view, Rule,
+    utils, view, Result, Rule,
 };
 
 /// Shorthand for type of constraint declarations returned by this parser.
@@ -19,26 +19,37 @@ pub type Constraint<'inp> = constraint::Constraint<Option<Span<'inp>>, Identifie
 /// Shorthand for type of entailment bodies returned by this parser.
 pub type Entailment<'inp> = constraint::Entailment<Option<Span<'inp>>, Identifier<'inp>>;
 
+/// Shorthand for type of exact definition bodies returned by this parser.
+pub type Definition<'inp> = constraint::Definition<Option<Span<'inp>>, Identifier<'inp>>;
+
 /// Parses a constraint declaration from a list of pairs.
-#[must_use]
-pub fn decl(pairs: Pairs<Rule>) -> Decl {
+pub fn decl(pairs: Pairs<Rule>) -> Result<Decl> {
     utils::match_rules!(pair in pairs, constr : Decl {
-        view_pattern => constr.views.push(utils::lift_many(pair, view::pattern::parse)),
-        constraint => constr.body = utils::lift_one(pair, parse)
+        view_pattern => constr.views.push(utils::lift_many(pair, view::pattern::parse)?),
+        constraint => constr.body = utils::lift_one(pair, parse)?
     })
 }
 
 /// Parses a constraint body from a pair.
-fn parse(pair: Pair<Rule>) -> Constraint {
-    match pair.as_rule() {
-        Rule::entails_constraint => Constraint::Entails(entailment(utils::one_inner(pair))),
-        r => utils::unexpected_rule(r),
-    }
+fn parse(pair: Pair<Rule>) -> Result<Constraint> {
+    utils::match_rule!(pair {
+        entails_constraint => Constraint::Entails(entailment(utils::one_inner(pair)?)?),
+        exact_constraint => Constraint::Exact(definition(utils::one_inner(pair)?)?),
+        indefinite_constraint => Constraint::Indefinite,
+        fails_constraint => Constraint::Fails
+    })
 }
 
 /// Constructs an entailment body from a pair.
-fn entailment(pair: Pair<Rule>) -> Entailment {
+fn entailment(pair: Pair<Rule>) -> Result<Entailment> {
+    utils::match_rule!(pair {
+        expr => Entailment{ entails: utils::lift_many(pair, super::expr::parse)? }
+    })
+}
+
+/// Constructs an exact definition body from a pair.
+fn definition(pair: Pair<Rule>) -> Result<Definition> {
     utils::match_rule!(pair {
-        expr => Entailment{ entails: utils::lift_many(pair, super::expr::parse) }
+        expr => Definition{ definition: utils::lift_many(pair, super::expr::parse)? }
     })
 }
@@ -10,25 +10,37 @@ use pest::{
 use super::{
     super::language::{ast::Identifier, expr},
     utils::{self, l_infix},
-    Rule,
+    Error, Result, Rule,
 };
 
 static PARSER: OnceCell<PrattParser<Rule>> = OnceCell::new();
 
 /// Initialises the Pratt parser.
+///
+/// Operators are listed from loosest to tightest binding.  Unlike Pascal, the Boolean connectives
+/// bind more loosely than the relational operators, so that `x = 1 and y = 2 implies z` means
+/// `((x = 1) and (y = 2)) implies z`.
+///
+/// The grammar allows argument lists as postfix operators, but expressions can't contain calls
+/// yet.  We still register them, so that the Pratt parser hands them to `postfix` to be reported as
+/// unsupported instead of panicking.
 fn init() -> PrattParser<Rule> {
     PrattParser::new()
+        .op(l_infix(Rule::implies) | l_infix(Rule::iff))
+        .op(l_infix(Rule::or))
+        .op(l_infix(Rule::and))
         .op(l_infix(Rule::eq)
             | l_infix(Rule::not_eq)
             | l_infix(Rule::less)
             | l_infix(Rule::less_eq)
             | l_infix(Rule::greater)
             | l_infix(Rule::greater_eq))
-        .op(l_infix(Rule::add) | l_infix(Rule::sub) | l_infix(Rule::or))
-        .op(l_infix(Rule::mul) | l_infix(Rule::div) | l_infix(Rule::modulus) | l_infix(Rule::and))
-        .op(l_infix(Rule::implies) | l_infix(Rule::iff))
+        .op(l_infix(Rule::add) | l_infix(Rule::sub))
+        .op(l_infix(Rule::mul) | l_infix(Rule::div) | l_infix(Rule::modulus))
         .op(Op::prefix(Rule::not) | Op::prefix(Rule::minus) | Op::prefix(Rule::plus))
-        .op(Op::postfix(Rule::subscript))
+        .op(Op::postfix(Rule::subscript)
+            | Op::postfix(Rule::deref)
+            | Op::postfix(Rule::argument_list))
 }
 
 /// Shorthand for type of expressions returned by this parser.
@@ -38,27 +50,26 @@ pub type Expr<'inp> = expr::Expr<Option<Span<'inp>>, Identifier<'inp>>;
 ///
 /// The expression parser is a Pest-based Pratt parser, so it takes a pairs iterator rather than
 /// a single pair.
-#[must_use]
-pub fn parse(pairs: Pairs<Rule>) -> Expr {
+pub fn parse(pairs: Pairs<Rule>) -> Result<Expr> {
     let parser = PARSER.get_or_init(init);
     parser
         .map_primary(primary)
-        .map_prefix(|op, rhs| Expr::uop(prefix_op(&op), rhs))
-        .map_postfix(|lhs, op| Expr::uop(postfix_op(&op), lhs))
-        .map_infix(|lhs, op, rhs| Expr::bop(lhs, infix_op(&op), rhs))
+        .map_prefix(|op, rhs| Ok(Expr::uop(prefix_op(&op)?, rhs?)))
+        .map_postfix(postfix)
+        .map_infix(|lhs, op, rhs| Ok(Expr::bop(lhs?, infix_op(&op)?, rhs?)))
         .parse(pairs)
 }
 
-fn primary(primary: Pair<Rule>) -> Expr {
+fn primary(primary: Pair<Rule>) -> Result<Expr> {
     utils::match_rule!(primary {
         identifier => Expr::Var(utils::spanned_id(&primary)),
-        literal => literal(utils::one(primary.into_inner())),
-        expr => parse(primary.into_inner())
+        literal => literal(utils::one_inner(primary)?)?,
+        expr => parse(primary.into_inner())?
     })
 }
 
 /// Parses prefix operators.
-fn prefix_op(op: &Pair<Rule>) -> expr::Uop {
+fn prefix_op(op: &Pair<Rule>) -> Result<expr::Uop> {
     utils::match_rule!(op {
         minus => expr::Uop::Minus,
         not => expr::Uop::Not,
@@ -66,15 +77,36 @@ fn prefix_op(op: &Pair<Rule>) -> expr::Uop {
     })
 }
 
-/// Parses postfix operators.
-fn postfix_op(op: &Pair<Rule>) -> expr::Uop {
+/// Parses postfix operators, applying them to `lhs`.
+fn postfix<'inp>(lhs: Result<Expr<'inp>>, op: Pair<'inp, Rule>) -> Result<Expr<'inp>> {
+    let lhs = lhs?;
+    if op.as_rule() == Rule::argument_list {
+        return Err(call(&lhs, &op));
+    }
     utils::match_rule!(op {
-        deref => expr::Uop::Deref
+        deref => Expr::deref(lhs),
+        subscript => Expr::index(lhs, parse(op.into_inner())?)
     })
 }
 
+/// Makes the error for a call of `callee` with arguments `args` inside an expression.
+///
+/// The error covers the whole call when the callee is a name, and just the arguments otherwise.
+fn call(callee: &Expr, args: &Pair<Rule>) -> Error {
+    let span = args.as_span();
+    let start = match callee {
+        expr::Expr::Var(v) => v.meta.map_or(span.start(), |s| s.start()),
+        _ => span.start(),
+    };
+    Error::Unsupported {
+        what: "calls inside expressions",
+        hint: "calls can only be statements",
+        span: start..span.end(),
+    }
+}
+
 /// Parses infix operators.
-fn infix_op(pair: &Pair<Rule>) -> expr::Bop {
+fn infix_op(pair: &Pair<Rule>) -> Result<expr::Bop> {
     use super::super::language::expr::bop::{Arith, Bool, Bop, Rel};
     utils::match_rule!(pair {
         add => Bop::Arith(Arith::Add),
@@ -96,31 +128,90 @@ fn infix_op(pair: &Pair<Rule>) -> expr::Bop {
 }
 
 /// Parses a literal expression.
-fn literal(pair: Pair<Rule>) -> Expr {
-    Expr::Literal(utils::spanned(
-        pair.as_span(),
-        utils::match_rule!(pair {
-            int_literal => expr::Constant::Int(int(pair.as_str())),
-            bool_literal => expr::Constant::Bool(bool(&utils::one_inner(pair)))
-        }),
-    ))
+fn literal(pair: Pair<Rule>) -> Result<Expr> {
+    let span = pair.as_span();
+    let constant = utils::match_rule!(pair {
+        int_literal => expr::Constant::Int(int(&pair)?),
+        bool_literal => expr::Constant::Bool(bool(&utils::one_inner(pair)?)?)
+    })?;
+    Ok(Expr::Literal(utils::spanned(span, constant)))
 }
 
-/// Parses an integer.
+/// Parses an integer from its `pair`.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if the upstream parser sent us an integer literal that is badly formed.
-fn int(inp: &str) -> num_bigint::BigInt {
-    inp.parse()
-        .expect("parser should have disallowed erroneous integer input")
+/// Fails with an internal error if the upstream parser sent us an integer literal that is badly
+/// formed.
+pub(super) fn int(pair: &Pair<Rule>) -> Result<num_bigint::BigInt> {
+    pair.as_str().parse().map_err(|_| utils::unexpected(pair))
 }
 
 /// Parses a Boolean.
-///
-fn bool(pair: &Pair<Rule>) -> bool {
+fn bool(pair: &Pair<Rule>) -> Result<bool> {
     utils::match_rule!(pair {
         true_literal => true,
         false_literal => false
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::language::{
+        expr::{
+            bop::{Bool, Rel},
+            map::{HasMeta, HasVars},
+            Expr,
+        },
+        var::Variable,
+    };
+
+    /// Tests that Boolean connectives bind more loosely than relational operators.
+    #[test]
+    fn connective_precedence() {
+        let parsed = crate::parser::expr("c = 1 implies free = false and count = 0").unwrap();
+        let parsed = parsed.item.map_meta(|_| ()).map_var(Variable::into_symbol);
+
+        let var = |x: &str| Expr::Var(egg::Symbol::new(x).into());
+        let expected: Expr<(), egg::Symbol> = Expr::bop(
+            Expr::bop(var("c"), Rel::Eq, Expr::i64(1)),
+            Bool::Implies,
+            Expr::bop(
+                Expr::bop(var("free"), Rel::Eq, Expr::bool(false)),
+                Bool::And,
+                Expr::bop(var("count"), Rel::Eq, Expr::i64(0)),
+            ),
+        );
+        assert_eq!(expected, parsed);
+    }
+
+    /// Tests that word operators and literals only match whole words.
+    #[test]
+    fn word_operator_prefixes() {
+        let parsed = crate::parser::expr("notx and orange or trueish").unwrap();
+        let parsed = parsed.item.map_meta(|_| ()).map_var(Variable::into_symbol);
+
+        let var = |x: &str| Expr::Var(egg::Symbol::new(x).into());
+        let expected: Expr<(), egg::Symbol> = Expr::bop(
+            Expr::bop(var("notx"), Bool::And, var("orange")),
+            Bool::Or,
+            var("trueish"),
+        );
+        assert_eq!(expected, parsed);
+    }
+
+    /// Tests that calls inside expressions, which the grammar accepts but the AST can't represent,
+    /// are reported as unsupported syntax at the call.
+    #[test]
+    fn call_is_unsupported() {
+        let result = crate::parser::expr("f(1) = 2");
+        let Err(err @ crate::parser::Error::Unsupported { span, .. }) = &result else {
+            panic!("expected unsupported syntax, got {result:?}");
+        };
+        assert_eq!(0..4, *span);
+        assert_eq!(
+            "calls inside expressions are not supported",
+            err.to_string()
+        );
+    }
+}
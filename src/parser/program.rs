@@ -6,8 +6,11 @@ use pest::{
 };
 
 use super::{
-    super::language::ast::{program, Identifier},
-    call, constraint, stm, utils, var, view, Rule,
+    super::language::{
+        ast::{program, Identifier},
+        tagged::Spanned,
+    },
+    call, constraint, stm, typing, utils, var, view, Result, Rule,
 };
 
 /// Type of program as parsed by the parser.
@@ -17,33 +20,129 @@ pub type Program<'inp> = program::Program<'inp, Option<Span<'inp>>, Identifier<'
 pub type Decl<'inp> = program::Decl<'inp, Option<Span<'inp>>, Identifier<'inp>>;
 
 /// Parses `pairs` as a program.
-#[must_use]
-pub fn parse(pairs: Pairs<Rule>) -> Program {
+pub fn parse(pairs: Pairs<Rule>) -> Result<Program> {
     utils::match_rules!(pair in pairs, prog: Program {
         identifier => prog.name = utils::spanned_id(&pair),
-        decl => prog.decls.push(utils::lift_one(pair, decl)),
+        decl => prog.decls.push(utils::lift_one(pair, decl)?),
+        EOI => ()
+    })
+}
+
+/// Parses `pairs` as a program parsed with error recovery.
+///
+/// We push each skipped declaration or statement onto `skipped`, and leave it out of the program.
+pub fn recovering<'inp>(
+    pairs: Pairs<'inp, Rule>,
+    skipped: &mut Vec<Pair<'inp, Rule>>,
+) -> Result<Program<'inp>> {
+    utils::match_rules!(pair in pairs, prog: Program {
+        identifier => prog.name = utils::spanned_id(&pair),
+        decl => prog.decls.push(utils::lift_one(pair, decl)?),
+        recovering_procedure_decl => {
+            let proc = recovering_procedure(pair.clone().into_inner(), skipped)?;
+            prog.decls.push(utils::spanned(pair.as_span(), Decl::Procedure(proc)));
+        },
+        skipped_decl => skipped.push(pair),
         EOI => ()
     })
 }
 
 /// Parses `pair` as a decl.
-#[must_use]
-pub fn decl(pair: Pair<Rule>) -> Decl {
+pub fn decl(pair: Pair<Rule>) -> Result<Decl> {
     utils::match_rule!(pair {
-        constraint_decl => Decl::Constraint(constraint::decl(pair.into_inner())),
-        procedure_decl => Decl::Procedure(procedure(pair.into_inner())),
-        var_decl => Decl::Var(var::decl(pair.into_inner())),
-        view_decl => Decl::View(view::decl::parse(pair.into_inner()))
+        constraint_decl => Decl::Constraint(constraint::decl(pair.into_inner())?),
+        procedure_decl => Decl::Procedure(procedure(pair.into_inner())?),
+        type_decl => Decl::Type(typing::decl(pair.into_inner())?),
+        var_decl => Decl::Var(var::decl(pair.into_inner())?),
+        view_decl => Decl::View(view::decl::parse(pair.into_inner())?)
     })
 }
 
 /// Shorthand for the type of procedures returned by this parser.
 pub type Procedure<'inp> = program::Procedure<'inp, Option<Span<'inp>>, Identifier<'inp>>;
 
-#[must_use]
-fn procedure(pairs: Pairs<Rule>) -> program::Procedure<Option<Span>, Identifier> {
+fn procedure(pairs: Pairs<Rule>) -> Result<program::Procedure<Option<Span>, Identifier>> {
     utils::match_rules!(pair in pairs, proc : Procedure {
-        prototype => proc.prototype = utils::lift_many(pair, call::prototype),
-        block => proc.body = stm::block(utils::one_inner(pair))
+        prototype => proc.prototype = utils::lift_many(pair, call::prototype)?,
+        requires_clause => proc.requires = Some(spec(pair)?),
+        ensures_clause => proc.ensures = Some(spec(pair)?),
+        local_vars => proc.locals = locals(pair.into_inner())?,
+        block => proc.body = stm::block(pair.into_inner())?
+    })
+}
+
+/// Parses `pairs` as a procedure whose body may contain skipped statements, pushing those
+/// statements onto `skipped`.
+fn recovering_procedure<'inp>(
+    pairs: Pairs<'inp, Rule>,
+    skipped: &mut Vec<Pair<'inp, Rule>>,
+) -> Result<Procedure<'inp>> {
+    utils::match_rules!(pair in pairs, proc: Procedure {
+        prototype => proc.prototype = utils::lift_many(pair, call::prototype)?,
+        requires_clause => proc.requires = Some(spec(pair)?),
+        ensures_clause => proc.ensures = Some(spec(pair)?),
+        local_vars => proc.locals = locals(pair.into_inner())?,
+        stm_triple => proc.body.push(utils::lift_many(pair, stm::triple)?),
+        skipped_stm => skipped.push(pair)
     })
 }
+
+/// Parses `pair` as the view assertion of a `requires` or `ensures` clause.
+fn spec(pair: Pair<Rule>) -> Result<Spanned<view::assertion::Assertion>> {
+    utils::lift_many(utils::one_inner(pair)?, view::assertion::parse)
+}
+
+/// Parses `pairs` as the local variables of a procedure.
+fn locals(pairs: Pairs<Rule>) -> Result<Vec<Spanned<call::Parameter>>> {
+    utils::match_rules!(pair in pairs, locals: Vec<Spanned<call::Parameter>> {
+        parameter => locals.push(utils::lift_many(pair, call::parameter)?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::language::ast;
+
+    /// Tests that identifiers starting with `requires` or `ensures` aren't taken for the start of a
+    /// specification.
+    #[test]
+    fn spec_keyword_prefixes() {
+        let source = "program T; view V;
+procedure requiresLock(ensuresOk : boolean) requires { V } ensures { V };
+begin end;
+begin end.";
+        let program = crate::parser::parse(source).unwrap();
+        let ast::Decl::Procedure(proc) = &program.item.decls[1].item else {
+            panic!("expected a procedure");
+        };
+        let proto = &proc.prototype.item;
+        assert_eq!("requiresLock", proto.name.item.to_string());
+        assert_eq!("ensuresOk", proto.args[0].item.name.item.to_string());
+        assert!(proc.requires.is_some() && proc.ensures.is_some());
+    }
+
+    /// Tests that calls to procedures whose names start with statement keywords aren't taken for
+    /// those statements.
+    #[test]
+    fn stm_keyword_prefixes() {
+        let source = "program T;
+procedure P;
+begin
+  begind(1);
+  atomics(2);
+  whiles(3)
+end;
+begin end.";
+        let program = crate::parser::parse(source).unwrap();
+        let ast::Decl::Procedure(proc) = &program.item.decls[0].item else {
+            panic!("expected a procedure");
+        };
+        let names: Vec<_> = (proc.body.iter())
+            .map(|t| match &t.item.stm.item {
+                ast::stm::Stm::Call(c) => c.name.item.to_string(),
+                stm => panic!("expected a call, got {stm:?}"),
+            })
+            .collect();
+        assert_eq!(vec!["begind", "atomics", "whiles"], names);
+    }
+}
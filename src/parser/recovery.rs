@@ -0,0 +1,152 @@
+//! Collection of syntax errors after error recovery.
+//!
+//! The recovering grammar tells us which declarations and statements it skipped, but not what
+//! was wrong with them.  To find out, we parse the program strictly, then patch out the skipped
+//! construct containing the error and parse again, until either the program parses or an error
+//! turns up outside any skipped construct.  This gives each error the same message it would have
+//! had if it were the first error in the program.
+
+use pest::{error::InputLocation, iterators::Pair, Parser, Position, Span};
+
+use super::{Pvc, Rule};
+
+/// Shorthand for the type of raw `pest` errors.
+type PestError = pest::error::Error<Rule>;
+
+/// Collects the errors in `input`, given the error `first` from parsing it strictly and the
+/// constructs `skipped` while parsing it with recovery.
+///
+/// The result always begins with `first`.
+pub fn errors(input: &str, first: PestError, skipped: &[Pair<Rule>]) -> Vec<PestError> {
+    let mut skipped: Vec<_> = skipped.iter().map(|p| (p.as_rule(), p.as_span())).collect();
+    let mut patched = input.to_owned();
+    let mut errors = vec![first];
+
+    loop {
+        let offset = start(&errors[errors.len() - 1]);
+        let Some(i) = skipped
+            .iter()
+            .position(|(_, span)| span.start() <= offset && offset <= span.end())
+        else {
+            break;
+        };
+        let (rule, span) = skipped.swap_remove(i);
+        patch(&mut patched, rule, &span);
+
+        let Err(error) = Pvc::parse(Rule::program, &patched) else {
+            break;
+        };
+        let offset = start(&error);
+        if !skipped
+            .iter()
+            .any(|(_, span)| span.start() <= offset && offset <= span.end())
+        {
+            // Anything else is likely to be fallout from the patching.
+            break;
+        }
+        errors.push(rebase(error, input));
+    }
+
+    errors
+}
+
+/// Gets the byte offset at which `error` starts.
+fn start(error: &PestError) -> usize {
+    match error.location {
+        InputLocation::Pos(p) => p,
+        InputLocation::Span((start, _)) => start,
+    }
+}
+
+/// Patches the construct at `span` out of `text`, keeping the byte offsets of everything else.
+///
+/// Skipped declarations become whitespace; skipped statements become a call to `x`, as the
+/// statement list around them would otherwise be malformed.
+fn patch(text: &mut String, rule: Rule, span: &Span) {
+    let mut replacement = " ".repeat(span.end() - span.start());
+    if rule == Rule::skipped_stm {
+        replacement.replace_range(..1, "x");
+    }
+    text.replace_range(span.start()..span.end(), &replacement);
+}
+
+/// Moves `error`, which came from parsing a patched copy of `input`, back onto `input`.
+///
+/// This makes sure that the error quotes the original source when displayed.
+fn rebase(error: PestError, input: &str) -> PestError {
+    match error.location {
+        InputLocation::Pos(p) => match Position::new(input, p) {
+            Some(pos) => PestError::new_from_pos(error.variant, pos),
+            None => error,
+        },
+        InputLocation::Span((start, end)) => match Span::new(input, start, end) {
+            Some(span) => PestError::new_from_span(error.variant, span),
+            None => error,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_recovering, program::Decl, Error};
+
+    /// Gets the byte offsets at which the errors from recovering `input` start.
+    fn error_offsets(input: &str) -> Vec<usize> {
+        let (_, errors) = parse_recovering(input);
+        errors
+            .iter()
+            .map(|e| match e {
+                Error::Parse(e) => super::start(e),
+                Error::Internal { .. } | Error::Unsupported { .. } => {
+                    panic!("unexpected builder error: {e}")
+                }
+            })
+            .collect()
+    }
+
+    /// Tests that a well-formed program parses without errors.
+    #[test]
+    fn no_errors() {
+        let (program, errors) = parse_recovering("program x; var y : integer; begin end.");
+        assert!(errors.is_empty());
+        assert_eq!(1, program.item.decls.len());
+    }
+
+    /// Tests recovery from several malformed declarations.
+    #[test]
+    fn decls() {
+        let input = "program x; var y : ; var z : integer; view 1; view V; begin end.";
+        assert_eq!(vec![19, 43], error_offsets(input));
+
+        let (program, _) = parse_recovering(input);
+        assert_eq!(2, program.item.decls.len());
+    }
+
+    /// Tests recovery from several malformed statements in one procedure, including a statement
+    /// that contains a nested block.
+    #[test]
+    fn stms() {
+        let input = "program x;
+procedure P;
+begin
+  x := ;
+  y := 1;
+  begin z := 1 w end;
+  x := 2 y := 3
+end;
+begin end.";
+        let offsets = error_offsets(input);
+        let expected: Vec<usize> = [";\n  y", "w end", "y := 3"]
+            .iter()
+            .map(|s| input.find(s).unwrap())
+            .collect();
+        assert_eq!(expected, offsets);
+
+        let (program, _) = parse_recovering(input);
+        let Decl::Procedure(proc) = &program.item.decls[0].item else {
+            panic!("expected a procedure");
+        };
+        assert_eq!(1, proc.body.len());
+        assert_eq!("y := 1", proc.body[0].meta.unwrap().as_str());
+    }
+}
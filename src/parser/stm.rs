@@ -1,5 +1,6 @@
 //! Parsers for statements and related constructs.
 
+use itertools::Itertools;
 use pest::{
     iterators::{Pair, Pairs},
     Span,
@@ -8,7 +9,7 @@ use std::mem;
 
 use super::{
     super::language::ast::{ite, stm, Identifier},
-    call, expr, utils, Rule,
+    call, expr, utils, Result, Rule,
 };
 
 /// Shorthand for type of statement lists returned by the parser.
@@ -26,60 +27,78 @@ pub type Assign<'inp> = stm::Assign<Option<Span<'inp>>, Identifier<'inp>>;
 /// Shorthand for type of if-then-else returned by the parser.
 pub type Ite<'inp> = stm::Ite<'inp, Option<Span<'inp>>, Identifier<'inp>>;
 
+/// Shorthand for type of while loops returned by the parser.
+pub type While<'inp> = stm::While<'inp, Option<Span<'inp>>, Identifier<'inp>>;
+
+/// Shorthand for type of repeat-until loops returned by the parser.
+pub type Repeat<'inp> = stm::Repeat<'inp, Option<Span<'inp>>, Identifier<'inp>>;
+
+/// Shorthand for type of loop invariants returned by the parser.
+pub type Invariant<'inp> = stm::Invariant<'inp, Option<Span<'inp>>, Identifier<'inp>>;
+
 /// Shorthand for type of condition returned by the parser.
 pub type Condition<'inp> = ite::Condition<Option<Span<'inp>>, Identifier<'inp>>;
 
 /// Parses `pairs` as a list of triples.
-#[must_use]
-pub fn list(pairs: Pairs<Rule>) -> List {
+pub fn list(pairs: Pairs<Rule>) -> Result<List> {
     utils::match_rules!(pair in pairs, triples: List {
-        stm_triple => triples.push(utils::lift_many(pair, triple))
+        stm_triple => triples.push(utils::lift_many(pair, triple)?)
     })
 }
 
 /// Parses `pairs` as a triple.
-#[must_use]
-pub fn triple(pairs: Pairs<Rule>) -> Triple {
+pub fn triple(pairs: Pairs<Rule>) -> Result<Triple> {
+    let mut seen_stm = false;
     utils::match_rules!(pair in pairs, triple: Triple {
-        view_assertion => triple_view(&mut triple, pair),
-        stm => triple.stm = utils::lift_one(pair, parse)
+        view_assertion => triple_view(&mut triple, pair, seen_stm)?,
+        stm => {
+            seen_stm = true;
+            triple.stm = utils::lift_one(pair, parse)?;
+        }
     })
 }
 
-/// Places the assertion parsed from `pair` in the first slot in `triple` that is available.
+/// Places the assertion parsed from `pair` in `triple`, as its post-condition if we have already
+/// seen the statement and its pre-condition otherwise.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if this is the third assertion we have parsed for `triple` (there should be only two).
-fn triple_view<'i>(triple: &mut Triple<'i>, pair: Pair<'i, Rule>) {
-    let dst = triple
-        .first_empty_assertion_mut()
-        .expect("should be only two views in a triple");
-    *dst = Some(utils::lift_many(pair, super::view::assertion::parse));
+/// Fails with an internal error if `triple` already has that assertion.
+fn triple_view<'i>(triple: &mut Triple<'i>, pair: Pair<'i, Rule>, seen_stm: bool) -> Result<()> {
+    let dst = if seen_stm {
+        &mut triple.post
+    } else {
+        &mut triple.pre
+    };
+    if dst.is_some() {
+        return Err(utils::unexpected(&pair));
+    }
+    *dst = Some(utils::lift_many(pair, super::view::assertion::parse)?);
+    Ok(())
 }
 
 /// Parses `pair` as a statement.
-#[must_use]
-pub fn parse(pair: Pair<Rule>) -> Stm {
+pub fn parse(pair: Pair<Rule>) -> Result<Stm> {
     utils::match_rule!(pair {
-        atomic_stm => Stm::Atomic(block(utils::one_inner(pair))),
-        assign => Stm::Assign(assign(pair.into_inner())),
-        block => Stm::Block(block(utils::one_inner(pair))),
-        call => Stm::Call(call::parse(pair.into_inner())),
-        ite_stm => Stm::Ite(ite(pair.into_inner())),
-        nop_stm => Stm::Nop
+        atomic_stm => Stm::Atomic(atomic(pair)?),
+        assign => Stm::Assign(assign(pair.into_inner())?),
+        block => Stm::Block(block(pair.into_inner())?),
+        call => Stm::Call(call::parse(pair.into_inner())?),
+        ite_stm => Stm::Ite(ite(pair.into_inner())?),
+        nop_stm => Stm::Nop,
+        repeat_stm => Stm::Repeat(repeat(pair.into_inner())?),
+        while_stm => Stm::While(while_loop(pair.into_inner())?)
     })
 }
 
 /// Parses `pairs` as an assign statement.
-#[must_use]
-pub fn assign(pairs: Pairs<Rule>) -> Assign {
+pub fn assign(pairs: Pairs<Rule>) -> Result<Assign> {
     // Expecting two expressions: lvalue and rvalue.
     utils::match_rules!(pair in pairs, asn : Assign {
         expr => {
             // We don't check to see if we're receiving more expressions than wanted here, as it'd
             // complicate either the parser or the AST to do so.
-            let x = utils::lift_many(pair, expr::parse);
+            let x = utils::lift_many(pair, expr::parse)?;
             if asn.lvalue.is_none() {
                 // The first expression seen is the lvalue.
                 asn.lvalue = Some(x);
@@ -91,32 +110,70 @@ pub fn assign(pairs: Pairs<Rule>) -> Assign {
     })
 }
 
-/// Parses `pair` as a block statement.
-#[must_use]
-pub fn block(pair: Pair<Rule>) -> List {
+/// Parses `pair` as an atomic block statement.
+fn atomic(pair: Pair<Rule>) -> Result<stm::Atomic<Option<Span>, Identifier>> {
+    let syntax = if pair.as_str().starts_with("<|") {
+        stm::AtomicSyntax::Brackets
+    } else {
+        stm::AtomicSyntax::Keywords
+    };
+    Ok(stm::Atomic {
+        syntax,
+        body: stm_list(utils::one_inner(pair)?)?,
+    })
+}
+
+/// Parses `pairs` as the contents of a block statement, which may be empty.
+pub fn block(pairs: Pairs<Rule>) -> Result<List> {
+    pairs.map(stm_list).flatten_ok().collect()
+}
+
+/// Parses `pair` as a non-empty statement list.
+fn stm_list(pair: Pair<Rule>) -> Result<List> {
     utils::match_rule!(pair {
-        stm_list => list(pair.into_inner())
+        stm_list => list(pair.into_inner())?
     })
 }
 
 /// Parses `pairs` as an if-then-else statement.
-#[must_use]
-fn ite(pairs: Pairs<Rule>) -> Ite {
+fn ite(pairs: Pairs<Rule>) -> Result<Ite> {
     let mut need_true = true;
     utils::match_rules!(pair in pairs, stm: Ite {
-        condition => stm.cond = utils::lift_one(pair, condition),
+        condition => stm.cond = utils::lift_one(pair, condition)?,
         stm => {
             let next_branch = mem::replace(&mut need_true, false);
-            *stm.branch_mut(next_branch) = utils::lift_one(pair, parse).map(Box::new);
+            *stm.branch_mut(next_branch) = utils::lift_one(pair, parse)?.map(Box::new);
         }
     })
 }
 
-/// Parses `pair` as a block statement.
-#[must_use]
-fn condition(pair: Pair<Rule>) -> Condition {
+/// Parses `pairs` as a while loop.
+fn while_loop(pairs: Pairs<Rule>) -> Result<While> {
+    utils::match_rules!(pair in pairs, stm: While {
+        condition => stm.cond = utils::lift_one(pair, condition)?,
+        loop_invariant => stm.invariant = invariant(pair)?,
+        stm => stm.body = utils::lift_one(pair, parse)?.map(Box::new)
+    })
+}
+
+/// Parses `pairs` as a repeat-until loop.
+fn repeat(pairs: Pairs<Rule>) -> Result<Repeat> {
+    utils::match_rules!(pair in pairs, stm: Repeat {
+        loop_invariant => stm.invariant = invariant(pair)?,
+        stm_list => stm.body = list(pair.into_inner())?,
+        condition => stm.cond = utils::lift_one(pair, condition)?
+    })
+}
+
+/// Parses `pair` as a loop invariant.
+fn invariant(pair: Pair<Rule>) -> Result<Invariant> {
+    utils::lift_many(utils::one_inner(pair)?, super::view::assertion::parse)
+}
+
+/// Parses `pair` as an if-then-else condition.
+pub fn condition(pair: Pair<Rule>) -> Result<Condition> {
     utils::match_rule!(pair {
         nondeterminism => Condition::Nondeterministic,
-        expr => Condition::Deterministic(expr::parse(pair.into_inner()))
+        expr => Condition::Deterministic(expr::parse(pair.into_inner())?)
     })
 }
@@ -1,27 +1,123 @@
 //! Parsers for types.
 
-use pest::{iterators::Pair, Span};
+use itertools::Itertools;
+use num_bigint::BigInt;
+use num_traits::Zero;
+use pest::{
+    iterators::{Pair, Pairs},
+    Span,
+};
 
 use super::{
-    super::language::{ast::Identifier, typing},
-    utils, Rule,
+    super::language::{
+        ast::{typedef, Identifier},
+        expr::Expr,
+        typing,
+    },
+    expr, utils, Result, Rule,
 };
 
 /// Shorthand for the type of type parsed by `starling_type`.
 pub type Type<'inp> = typing::Type<Option<Span<'inp>>, Identifier<'inp>>;
 
+/// Shorthand for the type of decl returned by `decl`.
+pub type Decl<'inp> = typedef::Decl<'inp, Option<Span<'inp>>, Identifier<'inp>>;
+
+/// Shorthand for the type of definition returned by `def`.
+pub type Def<'inp> = typedef::Def<'inp, Option<Span<'inp>>, Identifier<'inp>>;
+
+/// Parses `pairs` as a type declaration.
+pub fn decl(pairs: Pairs<Rule>) -> Result<Decl> {
+    utils::match_rules!(pair in pairs, dec: Decl {
+        type_def => dec.defs.push(utils::lift_many(pair, def)?)
+    })
+}
+
+/// Parses `pairs` as a type definition.
+fn def(pairs: Pairs<Rule>) -> Result<Def> {
+    utils::match_rules!(pair in pairs, def: Def {
+        identifier => def.name = utils::spanned_id(&pair),
+        starling_type => def.ty = utils::lift_one(pair, starling_type)?
+    })
+}
+
 /// Parses `pair` as a Starling type.
-#[must_use]
-pub fn starling_type(pair: Pair<Rule>) -> Type {
+pub fn starling_type(pair: Pair<Rule>) -> Result<Type> {
     utils::match_rule!(pair {
-        primitive_type => Type::Prim(primitive_type(&utils::one_inner(pair)))
-        // TODO(@MattWindsor91): other forms of type
+        primitive_type => Type::Prim(primitive_type(&utils::one_inner(pair)?)?),
+        identifier => Type::Named(utils::spanned_id(&pair)),
+        refinement_type => Type::Refined(refinement_type(pair.into_inner())?),
+        array_type => Type::Array(array_type(pair.into_inner())?)
     })
 }
 
-fn primitive_type(pair: &Pair<Rule>) -> typing::Prim {
+/// Parses `pairs` as the components of a refinement type.
+fn refinement_type(pairs: Pairs<Rule>) -> Result<typing::Refined<Option<Span>, Identifier>> {
+    let mut var = "";
+    let mut element = Type::INT;
+    let mut refinement = Expr::bool(true);
+    for pair in pairs {
+        utils::match_rule!(pair {
+            identifier => var = pair.as_str(),
+            starling_type => element = starling_type(utils::one_inner(pair)?)?,
+            expr => refinement = expr::parse(pair.into_inner())?
+        })?;
+    }
+    Ok(typing::Refined::new(var, element, refinement))
+}
+
+/// Parses `pairs` as the components of an array type.
+fn array_type(pairs: Pairs<Rule>) -> Result<typing::Array<Option<Span>, Identifier>> {
+    let mut bounds = (BigInt::zero(), BigInt::zero());
+    let mut base = Type::INT;
+    for pair in pairs {
+        utils::match_rule!(pair {
+            array_bound => bounds = array_bound(pair)?,
+            starling_type => base = starling_type(utils::one_inner(pair)?)?
+        })?;
+    }
+    Ok(typing::Array::new(base, bounds.0, bounds.1))
+}
+
+/// Parses `pair` as the low and high bounds of an array type.
+fn array_bound(pair: Pair<Rule>) -> Result<(BigInt, BigInt)> {
+    let error = utils::unexpected(&pair);
+    let (low, high) = pair
+        .into_inner()
+        .map(|p| expr::int(&p))
+        .collect_tuple()
+        .ok_or(error)?;
+    Ok((low?, high?))
+}
+
+fn primitive_type(pair: &Pair<Rule>) -> Result<typing::Prim> {
     utils::match_rule!(pair {
         integer_type => typing::Prim::Int,
         boolean_type => typing::Prim::Bool
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::language::{ast, typing};
+
+    /// Tests that identifiers starting with `type` can name both types and variables.
+    #[test]
+    fn type_keyword_prefixes() {
+        let source = "program T; type types = integer; var typeOf : types; begin end.";
+        let program = crate::parser::parse(source).unwrap();
+        let [ty, var] = &program.item.decls[..] else {
+            panic!("expected two declarations");
+        };
+        let ast::Decl::Type(ty) = &ty.item else {
+            panic!("expected a type declaration");
+        };
+        assert_eq!("types", ty.defs[0].item.name.item.to_string());
+        let ast::Decl::Var(var) = &var.item else {
+            panic!("expected a variable declaration");
+        };
+        let param = &var.vars[0].item;
+        assert_eq!("typeOf", param.name.item.to_string());
+        assert!(matches!(&param.ty.item, typing::Type::Named(n) if n.item == "types"));
+    }
+}
@@ -6,34 +6,38 @@ use pest::{
     Span,
 };
 
-use super::{super::language::tagged::Spanned, Rule};
+use super::{super::language::tagged::Spanned, Error, Result, Rule};
 
 /// Repeatedly matches rules in a pairs iterator against a list of patterns, using them to populate
 /// an initially-default syntactic construct; finally returns that construct.
 ///
-/// This macro is syntactic sugar over a match, fold, and default.
+/// This macro is syntactic sugar over a match, fold, and default.  The patterns may use `?` to
+/// propagate errors, and the whole macro evaluates to a `Result`; any rule not in the patterns
+/// becomes an internal error.
 macro_rules! match_rules {
     ($pair:ident in $pairs:expr, $binder:ident : $node_type:ty { $($rule:ident => $rule_expr:expr),* }) => {
-        $pairs.fold(<$node_type>::default(), |mut $binder, $pair| {
+        $pairs.into_iter().try_fold(<$node_type>::default(), |mut $binder, $pair| {
             match $pair.as_rule() {
                 $(crate::parser::Rule::$rule => $rule_expr),*,
-                r => crate::parser::utils::unexpected_rule(r),
+                _ => return Err(crate::parser::utils::unexpected(&$pair)),
             };
-            $binder
+            Ok::<_, crate::parser::Error>($binder)
         })
     };
 }
 pub(crate) use match_rules;
 
-/// Repeatedly matches rules in a pairs iterator against a list of patterns, using them to populate
-/// an initially-default syntactic construct; finally returns that construct.
+/// Matches the rule of a pair against a list of patterns, returning the result of the matching
+/// pattern.
 ///
-/// This macro is syntactic sugar over a match, fold, and default.
+/// This macro is syntactic sugar over a match.  The patterns may use `?` to propagate errors, and
+/// the whole macro evaluates to a `Result`; any rule not in the patterns becomes an internal
+/// error.
 macro_rules! match_rule {
     ($pair:ident { $($rule:ident => $rule_expr:expr),* }) => {
         match $pair.as_rule() {
-            $(crate::parser::Rule::$rule => $rule_expr),*,
-            r => crate::parser::utils::unexpected_rule(r),
+            $(crate::parser::Rule::$rule => Ok($rule_expr)),*,
+            _ => Err(crate::parser::utils::unexpected(&$pair)),
         }
     };
 }
@@ -63,24 +67,30 @@ pub fn spanned_id<'inp>(pair: &Pair<'inp, Rule>) -> Spanned<'inp, super::ast::Id
 ///
 /// We use the parent `pair` to discern the span for the whole alternative and lift the result with
 /// that span.
-#[must_use]
+///
+/// # Errors
+///
+/// Fails if `parser` fails.
 pub fn lift_many<'inp, T>(
     pair: Pair<'inp, Rule>,
-    parser: impl FnOnce(Pairs<'inp, Rule>) -> T,
-) -> Spanned<'inp, T> {
-    spanned(pair.as_span(), parser(pair.into_inner()))
+    parser: impl FnOnce(Pairs<'inp, Rule>) -> Result<T>,
+) -> Result<Spanned<'inp, T>> {
+    Ok(spanned(pair.as_span(), parser(pair.into_inner())?))
 }
 
 /// Given a pair that wraps one of multiple alternatives, applies `parser` to that alternative.
 ///
 /// We use the parent `pair` to discern the span for the whole alternative and lift the result with
 /// that span.
-#[must_use]
+///
+/// # Errors
+///
+/// Fails if `pair` doesn't wrap exactly one alternative, or `parser` fails.
 pub fn lift_one<'inp, T>(
     pair: Pair<'inp, Rule>,
-    parser: impl FnOnce(Pair<'inp, Rule>) -> T,
-) -> Spanned<'inp, T> {
-    spanned(pair.as_span(), parser(one_inner(pair)))
+    parser: impl FnOnce(Pair<'inp, Rule>) -> Result<T>,
+) -> Result<Spanned<'inp, T>> {
+    Ok(spanned(pair.as_span(), parser(one_inner(pair)?)?))
 }
 
 //
@@ -89,38 +99,33 @@ pub fn lift_one<'inp, T>(
 
 /// Enforces that exactly one pair exists in the inner pairs of `pair`, and extracts it.
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if there is more or less than one pair in `pair.into_inner()`.
-#[must_use]
-pub fn one_inner(pair: Pair<Rule>) -> Pair<Rule> {
-    one(pair.into_inner())
+/// Fails with an internal error if there is more or less than one pair in `pair.into_inner()`.
+pub fn one_inner(pair: Pair<Rule>) -> Result<Pair<Rule>> {
+    let (rule, span) = (pair.as_rule(), pair.as_span());
+    one(pair.into_inner(), rule, span)
 }
 
 /// Enforces that exactly one pair exists in `pairs`, and extracts it.
 ///
-/// # Panics
+/// The pairs come from the rule `rule` spanning `span`, which we use to report errors.
 ///
-/// Panics if there is more or less than one pair in `pairs`.
-#[must_use]
-pub fn one(pairs: Pairs<Rule>) -> Pair<Rule> {
-    pairs
-        .exactly_one()
-        .expect("expected exactly one match here")
+/// # Errors
+///
+/// Fails with an internal error if there is more or less than one pair in `pairs`.
+pub fn one<'inp>(pairs: Pairs<'inp, Rule>, rule: Rule, span: Span) -> Result<Pair<'inp, Rule>> {
+    pairs.exactly_one().map_err(|_| Error::internal(rule, span))
 }
 
 //
 // Error conditions
 //
 
-/// Panics with an unexpected rule.
-///
-/// # Panics
-///
-/// Always.
-#[inline]
-pub fn unexpected_rule(rule: Rule) -> ! {
-    unreachable!("parser should not have accepted {:?} here", rule)
+/// Makes an internal error for a pair that the parser should not have accepted where it did.
+#[must_use]
+pub fn unexpected(pair: &Pair<Rule>) -> Error {
+    Error::internal(pair.as_rule(), pair.as_span())
 }
 
 //
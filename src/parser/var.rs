@@ -7,26 +7,82 @@ use pest::{
 
 use super::{
     super::language::{ast::var, var::Scope},
-    call, utils, Rule,
+    call, utils, Result, Rule,
 };
 
 /// Shorthand for the type of decl returned by `decl`.
 pub type Decl<'inp> = var::Decl<'inp, Option<Span<'inp>>, var::Identifier<'inp>>;
 
 /// Parses `pairs` as a variable declaration.
-#[must_use]
-pub fn decl(pairs: Pairs<Rule>) -> Decl {
+pub fn decl(pairs: Pairs<Rule>) -> Result<Decl> {
     utils::match_rules!(pair in pairs, dec: Decl {
-        var_scope => dec.scope = utils::lift_one(pair, |p| scope(&p)),
-        parameter => dec.vars.push(utils::lift_many(pair, call::parameter))
+        var_scope => dec.scope = utils::lift_one(pair, |p| scope(&p))?,
+        parameter => dec.vars.push(utils::lift_many(pair, call::parameter)?)
     })
 }
 
 /// Parses `pair` as a scope.
-#[must_use]
-fn scope(pair: &Pair<Rule>) -> Scope {
+fn scope(pair: &Pair<Rule>) -> Result<Scope> {
     utils::match_rule!(pair {
         shared_scope => Scope::Shared,
         thread_scope => Scope::Thread
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::language::ast;
+
+    /// Parses a program declaring `vars`, returning the names of the variables it declares.
+    fn declared(vars: &str) -> crate::parser::Result<Vec<String>> {
+        let source = format!("program T; var {vars}; begin end.");
+        let program = crate::parser::parse(&source)?;
+        Ok((program.item.decls.iter())
+            .filter_map(|d| match &d.item {
+                ast::Decl::Var(v) => Some(v.vars.iter().map(|p| p.item.name.item.to_string())),
+                _ => None,
+            })
+            .flatten()
+            .collect())
+    }
+
+    /// Tests that identifiers starting with loop keywords aren't taken for those keywords.
+    #[test]
+    fn loop_keyword_prefixes() {
+        assert_eq!(
+            vec![
+                "done",
+                "down",
+                "untilX",
+                "whileOK",
+                "repeated",
+                "invariants"
+            ],
+            declared(
+                "done : integer; down : integer; untilX : integer; whileOK : integer; \
+                 repeated : integer; invariants : integer"
+            )
+            .unwrap()
+        );
+        for keyword in ["do", "until", "while", "repeat", "invariant"] {
+            assert!(declared(&format!("{keyword} : integer")).is_err());
+        }
+    }
+
+    /// Tests that identifiers starting with keywords or keyword terminals from before the loops
+    /// aren't taken for them either, but that whole terminals still are.
+    #[test]
+    fn terminal_prefixes() {
+        assert_eq!(
+            vec!["iffy", "offset", "ending", "variable", "nope"],
+            declared(
+                "iffy : integer; offset : integer; ending : integer; variable : integer; \
+                 nope : integer"
+            )
+            .unwrap()
+        );
+        for keyword in ["if", "iff", "of", "end", "var", "nop", "integer", "true"] {
+            assert!(declared(&format!("{keyword} : integer")).is_err());
+        }
+    }
+}
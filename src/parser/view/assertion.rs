@@ -11,10 +11,13 @@ use pest::{
 };
 
 use super::super::{
-    super::language::ast::{view::assertion, Identifier},
-    call, expr,
+    super::language::{
+        ast::{view::assertion, Identifier},
+        ite,
+    },
+    call, expr, stm,
     utils::{self, l_infix},
-    Rule,
+    Result, Rule,
 };
 
 static PARSER: OnceCell<PrattParser<Rule>> = OnceCell::new();
@@ -32,42 +35,63 @@ fn init() -> PrattParser<Rule> {
 pub type Assertion<'inp> = assertion::Assertion<'inp, Option<Span<'inp>>, Identifier<'inp>>;
 
 /// Parses a view assertion body given the `pairs` over its contents.
-#[must_use]
-pub fn parse(pairs: Pairs<Rule>) -> Assertion {
+pub fn parse(pairs: Pairs<Rule>) -> Result<Assertion> {
     let parser = PARSER.get_or_init(init);
     parser
         .map_primary(primary)
-        .map_infix(|lhs, op, rhs| infix(lhs, &op, rhs))
+        .map_prefix(prefix)
+        .map_infix(infix)
         .map_postfix(postfix)
         .parse(pairs)
 }
 
 /// Parses a primary view assertion.
-fn primary(pair: Pair<Rule>) -> Assertion {
+fn primary(pair: Pair<Rule>) -> Result<Assertion> {
     utils::match_rule!(pair {
-        call => Assertion::Atom(utils::lift_many(pair, call::parse)),
-        expr => Assertion::Local(utils::lift_many(pair, expr::parse)),
+        call => Assertion::Atom(utils::lift_many(pair, call::parse)?),
+        expr => Assertion::Local(utils::lift_many(pair, expr::parse)?),
         empty_view => Assertion::Emp,
-        view_assertion => parse(pair.into_inner())
+        view_assertion => parse(pair.into_inner())?
+    })
+}
+
+/// Parses a prefix view assertion operator.
+///
+/// Right now, there is only one prefix operator: guarding.
+fn prefix<'inp>(op: Pair<'inp, Rule>, rhs: Result<Assertion<'inp>>) -> Result<Assertion<'inp>> {
+    let rhs = rhs?;
+    utils::match_rule!(op {
+        view_guard => Assertion::Guarded(assertion::Guarded {
+            guard: expr::parse(op.into_inner())?,
+            view: Box::new(rhs),
+        })
     })
 }
 
 /// Parses an infix view assertion operator.
 ///
-/// Right now, there is only one infix operator: view join.
+/// The if-then-else operator `lhs <[ cond ]> rhs` is Hoare-style: `lhs` holds if `cond` is true,
+/// and `rhs` holds otherwise.
 fn infix<'inp>(
-    lhs: Assertion<'inp>,
-    op: &Pair<'inp, Rule>,
-    rhs: Assertion<'inp>,
-) -> Assertion<'inp> {
+    lhs: Result<Assertion<'inp>>,
+    op: Pair<'inp, Rule>,
+    rhs: Result<Assertion<'inp>>,
+) -> Result<Assertion<'inp>> {
+    let (lhs, rhs) = (lhs?, rhs?);
     utils::match_rule!(op {
-        view_join => Assertion::Join(Box::new(lhs), Box::new(rhs))
+        view_join => Assertion::Join(Box::new(lhs), Box::new(rhs)),
+        view_ite => Assertion::Ite(ite::Ite::new(
+            Box::new(lhs),
+            utils::lift_one(utils::one_inner(op)?, stm::condition)?,
+            Box::new(rhs),
+        ))
     })
 }
 
 /// Parses a postfix view assertion operator.
-fn postfix<'inp>(lhs: Assertion<'inp>, op: Pair<'inp, Rule>) -> Assertion<'inp> {
+fn postfix<'inp>(lhs: Result<Assertion<'inp>>, op: Pair<'inp, Rule>) -> Result<Assertion<'inp>> {
+    let lhs = lhs?;
     utils::match_rule!(op {
-        view_iterate => Assertion::Iterated(super::iterate(utils::one_inner(op), Box::new(lhs)))
+        view_iterate => Assertion::Iterated(super::iterate(utils::one_inner(op)?, Box::new(lhs))?)
     })
 }
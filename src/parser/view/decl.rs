@@ -4,17 +4,16 @@ use pest::{iterators::Pairs, Span};
 
 use super::super::{
     super::language::ast::{view::decl, Identifier},
-    call, utils, Rule,
+    call, utils, Result, Rule,
 };
 
 /// Shorthand for type of declarations returned by `parse`.
 pub type Decl<'inp> = decl::Decl<'inp, Option<Span<'inp>>, Identifier<'inp>>;
 
 /// Parses `pairs` into a view declaration.
-#[must_use]
-pub fn parse(pairs: Pairs<Rule>) -> decl::Decl<Option<Span>, Identifier> {
+pub fn parse(pairs: Pairs<Rule>) -> Result<Decl> {
     utils::match_rules!(pair in pairs, views: Decl {
         // We parse view prototypes in the same way as procedure prototypes.
-        prototype => views.contents.push(utils::lift_many(pair, call::prototype))
+        prototype => views.contents.push(utils::lift_many(pair, call::prototype)?)
     })
 }
@@ -1,13 +1,16 @@
 //! Parser for view patterns.
 
-use pest::{iterators::Pairs, Span};
+use pest::{
+    iterators::{Pair, Pairs},
+    Span,
+};
 
 use super::super::{
     super::language::{
         ast::{view::pattern, Identifier},
         tagged::Spanned,
     },
-    expr, utils, Rule,
+    expr, utils, Result, Rule,
 };
 
 /// Shorthand for the type of pattern produced by the parser.
@@ -20,27 +23,30 @@ pub type Atom<'inp> = pattern::Atom<Option<Span<'inp>>, Identifier<'inp>>;
 pub type Argument<'inp> = pattern::Argument<Option<Span<'inp>>, Identifier<'inp>>;
 
 /// Parses a view pattern given the `pairs` over its contents.
-#[must_use]
-pub fn parse(pairs: Pairs<Rule>) -> Pattern {
+pub fn parse(pairs: Pairs<Rule>) -> Result<Pattern> {
     utils::match_rules!(pair in pairs, pat: Pattern {
-        view_pattern_atom => pat.contents.push(utils::lift_many(pair, atom))
+        empty_view => (),
+        view_pattern_atom => pat.contents.push(utils::lift_many(pair, atom)?)
     })
 }
 
-fn atom(pairs: Pairs<Rule>) -> Atom {
+fn atom(pairs: Pairs<Rule>) -> Result<Atom> {
     utils::match_rules!(pair in pairs, pat: Atom {
         identifier => pat.name = utils::spanned_id(&pair),
-        view_pattern_argument_list => pat.args = arguments(pair.into_inner()),
-        iterator_pattern => pat.iterator = utils::lift_many(pair, argument)
+        view_pattern_argument_list => pat.args = arguments(pair.into_inner())?,
+        iterator_pattern => pat.iterator = utils::lift_one(pair, argument)?
     })
 }
 
-fn arguments(pairs: Pairs<Rule>) -> Vec<Spanned<Argument>> {
-    utils::match_rules!(pair in pairs, args: Vec<Spanned<Argument >> {
-        expr => args.push(utils::lift_many(pair, argument))
-    })
+fn arguments(pairs: Pairs<Rule>) -> Result<Vec<Spanned<Argument>>> {
+    pairs
+        .map(|pair| Ok(utils::spanned(pair.as_span(), argument(pair)?)))
+        .collect()
 }
 
-fn argument(pairs: Pairs<Rule>) -> Argument {
-    Argument::Expr(expr::parse(pairs))
+fn argument(pair: Pair<Rule>) -> Result<Argument> {
+    utils::match_rule!(pair {
+        wildcard => Argument::Wildcard,
+        expr => Argument::Expr(expr::parse(pair.into_inner())?)
+    })
 }
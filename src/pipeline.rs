@@ -0,0 +1,95 @@
+//! The checking pipeline shared by the command line and the language server.
+//!
+//! Checking a script parses it, binds it, type checks it, and then checks the well-formedness of
+//! its constraints and its variable discipline.  Each stage only runs if the stages before it found
+//! no errors, as later stages assume earlier ones have passed.
+//!
+//! The stages report their problems as diagnostics, so callers can show them however they like.
+//! Parsing is separate from the rest of the pipeline because bound programs borrow from the
+//! syntax tree, which the caller must keep alive.
+
+use super::{
+    binder::{self, Bound, Program},
+    diagnostic::{Diagnostic, FileId, Severity, ToDiagnostic},
+    discipline,
+    language::tagged::Spanned,
+    parser, typechecker, wellformed,
+};
+
+/// Parses `source`, with error recovery, as the contents of `file`.
+///
+/// # Errors
+///
+/// Fails with a diagnostic for every syntax error in `source`.
+pub fn parse(source: &str, file: FileId) -> Result<Spanned<'_, Program<'_>>, Vec<Diagnostic>> {
+    let (ast, errors) = parser::parse_recovering(source);
+    if errors.is_empty() {
+        Ok(ast)
+    } else {
+        Err(diagnostics(&errors, file))
+    }
+}
+
+/// Binds and checks `ast`, which was parsed from `file`.
+///
+/// On success, returns the bound program along with any warnings found while checking it.
+///
+/// # Errors
+///
+/// Fails with every diagnostic, including warnings, from the first stage that found an error.
+pub fn check<'inp>(
+    ast: &'inp Program<'inp>,
+    file: FileId,
+) -> Result<(Bound<'inp>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let program = binder::bind(ast).map_err(|e| vec![e.to_diagnostic(file)])?;
+
+    let errors = typechecker::check(&program);
+    if !errors.is_empty() {
+        return Err(diagnostics(&errors, file));
+    }
+
+    let mut found = diagnostics(&wellformed::check(&program), file);
+    found.extend(diagnostics(&discipline::check(&program), file));
+    if found.iter().any(|d| d.severity == Severity::Error) {
+        Err(found)
+    } else {
+        Ok((program, found))
+    }
+}
+
+/// Converts every error in `errors` into a diagnostic in `file`.
+fn diagnostics(errors: &[impl ToDiagnostic], file: FileId) -> Vec<Diagnostic> {
+    errors.iter().map(|e| e.to_diagnostic(file)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::code;
+
+    /// Parses and checks `source`, panicking on syntax errors.
+    fn run(source: &str) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+        let file = FileId::default();
+        let ast = parse(source, file).unwrap();
+        check(&ast.item, file).map(|(_, warnings)| warnings)
+    }
+
+    /// Tests that warnings from checking constraints come back alongside the program.
+    #[test]
+    fn warnings_pass() {
+        let warnings = run("program T; view V; begin end.").unwrap();
+        assert_eq!(1, warnings.len());
+        assert_eq!(Some(code::UNCONSTRAINED_VIEW), warnings[0].code);
+    }
+
+    /// Tests that type errors stop the pipeline before the later checks.
+    #[test]
+    fn type_errors_fail() {
+        let errors = run(
+            "program T; var x : integer; view V; procedure P; begin <| x := true |> end; begin end.",
+        )
+        .unwrap_err();
+        assert_eq!(1, errors.len());
+        assert_eq!(Some(code::TYPE_MISMATCH), errors[0].code);
+    }
+}
@@ -0,0 +1,808 @@
+//! The PVC pretty-printer.
+//!
+//! The printer turns ASTs back into PVC source, laying out declarations and statements in a
+//! canonical style.  It inserts only the parentheses needed for the parser to rebuild the same AST,
+//! and otherwise preserves the syntactic choices recorded in the AST (such as which of the two
+//! atomic block syntaxes a block uses).
+//!
+//! Expressions print through their `Display` implementation, which is precedence-aware.
+//!
+//! When formatting source (rather than printing a synthesised AST), the printer also takes the
+//! comments in the source, and places them near the constructs they preceded.
+
+use std::fmt::{Display, Write as _};
+
+use super::language::{
+    ast::{self, call, constraint, typedef, var},
+    typing,
+    var::Scope,
+};
+
+pub use comment::{Comment, Location};
+
+pub mod comment;
+mod stm;
+mod view;
+
+/// Configuration for the pretty-printer.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Config {
+    /// The number of spaces in each level of indentation.
+    pub indent: usize,
+}
+
+/// The default configuration indents by two spaces.
+impl Default for Config {
+    fn default() -> Self {
+        Self { indent: 2 }
+    }
+}
+
+/// Prints `program` as PVC source, using the given configuration.
+#[must_use]
+pub fn program<M: Location, V: Display>(
+    program: &ast::Program<'_, M, V>,
+    config: Config,
+) -> String {
+    let mut printer = Printer::new(config);
+    printer.program(program);
+    printer.finish()
+}
+
+/// Formats PVC `source`, whose parsed form is `program`, keeping its comments.
+#[must_use]
+pub fn format<V: Display>(
+    source: &str,
+    program: &ast::Program<'_, Option<pest::Span<'_>>, V>,
+    config: Config,
+) -> String {
+    let mut printer = Printer::new(config).with_comments(comment::scan(source));
+    printer.program(program);
+    printer.finish()
+}
+
+/// A pretty-printer, accumulating PVC source.
+///
+/// The printer keeps a cursor that is always somewhere on the current line; multi-line constructs
+/// start at the cursor and leave it at the end of their last line.
+#[derive(Debug, Clone, Default)]
+pub struct Printer {
+    /// The configuration of the printer.
+    config: Config,
+    /// The source printed so far.
+    out: String,
+    /// The current indentation level.
+    level: usize,
+    /// Comments not yet printed, in reverse order of appearance.
+    comments: Vec<Comment>,
+}
+
+impl Printer {
+    /// Constructs a printer with the given configuration.
+    #[must_use]
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Adds `comments`, which should be in order of appearance, to the printer.
+    ///
+    /// Each comment prints before the first construct that starts after it in the source.
+    #[must_use]
+    pub fn with_comments(mut self, mut comments: Vec<Comment>) -> Self {
+        comments.reverse();
+        self.comments = comments;
+        self
+    }
+
+    /// Consumes the printer, returning the source printed so far.
+    #[must_use]
+    pub fn finish(mut self) -> String {
+        self.comments_before(usize::MAX);
+        if !self.out.ends_with('\n') {
+            self.newline();
+        }
+        self.out
+    }
+
+    /// Prints `program`.
+    pub fn program<M: Location, V: Display>(&mut self, program: &ast::Program<'_, M, V>) {
+        if self.comments_before_meta(&program.name.meta) {
+            self.newline();
+        }
+        self.write(format_args!("program {};", program.name.item));
+        for decl in &program.decls {
+            let start = decl.meta.range().map_or(usize::MAX, |r| r.start);
+            self.paragraph(start);
+            self.decl(&decl.item, &decl.meta);
+            self.write(";");
+        }
+        self.paragraph(usize::MAX);
+        self.write("begin");
+        self.newline();
+        self.write("end.");
+    }
+
+    /// Starts a new paragraph, separated from the last by a blank line, for a construct starting
+    /// at `offset`.
+    ///
+    /// Comments before `offset` that trail the last paragraph stay there; others move to the top
+    /// of the new paragraph.
+    fn paragraph(&mut self, offset: usize) {
+        while let Some(comment) = self.next_comment(|c| c.trailing && c.start < offset) {
+            self.comment(comment);
+        }
+        self.newline();
+        self.newline();
+        if self.comments_before(offset) {
+            self.newline();
+        }
+    }
+
+    /// Prints `decl`, whose metadata is `meta`, without its terminating semicolon.
+    pub fn decl<M: Location, V: Display>(&mut self, decl: &ast::Decl<'_, M, V>, meta: &M) {
+        match decl {
+            ast::Decl::Constraint(c) => self.constraint(c),
+            ast::Decl::Procedure(p) => self.procedure(p, meta),
+            ast::Decl::Type(t) => self.type_decl(t),
+            ast::Decl::Var(v) => self.var_decl(v),
+            ast::Decl::View(v) => self.section(
+                "view",
+                v.contents
+                    .iter()
+                    .map(|p| (&p.meta, call(&p.item, parameter))),
+            ),
+        }
+    }
+
+    fn constraint<M, V: Display>(&mut self, constraint: &constraint::Decl<M, V>) {
+        self.write("constraint ");
+        for (i, pattern) in constraint.views.iter().enumerate() {
+            if i != 0 {
+                self.write(", ");
+            }
+            self.write(view::pattern(&pattern.item));
+        }
+        match &constraint.body.item {
+            constraint::Constraint::Entails(e) => {
+                self.write(format_args!(" entails {}", e.entails.item));
+            }
+            constraint::Constraint::Exact(d) => {
+                self.write(format_args!(" = {}", d.definition.item));
+            }
+            constraint::Constraint::Indefinite => self.write(" ?"),
+            constraint::Constraint::Fails => self.write(" fails"),
+        }
+    }
+
+    fn procedure<M: Location, V: Display>(
+        &mut self,
+        procedure: &ast::Procedure<'_, M, V>,
+        meta: &M,
+    ) {
+        self.write(format_args!(
+            "procedure {}",
+            call(&procedure.prototype.item, parameter)
+        ));
+        if let Some(requires) = &procedure.requires {
+            self.write(format_args!(
+                " requires {{ {} }}",
+                view::assertion(&requires.item)
+            ));
+        }
+        if let Some(ensures) = &procedure.ensures {
+            self.write(format_args!(
+                " ensures {{ {} }}",
+                view::assertion(&ensures.item)
+            ));
+        }
+        self.write(";");
+        self.newline();
+        if !procedure.locals.is_empty() {
+            self.section(
+                "var",
+                procedure
+                    .locals
+                    .iter()
+                    .map(|p| (&p.meta, parameter(&p.item))),
+            );
+            self.write(";");
+            self.newline();
+        }
+        self.block(&procedure.body, meta);
+    }
+
+    fn type_decl<M: Location, V: Display>(&mut self, decl: &typedef::Decl<'_, M, V>) {
+        self.section(
+            "type",
+            decl.defs.iter().map(|d| {
+                (
+                    &d.meta,
+                    format!("{} = {}", d.item.name.item, ty(&d.item.ty.item)),
+                )
+            }),
+        );
+    }
+
+    fn var_decl<M: Location, V: Display>(&mut self, decl: &var::Decl<'_, M, V>) {
+        let keyword = match decl.scope.item {
+            Scope::Thread => "threadvar",
+            _ => "var",
+        };
+        self.section(
+            keyword,
+            decl.vars.iter().map(|p| (&p.meta, parameter(&p.item))),
+        );
+    }
+
+    /// Prints a declaration section that starts with `keyword` and has semicolon-separated
+    /// `items`, each paired with its metadata.
+    ///
+    /// Sections with one item print on one line; others print one item per line.
+    fn section<'a, M: Location + 'a>(
+        &mut self,
+        keyword: &str,
+        items: impl ExactSizeIterator<Item = (&'a M, String)>,
+    ) {
+        self.write(keyword);
+        if items.len() == 1 {
+            self.write(" ");
+            items.for_each(|(_, i)| self.write(i));
+            return;
+        }
+        self.indented(|p| {
+            for (i, (meta, item)) in items.enumerate() {
+                if i != 0 {
+                    p.write(";");
+                }
+                p.comments_before_meta(meta);
+                p.newline();
+                p.write(item);
+            }
+        });
+    }
+
+    /// Prints any pending comments that start before the construct with metadata `meta`.
+    ///
+    /// Returns whether there were any such comments.
+    fn comments_before_meta(&mut self, meta: &impl Location) -> bool {
+        meta.range()
+            .is_some_and(|range| self.comments_before(range.start))
+    }
+
+    /// Prints any pending comments that start before `offset`.
+    ///
+    /// Trailing comments go at the end of the current line, if it has any code on it; all other
+    /// comments go on their own lines.  Either way, the cursor ends up at the end of the last
+    /// comment printed.  Returns whether there were any such comments.
+    fn comments_before(&mut self, offset: usize) -> bool {
+        let mut any = false;
+        while let Some(comment) = self.next_comment(|c| c.start < offset) {
+            self.comment(comment);
+            any = true;
+        }
+        any
+    }
+
+    /// Takes the next pending comment, if there is one and it satisfies `pred`.
+    fn next_comment(&mut self, pred: impl FnOnce(&Comment) -> bool) -> Option<Comment> {
+        self.comments.pop_if(|c| pred(c))
+    }
+
+    /// Prints `comment`.
+    fn comment(&mut self, comment: Comment) {
+        let blank = self.current_line().trim().is_empty();
+        if comment.trailing && !blank {
+            self.write(" ");
+        } else if !blank {
+            self.newline();
+        }
+        self.write(comment.text);
+    }
+
+    /// Borrows the line on which the cursor sits.
+    fn current_line(&self) -> &str {
+        self.out.rsplit('\n').next().unwrap_or_default()
+    }
+
+    /// Writes `text` at the cursor.
+    fn write(&mut self, text: impl Display) {
+        let _ = write!(self.out, "{text}");
+    }
+
+    /// Ends the current line, moving the cursor to the indentation of the next line.
+    fn newline(&mut self) {
+        // We don't leave trailing whitespace on blank lines.
+        while self.out.ends_with(' ') {
+            self.out.pop();
+        }
+        self.out.push('\n');
+        self.out
+            .extend(std::iter::repeat_n(' ', self.level * self.config.indent));
+    }
+
+    /// Runs `f` with the indentation level increased by one.
+    fn indented(&mut self, f: impl FnOnce(&mut Self)) {
+        self.level += 1;
+        f(self);
+        self.level -= 1;
+    }
+}
+
+/// Renders a call, prototype, or view atom, rendering each argument with `arg`.
+///
+/// Calls with no arguments have no parentheses, as the grammar does not accept empty argument
+/// lists in calls.
+fn call<M, Arg>(call: &call::Generic<'_, M, Arg>, arg: impl Fn(&Arg) -> String) -> String {
+    let name = &call.name.item;
+    if call.args.is_empty() {
+        name.to_string()
+    } else {
+        let args: Vec<_> = call.args.iter().map(|a| arg(&a.item)).collect();
+        format!("{name}({})", args.join(", "))
+    }
+}
+
+/// Renders a constraint declaration, without its terminating semicolon.
+#[must_use]
+pub fn constraint<M, V: Display>(constraint: &constraint::Decl<M, V>) -> String {
+    let mut printer = Printer::default();
+    printer.constraint(constraint);
+    printer.out
+}
+
+/// Renders a procedure or view prototype.
+#[must_use]
+pub fn prototype<M, V: Display>(proto: &call::Prototype<'_, M, V>) -> String {
+    call(proto, parameter)
+}
+
+/// Renders a parameter or variable declaration.
+fn parameter<M, V: Display>(param: &call::Parameter<'_, M, V>) -> String {
+    format!("{} : {}", param.name.item, ty(&param.ty.item))
+}
+
+/// Renders a type.
+///
+/// We can't use the `Display` implementation of types here, as it uses the short names of
+/// primitive types rather than their PVC keywords.
+#[must_use]
+pub fn ty<M, V: Display>(ty: &typing::Type<M, V>) -> String {
+    match ty {
+        typing::Type::Array(a) => {
+            format!("array[{}..{}] of {}", a.low(), a.high(), self::ty(a.base()))
+        }
+        typing::Type::Refined(r) => format!(
+            "{{{} : {} | {}}}",
+            r.var(),
+            self::ty(r.element()),
+            r.refinement()
+        ),
+        typing::Type::Named(n) => n.item.to_string(),
+        typing::Type::Prim(typing::Prim::Int) => "integer".to_owned(),
+        typing::Type::Prim(typing::Prim::Bool) => "boolean".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use proptest::{collection::vec, option, prelude::*};
+
+    use super::*;
+    use crate::{
+        language::{
+            ast::{stm, view, Identifier},
+            expr::{bop, map::HasMeta, Expr, Uop},
+            ite::Ite,
+            tagged::Tagged,
+        },
+        parser,
+    };
+
+    /// Shorthand for the type of ASTs we generate.
+    type Program = ast::Program<'static, (), Identifier<'static>>;
+    type Decl = ast::Decl<'static, (), Identifier<'static>>;
+    type Triple = stm::Triple<'static, (), Identifier<'static>>;
+    type Stm = stm::Stm<'static, (), Identifier<'static>>;
+    type Assertion = view::Assertion<'static, (), Identifier<'static>>;
+    type Type = typing::Type<(), Identifier<'static>>;
+
+    /// Parses `input` as a program, discarding all metadata.
+    fn parse(input: &str) -> ast::Program<'_, (), Identifier<'_>> {
+        parser::parse(input).unwrap().item.map_meta(|_| ())
+    }
+
+    /// Tests that printing the ARC example gives a program that parses to the same AST.
+    #[test]
+    fn round_trip_arc() {
+        let ast = parse(include_str!("../examples/arc.pvc"));
+        assert_eq!(ast, parse(&program(&ast, Config::default())));
+    }
+
+    /// Tests that formatting keeps comments in place, and is idempotent.
+    #[test]
+    fn format_comments() {
+        let input = "(* header *)\nprogram P;\nvar x : integer;  (* counter *)\n\
+            procedure Q; begin (* first *) { emp } x := 1 (* done *) end; begin end.";
+        let expected = "(* header *)\nprogram P;\n\nvar x : integer; (* counter *)\n\n\
+            procedure Q;\nbegin (* first *)\n  { emp }\n    x := 1 (* done *)\nend;\n\n\
+            begin\nend.\n";
+
+        let ast = parser::parse(input).unwrap();
+        let formatted = format(input, &ast.item, Config::default());
+        assert_eq!(expected, formatted);
+
+        let ast = parser::parse(&formatted).unwrap();
+        assert_eq!(formatted, format(&formatted, &ast.item, Config::default()));
+    }
+
+    /// Tests that expressions get parentheses only where precedence and associativity need them.
+    #[test]
+    fn expr_parentheses() {
+        let x = || Expr::<(), &str>::Var(Tagged::with_default("x"));
+        let add = |l, r| Expr::bop(l, bop::Arith::Add, r);
+        let mul = |l, r| Expr::bop(l, bop::Arith::Mul, r);
+
+        assert_eq!("x + x * x", add(x(), mul(x(), x())).to_string());
+        assert_eq!("(x + x) * x", mul(add(x(), x()), x()).to_string());
+        assert_eq!("x + x + x", add(add(x(), x()), x()).to_string());
+        assert_eq!("x + (x + x)", add(x(), add(x(), x())).to_string());
+        assert_eq!("-(x + x)", Expr::uop(Uop::Minus, add(x(), x())).to_string());
+        assert_eq!("(-x)^", Expr::deref(Expr::uop(Uop::Minus, x())).to_string());
+        assert_eq!(
+            "not x and x",
+            Expr::bop(Expr::not(x()), bop::Bool::And, x()).to_string()
+        );
+    }
+
+    /// Tests that nested `if` statements keep their `else` branches in the right place.
+    #[test]
+    fn dangling_else() {
+        let input = "program P; procedure Q; begin if * then if * then x := 1 else nop else x := 2 end; begin end.";
+        let ast = parse(input);
+        let printed = program(&ast, Config::default());
+        assert!(printed.contains("if * then if * then x := 1 else nop else x := 2"));
+        assert_eq!(ast, parse(&printed));
+    }
+
+    proptest! {
+        /// Tests that printing any program gives a program that parses to the same AST.
+        #[test]
+        fn round_trip(ast in arb_program()) {
+            let printed = program(&ast, Config::default());
+            prop_assert_eq!(ast, parse(&printed));
+        }
+    }
+
+    /// The keywords of PVC, in lower case.
+    const KEYWORDS: &[&str] = &[
+        "and",
+        "array",
+        "begin",
+        "boolean",
+        "constraint",
+        "div",
+        "do",
+        "else",
+        "emp",
+        "end",
+        "ensures",
+        "false",
+        "if",
+        "iff",
+        "implies",
+        "integer",
+        "invariant",
+        "mod",
+        "nop",
+        "not",
+        "of",
+        "or",
+        "procedure",
+        "program",
+        "repeat",
+        "requires",
+        "then",
+        "threadvar",
+        "true",
+        "type",
+        "until",
+        "var",
+        "view",
+        "while",
+    ];
+
+    /// Generates identifiers that are not keywords, often starting with a keyword.
+    ///
+    /// The Unicode tables behind the regex generator can be newer than those behind the grammar,
+    /// so we keep only names that the grammar's own tables agree are identifiers.
+    fn arb_name() -> impl Strategy<Value = Identifier<'static>> {
+        const CONTINUE: &str = "\\p{XID_Continue}";
+        let regex = |r: String| proptest::string::string_regex(&r).unwrap();
+        let arbitrary = regex(format!("\\p{{XID_Start}}{CONTINUE}{{0,6}}"));
+        let prefixed = (
+            prop::sample::select(KEYWORDS),
+            regex(format!("{CONTINUE}{{1,3}}")),
+        )
+            .prop_map(|(keyword, rest)| format!("{keyword}{rest}"));
+        prop_oneof![arbitrary, prefixed]
+            .prop_filter("not an identifier to the grammar", |name| {
+                let mut chars = name.chars();
+                chars.next().is_some_and(pest::unicode::XID_START)
+                    && chars.all(pest::unicode::XID_CONTINUE)
+            })
+            .prop_filter("keywords aren't identifiers", |name| {
+                !KEYWORDS.contains(&name.to_ascii_lowercase().as_str())
+            })
+            .prop_map(Cow::Owned)
+    }
+
+    fn arb_expr() -> impl Strategy<Value = Expr<(), Identifier<'static>>> {
+        let leaf = prop_oneof![
+            any::<bool>().prop_map(Expr::bool),
+            (0..100_i64).prop_map(Expr::i64),
+            arb_name().prop_map(|v| Expr::Var(Tagged::with_default(v))),
+        ];
+        leaf.prop_recursive(4, 24, 2, |inner| {
+            prop_oneof![
+                (inner.clone(), arb_bop(), inner.clone())
+                    .prop_map(|(l, op, r)| Expr::bop(l, op, r)),
+                (arb_uop(), inner.clone()).prop_map(|(op, e)| Expr::uop(op, e)),
+                (inner.clone(), inner).prop_map(|(a, i)| Expr::index(a, i)),
+            ]
+        })
+    }
+
+    fn arb_bop() -> impl Strategy<Value = bop::Bop> {
+        use bop::{Arith, Bool, Rel};
+        prop::sample::select(vec![
+            Arith::Add.into(),
+            Arith::Sub.into(),
+            Arith::Mul.into(),
+            Arith::Div.into(),
+            Arith::Modulus.into(),
+            Bool::And.into(),
+            Bool::Or.into(),
+            Bool::Implies.into(),
+            Bool::Iff.into(),
+            Rel::Eq.into(),
+            Rel::NotEq.into(),
+            Rel::Less.into(),
+            Rel::LessEq.into(),
+            Rel::Greater.into(),
+            Rel::GreaterEq.into(),
+        ])
+    }
+
+    fn arb_uop() -> impl Strategy<Value = Uop> {
+        prop::sample::select(&[Uop::Deref, Uop::Plus, Uop::Minus, Uop::Not][..])
+    }
+
+    fn arb_condition(
+    ) -> impl Strategy<Value = Tagged<(), ast::ite::Condition<(), Identifier<'static>>>> {
+        option::of(arb_expr()).prop_map(|c| {
+            Tagged::with_default(c.map_or(
+                crate::language::ite::Condition::Nondeterministic,
+                crate::language::ite::Condition::Deterministic,
+            ))
+        })
+    }
+
+    fn arb_call<T: std::fmt::Debug>(
+        arg: impl Strategy<Value = T>,
+    ) -> impl Strategy<Value = call::Generic<'static, (), T>> {
+        (arb_name(), vec(arg, 0..3)).prop_map(|(name, args)| call::Generic {
+            name: Tagged::with_default(name),
+            args: args.into_iter().map(Tagged::with_default).collect(),
+        })
+    }
+
+    fn arb_type() -> impl Strategy<Value = Type> {
+        let leaf = prop_oneof![
+            Just(Type::INT),
+            Just(Type::BOOL),
+            arb_name().prop_map(|n| Type::Named(Tagged::with_default(n))),
+        ];
+        leaf.prop_recursive(2, 4, 1, |inner| {
+            prop_oneof![
+                (inner.clone(), 0..10_i64, 0..10_i64)
+                    .prop_map(|(t, l, h)| Type::Array(typing::Array::new(t, l.into(), h.into()))),
+                (arb_name(), inner, arb_expr()).prop_map(|(v, t, e)| Type::Refined(
+                    typing::Refined::new(v.into_owned(), t, e)
+                )),
+            ]
+        })
+    }
+
+    fn arb_parameter() -> impl Strategy<Value = call::Parameter<'static, (), Identifier<'static>>> {
+        (arb_name(), arb_type()).prop_map(|(name, ty)| call::Parameter {
+            name: Tagged::with_default(name),
+            ty: Tagged::with_default(ty),
+        })
+    }
+
+    fn arb_assertion() -> BoxedStrategy<Assertion> {
+        let leaf = prop_oneof![
+            Just(Assertion::Emp),
+            arb_expr().prop_map(|e| Assertion::Local(Tagged::with_default(e))),
+            arb_call(arb_expr()).prop_map(|c| Assertion::Atom(Tagged::with_default(c))),
+        ];
+        leaf.prop_recursive(3, 12, 2, |inner| {
+            prop_oneof![
+                (inner.clone(), inner.clone())
+                    .prop_map(|(l, r)| Assertion::Join(Box::new(l), Box::new(r))),
+                (inner.clone(), arb_condition(), inner.clone()).prop_map(|(t, c, f)| {
+                    Assertion::Ite(Ite::new(Box::new(t), c, Box::new(f)))
+                }),
+                (arb_expr(), inner.clone()).prop_map(|(guard, view)| {
+                    Assertion::Guarded(view::assertion::Guarded {
+                        guard,
+                        view: Box::new(view),
+                    })
+                }),
+                (inner, arb_expr()).prop_map(|(item, n)| Assertion::Iterated(view::Iterated {
+                    item: Box::new(item),
+                    iterator: Tagged::with_default(n),
+                })),
+            ]
+        })
+        .boxed()
+    }
+
+    fn arb_stm() -> BoxedStrategy<Stm> {
+        let leaf = prop_oneof![
+            Just(Stm::Nop),
+            (arb_expr(), arb_expr()).prop_map(|(l, r)| Stm::Assign(stm::Assign {
+                lvalue: Some(Tagged::with_default(l)),
+                rvalue: Tagged::with_default(r),
+            })),
+            arb_call(arb_expr()).prop_map(Stm::Call),
+        ];
+        leaf.prop_recursive(3, 12, 3, |inner| {
+            let triple = arb_triple(inner.clone().boxed());
+            prop_oneof![
+                vec(triple.clone(), 0..3).prop_map(Stm::Block),
+                (any::<bool>(), vec(triple.clone(), 1..3)).prop_map(|(keywords, body)| {
+                    Stm::Atomic(stm::Atomic {
+                        syntax: if keywords {
+                            stm::AtomicSyntax::Keywords
+                        } else {
+                            stm::AtomicSyntax::Brackets
+                        },
+                        body,
+                    })
+                }),
+                (inner.clone(), arb_condition(), inner.clone()).prop_map(|(t, c, f)| {
+                    Stm::Ite(Ite::new(
+                        Tagged::with_default(Box::new(t)),
+                        c,
+                        Tagged::with_default(Box::new(f)),
+                    ))
+                }),
+                (arb_condition(), arb_assertion(), inner).prop_map(|(c, i, body)| {
+                    Stm::While(stm::While {
+                        cond: c,
+                        invariant: Tagged::with_default(i),
+                        body: Tagged::with_default(Box::new(body)),
+                    })
+                }),
+                (arb_assertion(), vec(triple, 1..3), arb_condition()).prop_map(|(i, body, c)| {
+                    Stm::Repeat(stm::Repeat {
+                        invariant: Tagged::with_default(i),
+                        body,
+                        cond: c,
+                    })
+                }),
+            ]
+        })
+        .boxed()
+    }
+
+    fn arb_triple(stm: BoxedStrategy<Stm>) -> BoxedStrategy<Tagged<(), Triple>> {
+        let assertion = || option::of(arb_assertion().prop_map(Tagged::with_default));
+        (assertion(), stm, assertion())
+            .prop_map(|(pre, stm, post)| {
+                Tagged::with_default(stm::Triple {
+                    pre,
+                    stm: Tagged::with_default(stm),
+                    post,
+                })
+            })
+            .boxed()
+    }
+
+    fn arb_pattern() -> impl Strategy<Value = view::Pattern<(), Identifier<'static>>> {
+        use view::pattern::Argument;
+        let argument = || {
+            prop_oneof![
+                Just(Argument::Wildcard),
+                arb_expr().prop_map(Argument::Expr),
+            ]
+        };
+        let atom =
+            (arb_name(), vec(argument(), 0..3), argument()).prop_map(|(name, args, iterator)| {
+                view::pattern::Atom {
+                    name: Tagged::with_default(name),
+                    args: args.into_iter().map(Tagged::with_default).collect(),
+                    iterator: Tagged::with_default(iterator),
+                }
+            });
+        vec(atom.prop_map(Tagged::with_default), 0..3)
+            .prop_map(|contents| view::Pattern { contents })
+    }
+
+    fn arb_decl() -> impl Strategy<Value = Decl> {
+        prop_oneof![
+            (
+                vec(arb_pattern().prop_map(Tagged::with_default), 1..3),
+                prop_oneof![
+                    arb_expr().prop_map(|e| constraint::Constraint::Entails(
+                        constraint::Entailment {
+                            entails: Tagged::with_default(e),
+                        }
+                    )),
+                    arb_expr().prop_map(|e| constraint::Constraint::Exact(
+                        constraint::Definition {
+                            definition: Tagged::with_default(e),
+                        }
+                    )),
+                    Just(constraint::Constraint::Indefinite),
+                    Just(constraint::Constraint::Fails),
+                ]
+            )
+                .prop_map(|(views, body)| Decl::Constraint(constraint::Decl {
+                    views,
+                    body: Tagged::with_default(body),
+                })),
+            (
+                arb_call(arb_parameter()),
+                option::of(arb_assertion()),
+                option::of(arb_assertion()),
+                vec(arb_parameter(), 0..3),
+                vec(arb_triple(arb_stm()), 0..4)
+            )
+                .prop_map(|(prototype, requires, ensures, locals, body)| {
+                    Decl::Procedure(ast::Procedure {
+                        prototype: Tagged::with_default(prototype),
+                        requires: requires.map(Tagged::with_default),
+                        ensures: ensures.map(Tagged::with_default),
+                        locals: locals.into_iter().map(Tagged::with_default).collect(),
+                        body,
+                    })
+                }),
+            vec((arb_name(), arb_type()), 1..3).prop_map(|defs| Decl::Type(typedef::Decl {
+                defs: defs
+                    .into_iter()
+                    .map(|(name, ty)| {
+                        Tagged::with_default(typedef::Def {
+                            name: Tagged::with_default(name),
+                            ty: Tagged::with_default(ty),
+                        })
+                    })
+                    .collect(),
+            })),
+            (any::<bool>(), vec(arb_parameter(), 1..3)).prop_map(|(thread, vars)| {
+                Decl::Var(var::Decl {
+                    scope: Tagged::with_default(if thread { Scope::Thread } else { Scope::Shared }),
+                    vars: vars.into_iter().map(Tagged::with_default).collect(),
+                })
+            }),
+            vec(arb_call(arb_parameter()), 1..3).prop_map(|contents| {
+                Decl::View(ast::view::Decl {
+                    contents: contents.into_iter().map(Tagged::with_default).collect(),
+                })
+            }),
+        ]
+    }
+
+    fn arb_program() -> impl Strategy<Value = Program> {
+        (arb_name(), vec(arb_decl(), 0..4)).prop_map(|(name, decls)| Program {
+            name: Tagged::with_default(name),
+            decls: decls.into_iter().map(Tagged::with_default).collect(),
+        })
+    }
+}
@@ -0,0 +1,95 @@
+//! Comments, and their placement in printed programs.
+//!
+//! The parser discards comments as trivia, so the printer recovers them by scanning the source
+//! separately, then places each one before the first construct that follows it in the source.
+
+use std::ops::Range;
+
+use pest::Span;
+
+/// A comment in PVC source.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Comment {
+    /// The byte offset at which the comment starts.
+    pub start: usize,
+    /// The text of the comment, including its delimiters.
+    pub text: String,
+    /// Whether the comment follows code on the same line.
+    ///
+    /// Trailing comments stay at the end of the line they follow where possible.
+    pub trailing: bool,
+}
+
+/// Scans `source` for comments, in order of appearance.
+///
+/// Neither `(*` nor `*)` can appear in PVC source outside of comments, so we don't need to parse
+/// the source to find them.  An unterminated comment runs to the end of the source.
+#[must_use]
+pub fn scan(source: &str) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut rest = 0;
+    while let Some(offset) = source[rest..].find("(*") {
+        let start = rest + offset;
+        let end = source[start + 2..]
+            .find("*)")
+            .map_or(source.len(), |e| start + 2 + e + 2);
+        let line_start = source[..start].rfind('\n').map_or(0, |n| n + 1);
+        comments.push(Comment {
+            start,
+            text: source[start..end].to_owned(),
+            trailing: !source[line_start..start].trim().is_empty(),
+        });
+        rest = end;
+    }
+    comments
+}
+
+/// Trait of metadata that may carry a source location, used to place comments.
+pub trait Location {
+    /// Gets the range of bytes in the source covered by this metadata, if any.
+    fn range(&self) -> Option<Range<usize>>;
+}
+
+/// Unit metadata has no location.
+impl Location for () {
+    fn range(&self) -> Option<Range<usize>> {
+        None
+    }
+}
+
+impl Location for Option<Span<'_>> {
+    fn range(&self) -> Option<Range<usize>> {
+        self.map(|s| s.start()..s.end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests scanning a mixture of trailing and own-line comments.
+    #[test]
+    fn scan_comments() {
+        let comments = scan("(* a *)\nx := 1; (* b *)\n  (* c\n d *)");
+        assert_eq!(
+            vec![
+                Comment {
+                    start: 0,
+                    text: "(* a *)".to_owned(),
+                    trailing: false
+                },
+                Comment {
+                    start: 16,
+                    text: "(* b *)".to_owned(),
+                    trailing: true
+                },
+                Comment {
+                    start: 26,
+                    text: "(* c\n d *)".to_owned(),
+                    trailing: false
+                },
+            ],
+            comments
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Printing of statements and triples.
+
+use std::fmt::Display;
+
+use super::{
+    super::language::ast::stm::{self, Stm},
+    view, Location, Printer,
+};
+
+impl Printer {
+    /// Prints a `begin`-`end` block containing `triples`, where `meta` is the metadata of the
+    /// construct the block ends.
+    pub fn block<M: Location, V: Display>(&mut self, triples: &stm::List<'_, M, V>, meta: &M) {
+        self.write("begin");
+        self.list(triples);
+        let commented = meta
+            .range()
+            .is_some_and(|r| self.inner_comments_before(r.end));
+        if triples.is_empty() && !commented {
+            self.write(" ");
+        } else {
+            self.newline();
+        }
+        self.write("end");
+    }
+
+    /// Prints `stm`, whose metadata is `meta`.
+    pub fn stm<M: Location, V: Display>(&mut self, stm: &Stm<'_, M, V>, meta: &M) {
+        self.stm_with_else(stm, meta, false);
+    }
+
+    /// Prints each of `triples` on its own line, one level of indentation deeper than the cursor.
+    fn list<M: Location, V: Display>(&mut self, triples: &stm::List<'_, M, V>) {
+        self.indented(|p| {
+            for (i, triple) in triples.iter().enumerate() {
+                if i != 0 {
+                    p.write(";");
+                }
+                p.comments_before_meta(&triple.meta);
+                p.newline();
+                p.triple(&triple.item);
+            }
+        });
+    }
+
+    /// Prints `triple`.
+    ///
+    /// If the triple has any assertions, they sit on their own lines, and the statement is
+    /// indented between them.
+    fn triple<M: Location, V: Display>(&mut self, triple: &stm::Triple<'_, M, V>) {
+        if triple.pre.is_none() && triple.post.is_none() {
+            self.stm(&triple.stm.item, &triple.stm.meta);
+            return;
+        }
+
+        if let Some(pre) = &triple.pre {
+            self.write(format_args!("{{ {} }}", view::assertion(&pre.item)));
+        }
+        self.indented(|p| {
+            if triple.pre.is_some() {
+                p.comments_before_meta(&triple.stm.meta);
+                p.newline();
+            }
+            p.stm(&triple.stm.item, &triple.stm.meta);
+        });
+        if let Some(post) = &triple.post {
+            self.comments_before_meta(&post.meta);
+            self.newline();
+            self.write(format_args!("{{ {} }}", view::assertion(&post.item)));
+        }
+    }
+
+    /// Prints `stm`, forcing any trailing `if` to print an `else` branch if `force_else` is set.
+    ///
+    /// We need to force `else` branches on any `if` statement that ends the `then` branch of an
+    /// enclosing `if` with its own `else`; otherwise, the enclosing `else` would attach to the
+    /// inner `if` when parsed.
+    fn stm_with_else<M: Location, V: Display>(
+        &mut self,
+        stm: &Stm<'_, M, V>,
+        meta: &M,
+        force_else: bool,
+    ) {
+        match stm {
+            Stm::Assign(a) => {
+                if let Some(l) = &a.lvalue {
+                    self.write(format_args!("{} := ", l.item));
+                }
+                self.write(&a.rvalue.item);
+            }
+            Stm::Atomic(a) => self.atomic(a, meta),
+            Stm::Block(b) => self.block(b, meta),
+            Stm::Call(c) => self.write(super::call(c, ToString::to_string)),
+            Stm::Ite(i) => self.ite(i, force_else),
+            Stm::Nop => self.write("nop"),
+            Stm::Primitive(p) => self.write(primitive(p)),
+            Stm::Repeat(r) => self.repeat(r),
+            Stm::While(w) => {
+                self.write(format_args!(
+                    "while {} invariant {{ {} }} do ",
+                    view::condition(&w.cond.item),
+                    view::assertion(&w.invariant.item)
+                ));
+                self.stm_with_else(&w.body.item, &w.body.meta, force_else);
+            }
+        }
+    }
+
+    /// Prints a `repeat`-`until` loop.
+    fn repeat<M: Location, V: Display>(&mut self, repeat: &stm::Repeat<'_, M, V>) {
+        self.write(format_args!(
+            "repeat invariant {{ {} }}",
+            view::assertion(&repeat.invariant.item)
+        ));
+        self.list(&repeat.body);
+        if let Some(r) = repeat.cond.meta.range() {
+            self.inner_comments_before(r.start);
+        }
+        self.newline();
+        self.write(format_args!("until {}", view::condition(&repeat.cond.item)));
+    }
+
+    /// Prints an atomic block, using whichever syntax it was written with.
+    ///
+    /// Blocks of simple statements print on one line, unless they contain comments.
+    fn atomic<M: Location, V: Display>(&mut self, atomic: &stm::Atomic<'_, M, V>, meta: &M) {
+        let (open, close) = match atomic.syntax {
+            stm::AtomicSyntax::Brackets => ("<|", "|>"),
+            stm::AtomicSyntax::Keywords => ("atomic", "end"),
+        };
+        let end = meta.range().map_or(0, |r| r.end);
+        let commented = self.comments.last().is_some_and(|c| c.start < end);
+
+        self.write(open);
+        if !commented && atomic.body.iter().all(|t| is_simple(&t.item)) {
+            for (i, triple) in atomic.body.iter().enumerate() {
+                self.write(if i == 0 { " " } else { "; " });
+                self.stm(&triple.item.stm.item, &triple.item.stm.meta);
+            }
+            self.write(" ");
+        } else {
+            self.list(&atomic.body);
+            self.inner_comments_before(end);
+            self.newline();
+        }
+        self.write(close);
+    }
+
+    /// Prints any pending comments that start before `offset`, one level of indentation deeper
+    /// than the cursor.
+    ///
+    /// We use this to print comments at the end of blocks.
+    fn inner_comments_before(&mut self, offset: usize) -> bool {
+        let mut any = false;
+        self.indented(|p| any = p.comments_before(offset));
+        any
+    }
+
+    /// Prints an `if` statement.
+    fn ite<M: Location, V: Display>(&mut self, ite: &stm::Ite<'_, M, V>, force_else: bool) {
+        let has_else = force_else || !matches!(*ite.false_branch.item, Stm::Nop);
+        self.write(format_args!("if {} then ", view::condition(&ite.cond.item)));
+        self.stm_with_else(&ite.true_branch.item, &ite.true_branch.meta, has_else);
+        if has_else {
+            self.write(" else ");
+            self.stm_with_else(&ite.false_branch.item, &ite.false_branch.meta, force_else);
+        }
+    }
+}
+
+/// Checks whether `triple` has no assertions and a statement that prints on one line.
+fn is_simple<M, V>(triple: &stm::Triple<'_, M, V>) -> bool {
+    triple.pre.is_none()
+        && triple.post.is_none()
+        && matches!(
+            triple.stm.item,
+            Stm::Assign(_) | Stm::Call(_) | Stm::Nop | Stm::Primitive(_)
+        )
+}
+
+/// Renders a primitive call, using the canonical name of the primitive.
+fn primitive<M, V: Display>(call: &stm::Primitive<M, V>) -> String {
+    let name = call.primitive.item;
+    if call.args.is_empty() {
+        name.to_string()
+    } else {
+        let args: Vec<_> = call.args.iter().map(|a| a.item.to_string()).collect();
+        format!("{name}({})", args.join(", "))
+    }
+}
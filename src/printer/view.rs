@@ -0,0 +1,111 @@
+//! Printing of view assertions, view patterns, and conditions.
+//!
+//! View assertions use their own precedence levels, mirroring those of the assertion parser.
+
+use std::fmt::{Display, Write as _};
+
+use super::super::language::{
+    ast::{
+        ite,
+        view::{assertion::Assertion, pattern},
+    },
+    expr::{Constant, Expr},
+};
+
+/// Precedence of joins, which are left-associative.
+const JOIN_PRECEDENCE: u8 = 1;
+/// Precedence of if-then-else assertions, which are left-associative.
+const ITE_PRECEDENCE: u8 = 2;
+/// Precedence of guards, which are prefix.
+const GUARD_PRECEDENCE: u8 = 3;
+/// Precedence of iterations, which are postfix.
+const ITERATE_PRECEDENCE: u8 = 4;
+/// Precedence of assertions that never need parentheses.
+const ATOM_PRECEDENCE: u8 = 5;
+
+/// Renders `assertion`.
+pub fn assertion<M, V: Display>(assertion: &Assertion<'_, M, V>) -> String {
+    match assertion {
+        Assertion::Atom(a) => super::call(&a.item, ToString::to_string),
+        Assertion::Emp => "emp".to_owned(),
+        Assertion::Guarded(g) => {
+            format!("[{}] {}", g.guard, operand(&g.view, GUARD_PRECEDENCE))
+        }
+        Assertion::Ite(i) => format!(
+            "{} <[ {} ]> {}",
+            operand(&i.true_branch, ITE_PRECEDENCE),
+            condition(&i.cond.item),
+            operand(&i.false_branch, ITE_PRECEDENCE + 1)
+        ),
+        Assertion::Iterated(i) => format!(
+            "{}[{}]",
+            operand(&i.item, ITERATE_PRECEDENCE),
+            i.iterator.item
+        ),
+        Assertion::Join(l, r) => format!(
+            "{} * {}",
+            operand(l, JOIN_PRECEDENCE),
+            operand(r, JOIN_PRECEDENCE + 1)
+        ),
+        Assertion::Local(l) => format!("@({})", l.item),
+    }
+}
+
+/// Renders `assertion` as the operand of an operator with precedence `prec`.
+fn operand<M, V: Display>(assertion: &Assertion<'_, M, V>, prec: u8) -> String {
+    let own = match assertion {
+        Assertion::Join(..) => JOIN_PRECEDENCE,
+        Assertion::Ite(_) => ITE_PRECEDENCE,
+        Assertion::Guarded(_) => GUARD_PRECEDENCE,
+        Assertion::Iterated(_) => ITERATE_PRECEDENCE,
+        Assertion::Atom(_) | Assertion::Emp | Assertion::Local(_) => ATOM_PRECEDENCE,
+    };
+    if own < prec {
+        format!("({})", self::assertion(assertion))
+    } else {
+        self::assertion(assertion)
+    }
+}
+
+/// Renders an if-then-else condition.
+pub fn condition<M, V: Display>(cond: &ite::Condition<M, V>) -> String {
+    match cond {
+        ite::Condition::Nondeterministic => "*".to_owned(),
+        ite::Condition::Deterministic(e) => e.to_string(),
+    }
+}
+
+/// Renders a view pattern.
+pub fn pattern<M, V: Display>(pattern: &pattern::Pattern<M, V>) -> String {
+    if pattern.contents.is_empty() {
+        return "emp".to_owned();
+    }
+    let atoms: Vec<_> = pattern.contents.iter().map(|a| atom(&a.item)).collect();
+    atoms.join(" * ")
+}
+
+/// Renders a view pattern atom, leaving out its iterator if it is the default of 1.
+fn atom<M, V: Display>(atom: &pattern::Atom<M, V>) -> String {
+    let mut out = atom.name.item.to_string();
+    if !atom.args.is_empty() {
+        let args: Vec<_> = atom.args.iter().map(|a| argument(&a.item)).collect();
+        let _ = write!(out, "({})", args.join(", "));
+    }
+    if !matches!(&atom.iterator.item, pattern::Argument::Expr(e) if is_one(e)) {
+        let _ = write!(out, "[{}]", argument(&atom.iterator.item));
+    }
+    out
+}
+
+/// Renders a view pattern argument.
+fn argument<M, V: Display>(arg: &pattern::Argument<M, V>) -> String {
+    match arg {
+        pattern::Argument::Wildcard => "_".to_owned(),
+        pattern::Argument::Expr(e) => e.to_string(),
+    }
+}
+
+/// Checks whether `expr` is the literal 1.
+fn is_one<M, V>(expr: &Expr<M, V>) -> bool {
+    matches!(expr.as_literal(), Some(Constant::Int(i)) if *i == 1.into())
+}
@@ -0,0 +1,280 @@
+//! Discharging verification conditions with external SMT solvers.
+//!
+//! Rather than linking against a solver, we pipe each condition, as an SMT-LIB 2 script, into a
+//! fresh solver process over standard input, and parse the solver's response from standard
+//! output.  Any solver that speaks SMT-LIB 2 over standard input should work; we know the command
+//! lines for Z3, CVC5, and Yices.
+//!
+//! Inferring the definitions of indefinite constraints needs a CHC solver instead (see [horn]).
+
+use std::{
+    fmt::{Display, Formatter},
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use super::{
+    diagnostic::{self, code, Diagnostic, FileId},
+    language::cfg,
+    vcgen::{smt2, Condition, Origin},
+};
+
+pub mod counterexample;
+pub mod horn;
+pub mod response;
+
+pub use counterexample::Counterexample;
+pub use response::{Model, Value};
+
+/// The solvers we know how to drive.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+#[non_exhaustive]
+pub enum Kind {
+    /// Microsoft Research's Z3.
+    #[default]
+    Z3,
+    /// CVC5.
+    Cvc5,
+    /// SRI's Yices 2, through its SMT-LIB 2 frontend.
+    Yices,
+}
+
+impl Kind {
+    /// Gets the name of the binary for this solver, as it is usually installed.
+    #[must_use]
+    pub fn binary(self) -> &'static str {
+        match self {
+            Self::Z3 => "z3",
+            Self::Cvc5 => "cvc5",
+            Self::Yices => "yices-smt2",
+        }
+    }
+
+    /// Gets the arguments that make this solver read SMT-LIB 2 from standard input.
+    #[must_use]
+    pub fn args(self) -> &'static [&'static str] {
+        match self {
+            Self::Z3 => &["-in", "-smt2"],
+            Self::Cvc5 => &["--lang", "smt2"],
+            Self::Yices => &["--incremental"],
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Z3 => "z3",
+            Self::Cvc5 => "cvc5",
+            Self::Yices => "yices",
+        })
+    }
+}
+
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "z3" => Ok(Self::Z3),
+            "cvc5" => Ok(Self::Cvc5),
+            "yices" | "yices2" | "yices-smt2" => Ok(Self::Yices),
+            _ => Err(Error::UnknownSolver {
+                name: s.to_owned(),
+                expected: "z3, cvc5, yices",
+            }),
+        }
+    }
+}
+
+/// Configuration for a solver process.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Config {
+    /// The solver to use.
+    pub kind: Kind,
+    /// The path to the solver binary; if absent, we look up the usual binary name on the path.
+    pub path: Option<PathBuf>,
+}
+
+impl Config {
+    /// Checks `condition`, which belongs to `program`, by running the solver on it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the condition cannot be emitted as SMT-LIB, the solver cannot be run, or the
+    /// solver reports an error or responds in a way we do not understand.
+    pub fn check<M>(
+        &self,
+        program: &cfg::Program<'_, M>,
+        condition: &Condition<M>,
+    ) -> Result<Verdict> {
+        let mut script = b"(set-option :produce-models true)\n".to_vec();
+        smt2::write(program, condition, &mut script)?;
+        // If the condition is unsatisfiable, asking for a model is an error, but one that comes
+        // after the verdict we need.
+        script.extend_from_slice(b"(get-model)\n(exit)\n");
+
+        let binary = self
+            .path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(self.kind.binary()));
+        let output = run(binary, self.kind.args(), &script)?;
+        response::parse(&output)
+    }
+}
+
+/// Runs the solver `binary` with `args` on `script`, returning its standard output.
+fn run(binary: PathBuf, args: &[&str], script: &[u8]) -> Result<String> {
+    let mut child = match Command::new(&binary)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(source) => return Err(Error::Spawn { binary, source }),
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(script)?;
+    }
+    let output = child.wait_with_output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The outcome of checking a verification condition.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Verdict {
+    /// The condition holds (its negation is unsatisfiable).
+    Valid,
+    /// The condition does not hold; the solver may have given a counterexample.
+    Invalid(Option<Model>),
+    /// The solver could not decide the condition.
+    Unknown,
+}
+
+impl Verdict {
+    /// Gets whether this verdict is [`Verdict::Valid`].
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Valid => "valid",
+            Self::Invalid(_) => "FAILED",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+/// The result of checking one verification condition.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Outcome<'a, M> {
+    /// The condition checked.
+    pub condition: &'a Condition<M>,
+    /// The solver's verdict.
+    pub verdict: Verdict,
+}
+
+impl<'inp> Outcome<'_, Option<pest::Span<'inp>>> {
+    /// Reports this outcome as a diagnostic, taking spans to be in `file`.
+    ///
+    /// Valid conditions need no report.  Failed conditions point at the triple they came from,
+    /// and carry any counterexample decoded against `program` as notes.
+    #[must_use]
+    pub fn to_diagnostic(
+        &self,
+        program: &cfg::Program<'_, Option<pest::Span<'inp>>>,
+        file: FileId,
+    ) -> Option<Diagnostic> {
+        let cond = self.condition;
+        let (code, message) = match &self.verdict {
+            Verdict::Valid => return None,
+            Verdict::Invalid(_) => (code::FAILED_CONDITION, "does not hold"),
+            Verdict::Unknown => (code::UNKNOWN_CONDITION, "could not be decided"),
+        };
+
+        let diagnostic = Diagnostic::error(format!(
+            "verification condition `{}` {message}",
+            cond.name()
+        ));
+        let [pre, meta, post] = match cond.origin {
+            Origin::Edge { .. } => ["pre-condition", "command", "post-condition"],
+            Origin::Constraint => ["pattern", "constraint", "body"],
+        };
+        let mut diagnostic = diagnostic
+            .with_code(code)
+            .with_label_opt(diagnostic::label(file, &cond.pre, false, pre))
+            .with_label_opt(diagnostic::label(file, &cond.meta, true, meta))
+            .with_label_opt(diagnostic::label(file, &cond.post, false, post));
+
+        if let Verdict::Invalid(Some(model)) = &self.verdict {
+            let cex = Counterexample::decode(program, cond, model);
+            for line in cex.to_string().lines() {
+                diagnostic = diagnostic.with_note(line);
+            }
+        }
+        Some(diagnostic)
+    }
+}
+
+/// Checks every condition in `conditions` with the solver described by `config`.
+///
+/// # Errors
+///
+/// Fails on the first condition that the solver cannot check; see [`Config::check`].
+pub fn verify<'a, M>(
+    config: &Config,
+    program: &cfg::Program<'_, M>,
+    conditions: &'a [Condition<M>],
+) -> Result<Vec<Outcome<'a, M>>> {
+    conditions
+        .iter()
+        .map(|condition| {
+            Ok(Outcome {
+                condition,
+                verdict: config.check(program, condition)?,
+            })
+        })
+        .collect()
+}
+
+/// Errors that can occur when running a solver.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The user asked for a solver we do not know.
+    #[error("unknown solver `{name}` (expected one of {expected})")]
+    UnknownSolver {
+        name: String,
+        expected: &'static str,
+    },
+    /// The solver binary could not be started.
+    #[error("couldn't run solver `{}`: {source}", binary.display())]
+    Spawn {
+        binary: PathBuf,
+        source: std::io::Error,
+    },
+    /// Communicating with the solver failed.
+    #[error("couldn't communicate with solver: {0}")]
+    Io(#[from] std::io::Error),
+    /// The condition could not be emitted.
+    #[error(transparent)]
+    Smt2(#[from] smt2::Error),
+    /// The solver reported an error.
+    #[error("solver error: {0}")]
+    Solver(String),
+    /// The solver responded in a way we did not understand.
+    #[error("unexpected solver response: {0}")]
+    Response(String),
+}
+
+/// Shorthand for results over [Error].
+pub type Result<T> = std::result::Result<T, Error>;
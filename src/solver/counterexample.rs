@@ -0,0 +1,222 @@
+//! Decoding solver models into counterexamples.
+//!
+//! A solver model assigns values to the SMT-LIB symbols of a condition.  We decode it back into
+//! the terms of the PVC program: valuations of shared variables and thread-local variables before
+//! and after the command, and valuations of the arguments of the goal view.
+
+use std::fmt::{Display, Formatter};
+
+use egg::Symbol;
+
+use super::{
+    super::{
+        language::{cfg, var::Scope},
+        vcgen::{Condition, Var},
+    },
+    Model, Value,
+};
+
+/// A counterexample to a verification condition.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Counterexample {
+    /// Valuations of shared variables.
+    pub shared: Vec<Valuation>,
+    /// Valuations of thread-local variables.
+    pub thread: Vec<Valuation>,
+    /// Valuations of the arguments of the goal view.
+    pub view_args: Vec<Valuation>,
+}
+
+impl Counterexample {
+    /// Decodes `model`, which the solver gave for `condition` in `program`.
+    #[must_use]
+    pub fn decode<M>(
+        program: &cfg::Program<'_, M>,
+        condition: &Condition<M>,
+        model: &Model,
+    ) -> Self {
+        let mut result = Self::default();
+
+        let mut vars = vec![];
+        condition.for_each_var(&mut |v| {
+            if !vars.contains(&v) {
+                vars.push(v);
+            }
+        });
+
+        for var in vars {
+            let Some(value) = model.get(var) else {
+                continue;
+            };
+            let sym = var.symbol();
+            let group = match var {
+                // Intermediate values are an artefact of how we encode primitives.
+                Var::Inter(..) => continue,
+                Var::Goal(_) => &mut result.view_args,
+                Var::Pre(_) | Var::Post(_) => {
+                    match program.variables.get_exact(sym).map(|r| r.scope) {
+                        Some(Scope::Thread) => &mut result.thread,
+                        Some(Scope::Bound) => &mut result.view_args,
+                        Some(Scope::Shared) | None => &mut result.shared,
+                    }
+                }
+            };
+
+            let index = group
+                .iter()
+                .position(|v| v.symbol == *sym)
+                .unwrap_or_else(|| {
+                    group.push(Valuation {
+                        symbol: sym.clone(),
+                        name: sym.name,
+                        before: None,
+                        after: None,
+                    });
+                    group.len() - 1
+                });
+            let valuation = &mut group[index];
+            if let Var::Post(_) = var {
+                valuation.after = Some(value.clone());
+            } else {
+                valuation.before = Some(value.clone());
+            }
+        }
+
+        result.qualify_collisions();
+        result
+    }
+
+    /// Qualifies the names of variables that share their name with another variable.
+    ///
+    /// Variables declared in different blocks can have the same name, such as a procedure
+    /// parameter shadowing a shared variable, so we name those by their blocks to tell them apart.
+    fn qualify_collisions(&mut self) {
+        let symbols: Vec<_> = self.valuations().map(|v| v.symbol.clone()).collect();
+        for group in [&mut self.shared, &mut self.thread, &mut self.view_args] {
+            for valuation in group {
+                let symbol = &valuation.symbol;
+                if symbols.iter().any(|s| s.name == symbol.name && s != symbol) {
+                    valuation.name = Symbol::new(symbol.to_string());
+                }
+            }
+        }
+    }
+
+    /// Iterates over every valuation in the counterexample.
+    fn valuations(&self) -> impl Iterator<Item = &Valuation> {
+        self.shared
+            .iter()
+            .chain(&self.thread)
+            .chain(&self.view_args)
+    }
+}
+
+/// Counterexamples are displayed as one line per group of variables.
+impl Display for Counterexample {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let groups = [
+            ("shared", &self.shared),
+            ("thread", &self.thread),
+            ("view arguments", &self.view_args),
+        ];
+        for (name, group) in groups.into_iter().filter(|(_, g)| !g.is_empty()) {
+            write!(f, "{name}: ")?;
+            for (i, v) in group.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(", ")?;
+                }
+                v.fmt(f)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of a variable before and after a command.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Valuation {
+    /// The variable.
+    pub symbol: cfg::var::Symbol,
+    /// The name to show for the variable.
+    ///
+    /// This is the name written in the program, unless another variable in the counterexample has
+    /// the same name, in which case it is qualified by the block declaring the variable.
+    pub name: Symbol,
+    /// The value before the command, if the model gives one.
+    pub before: Option<Value>,
+    /// The value after the command, if the model gives one.
+    pub after: Option<Value>,
+}
+
+/// Valuations display as `x = v` if the value does not change, and `x = v -> w` otherwise.
+impl Display for Valuation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = ", self.name)?;
+        match (&self.before, &self.after) {
+            (Some(b), Some(a)) if a != b => write!(f, "{b} -> {a}"),
+            (Some(v), _) => v.fmt(f),
+            (None, Some(a)) => write!(f, "? -> {a}"),
+            (None, None) => f.write_str("?"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{response, Verdict};
+
+    /// Tests that model values are sorted into shared and thread-local groups.
+    #[test]
+    fn decode_groups() {
+        let input = "program P;
+var x : integer;
+threadvar t : integer;
+procedure Q;
+begin
+  { emp } <| t := x; x := 2 |> { @(t = 2) }
+end;
+begin end.";
+        let ast = crate::parser::parse(input).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let conds = crate::vcgen::generate(&program).unwrap();
+
+        let output = "sat
+((define-fun x () Int 1)
+ (define-fun |t!after| () Int 1))";
+        let Verdict::Invalid(Some(model)) = response::parse(output).unwrap() else {
+            panic!("expected a model");
+        };
+        let cex = Counterexample::decode(&program, &conds[0], &model);
+        assert_eq!("shared: x = 1\nthread: t = ? -> 1\n", cex.to_string());
+    }
+
+    /// Tests that variables sharing a name are told apart by their blocks.
+    #[test]
+    fn decode_collisions() {
+        let input = "program P;
+var x : integer;
+view V(n : integer);
+procedure Q(x : integer);
+begin
+  { V(x) } <| nop |> { V(x + 1) }
+end;
+constraint V(n) entails n = x;
+begin end.";
+        let ast = crate::parser::parse(input).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let conds = crate::vcgen::generate(&program).unwrap();
+
+        let output = "sat
+((define-fun Q.x () Int 1)
+ (define-fun x () Int 1)
+ (define-fun |Q.x!after| () Int 1)
+ (define-fun |x!after| () Int 2))";
+        let Verdict::Invalid(Some(model)) = response::parse(output).unwrap() else {
+            panic!("expected a model");
+        };
+        let cex = Counterexample::decode(&program, &conds[0], &model);
+        assert_eq!("shared: x = 1 -> 2\nthread: Q.x = 1\n", cex.to_string());
+    }
+}
@@ -0,0 +1,523 @@
+//! Inferring the definitions of indefinite constraints with external CHC solvers.
+//!
+//! We emit the whole proof as a system of constrained Horn clauses (see
+//! [`vcgen::horn`](crate::vcgen::horn)), pipe it into a CHC solver, and read the definitions of
+//! the unknown predicates back out of the solver's model.  We know the command lines for Z3's
+//! Spacer engine and for Eldarica.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
+    path::PathBuf,
+    str::FromStr,
+};
+
+use num_bigint::BigInt;
+
+use super::{
+    super::{
+        language::{
+            ast::{constraint, view::pattern},
+            cfg::{self, var},
+            expr::{
+                bop::{Arith, Bool, Rel},
+                map::{HasMeta, HasVars},
+                Constant, Expr, Uop,
+            },
+            tagged::Tagged,
+        },
+        vcgen::{horn, Condition},
+    },
+    response::{self, Lexer, Sexp},
+    Error, Result,
+};
+
+/// The CHC solvers we know how to drive.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+#[non_exhaustive]
+pub enum Kind {
+    /// Microsoft Research's Z3, with its Spacer engine.
+    #[default]
+    Z3,
+    /// Eldarica.
+    Eldarica,
+}
+
+impl Kind {
+    /// Gets the name of the binary for this solver, as it is usually installed.
+    #[must_use]
+    pub fn binary(self) -> &'static str {
+        match self {
+            Self::Z3 => "z3",
+            Self::Eldarica => "eld",
+        }
+    }
+
+    /// Gets the arguments that make this solver read Horn clauses from standard input, and print
+    /// a model if it finds one.
+    #[must_use]
+    pub fn args(self) -> &'static [&'static str] {
+        match self {
+            Self::Z3 => &["-in", "-smt2", "fp.engine=spacer"],
+            Self::Eldarica => &["-in", "-ssol"],
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Z3 => "z3",
+            Self::Eldarica => "eldarica",
+        })
+    }
+}
+
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "z3" | "spacer" => Ok(Self::Z3),
+            "eldarica" | "eld" => Ok(Self::Eldarica),
+            _ => Err(Error::UnknownSolver {
+                name: s.to_owned(),
+                expected: "z3, eldarica",
+            }),
+        }
+    }
+}
+
+/// Configuration for a CHC solver process.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Config {
+    /// The solver to use.
+    pub kind: Kind,
+    /// The path to the solver binary; if absent, we look up the usual binary name on the path.
+    pub path: Option<PathBuf>,
+}
+
+impl Config {
+    /// Infers definitions for the indefinite constraints of `program` that make every one of
+    /// `conditions` hold.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the clauses cannot be emitted, the solver cannot be run, or the solver reports an
+    /// error or responds in a way we do not understand.
+    pub fn infer<M>(
+        &self,
+        program: &cfg::Program<'_, M>,
+        conditions: &[Condition<M>],
+    ) -> Result<Inference> {
+        let mut script = b"(set-option :produce-models true)\n".to_vec();
+        horn::write(program, conditions, &mut script)?;
+        script.extend_from_slice(b"(get-model)\n(exit)\n");
+
+        let binary = self
+            .path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(self.kind.binary()));
+        let output = super::run(binary, self.kind.args(), &script)?;
+        parse(&horn::predicates(program), &output)
+    }
+}
+
+/// The outcome of inferring definitions.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Inference {
+    /// The given definitions make every condition hold.
+    Solved(Vec<Definition>),
+    /// No definitions make every condition hold.
+    Refuted,
+    /// The solver could not decide whether any definitions make every condition hold.
+    Unknown,
+}
+
+/// An inferred definition of the pattern of an indefinite constraint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Definition {
+    /// The index of the constraint.
+    pub constraint: usize,
+    /// The index of the pattern in the constraint.
+    pub pattern: usize,
+    /// The definition, over the pattern's variables and the global variables.
+    pub body: Expr<(), var::Symbol>,
+}
+
+impl Definition {
+    /// Renders this definition as a declaration of the constraint defining its pattern in
+    /// `program`.
+    ///
+    /// PVC has no syntax for a global variable shadowed by a pattern variable, so we rename every
+    /// pattern variable that has the name of a global variable to a fresh name.
+    #[must_use]
+    pub fn to_decl<M: Clone>(&self, program: &cfg::Program<'_, M>) -> constraint::Decl<(), String> {
+        let block = cfg::BlockRef::Constraint(self.constraint);
+        let globals: HashSet<egg::Symbol> = (program.variables.iter())
+            .filter(|(v, _)| v.block == cfg::BlockRef::Global)
+            .map(|(v, _)| v.name)
+            .collect();
+        let mut taken: HashSet<String> = (program.variables.iter())
+            .filter(|(v, _)| v.block == cfg::BlockRef::Global || v.block == block)
+            .map(|(v, _)| v.name.to_string())
+            .collect();
+        let mut names = HashMap::new();
+        let mut rename = |v: var::Symbol| {
+            if v.block == cfg::BlockRef::Global || !globals.contains(&v.name) {
+                return v.name.to_string();
+            }
+            let name = names.entry(v.name).or_insert_with(|| {
+                let mut suffix = 1;
+                while taken.contains(&format!("{}{suffix}", v.name)) {
+                    suffix += 1;
+                }
+                let fresh = format!("{}{suffix}", v.name);
+                taken.insert(fresh.clone());
+                fresh
+            });
+            name.clone()
+        };
+
+        let pattern = &program.constraints[self.constraint].item.views[self.pattern].item;
+        let contents = (pattern.contents.iter())
+            .map(|atom| {
+                Tagged::new(
+                    (),
+                    pattern::Atom {
+                        name: Tagged::new((), atom.item.name.item.name.to_string()),
+                        args: (atom.item.args.iter())
+                            .map(|a| rename_argument(&a.item, &mut rename))
+                            .collect(),
+                        iterator: rename_argument(&atom.item.iterator.item, &mut rename),
+                    },
+                )
+            })
+            .collect();
+
+        constraint::Decl {
+            views: vec![Tagged::new((), pattern::Pattern { contents })],
+            body: Tagged::new(
+                (),
+                constraint::Constraint::Exact(constraint::Definition {
+                    definition: Tagged::new((), self.body.clone().map_var(rename)),
+                }),
+            ),
+        }
+    }
+}
+
+/// Renames the variables of a pattern argument with `rename`, dropping its metadata.
+fn rename_argument<M: Clone>(
+    arg: &pattern::Argument<M, var::Symbol>,
+    rename: impl FnMut(var::Symbol) -> String,
+) -> Tagged<(), pattern::Argument<(), String>> {
+    Tagged::new(
+        (),
+        match arg.clone().map_meta(|_| ()) {
+            pattern::Argument::Wildcard => pattern::Argument::Wildcard,
+            pattern::Argument::Expr(e) => pattern::Argument::Expr(e.map_var(rename)),
+        },
+    )
+}
+
+/// Parses the standard output of a CHC solver run on the clauses over `predicates`.
+///
+/// We accept models both as a list of definitions, as Z3 gives them, and as bare definitions, as
+/// Eldarica gives them.
+///
+/// # Errors
+///
+/// Fails if the solver reported an error before giving a verdict, if the output is not
+/// well-formed, or if the model leaves out a predicate or defines it in a way we can't express in
+/// PVC.
+pub fn parse(predicates: &[horn::Predicate], output: &str) -> Result<Inference> {
+    let mut sexps = Lexer::new(output).sexps()?.into_iter();
+    let verdict = response::verdict(&mut sexps)?;
+
+    match verdict.as_str() {
+        "sat" => (),
+        "unsat" => return Ok(Inference::Refuted),
+        "unknown" => return Ok(Inference::Unknown),
+        _ => return Err(Error::Response(verdict)),
+    }
+
+    let sexps: Vec<Sexp> = sexps.collect();
+    let mut defs = HashMap::new();
+    for sexp in &sexps {
+        collect(sexp, &mut defs);
+    }
+
+    let definitions = predicates
+        .iter()
+        .map(|predicate| {
+            let name = predicate.name();
+            let (params, body) = defs
+                .get(name.as_str())
+                .ok_or_else(|| Error::Response(format!("no definition for `{name}`")))?;
+            if params.len() != predicate.params.len() {
+                return Err(Error::Response(format!(
+                    "definition for `{name}` has the wrong number of parameters"
+                )));
+            }
+            let env = (params.iter().zip(&predicate.params))
+                .map(|((param, sort), var)| {
+                    let expr = Expr::Var(Tagged::new((), var.clone()));
+                    (param.clone(), (expr, *sort))
+                })
+                .collect();
+            let (body, _) = translate(body, &env)?;
+            Ok(Definition {
+                constraint: predicate.constraint,
+                pattern: predicate.pattern,
+                body,
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(Inference::Solved(definitions))
+}
+
+/// Function definitions in a model, by name, as their parameters and bodies.
+type Definitions<'a> = HashMap<&'a str, (Vec<(String, Sort)>, &'a Sexp)>;
+
+/// Collects the function definitions in `sexp` into `defs`.
+fn collect<'a>(sexp: &'a Sexp, defs: &mut Definitions<'a>) {
+    let Sexp::List(items) = sexp else {
+        return;
+    };
+    match items.as_slice() {
+        [Sexp::Atom(kw), Sexp::Atom(name), Sexp::List(params), _, body] if kw == "define-fun" => {
+            let params = params
+                .iter()
+                .filter_map(|p| match p {
+                    Sexp::List(p) => match p.as_slice() {
+                        [Sexp::Atom(name), sort] => Some((name.clone(), Sort::of(sort))),
+                        _ => None,
+                    },
+                    Sexp::Atom(_) => None,
+                })
+                .collect();
+            defs.insert(name, (params, body));
+        }
+        _ => {
+            for item in items {
+                collect(item, defs);
+            }
+        }
+    }
+}
+
+/// The sorts of the terms we translate, as far as translation needs to know them.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Sort {
+    Bool,
+    Int,
+    Other,
+}
+
+impl Sort {
+    /// Gets the sort named by `sexp`.
+    fn of(sexp: &Sexp) -> Self {
+        match sexp {
+            Sexp::Atom(a) if a == "Bool" => Self::Bool,
+            Sexp::Atom(a) if a == "Int" => Self::Int,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// A translated term, with its sort.
+type Term = (Expr<(), var::Symbol>, Sort);
+
+/// Translates the SMT-LIB term `sexp` into an expression, interpreting its symbols using `env`.
+fn translate(sexp: &Sexp, env: &HashMap<String, Term>) -> Result<Term> {
+    let untranslatable = || Error::Response(format!("can't express `{sexp}` in PVC"));
+
+    let items = match sexp {
+        Sexp::Atom(a) if a == "true" => return Ok((Expr::bool(true), Sort::Bool)),
+        Sexp::Atom(a) if a == "false" => return Ok((Expr::bool(false), Sort::Bool)),
+        Sexp::Atom(a) => {
+            if let Some(term) = env.get(a) {
+                return Ok(term.clone());
+            }
+            let int: BigInt = a.parse().map_err(|_| untranslatable())?;
+            return Ok((Expr::int(int), Sort::Int));
+        }
+        Sexp::List(items) => items,
+    };
+    let Some((Sexp::Atom(head), args)) = items.split_first() else {
+        return Err(untranslatable());
+    };
+
+    if head == "let" {
+        let [Sexp::List(bindings), body] = args else {
+            return Err(untranslatable());
+        };
+        let mut inner = env.clone();
+        for binding in bindings {
+            let Sexp::List(binding) = binding else {
+                return Err(untranslatable());
+            };
+            let [Sexp::Atom(name), value] = binding.as_slice() else {
+                return Err(untranslatable());
+            };
+            inner.insert(name.clone(), translate(value, env)?);
+        }
+        return translate(body, &inner);
+    }
+
+    let (args, sorts): (Vec<_>, Vec<_>) = (args.iter())
+        .map(|a| translate(a, env))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let fold = |op: Arith| args.clone().into_iter().reduce(|l, r| Expr::bop(l, op, r));
+    let chain = |op: Rel| {
+        let pairs = args
+            .windows(2)
+            .map(|w| Expr::bop(w[0].clone(), op, w[1].clone()));
+        Expr::conjunction(pairs)
+    };
+
+    let term = match (head.as_str(), args.as_slice()) {
+        ("and", _) => (Expr::conjunction(args.clone()), Sort::Bool),
+        ("or", _) => (
+            (args.clone().into_iter())
+                .reduce(|l, r| Expr::bop(l, Bool::Or, r))
+                .unwrap_or_else(|| Expr::bool(false)),
+            Sort::Bool,
+        ),
+        ("not", [x]) => (Expr::not(x.clone()), Sort::Bool),
+        ("=>", [_, ..]) => (
+            (args.clone().into_iter())
+                .rev()
+                .reduce(|r, l| Expr::bop(l, Bool::Implies, r))
+                .ok_or_else(untranslatable)?,
+            Sort::Bool,
+        ),
+        ("=", [x, y]) if sorts[0] == Sort::Bool => {
+            (Expr::bop(x.clone(), Bool::Iff, y.clone()), Sort::Bool)
+        }
+        ("=", [_, _, ..]) => (chain(Rel::Eq), Sort::Bool),
+        ("distinct", [x, y]) => (Expr::bop(x.clone(), Rel::NotEq, y.clone()), Sort::Bool),
+        ("<", [_, _, ..]) => (chain(Rel::Less), Sort::Bool),
+        ("<=", [_, _, ..]) => (chain(Rel::LessEq), Sort::Bool),
+        (">", [_, _, ..]) => (chain(Rel::Greater), Sort::Bool),
+        (">=", [_, _, ..]) => (chain(Rel::GreaterEq), Sort::Bool),
+        ("-", [x]) => match x.as_literal() {
+            Some(Constant::Int(i)) => (Expr::int(-i), Sort::Int),
+            _ => (Expr::uop(Uop::Minus, x.clone()), Sort::Int),
+        },
+        ("+", [_, ..]) => (fold(Arith::Add).ok_or_else(untranslatable)?, Sort::Int),
+        ("-", [_, _, ..]) => (fold(Arith::Sub).ok_or_else(untranslatable)?, Sort::Int),
+        ("*", [_, ..]) => (fold(Arith::Mul).ok_or_else(untranslatable)?, Sort::Int),
+        ("div", [x, y]) => (Expr::bop(x.clone(), Arith::Div, y.clone()), Sort::Int),
+        ("mod", [x, y]) => (Expr::bop(x.clone(), Arith::Modulus, y.clone()), Sort::Int),
+        // PVC has no conditional expressions, but Boolean ones have a logical form.
+        ("ite", [c, t, e]) if sorts[1] == Sort::Bool => (
+            Expr::bop(
+                Expr::bop(c.clone(), Bool::Implies, t.clone()),
+                Bool::And,
+                Expr::bop(Expr::not(c.clone()), Bool::Implies, e.clone()),
+            ),
+            Sort::Bool,
+        ),
+        _ => return Err(untranslatable()),
+    };
+    Ok(term)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the predicates of the program with the given declarations.
+    fn predicates(decls: &str) -> Vec<horn::Predicate> {
+        let source = format!("program T; var x : integer; {decls} begin end.");
+        let ast = crate::parser::parse(&source).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        horn::predicates(&program)
+    }
+
+    /// Renders the definitions inferred from `output`, using variable names only.
+    fn inferred(predicates: &[horn::Predicate], output: &str) -> Vec<String> {
+        let Inference::Solved(defs) = parse(predicates, output).unwrap() else {
+            panic!("expected definitions");
+        };
+        (defs.into_iter())
+            .map(|d| {
+                d.body
+                    .subst(&mut |v| Expr::Var(v.map(|s| s.name)))
+                    .to_string()
+            })
+            .collect()
+    }
+
+    /// Tests reading definitions from a Z3 model, including `let` and n-ary operators.
+    #[test]
+    fn parse_z3_model() {
+        let predicates = predicates("view V(n : integer); constraint V(n) ?;");
+        let output = "sat
+(
+  (define-fun c0p0!pred ((x!0 Int) (x!1 Int)) Bool
+    (let ((a!1 (+ x!1 (* (- 1) x!0))))
+      (and (>= a!1 0) (<= x!0 x!1 5) (not (= x!0 (- 2))))))
+)
+";
+        assert_eq!(
+            vec!["x + -1 * n >= 0 and (n <= x and x <= 5) and not (n = -2)"],
+            inferred(&predicates, output)
+        );
+    }
+
+    /// Tests reading definitions from an Eldarica solution, and reading refutations.
+    #[test]
+    fn parse_eldarica_solution() {
+        let predicates = predicates("view V; constraint V ?;");
+        let output = "sat\n(define-fun c0p0!pred ((A Int)) Bool (ite (> A 0) true (= A 0)))\n";
+        assert_eq!(
+            vec!["(x > 0 implies true) and (not (x > 0) implies x = 0)"],
+            inferred(&predicates, output)
+        );
+        assert_eq!(Inference::Refuted, parse(&predicates, "unsat\n").unwrap());
+    }
+
+    /// Tests that rendered definitions rename pattern variables that shadow globals, and parse
+    /// back to the same definition.
+    #[test]
+    fn to_decl_shadowed() {
+        let decls = "view V(n : integer); constraint V(x) * V(x1) ?;";
+        let source = format!("program T; var x : integer; {decls} begin end.");
+        let ast = crate::parser::parse(&source).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let predicates = horn::predicates(&program);
+        let output = "sat
+(define-fun c0p0!pred ((A Int) (B Int) (C Int)) Bool (< A (+ B C)))
+";
+        let Inference::Solved(defs) = parse(&predicates, output).unwrap() else {
+            panic!("expected definitions");
+        };
+        let rendered = crate::printer::constraint(&defs[0].to_decl(&program));
+        assert_eq!("constraint V(x2) * V(x1) = x2 < x1 + x", rendered);
+
+        let source =
+            format!("program T; var x : integer; view V(n : integer); {rendered}; begin end.");
+        let ast = crate::parser::parse(&source).unwrap();
+        let reparsed = crate::binder::bind(&ast.item).unwrap();
+        let constraint::Constraint::Exact(d) = &reparsed.constraints[0].item.body.item else {
+            panic!("expected a definition");
+        };
+        let mut blocks = vec![];
+        d.definition
+            .item
+            .for_each_tagged_var(&mut |v| blocks.push(v.item.block.clone()));
+        assert_eq!(
+            vec![
+                cfg::BlockRef::Constraint(0),
+                cfg::BlockRef::Constraint(0),
+                cfg::BlockRef::Global,
+            ],
+            blocks
+        );
+    }
+}
@@ -0,0 +1,317 @@
+//! Parsing of solver responses.
+//!
+//! Solvers respond to our scripts with a sequence of S-expressions: a verdict (`sat`, `unsat`, or
+//! `unknown`) for the `check-sat` command, and then either a model or an error for the
+//! `get-model` command.
+
+use std::fmt::{Display, Formatter};
+
+use num_bigint::BigInt;
+
+use super::{
+    super::vcgen::{smt2, Var},
+    Error, Result, Verdict,
+};
+
+/// A model returned by a solver: an assignment of values to the symbols of a condition.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Model {
+    /// The values of each symbol, in the order in which the solver gave them.
+    ///
+    /// Symbol names are unquoted.
+    pub values: Vec<(String, Value)>,
+}
+
+impl Model {
+    /// Looks up the value the model gives to `var`, if any.
+    #[must_use]
+    pub fn get(&self, var: &Var) -> Option<&Value> {
+        let name = smt2::name(var);
+        self.values
+            .iter()
+            .find_map(|(k, v)| (*k == name).then_some(v))
+    }
+}
+
+/// A value in a model.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Value {
+    /// An integer.
+    Int(BigInt),
+    /// A Boolean.
+    Bool(bool),
+    /// Any other value, as the solver rendered it.
+    Other(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(i) => i.fmt(f),
+            Self::Bool(b) => b.fmt(f),
+            Self::Other(o) => o.fmt(f),
+        }
+    }
+}
+
+/// Parses the standard output of a solver run into a verdict.
+///
+/// # Errors
+///
+/// Fails if the solver reported an error before giving a verdict, or if the output is not
+/// well-formed.
+pub fn parse(output: &str) -> Result<Verdict> {
+    let mut sexps = Lexer::new(output).sexps()?.into_iter();
+    let verdict = verdict(&mut sexps)?;
+
+    match verdict.as_str() {
+        "unsat" => Ok(Verdict::Valid),
+        "unknown" => Ok(Verdict::Unknown),
+        "sat" => Ok(Verdict::Invalid(sexps.next().and_then(|s| model(&s)))),
+        _ => Err(Error::Response(verdict)),
+    }
+}
+
+/// Takes S-expressions from `sexps` up to, and including, the verdict atom, returning that atom.
+///
+/// # Errors
+///
+/// Fails if the solver reported an error before giving a verdict, or gave no verdict.
+pub(super) fn verdict(sexps: &mut impl Iterator<Item = Sexp>) -> Result<String> {
+    loop {
+        match sexps.next() {
+            None => return Err(Error::Response("no verdict".to_owned())),
+            Some(Sexp::Atom(a)) if a == "success" => (),
+            Some(Sexp::Atom(a)) => return Ok(a),
+            Some(s) => {
+                return Err(match s.error_message() {
+                    Some(msg) => Error::Solver(msg),
+                    None => Error::Response(s.to_string()),
+                })
+            }
+        }
+    }
+}
+
+/// Interprets `sexp` as a model, if it is one.
+///
+/// We accept both the SMT-LIB 2.6 form (a list of definitions) and the older Z3 form (the same,
+/// preceded by `model`).
+fn model(sexp: &Sexp) -> Option<Model> {
+    let Sexp::List(items) = sexp else {
+        return None;
+    };
+    let defs = match items.first() {
+        Some(Sexp::Atom(a)) if a == "model" => &items[1..],
+        Some(Sexp::Atom(_)) => return None,
+        _ => &items[..],
+    };
+
+    let values = defs
+        .iter()
+        .filter_map(|def| match def {
+            Sexp::List(parts) => match parts.as_slice() {
+                [Sexp::Atom(kw), Sexp::Atom(name), Sexp::List(params), _, value]
+                    if kw == "define-fun" && params.is_empty() =>
+                {
+                    Some((name.clone(), Value::from(value)))
+                }
+                _ => None,
+            },
+            Sexp::Atom(_) => None,
+        })
+        .collect();
+    Some(Model { values })
+}
+
+impl From<&Sexp> for Value {
+    fn from(sexp: &Sexp) -> Self {
+        match sexp {
+            Sexp::Atom(a) if a == "true" => Self::Bool(true),
+            Sexp::Atom(a) if a == "false" => Self::Bool(false),
+            Sexp::Atom(a) => a.parse().map_or_else(|_| Self::Other(a.clone()), Self::Int),
+            Sexp::List(l) => match l.as_slice() {
+                [Sexp::Atom(minus), Sexp::Atom(n)] if minus == "-" => n
+                    .parse::<BigInt>()
+                    .map_or_else(|_| Self::Other(sexp.to_string()), |n| Self::Int(-n)),
+                _ => Self::Other(sexp.to_string()),
+            },
+        }
+    }
+}
+
+/// A parsed S-expression.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) enum Sexp {
+    /// An atom, with any `|` quotes removed.
+    Atom(String),
+    /// A list.
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    /// If this is an `(error "...")` response, gets its message.
+    pub(super) fn error_message(&self) -> Option<String> {
+        match self {
+            Self::List(l) => match l.as_slice() {
+                [Self::Atom(e), Self::Atom(msg)] if e == "error" => Some(msg.clone()),
+                _ => None,
+            },
+            Self::Atom(_) => None,
+        }
+    }
+}
+
+impl Display for Sexp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Atom(a) => a.fmt(f),
+            Self::List(l) => {
+                f.write_str("(")?;
+                for (i, s) in l.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(" ")?;
+                    }
+                    s.fmt(f)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// A minimal S-expression lexer and parser.
+pub(super) struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    pub(super) fn new(input: &'a str) -> Self {
+        Self {
+            chars: input.chars().peekable(),
+        }
+    }
+
+    /// Parses every S-expression in the input.
+    pub(super) fn sexps(mut self) -> Result<Vec<Sexp>> {
+        let mut stack: Vec<Vec<Sexp>> = vec![vec![]];
+
+        while let Some(c) = self.chars.next() {
+            match c {
+                '(' => stack.push(vec![]),
+                ')' => {
+                    let list = stack
+                        .pop()
+                        .filter(|_| !stack.is_empty())
+                        .ok_or_else(|| Error::Response("unbalanced `)`".to_owned()))?;
+                    push(&mut stack, Sexp::List(list));
+                }
+                ';' => self.skip_line(),
+                '|' => {
+                    let atom = self.until('|')?;
+                    push(&mut stack, Sexp::Atom(atom));
+                }
+                '"' => {
+                    let atom = self.string()?;
+                    push(&mut stack, Sexp::Atom(atom));
+                }
+                c if c.is_whitespace() => (),
+                c => {
+                    let mut atom = String::from(c);
+                    while let Some(&c) = self.chars.peek() {
+                        if c.is_whitespace() || "()|\";".contains(c) {
+                            break;
+                        }
+                        atom.push(c);
+                        self.chars.next();
+                    }
+                    push(&mut stack, Sexp::Atom(atom));
+                }
+            }
+        }
+
+        match (stack.pop(), stack.is_empty()) {
+            (Some(top), true) => Ok(top),
+            _ => Err(Error::Response("unbalanced `(`".to_owned())),
+        }
+    }
+
+    fn skip_line(&mut self) {
+        for c in self.chars.by_ref() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    /// Reads characters up to, and consuming, `end`.
+    fn until(&mut self, end: char) -> Result<String> {
+        let mut atom = String::new();
+        for c in self.chars.by_ref() {
+            if c == end {
+                return Ok(atom);
+            }
+            atom.push(c);
+        }
+        Err(Error::Response(format!("unterminated `{end}`")))
+    }
+
+    /// Reads the rest of a string literal, in which `""` stands for `"`.
+    fn string(&mut self) -> Result<String> {
+        let mut atom = self.until('"')?;
+        while self.chars.peek() == Some(&'"') {
+            self.chars.next();
+            atom.push('"');
+            atom.push_str(&self.until('"')?);
+        }
+        Ok(atom)
+    }
+}
+
+/// Pushes `sexp` onto the innermost list being parsed.
+fn push(stack: &mut [Vec<Sexp>], sexp: Sexp) {
+    if let Some(top) = stack.last_mut() {
+        top.push(sexp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::cfg::var::Symbol;
+
+    /// Tests parsing a Z3 counterexample.
+    #[test]
+    fn parse_sat_model() {
+        let output = "sat
+(
+  (define-fun count () Int
+    (- 2))
+  (define-fun |held!after| () Bool
+    false)
+)
+";
+        let Verdict::Invalid(Some(model)) = parse(output).unwrap() else {
+            panic!("expected a model");
+        };
+        let count = Var::Pre(Symbol::global("count"));
+        let held = Var::Post(Symbol::global("held"));
+        assert_eq!(Some(&Value::Int((-2).into())), model.get(&count));
+        assert_eq!(Some(&Value::Bool(false)), model.get(&held));
+    }
+
+    /// Tests that an error after `unsat` (from `get-model`) is ignored.
+    #[test]
+    fn parse_unsat() {
+        let output = "unsat\n(error \"line 9 column 10: model is not available\")\n";
+        assert_eq!(Verdict::Valid, parse(output).unwrap());
+    }
+
+    /// Tests that an error before the verdict is reported.
+    #[test]
+    fn parse_error() {
+        let output = "(error \"line 3: unknown constant x\")\nsat\n";
+        let err = parse(output).unwrap_err();
+        assert!(matches!(err, Error::Solver(msg) if msg == "line 3: unknown constant x"));
+    }
+}
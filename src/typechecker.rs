@@ -0,0 +1,278 @@
+//! Type checker for bound PVC programs.
+//!
+//! The type checker runs over the symbol tables produced by the [binder](super::binder), checking
+//! that:
+//!
+//! - arithmetic operators take integers, and Boolean operators take Booleans;
+//! - conditions, guards, local assertions, and constraint bodies are Boolean;
+//! - iterators are integers;
+//! - refinement predicates are Boolean;
+//! - assignments, procedure calls, and view atoms agree with the types of the variables,
+//!   parameters, and view prototypes they target.
+//!
+//! Types are compared up to refinement: a value of type `{x: int | P(x)}` can go wherever an
+//! `int` is expected, and vice versa.  Discharging the refinement predicates themselves is the job
+//! of verification condition generation.
+//!
+//! Unlike the binder, the type checker does not stop at the first error; instead, it reports every
+//! error it finds, taking care not to report errors that are consequences of others.
+
+use super::{
+    binder::{Bound, Meta},
+    language::{cfg, expr::Expr, typing},
+};
+
+mod constraint;
+mod expr;
+mod stm;
+
+/// Shorthand for the type of errors the type checker raises.
+pub type Error<'inp> = typing::Error<Meta<'inp>, egg::Symbol>;
+
+/// Shorthand for the type of types the type checker works with.
+type Type<'inp> = typing::Type<Meta<'inp>, egg::Symbol>;
+
+/// Type checks `program`, returning every error found.
+///
+/// An empty result means that `program` is well-typed.
+#[must_use]
+pub fn check<'inp>(program: &Bound<'inp>) -> Vec<Error<'inp>> {
+    let mut checker = Checker {
+        program,
+        errors: vec![],
+        refinement_var: None,
+    };
+
+    // The typing table is unordered, so we order its types by where they appear in the script.
+    let mut types: Vec<_> = program
+        .types
+        .values()
+        .filter_map(|t| Some((*t.origin.meta()?, &t.ty)))
+        .collect();
+    types.sort_by_key(|(meta, _)| meta.map(|m| m.start()));
+    for (meta, ty) in types {
+        checker.refinements(&program.expand(ty), meta);
+    }
+
+    for proc in &program.procedures {
+        for spec in proc.requires.iter().chain(&proc.ensures) {
+            checker.assertion(&spec.item, spec.meta);
+        }
+        checker.list(&proc.body);
+    }
+    for constraint in &program.constraints {
+        checker.constraint(constraint);
+    }
+    checker.errors
+}
+
+/// The state of the type checker.
+struct Checker<'a, 'inp> {
+    /// The program being checked.
+    program: &'a Bound<'inp>,
+    /// The errors found so far.
+    errors: Vec<Error<'inp>>,
+    /// The refinement variable in scope, and its type, if we are checking a refinement predicate.
+    refinement_var: Option<(cfg::var::Symbol, Type<'inp>)>,
+}
+
+impl<'inp> Checker<'_, 'inp> {
+    /// Looks up the type with symbol `sym` in the typing table, expanding any named types.
+    fn ty(&self, sym: egg::Symbol) -> Option<Type<'inp>> {
+        self.program.type_of(&sym)
+    }
+
+    /// Checks that the refinement predicates of `ty`, declared at `meta`, are Boolean.
+    fn refinements(&mut self, ty: &Type<'inp>, meta: Meta<'inp>) {
+        for refined in ty.refinements() {
+            // Refinement predicates only mention their refinement variable, which we bring into
+            // scope as if it were a global.
+            let var = cfg::var::Symbol::global(refined.var());
+            self.refinement_var = Some((var, refined.element().clone()));
+            let predicate = refined
+                .refinement()
+                .clone()
+                .subst(&mut |v| Expr::Var(v.map(cfg::var::Symbol::global)));
+            self.expect(&predicate, &Type::BOOL, meta);
+        }
+        self.refinement_var = None;
+    }
+
+    /// Looks up the declared type of the variable `var`.
+    fn var_type(&self, var: &cfg::var::Symbol) -> Option<Type<'inp>> {
+        if let Some((v, ty)) = &self.refinement_var {
+            if v == var {
+                return Some(ty.clone());
+            }
+        }
+        self.program
+            .variables
+            .get_exact(var)
+            .and_then(|r| self.ty(r.ty))
+    }
+
+    /// Gets the location at which the variable `var` was declared.
+    fn var_loc(&self, var: &cfg::var::Symbol) -> Meta<'inp> {
+        self.program
+            .variables
+            .get_exact(var)
+            .and_then(|r| r.origin.meta().copied().flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARC: &str = include_str!("../examples/arc.pvc");
+
+    /// Gets the source text at `loc`, less any trailing whitespace the parser included.
+    fn text(loc: Meta<'_>) -> &str {
+        loc.expect("location should be present").as_str().trim_end()
+    }
+
+    /// Tests that the ARC example is well-typed.
+    #[test]
+    fn check_arc() {
+        let ast = crate::parser::parse(ARC).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        assert!(check(&program).is_empty());
+    }
+
+    /// Tests that assigning a Boolean to an integer variable is an error at both sites.
+    #[test]
+    fn check_assign_mismatch() {
+        let ast = crate::parser::parse(
+            "program T; var x : integer; procedure P; begin <| x := true |> end; begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let errors = check(&program);
+        let [Error::Mismatch {
+            want,
+            got,
+            want_loc,
+            got_loc,
+        }] = &errors[..]
+        else {
+            panic!("expected one mismatch, got {errors:?}");
+        };
+        assert_eq!(&Type::INT, want);
+        assert_eq!(&Type::BOOL, got);
+        assert_eq!("x", text(*want_loc));
+        assert_eq!("true", text(*got_loc));
+    }
+
+    /// Tests that an ill-typed operand doesn't also make its operator ill-typed.
+    #[test]
+    fn check_arith_on_bool() {
+        let ast = crate::parser::parse("program T; var b : boolean; var x : integer; procedure P; begin <| x := b + 1 |> end; begin end.").unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let errors = check(&program);
+        let [Error::Mismatch { got_loc, .. }] = &errors[..] else {
+            panic!("expected one mismatch, got {errors:?}");
+        };
+        assert_eq!("b", text(*got_loc));
+    }
+
+    /// Tests that subscripts need arrays and integer indices, and give the element type.
+    #[test]
+    fn check_index() {
+        let ast = crate::parser::parse("program T; var a : array[0..3] of boolean; var x : integer; procedure P; begin <| a[true] := x[0] |> end; begin end.").unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let errors = check(&program);
+        let [Error::Mismatch { got_loc, .. }, Error::Index { loc, .. }] = &errors[..] else {
+            panic!("expected a mismatch and a bad index, got {errors:?}");
+        };
+        assert_eq!("true", text(*got_loc));
+        assert_eq!("x", text(*loc));
+    }
+
+    /// Tests that named types are expanded into their definitions.
+    #[test]
+    fn check_named_type() {
+        let ast = crate::parser::parse("program T; type N = integer; A = array[0..1] of N; var a : A; procedure P; begin <| a[0] := true |> end; begin end.").unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let errors = check(&program);
+        let [Error::Mismatch { want, .. }] = &errors[..] else {
+            panic!("expected one mismatch, got {errors:?}");
+        };
+        assert_eq!(Type::INT, *want);
+    }
+
+    /// Tests that view atom arguments are checked against the view's prototype.
+    #[test]
+    fn check_view_argument() {
+        let ast = crate::parser::parse("program T; view V(n : integer); procedure P; begin { V(true) } <| nop |> { V(1 = 1) } end; begin end.").unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let errors = check(&program);
+        assert_eq!(2, errors.len());
+        for e in &errors {
+            let Error::Mismatch { want_loc, .. } = e else {
+                panic!("expected mismatch, got {e:?}");
+            };
+            assert_eq!("integer", text(*want_loc));
+        }
+    }
+
+    /// Tests that view atoms with the wrong number of arguments, or of unknown views, are errors.
+    #[test]
+    fn check_view_atom_shape() {
+        let ast = crate::parser::parse("program T; view V(n : integer); procedure P; begin { V(1, 2) } <| nop |> { W } end; begin end.").unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let errors = check(&program);
+        assert!(matches!(
+            &errors[..],
+            [
+                Error::Arity {
+                    want: 1,
+                    got: 2,
+                    ..
+                },
+                Error::UnknownView { .. }
+            ]
+        ));
+    }
+
+    /// Tests that primitive calls are checked against the primitive's signature.
+    #[test]
+    fn check_primitive() {
+        let ast = crate::parser::parse("program T; var x : integer; var y : boolean; procedure P; begin <| CAS(x, y, 1, y); TAS(x) |> end; begin end.").unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let errors = check(&program);
+        let [Error::Mismatch {
+            want,
+            got,
+            want_loc,
+            got_loc,
+        }, Error::Arity {
+            want: 2, got: 1, ..
+        }, Error::Mismatch {
+            got_loc: tas_loc, ..
+        }] = &errors[..]
+        else {
+            panic!("expected a mismatch, a bad arity, and a mismatch, got {errors:?}");
+        };
+        assert_eq!(&Type::INT, want);
+        assert_eq!(&Type::BOOL, got);
+        assert_eq!("x", text(*want_loc));
+        assert_eq!("y", text(*got_loc));
+        assert_eq!("x", text(*tas_loc));
+    }
+
+    /// Tests that constraint bodies must be Boolean.
+    #[test]
+    fn check_constraint_body() {
+        let ast = crate::parser::parse(
+            "program T; view V(n : integer); constraint V(n) entails n + 1; begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let errors = check(&program);
+        let [Error::Mismatch { got, got_loc, .. }] = &errors[..] else {
+            panic!("expected one mismatch, got {errors:?}");
+        };
+        assert_eq!(&Type::INT, got);
+        assert_eq!("n + 1", text(*got_loc));
+    }
+}
@@ -0,0 +1,67 @@
+//! Type checking of constraints and view patterns.
+
+use super::{
+    super::{
+        binder::Meta,
+        language::{ast::view::pattern, cfg, tagged::Tagged},
+    },
+    expr::span,
+    Checker, Type,
+};
+
+impl<'inp> Checker<'_, 'inp> {
+    /// Type checks a constraint.
+    pub(super) fn constraint(
+        &mut self,
+        constraint: &Tagged<Meta<'inp>, cfg::Constraint<Meta<'inp>>>,
+    ) {
+        for pat in &constraint.item.views {
+            for atom in &pat.item.contents {
+                self.pattern_atom(&atom.item, atom.meta);
+            }
+        }
+
+        let body = &constraint.item.body;
+        if let Some(expr) = body.item.expr() {
+            self.expect_tagged(expr, &Type::BOOL, body.meta);
+        }
+    }
+
+    /// Type checks the expression arguments of a pattern atom found at `meta`.
+    ///
+    /// Patterns over undeclared views, or with the wrong number of arguments, are ill-formed
+    /// constraints rather than ill-typed ones, and so we don't report them here (see
+    /// [`wellformed`](crate::wellformed)).
+    fn pattern_atom(
+        &mut self,
+        atom: &pattern::Atom<Meta<'inp>, cfg::var::Symbol>,
+        meta: Meta<'inp>,
+    ) {
+        let params = self
+            .program
+            .views
+            .get(&atom.name.item.name)
+            .map(|v| v.parameters.as_slice())
+            .unwrap_or_default();
+
+        for (i, arg) in atom.args.iter().enumerate() {
+            let pattern::Argument::Expr(e) = &arg.item else {
+                continue;
+            };
+            let want = params.get(i).and_then(|p| Some((self.ty(p.item)?, p.meta)));
+            if let Some((want, want_loc)) = want {
+                self.expect_at(e, &want, want_loc, arg.meta.or(span(e)));
+            } else {
+                self.infer(e);
+            }
+        }
+
+        if let pattern::Argument::Expr(e) = &atom.iterator.item {
+            self.expect_tagged(
+                &Tagged::new(atom.iterator.meta, e.clone()),
+                &Type::INT,
+                meta,
+            );
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Type checking of expressions.
+
+use super::{
+    super::{
+        binder::Meta,
+        language::{
+            cfg::var::Symbol,
+            expr::{bop, Bop, Expr, Tagged, Uop},
+            typing,
+        },
+    },
+    Checker, Error, Type,
+};
+
+impl<'inp> Checker<'_, 'inp> {
+    /// Infers the type of `expr`, reporting any ill-typed subexpressions.
+    ///
+    /// Returns `None` if the type of `expr` cannot be determined; in that case, the reason has
+    /// already been reported.
+    pub(super) fn infer(&mut self, expr: &Expr<Meta<'inp>, Symbol>) -> Option<Type<'inp>> {
+        match expr {
+            Expr::Literal(l) => Some(Type::Prim(l.item.prim_type())),
+            Expr::Var(v) => self.var_type(&v.item),
+            Expr::Bop { op, lhs, rhs } => Some(self.bop(expr, *op, lhs, rhs)),
+            Expr::Uop { op, expr: inner } => self.uop(expr, *op, inner),
+            Expr::Index { array, index } => {
+                let array = self.array(array)?;
+                self.expect(index, &Type::INT, span(expr));
+                Some(array.base().clone())
+            }
+            Expr::Update {
+                array: a,
+                index,
+                value,
+            } => {
+                let array = self.array(a)?;
+                self.expect(index, &Type::INT, span(expr));
+                self.expect(value, array.base(), span(a));
+                Some(Type::Array(array))
+            }
+        }
+    }
+
+    /// Checks that `expr` has type `want`, as required at `want_loc`.
+    pub(super) fn expect(
+        &mut self,
+        expr: &Expr<Meta<'inp>, Symbol>,
+        want: &Type<'inp>,
+        want_loc: Meta<'inp>,
+    ) {
+        self.expect_at(expr, want, want_loc, span(expr));
+    }
+
+    /// Checks that the tagged expression `expr` has type `want`, as required at `want_loc`.
+    pub(super) fn expect_tagged(
+        &mut self,
+        expr: &Tagged<Meta<'inp>, Symbol>,
+        want: &Type<'inp>,
+        want_loc: Meta<'inp>,
+    ) {
+        self.expect_at(&expr.item, want, want_loc, expr.meta.or(span(&expr.item)));
+    }
+
+    /// Checks that `expr`, found at `got_loc`, has type `want`, as required at `want_loc`.
+    pub(super) fn expect_at(
+        &mut self,
+        expr: &Expr<Meta<'inp>, Symbol>,
+        want: &Type<'inp>,
+        want_loc: Meta<'inp>,
+        got_loc: Meta<'inp>,
+    ) {
+        if let Some(got) = self.infer(expr) {
+            if !got.compatible(want) {
+                self.errors.push(Error::Mismatch {
+                    want: want.clone(),
+                    got,
+                    want_loc,
+                    got_loc,
+                });
+            }
+        }
+    }
+
+    /// Infers the type of `expr`, which should be an array.
+    fn array(
+        &mut self,
+        expr: &Expr<Meta<'inp>, Symbol>,
+    ) -> Option<typing::Array<Meta<'inp>, egg::Symbol>> {
+        let got = self.infer(expr)?;
+        if let Type::Array(a) = got.base() {
+            return Some(a.clone());
+        }
+        self.errors.push(Error::Index {
+            got,
+            loc: span(expr),
+        });
+        None
+    }
+
+    /// Type checks the binary operation `whole`, returning its type.
+    ///
+    /// Every binary operation has a type determined by its operator, so we can always return one.
+    fn bop(
+        &mut self,
+        whole: &Expr<Meta<'inp>, Symbol>,
+        op: Bop,
+        lhs: &Expr<Meta<'inp>, Symbol>,
+        rhs: &Expr<Meta<'inp>, Symbol>,
+    ) -> Type<'inp> {
+        let loc = span(whole);
+        match op {
+            Bop::Arith(_) => {
+                self.expect(lhs, &Type::INT, loc);
+                self.expect(rhs, &Type::INT, loc);
+                Type::INT
+            }
+            Bop::Bool(_) => {
+                self.expect(lhs, &Type::BOOL, loc);
+                self.expect(rhs, &Type::BOOL, loc);
+                Type::BOOL
+            }
+            // Equality is defined on any type, so long as both sides agree.
+            Bop::Rel(bop::Rel::Eq | bop::Rel::NotEq) => {
+                if let Some(want) = self.infer(lhs) {
+                    self.expect(rhs, &want, span(lhs));
+                } else {
+                    self.infer(rhs);
+                }
+                Type::BOOL
+            }
+            Bop::Rel(_) => {
+                self.expect(lhs, &Type::INT, loc);
+                self.expect(rhs, &Type::INT, loc);
+                Type::BOOL
+            }
+        }
+    }
+
+    /// Type checks the unary operation `whole`, returning its type if possible.
+    fn uop(
+        &mut self,
+        whole: &Expr<Meta<'inp>, Symbol>,
+        op: Uop,
+        expr: &Expr<Meta<'inp>, Symbol>,
+    ) -> Option<Type<'inp>> {
+        let loc = span(whole);
+        match op {
+            Uop::Plus | Uop::Minus => {
+                self.expect(expr, &Type::INT, loc);
+                Some(Type::INT)
+            }
+            Uop::Not => {
+                self.expect(expr, &Type::BOOL, loc);
+                Some(Type::BOOL)
+            }
+            // There are no pointer types yet, so nothing can be dereferenced.
+            Uop::Deref => {
+                let got = self.infer(expr)?;
+                self.errors.push(Error::Deref { got, loc });
+                None
+            }
+        }
+    }
+}
+
+/// Approximates the span of `expr` by the spans of its leftmost and rightmost leaves.
+///
+/// Operators are not tagged, so the span of a prefix operator does not include the operator.
+pub(super) fn span<'inp, V>(expr: &Expr<Meta<'inp>, V>) -> Meta<'inp> {
+    match expr {
+        Expr::Literal(l) => l.meta,
+        Expr::Var(v) => v.meta,
+        Expr::Bop { lhs, rhs, .. } => match (span(lhs), span(rhs)) {
+            (Some(l), Some(r)) => Some(l.start_pos().span(&r.end_pos())),
+            (l, r) => l.or(r),
+        },
+        Expr::Uop { expr, .. } => span(expr),
+        Expr::Index {
+            array: first,
+            index: last,
+        }
+        | Expr::Update {
+            array: first,
+            value: last,
+            ..
+        } => match (span(first), span(last)) {
+            (Some(l), Some(r)) => Some(l.start_pos().span(&r.end_pos())),
+            (l, r) => l.or(r),
+        },
+    }
+}
@@ -0,0 +1,220 @@
+//! Type checking of statements and view assertions.
+
+use super::{
+    super::{
+        binder::Meta,
+        language::{
+            ast::{call, ite, stm, view},
+            cfg::var::Symbol,
+            primitive::ParamType,
+            tagged::Tagged,
+        },
+    },
+    Checker, Error, Type,
+};
+
+impl<'inp> Checker<'_, 'inp> {
+    /// Type checks a list of statement triples.
+    pub(super) fn list(&mut self, list: &stm::List<'inp, Meta<'inp>, Symbol>) {
+        for triple in list {
+            self.triple(&triple.item);
+        }
+    }
+
+    /// Type checks a statement triple.
+    fn triple(&mut self, triple: &stm::Triple<'inp, Meta<'inp>, Symbol>) {
+        for assertion in triple.pre.iter().chain(&triple.post) {
+            self.assertion(&assertion.item, assertion.meta);
+        }
+        self.stm(&triple.stm.item, triple.stm.meta);
+    }
+
+    /// Type checks a statement found at `meta`.
+    fn stm(&mut self, stm: &stm::Stm<'inp, Meta<'inp>, Symbol>, meta: Meta<'inp>) {
+        match stm {
+            stm::Stm::Assign(a) => self.assign(a),
+            stm::Stm::Atomic(stm::Atomic { body: l, .. }) | stm::Stm::Block(l) => self.list(l),
+            stm::Stm::Call(c) => self.call(c),
+            stm::Stm::Ite(i) => {
+                self.condition(&i.cond, meta);
+                for branch in [&i.true_branch, &i.false_branch] {
+                    self.stm(&branch.item, branch.meta);
+                }
+            }
+            stm::Stm::Nop => (),
+            stm::Stm::Primitive(p) => self.primitive(p),
+            stm::Stm::Repeat(r) => {
+                self.assertion(&r.invariant.item, r.invariant.meta);
+                self.list(&r.body);
+                self.condition(&r.cond, meta);
+            }
+            stm::Stm::While(w) => {
+                self.condition(&w.cond, meta);
+                self.assertion(&w.invariant.item, w.invariant.meta);
+                self.stm(&w.body.item, w.body.meta);
+            }
+        }
+    }
+
+    /// Type checks an assignment.
+    ///
+    /// Whether the left-hand side is assignable is a question for later phases; here, we just
+    /// check that both sides have the same type.
+    fn assign(&mut self, assign: &stm::Assign<Meta<'inp>, Symbol>) {
+        let want = assign.lvalue.as_ref().and_then(|l| {
+            let ty = self.infer(&l.item)?;
+            Some((ty, l.meta))
+        });
+        if let Some((want, want_loc)) = want {
+            self.expect_tagged(&assign.rvalue, &want, want_loc);
+        } else {
+            self.infer(&assign.rvalue.item);
+        }
+    }
+
+    /// Type checks a procedure call against the procedure's parameters.
+    ///
+    /// The binder turns calls to anything other than declared procedures into primitive calls, so
+    /// calls to unknown names can only appear in programs built by other means; we only check
+    /// their arguments.
+    fn call(&mut self, call: &call::Call<'inp, Meta<'inp>, Symbol>) {
+        let Some(proc) = self.program.procedure(egg::Symbol::new(&call.name.item)) else {
+            for arg in &call.args {
+                self.infer(&arg.item);
+            }
+            return;
+        };
+
+        self.arity(
+            &call.name.item,
+            (proc.parameters.len(), proc.origin.meta().copied().flatten()),
+            (call.args.len(), call.name.meta),
+        );
+        for (param, arg) in proc.parameters.iter().zip(&call.args) {
+            if let Some(want) = self.var_type(param) {
+                self.expect_tagged(arg, &want, self.var_loc(param));
+            }
+        }
+    }
+
+    /// Type checks a primitive call against the primitive's parameters.
+    ///
+    /// Whether assigned arguments are assignable is a question for later phases, as with
+    /// assignments.
+    fn primitive(&mut self, call: &stm::Primitive<Meta<'inp>, Symbol>) {
+        let name = call.primitive.item;
+        let site = call.primitive.meta;
+        let params = name.parameters();
+        self.arity(name.name(), (params.len(), site), (call.args.len(), site));
+
+        let mut first = None;
+        for (param, arg) in params.iter().zip(&call.args) {
+            match param.ty {
+                ParamType::Int => self.expect_tagged(arg, &Type::INT, site),
+                ParamType::Bool => self.expect_tagged(arg, &Type::BOOL, site),
+                ParamType::Scalar => {
+                    first = self.infer(&arg.item);
+                    if let Some(got) = &first {
+                        if !got.compatible(&Type::INT) && !got.compatible(&Type::BOOL) {
+                            self.errors.push(Error::Mismatch {
+                                want: Type::INT,
+                                got: got.clone(),
+                                want_loc: site,
+                                got_loc: arg.meta,
+                            });
+                        }
+                    }
+                }
+                ParamType::Same => match &first {
+                    Some(want) => self.expect_tagged(arg, &want.clone(), call.args[0].meta),
+                    None => {
+                        self.infer(&arg.item);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Type checks an if-then-else condition belonging to the construct at `site`.
+    fn condition(
+        &mut self,
+        cond: &Tagged<Meta<'inp>, ite::Condition<Meta<'inp>, Symbol>>,
+        site: Meta<'inp>,
+    ) {
+        if let ite::Condition::Deterministic(c) = &cond.item {
+            self.expect(c, &Type::BOOL, site);
+        }
+    }
+
+    /// Type checks a view assertion found at `site`.
+    pub(super) fn assertion(
+        &mut self,
+        assertion: &view::Assertion<'inp, Meta<'inp>, Symbol>,
+        site: Meta<'inp>,
+    ) {
+        use view::Assertion;
+
+        match assertion {
+            Assertion::Atom(a) => self.atom(&a.item, a.meta),
+            Assertion::Emp => (),
+            Assertion::Guarded(g) => {
+                self.expect(&g.guard, &Type::BOOL, site);
+                self.assertion(&g.view, site);
+            }
+            Assertion::Ite(i) => {
+                self.condition(&i.cond, site);
+                self.assertion(&i.true_branch, site);
+                self.assertion(&i.false_branch, site);
+            }
+            Assertion::Iterated(i) => {
+                self.assertion(&i.item, site);
+                self.expect_tagged(&i.iterator, &Type::INT, site);
+            }
+            Assertion::Join(l, r) => {
+                self.assertion(l, site);
+                self.assertion(r, site);
+            }
+            Assertion::Local(l) => self.expect_tagged(l, &Type::BOOL, site),
+        }
+    }
+
+    /// Type checks a view atom, found at `meta`, against its view's prototype.
+    fn atom(&mut self, atom: &view::assertion::Atom<'inp, Meta<'inp>, Symbol>, meta: Meta<'inp>) {
+        let Some(view) = self.program.views.get(&egg::Symbol::new(&atom.name.item)) else {
+            self.errors.push(Error::UnknownView {
+                name: atom.name.item.to_string(),
+                loc: atom.name.meta,
+            });
+            for arg in &atom.args {
+                self.infer(&arg.item);
+            }
+            return;
+        };
+
+        self.arity(
+            &atom.name.item,
+            (view.parameters.len(), view.origin.meta().copied().flatten()),
+            (atom.args.len(), meta),
+        );
+        for (param, arg) in view.parameters.iter().zip(&atom.args) {
+            if let Some(want) = self.ty(param.item) {
+                self.expect_tagged(arg, &want, param.meta);
+            }
+        }
+    }
+
+    /// Checks that `name` was given as many arguments as it has parameters.
+    ///
+    /// Both `want` and `got` pair a number of arguments with the site that determined it.
+    fn arity(&mut self, name: &str, want: (usize, Meta<'inp>), got: (usize, Meta<'inp>)) {
+        if want.0 != got.0 {
+            self.errors.push(Error::Arity {
+                name: name.to_owned(),
+                want: want.0,
+                got: got.0,
+                want_loc: want.1,
+                got_loc: got.1,
+            });
+        }
+    }
+}
@@ -0,0 +1,1082 @@
+//! Verification condition generation.
+//!
+//! Verification condition generation works on a bound program, and proceeds as follows:
+//!
+//! 1. we lower the body of each procedure into a proof outline (see [outline]): a graph whose
+//!    nodes are view assertions and whose edges are the commands that move between them, with
+//!    calls to other procedures asserting their pre-conditions and assuming their post-conditions;
+//! 2. for each edge, we compute the transition relation of its commands over pre-state and
+//!    post-state variables (see [command]);
+//! 3. for each edge and each _goal view_ (the empty view, plus a fresh instance of every pattern
+//!    in every constraint), we reify the edge's source and destination assertions, each joined
+//!    with the goal view, through the program's constraints (see [view] and [reify]).
+//!
+//! Each pair of edge and goal view gives one [Condition]: the reified source view and the
+//! transition relation must imply the reified destination view over the post-state.  Goal views
+//! count occurrences with goal variables, which the condition assumes are non-negative.
+//!
+//! Each edge also gives one _subtyping obligation_ per variable it assigns whose type is refined:
+//! the reified source view and the transition relation must imply the refinement predicate over
+//! the variable's post-state.  In every condition, the refinement predicates of the pre-state
+//! variables are assumed as hypotheses.
+//!
+//! Each edge also gives one _bounds obligation_ per array subscript its commands evaluate: the
+//! reified source view and the assumptions made so far must imply that the index lies within the
+//! bounds of the array's type.
+//!
+//! Each edge also gives one _error obligation_ per `error` primitive its commands run: the
+//! reified source view and the assumptions made so far must imply false.
+//!
+//! Each edge whose destination view has symbolic iterators also gives an _iterator obligation_:
+//! the reified source view and the transition relation must imply that those iterators are
+//! non-negative, as flattening the view assumes (see [`view::View::iterators`]).
+//!
+//! Finally, the constraints give _side-conditions_ checking that they are well-formed: that `emp`
+//! is defined as `true`, that iterated constraints are downward closed, and that exact definitions
+//! imply the other constraints over the same pattern (see [obligations]).
+//!
+//! Indefinite constraints have no definitions yet, so conditions record the instances of them that
+//! they assume and establish (see [Unknown]) rather than reifying them.
+//!
+//! The conditions can then be emitted in various formats (see [smt2]), including as a system of
+//! Horn clauses from which to infer the definitions of indefinite constraints (see [horn]).
+
+use std::fmt::{Display, Formatter};
+
+use egg::Symbol;
+use num_bigint::BigInt;
+
+use self::reify::Position;
+use super::{
+    diagnostic::{self, code, Diagnostic, FileId, Label, ToDiagnostic},
+    language::{
+        cfg::{self, var},
+        expr::{
+            bop::{self, Rel},
+            constant::Constant,
+            map::HasMeta,
+            Expr,
+        },
+        tagged::Tagged,
+        typing,
+        var::Variable,
+    },
+};
+
+pub mod command;
+pub mod horn;
+pub mod obligations;
+pub mod outline;
+pub mod reify;
+pub mod smt2;
+pub mod view;
+
+/// A variable in a verification condition.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Var {
+    /// A program variable in the state before the command.
+    Pre(var::Symbol),
+    /// A program variable in the state after the command.
+    Post(var::Symbol),
+    /// A variable bound by the goal view.
+    ///
+    /// Goal variables stand for the arguments of another thread's views, and so do not change
+    /// over the command.
+    Goal(var::Symbol),
+    /// The value of a program variable partway through the command.
+    ///
+    /// Some primitives choose a new value for a variable depending on a test; we name that value
+    /// with an intermediate variable, and constrain it with an assumption.  The number
+    /// distinguishes intermediate variables for the same program variable.
+    Inter(var::Symbol, usize),
+}
+
+impl Var {
+    /// Borrows the program variable underlying this variable.
+    #[must_use]
+    pub fn symbol(&self) -> &var::Symbol {
+        match self {
+            Self::Pre(s) | Self::Post(s) | Self::Goal(s) | Self::Inter(s, _) => s,
+        }
+    }
+}
+
+/// Variables mangle into their displayed form.
+impl Variable for Var {
+    fn to_symbol(&self) -> Symbol {
+        Symbol::new(self.to_string())
+    }
+
+    fn into_symbol(self) -> Symbol {
+        self.to_symbol()
+    }
+}
+
+/// Variables are displayed in a form that is also a valid SMT-LIB simple symbol if the underlying
+/// name is ASCII.
+impl Display for Var {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pre(s) => write!(f, "{s}"),
+            Self::Post(s) => write!(f, "{s}!after"),
+            Self::Goal(s) => write!(f, "{s}!goal"),
+            Self::Inter(s, n) => write!(f, "{s}!mid{n}"),
+        }
+    }
+}
+
+/// Type of formulae in verification conditions.
+///
+/// Formulae carry no metadata; the [Condition] containing them carries the metadata of the
+/// program fragment they came from.
+pub type Formula = Expr<(), Var>;
+
+/// A goal, identifying what a condition is trying to establish.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Goal {
+    /// The constraints over the empty goal view.
+    Emp,
+    /// The constraints over the pattern with the given index in the constraint with the given
+    /// index.
+    Pattern { constraint: usize, pattern: usize },
+    /// The refinement predicate on the type of the assigned variable with the given (mangled)
+    /// name.
+    Refinement(Symbol),
+    /// The in-bounds check of the array subscript with the given index in the edge's commands.
+    Bounds(usize),
+    /// The unreachability check of the `error` primitive with the given index in the edge's
+    /// commands.
+    Error(usize),
+    /// The non-negativity of the iterators of the edge's destination view.
+    Iterators,
+    /// The truth of the constraint over the given empty pattern of the given constraint.
+    EmpDefinition { constraint: usize, pattern: usize },
+    /// The implication of the constraint over the given pattern of the given constraint by the
+    /// exact definition that supersedes it.
+    Definition { constraint: usize, pattern: usize },
+    /// The downward closure of the iterated atom with the given index in the given pattern of the
+    /// given constraint.
+    Downclosure {
+        constraint: usize,
+        pattern: usize,
+        atom: usize,
+    },
+    /// The base case of the iterated atom with the given index in the given pattern of the given
+    /// constraint.
+    Base {
+        constraint: usize,
+        pattern: usize,
+        atom: usize,
+    },
+}
+
+impl Display for Goal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Emp => f.write_str("emp"),
+            Self::Pattern {
+                constraint,
+                pattern,
+            } => write!(f, "c{constraint}p{pattern}"),
+            Self::Refinement(var) => write!(f, "refine_{var}"),
+            Self::Bounds(index) => write!(f, "bounds{index}"),
+            Self::Error(index) => write!(f, "error{index}"),
+            Self::Iterators => f.write_str("iterators"),
+            Self::EmpDefinition {
+                constraint,
+                pattern,
+            } => write!(f, "c{constraint}p{pattern}_emp"),
+            Self::Definition {
+                constraint,
+                pattern,
+            } => write!(f, "c{constraint}p{pattern}_def"),
+            Self::Downclosure {
+                constraint,
+                pattern,
+                atom,
+            } => write!(f, "c{constraint}p{pattern}a{atom}_down"),
+            Self::Base {
+                constraint,
+                pattern,
+                atom,
+            } => write!(f, "c{constraint}p{pattern}a{atom}_base"),
+        }
+    }
+}
+
+/// Where a condition comes from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Origin {
+    /// An edge of a procedure's proof outline.
+    Edge {
+        /// The name of the procedure.
+        procedure: Symbol,
+        /// The index of the edge in the procedure's outline.
+        edge: usize,
+    },
+    /// A constraint, which the condition checks for well-formedness.
+    Constraint,
+}
+
+/// An instance of an indefinite constraint, whose definition is yet to be inferred.
+///
+/// Until then, instances are taken to hold.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Unknown {
+    /// The index of the constraint.
+    pub constraint: usize,
+    /// The index of the pattern in the constraint.
+    pub pattern: usize,
+    /// The condition under which the instance applies.
+    pub condition: Formula,
+    /// The values of the variables of the pattern, in order of first appearance, followed by
+    /// those of the global variables (see [globals]).
+    pub args: Vec<Formula>,
+}
+
+/// A verification condition.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Condition<M> {
+    /// Where the condition comes from.
+    pub origin: Origin,
+    /// The goal view framing the proof term, or the side-condition being checked.
+    pub goal: Goal,
+    /// Metadata for the proof term, pointing to the command at its heart.
+    ///
+    /// Side-conditions on constraints point to the constraint instead.
+    pub meta: M,
+    /// Metadata for the proof term's pre-condition assertion, or the constraint's pattern.
+    pub pre: M,
+    /// Metadata for the proof term's post-condition assertion, or the constraint's body.
+    pub post: M,
+    /// Hypotheses of the condition: the reified pre-condition and transition relation.
+    pub hypotheses: Vec<Formula>,
+    /// The conclusion of the condition: the reified post-condition.
+    pub conclusion: Formula,
+    /// Instances of indefinite constraints that the condition assumes, alongside its hypotheses.
+    pub assumed: Vec<Unknown>,
+    /// Instances of indefinite constraints that the condition establishes, alongside its
+    /// conclusion.
+    pub required: Vec<Unknown>,
+}
+
+impl<M> Condition<M> {
+    /// Gets a name for this condition that is unique within its program.
+    #[must_use]
+    pub fn name(&self) -> String {
+        match self.origin {
+            Origin::Edge { procedure, edge } => format!("{procedure}_{edge}_{}", self.goal),
+            Origin::Constraint => self.goal.to_string(),
+        }
+    }
+
+    /// Visits every variable in the condition.
+    pub fn for_each_var<'a>(&'a self, f: &mut impl FnMut(&'a Var)) {
+        for h in &self.hypotheses {
+            h.for_each_var(f);
+        }
+        self.conclusion.for_each_var(f);
+        for unknown in self.assumed.iter().chain(&self.required) {
+            unknown.condition.for_each_var(f);
+            for arg in &unknown.args {
+                arg.for_each_var(f);
+            }
+        }
+    }
+}
+
+/// Generates the verification conditions for every procedure in `program`.
+///
+/// # Errors
+///
+/// Fails if any procedure cannot be lowered into a proof outline, or uses a construct that the
+/// generator does not yet support.
+pub fn generate<M: Clone + Default>(program: &cfg::Program<'_, M>) -> Result<Vec<Condition<M>>, M> {
+    let goals = view::goals(program)?;
+    let mut conds = obligations::generate(program)?;
+
+    for proc in &program.procedures {
+        let outline = outline::lower(program, proc)?;
+        for (index, edge) in outline.edges.iter().enumerate() {
+            let transition = command::transition(&edge.commands)?;
+            let pre = &outline.nodes[edge.src];
+            let post = &outline.nodes[edge.dst];
+            // The obligations other than framing assume the source view without any goal view.
+            let source_view = view::flatten(&pre.item, Var::Pre)?;
+            let source = source_view.reify(program, Var::Pre, Position::Hypothesis)?;
+            let source_unknowns = source_view.unknowns(program, Var::Pre)?;
+            let condition = |goal, hypotheses, conclusion, assumed, required| Condition {
+                origin: Origin::Edge {
+                    procedure: proc.name,
+                    edge: index,
+                },
+                goal,
+                meta: edge.meta.clone(),
+                pre: pre.meta.clone(),
+                post: post.meta.clone(),
+                hypotheses,
+                conclusion,
+                assumed,
+                required,
+            };
+
+            for (goal, goal_view) in &goals {
+                let mut pre_view = view::flatten(&pre.item, Var::Pre)?;
+                pre_view.join(goal_view.clone());
+                let mut post_view = view::flatten(&post.item, Var::Post)?;
+                post_view.join(goal_view.clone());
+
+                let conclusion = post_view.reify(program, Var::Post, Position::Conclusion)?;
+                let mut hypotheses =
+                    vec![pre_view.reify(program, Var::Pre, Position::Hypothesis)?];
+                hypotheses.extend(goal_view.goal_bounds());
+                hypotheses.extend(transition.relation(&post_vars(&conclusion)));
+
+                let assumed = pre_view.unknowns(program, Var::Pre)?;
+                let required = post_view.unknowns(program, Var::Post)?;
+                conds.push(condition(*goal, hypotheses, conclusion, assumed, required));
+            }
+
+            // Builds a non-framing obligation from facts about the edge.
+            let obligation = |goal, facts: Vec<Formula>, conclusion| {
+                let mut hypotheses = vec![source.clone()];
+                hypotheses.extend(facts);
+                condition(
+                    goal,
+                    hypotheses,
+                    conclusion,
+                    source_unknowns.clone(),
+                    vec![],
+                )
+            };
+
+            let post_view = view::flatten(&post.item, Var::Post)?;
+            if !post_view.iterators.is_empty() {
+                let conclusion = Expr::conjunction(post_view.iterators);
+                let facts = transition.relation(&post_vars(&conclusion));
+                conds.push(obligation(Goal::Iterators, facts, conclusion));
+            }
+
+            for (var, _) in &transition.updates {
+                let Some(conclusion) = refinement(program, var, Var::Post) else {
+                    continue;
+                };
+                let facts = transition.relation(std::slice::from_ref(var));
+                conds.push(obligation(
+                    Goal::Refinement(var.to_symbol()),
+                    facts,
+                    conclusion,
+                ));
+            }
+
+            for (n, access) in transition.accesses.iter().enumerate() {
+                let Some(array) = array_type(program, &access.array) else {
+                    return Err(Error::Unsupported {
+                        what: "subscripts of expressions with no known array type",
+                        meta: edge.meta.clone(),
+                    });
+                };
+                let conclusion = in_bounds(&array, &access.index);
+                conds.push(obligation(
+                    Goal::Bounds(n),
+                    access.assumptions.clone(),
+                    conclusion,
+                ));
+            }
+
+            for (n, assumptions) in transition.errors.iter().enumerate() {
+                conds.push(obligation(
+                    Goal::Error(n),
+                    assumptions.clone(),
+                    Expr::bool(false),
+                ));
+            }
+        }
+    }
+
+    for cond in &mut conds {
+        let assumptions = pre_refinements(program, cond);
+        let at = cond.hypotheses.len().min(1);
+        cond.hypotheses.splice(at..at, assumptions);
+    }
+    Ok(conds)
+}
+
+/// Gets the global variables of `program`, ordered by name.
+///
+/// Definitions of indefinite constraints may mention these variables, and so their instances
+/// take them as arguments.
+#[must_use]
+pub fn globals<M>(program: &cfg::Program<'_, M>) -> Vec<var::Symbol> {
+    let mut globals: Vec<var::Symbol> = (program.variables.iter())
+        .map(|(sym, _)| sym)
+        .filter(|sym| sym.block == cfg::BlockRef::Global)
+        .cloned()
+        .collect();
+    globals.sort_by_key(|sym| sym.name.as_str());
+    globals
+}
+
+/// Gets the refinement predicates that hold of the pre-state variables mentioned in `cond`.
+fn pre_refinements<M: Clone>(program: &cfg::Program<'_, M>, cond: &Condition<M>) -> Vec<Formula> {
+    let mut vars = vec![];
+    cond.for_each_var(&mut |v| {
+        if let Var::Pre(s) = v {
+            if !vars.contains(&s) {
+                vars.push(s);
+            }
+        }
+    });
+    vars.into_iter()
+        .filter_map(|v| refinement(program, v, Var::Pre))
+        .collect()
+}
+
+/// Gets the conjunction of the refinement predicates on the type of `var`, if it has any.
+///
+/// The refinement variable of each predicate becomes `var` in the state given by `state`.
+fn refinement<M: Clone>(
+    program: &cfg::Program<'_, M>,
+    var: &var::Symbol,
+    state: fn(var::Symbol) -> Var,
+) -> Option<Formula> {
+    let record = program.variables.get_exact(var)?;
+    let ty = program.type_of(&record.ty)?;
+
+    let predicates: Vec<Formula> = ty
+        .refinements()
+        .map(|r| {
+            let name = Symbol::new(r.var());
+            r.refinement().clone().map_meta(|_| ()).subst(&mut |v| {
+                // The binder only admits the refinement variable in refinement predicates.
+                let sym = if v.item == name {
+                    var.clone()
+                } else {
+                    var::Symbol::global(v.item)
+                };
+                Expr::Var(Tagged::with_default(state(sym)))
+            })
+        })
+        .collect();
+    if predicates.is_empty() {
+        None
+    } else {
+        Some(Expr::conjunction(predicates))
+    }
+}
+
+/// Gets the type of the array denoted by `formula`, if it is an array.
+fn array_type<M: Clone>(
+    program: &cfg::Program<'_, M>,
+    formula: &Formula,
+) -> Option<typing::Array<M, Symbol>> {
+    let ty = match formula {
+        Expr::Var(v) => {
+            let record = program.variables.get_exact(v.item.symbol())?;
+            program.type_of(&record.ty)?
+        }
+        Expr::Index { array, .. } => array_type(program, array)?.base().clone(),
+        Expr::Update { array, .. } => return array_type(program, array),
+        _ => return None,
+    };
+    match ty.base() {
+        typing::Type::Array(a) => Some(a.clone()),
+        _ => None,
+    }
+}
+
+/// Constructs the formula stating that `index` lies within the bounds of `array`.
+fn in_bounds<M>(array: &typing::Array<M, Symbol>, index: &Formula) -> Formula {
+    let bound = |b: &BigInt| Expr::Literal(Tagged::with_default(Constant::Int(b.clone())));
+    Expr::bop(
+        Expr::bop(bound(array.low()), Rel::LessEq, index.clone()),
+        bop::Bool::And,
+        Expr::bop(index.clone(), Rel::LessEq, bound(array.high())),
+    )
+}
+
+/// Collects the program variables that `formula` mentions in their post-state, without duplicates.
+fn post_vars(formula: &Formula) -> Vec<var::Symbol> {
+    let mut vars = vec![];
+    formula.for_each_var(&mut |v| {
+        if let Var::Post(s) = v {
+            if !vars.contains(s) {
+                vars.push(s.clone());
+            }
+        }
+    });
+    vars
+}
+
+/// Errors that can occur during verification condition generation.
+#[derive(Debug, Clone, thiserror::Error, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Error<M> {
+    /// A command appears before the first assertion, or after the last assertion, of a path.
+    #[error("command is not surrounded by view assertions")]
+    MissingAssertion { meta: M },
+    /// Two atomic commands appear on a path without an assertion between them.
+    #[error("atomic commands must be separated by a view assertion")]
+    AdjacentAtomics { meta: M },
+    /// A view assertion appears inside an atomic block.
+    #[error("view assertions are not permitted inside atomic blocks")]
+    AssertionInAtomic { meta: M },
+    /// An assignment has an unsupported left-hand side.
+    #[error("can only assign to variables and array elements")]
+    BadLvalue { meta: M },
+    /// The generator does not yet support a construct.
+    #[error("unsupported construct: {what}")]
+    Unsupported { what: &'static str, meta: M },
+}
+
+impl<M> Error<M> {
+    /// Borrows the metadata of the program fragment that caused this error.
+    #[must_use]
+    pub fn meta(&self) -> &M {
+        match self {
+            Self::MissingAssertion { meta }
+            | Self::AdjacentAtomics { meta }
+            | Self::AssertionInAtomic { meta }
+            | Self::BadLvalue { meta }
+            | Self::Unsupported { meta, .. } => meta,
+        }
+    }
+}
+
+impl ToDiagnostic for Error<Option<pest::Span<'_>>> {
+    fn to_diagnostic(&self, file: FileId) -> Diagnostic {
+        let (code, label) = match self {
+            Self::MissingAssertion { .. } => (code::MISSING_ASSERTION, "needs a view assertion"),
+            Self::AdjacentAtomics { .. } => (
+                code::ADJACENT_ATOMICS,
+                "needs a view assertion before this command",
+            ),
+            Self::AssertionInAtomic { .. } => (code::ASSERTION_IN_ATOMIC, "inside an atomic block"),
+            Self::BadLvalue { .. } => (code::BAD_LVALUE, "cannot be assigned to"),
+            Self::Unsupported { .. } => (code::UNSUPPORTED, "not yet supported"),
+        };
+        Diagnostic::error(self.to_string())
+            .with_code(code)
+            .with_label_opt(
+                self.meta()
+                    .map(|s| Label::primary(file, diagnostic::range(&s)).with_message(label)),
+            )
+    }
+}
+
+/// Shorthand for results over [Error].
+pub type Result<T, M> = std::result::Result<T, Error<M>>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ticket lock with a single thread-local ticket, whose proof needs no atomic arithmetic.
+    const LOCK: &str = "program Lock;
+var held : boolean;
+view Holding;
+procedure Acquire;
+begin
+  { emp }
+    <| held := true |>
+  { Holding }
+end;
+constraint Holding entails held = true;
+constraint Holding * Holding entails false;
+begin end.";
+
+    /// Tests that there is one condition per edge and goal view.
+    #[test]
+    fn generate_lock() {
+        let ast = crate::parser::parse(LOCK).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let conds = generate(&program).unwrap();
+        let names: Vec<_> = conds.iter().map(Condition::name).collect();
+        assert_eq!(
+            vec!["Acquire_0_emp", "Acquire_0_c0p0", "Acquire_0_c1p0"],
+            names
+        );
+    }
+
+    /// Tests that procedure specifications bracket the procedure's body, and that calls assert
+    /// the callee's pre-condition and assume its post-condition, with the arguments substituted.
+    #[test]
+    fn generate_call() {
+        let ast = crate::parser::parse(
+            "program Call;
+var x : integer;
+view Token(n : integer);
+procedure Take(n : integer) requires { emp } ensures { Token(n) };
+begin
+  <| x := n |>
+end;
+procedure Main;
+var t : integer;
+begin
+  { emp } <| t := 1 |> { @(t = 1) };
+  Take(t)
+  { Token(t) }
+end;
+constraint Token(n) entails x = n;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+
+        let edges: Vec<_> = outline::lower(&program, &program.procedures[1])
+            .unwrap()
+            .edges
+            .iter()
+            .map(|e| (e.src, e.dst))
+            .collect();
+        assert_eq!(vec![(0, 1), (1, 2), (3, 4)], edges);
+
+        let conds = generate(&program).unwrap();
+        let names: Vec<_> = conds.iter().map(Condition::name).collect();
+        assert_eq!(
+            vec![
+                "Take_0_emp",
+                "Take_0_c0p0",
+                "Main_0_emp",
+                "Main_0_c0p0",
+                "Main_1_emp",
+                "Main_1_c0p0",
+                "Main_2_emp",
+                "Main_2_c0p0"
+            ],
+            names
+        );
+
+        let mut out = vec![];
+        smt2::write(&program, &conds[6], &mut out).unwrap();
+        assert_eq!(
+            "; Main_2_emp
+(set-logic ALL)
+(declare-const x Int)
+(declare-const Main.t Int)
+(declare-const x!after Int)
+(declare-const Main.t!after Int)
+(assert (= x Main.t))
+(assert (= x!after x))
+(assert (= Main.t!after Main.t))
+(assert (not (= x!after Main.t!after)))
+(check-sat)
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    /// Tests that calls reject post-conditions mentioning parameters the callee assigns to, or
+    /// local variables of the callee.
+    #[test]
+    fn generate_call_stale_ensures() {
+        let source = "program Call;
+var x : integer;
+view Token(n : integer);
+procedure Take(n : integer) requires { emp } ensures { Token(n) };
+var l : integer;
+begin
+  { emp } <| n := x |> { Token(n) }
+end;
+procedure Main;
+begin
+  { emp } Take(1) { Token(1) }
+end;
+constraint Token(n) entails x = n;
+begin end.";
+        let ast = crate::parser::parse(source).unwrap();
+        let mut program = crate::binder::bind(&ast.item).unwrap();
+        let main = program.procedures[1].clone();
+
+        let err = outline::lower(&program, &main).unwrap_err();
+        let n = source.rfind("Token(n) };").unwrap() + "Token(".len();
+        assert_eq!(
+            Error::Unsupported {
+                what: "post-conditions mentioning parameters the procedure assigns to",
+                meta: Some(pest::Span::new(source, n, n + 1).unwrap()),
+            },
+            err
+        );
+
+        // The binder hides locals from post-conditions, so we have to put one there ourselves.
+        let take = &mut program.procedures[0];
+        let local = take.locals[0].clone();
+        take.body.clear();
+        take.ensures = take
+            .ensures
+            .take()
+            .map(|e| e.map(|a| a.subst(&mut |v| Expr::Var(v.map(|_| local.clone())))));
+        let err = outline::lower(&program, &main).unwrap_err();
+        assert_eq!(
+            Error::Unsupported {
+                what: "post-conditions mentioning local variables",
+                meta: Some(pest::Span::new(source, n, n + 1).unwrap()),
+            },
+            err
+        );
+    }
+
+    /// Tests that loops close the paths into and through their bodies at their invariants.
+    #[test]
+    fn generate_loops() {
+        let ast = crate::parser::parse(
+            "program Loop;
+var x : integer;
+procedure Count;
+begin
+  { emp }
+  while x < 10 invariant { emp } do <| x := x + 1 |>;
+  { emp }
+  repeat invariant { @(x >= 0) }
+    <| x := x - 1 |>
+  until x = 0
+  { emp }
+end;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+
+        let outline = outline::lower(&program, &program.procedures[0]).unwrap();
+        let edges: Vec<_> = outline.edges.iter().map(|e| (e.src, e.dst)).collect();
+        assert_eq!(vec![(0, 1), (1, 1), (1, 2), (2, 3), (3, 3), (3, 4)], edges);
+
+        let conds = generate(&program).unwrap();
+        let mut out = vec![];
+        smt2::write(&program, &conds[4], &mut out).unwrap();
+        assert_eq!(
+            "; Count_4_emp
+(set-logic ALL)
+(declare-const x Int)
+(declare-const x!after Int)
+(assert (>= x 0))
+(assert (not (= (- x 1) 0)))
+(assert (= x!after (- x 1)))
+(assert (not (>= x!after 0)))
+(check-sat)
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    /// Tests that constraints over `emp` must hold outright.
+    #[test]
+    fn generate_emp_definition() {
+        let ast = crate::parser::parse(
+            "program Unit; var x : integer; constraint emp entails 0 <= x; begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+
+        let conds = generate(&program).unwrap();
+        let [cond] = &conds[..] else {
+            panic!("expected one condition, got {conds:?}");
+        };
+        assert_eq!("c0p0_emp", cond.name());
+        assert!(cond.hypotheses.is_empty());
+        assert_eq!("0 <= x", cond.conclusion.to_string());
+    }
+
+    /// Tests that exact definitions supersede entailments in conclusions and must imply them, that
+    /// `fails` patterns give no goal views, and that indefinite constraints mean nothing yet.
+    #[test]
+    fn generate_constraint_forms() {
+        let ast = crate::parser::parse(
+            "program Forms;
+var x : integer;
+view V(n : integer); W;
+procedure P;
+begin
+  { W } <| x := 1 |> { V(x) }
+end;
+constraint V(a) entails 0 < a;
+constraint V(b) = b = x;
+constraint W * W fails;
+constraint W ?;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+
+        let conds: Vec<_> = generate(&program)
+            .unwrap()
+            .iter()
+            .map(|c| {
+                let hypotheses: Vec<_> = c.hypotheses.iter().map(ToString::to_string).collect();
+                format!(
+                    "{}: {} |- {}",
+                    c.name(),
+                    hypotheses.join(", "),
+                    c.conclusion
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "c0p0_def: constraint0.a!goal = x |- 0 < constraint0.a!goal",
+                "P_0_emp: true, x!after = 1 |- x!after = x!after",
+                "P_0_c0p0: 0 < constraint0.a!goal and constraint0.a!goal = x, x!after = 1 \
+                 |- x!after = x!after and constraint0.a!goal = x!after",
+                "P_0_c1p0: 0 < constraint1.b!goal and constraint1.b!goal = x, x!after = 1 \
+                 |- x!after = x!after and constraint1.b!goal = x!after",
+                "P_0_c3p0: false, x!after = 1 |- x!after = x!after",
+            ],
+            conds
+        );
+    }
+
+    /// Tests that iterated constraints give downward closure and base case side-conditions, and
+    /// that goal views bind their iterators.
+    #[test]
+    fn generate_iterated() {
+        let ast = crate::parser::parse(
+            "program Count;
+var count : integer;
+view Arc;
+procedure Add;
+begin
+  { emp } <| Inc(count) |> { Arc }
+end;
+constraint Arc[n] entails n <= count;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+
+        let conds: Vec<_> = generate(&program)
+            .unwrap()
+            .iter()
+            .map(|c| {
+                let hypotheses: Vec<_> = c.hypotheses.iter().map(ToString::to_string).collect();
+                format!(
+                    "{}: {} |- {}",
+                    c.name(),
+                    hypotheses.join(", "),
+                    c.conclusion
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "c0p0a0_down: 0 <= constraint0.n!goal, constraint0.n!goal + 1 <= count \
+                 |- constraint0.n!goal <= count",
+                "c0p0a0_base: true |- 0 <= count",
+                "Add_0_emp: true, count!after = count + 1 |- 1 <= count!after",
+                "Add_0_c0p0: 0 <= constraint0.n!goal implies constraint0.n!goal <= count, \
+                 0 <= constraint0.n!goal, count!after = count + 1 \
+                 |- 0 <= constraint0.n!goal + 1 implies constraint0.n!goal + 1 <= count!after",
+            ],
+            conds
+        );
+    }
+
+    /// Tests that edges check that the iterators of their destination views are non-negative as
+    /// written, since merging atoms sums their iterators.
+    #[test]
+    fn generate_negative_iterator() {
+        let ast = crate::parser::parse(
+            "program Neg;
+view A;
+procedure P;
+var n : integer;
+begin
+  { A } <| n := -1 |> { [n < 5] (A[n] * A) }
+end;
+constraint A[k] entails true;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+
+        let conds = generate(&program).unwrap();
+        let iterators = conds.iter().find(|c| c.goal == Goal::Iterators).unwrap();
+        let hypotheses: Vec<_> = iterators
+            .hypotheses
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            "P_0_iterators: true, P.n!after = -1 |- P.n!after < 5 implies 0 <= P.n!after",
+            format!(
+                "{}: {} |- {}",
+                iterators.name(),
+                hypotheses.join(", "),
+                iterators.conclusion
+            )
+        );
+    }
+
+    /// Tests that framing conditions assume that goal iterators are non-negative, so that goal
+    /// views can't cancel out the atoms of the views they join.
+    #[test]
+    fn generate_arc_goal_bounds() {
+        let ast = crate::parser::parse(include_str!("../examples/arc.pvc")).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let conds = generate(&program).unwrap();
+
+        let clone = conds.iter().find(|c| c.name() == "Clone_0_c0p0").unwrap();
+        let hypotheses: Vec<_> = clone.hypotheses.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            vec![
+                "0 <= constraint0.n!goal + 1 implies (constraint0.n!goal + 1 > 0 implies \
+                 free = false and constraint0.n!goal + 1 <= count)",
+                "0 <= constraint0.n!goal",
+                "count!after = count + 1",
+                "free!after = free",
+            ],
+            hypotheses
+        );
+    }
+
+    /// Tests the transition relations of primitives, including the intermediate value chosen by
+    /// compare-and-swap and the obligation raised by `error`.
+    #[test]
+    fn generate_primitives() {
+        let ast = crate::parser::parse(
+            "program Prim;
+var x : integer;
+procedure P;
+var y : boolean; z : integer;
+begin
+  { emp } <| CAS(x, 0, 1, y); FAA(x, 2, z) |> { emp };
+  if y then <| error |>
+  { emp }
+end;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+
+        let conds = generate(&program).unwrap();
+        let mut out = vec![];
+        smt2::write(&program, &conds[0], &mut out).unwrap();
+        assert_eq!(
+            "; P_0_emp
+(set-logic ALL)
+(declare-const x Int)
+(declare-const x!mid0 Int)
+(declare-const x!after Int)
+(declare-const P.y!after Bool)
+(declare-const P.z!after Int)
+(assert true)
+(assert (and (=> (= x 0) (= x!mid0 1)) (=> (not (= x 0)) (= x!mid0 x))))
+(assert (= x!after (+ x!mid0 2)))
+(assert (= P.y!after (= x 0)))
+(assert (= P.z!after x!mid0))
+(assert (not true))
+(check-sat)
+",
+            String::from_utf8(out).unwrap()
+        );
+
+        let error = conds.iter().find(|c| c.goal == Goal::Error(0)).unwrap();
+        let mut out = vec![];
+        smt2::write(&program, error, &mut out).unwrap();
+        assert_eq!(
+            "; P_1_error0
+(set-logic ALL)
+(declare-const P.y Bool)
+(assert true)
+(assert P.y)
+(assert (not false))
+(check-sat)
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    /// Tests the emitted script for the empty goal view.
+    #[test]
+    fn smt2_lock() {
+        let ast = crate::parser::parse(LOCK).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let conds = generate(&program).unwrap();
+
+        let mut out = vec![];
+        smt2::write(&program, &conds[0], &mut out).unwrap();
+        assert_eq!(
+            "; Acquire_0_emp
+(set-logic ALL)
+(declare-const held!after Bool)
+(assert true)
+(assert (= held!after true))
+(assert (not (= held!after true)))
+(check-sat)
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    /// Tests that assigning a refined variable gives a subtyping obligation, and that the
+    /// refinement is assumed of the pre-state.
+    #[test]
+    fn smt2_refinement() {
+        let ast = crate::parser::parse(
+            "program R;
+var x : {v : integer | v >= 0};
+procedure Inc;
+begin
+  { emp } <| x := x + 1 |> { emp }
+end;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let conds = generate(&program).unwrap();
+        let names: Vec<_> = conds.iter().map(Condition::name).collect();
+        assert_eq!(vec!["Inc_0_emp", "Inc_0_refine_x"], names);
+
+        let mut out = vec![];
+        smt2::write(&program, &conds[1], &mut out).unwrap();
+        assert_eq!(
+            "; Inc_0_refine_x
+(set-logic ALL)
+(declare-const x Int)
+(declare-const x!after Int)
+(assert true)
+(assert (>= x 0))
+(assert (= x!after (+ x 1)))
+(assert (not (>= x!after 0)))
+(check-sat)
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    /// Tests that every subscript gives a bounds obligation, and that element assignment updates
+    /// the whole array.
+    #[test]
+    fn smt2_bounds() {
+        let ast = crate::parser::parse(
+            "program A;
+var a : array[0..3] of integer;
+var i : integer;
+procedure Shift;
+begin
+  { emp } <| a[i] := a[i + 1] |> { emp }
+end;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let conds = generate(&program).unwrap();
+        let names: Vec<_> = conds.iter().map(Condition::name).collect();
+        assert_eq!(
+            vec!["Shift_0_emp", "Shift_0_bounds0", "Shift_0_bounds1"],
+            names
+        );
+
+        let mut out = vec![];
+        smt2::write(&program, &conds[1], &mut out).unwrap();
+        assert_eq!(
+            "; Shift_0_bounds0
+(set-logic ALL)
+(declare-const i Int)
+(assert true)
+(assert (not (and (<= 0 (+ i 1)) (<= (+ i 1) 3))))
+(check-sat)
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! Transition relations of commands.
+//!
+//! This module gives atomic commands their two-state semantics: a relation between the values of
+//! variables before the command (unprimed, [`Var::Pre`]) and after it (primed, [`Var::Post`]).
+//!
+//! We compute the transition relation of a sequence of commands by symbolic execution: we keep a
+//! map from each assigned variable to a formula over the pre-state giving its current value, and
+//! evaluate every expression through that map.  Sequential composition falls out of executing
+//! statements in order.
+//!
+//! Conditionals execute each branch separately from the same state, then merge the results: the
+//! merged transition assumes that it took one of the two paths, where each path consists of the
+//! branch's condition (if deterministic), its assumptions, and the final values of the variables
+//! it assigned.  Variables whose final values differ between branches take intermediate values
+//! ([`Var::Inter`]) for the paths to constrain.
+//!
+//! Assigning to an array element updates the whole array.  We also record every array subscript
+//! that the commands evaluate, so that we can later check that it is in bounds.
+//!
+//! Primitives that update a variable only if a test succeeds, such as compare-and-swap, give that
+//! variable an intermediate value in the same way.  The `error` primitive records an obligation
+//! that it is unreachable, and then assumes false, so that nothing after it needs checking.
+
+use super::{
+    super::language::{
+        ast::stm::{self, Stm},
+        cfg::var,
+        expr::{
+            bop::{self, Rel},
+            Expr,
+        },
+        ite::Condition,
+        primitive::Primitive,
+        tagged::Tagged,
+    },
+    outline::Command,
+    view::formula,
+    Error, Formula, Result, Var,
+};
+
+/// The transition relation of a sequence of commands.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Transition {
+    /// Assumptions made on the pre-state.
+    pub assumptions: Vec<Formula>,
+    /// The final value of each assigned variable, as a formula over the pre-state, in order of
+    /// first assignment.
+    pub updates: Vec<(var::Symbol, Formula)>,
+    /// The array subscripts evaluated by the commands, in order of evaluation.
+    pub accesses: Vec<Access>,
+    /// The assumptions in force at each `error` primitive, in order of execution.
+    pub errors: Vec<Vec<Formula>>,
+    /// The number of intermediate variables introduced so far.
+    intermediates: usize,
+}
+
+/// An array subscript evaluated by a sequence of commands.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Access {
+    /// The array being subscripted, as a formula over the pre-state.
+    pub array: Formula,
+    /// The index, as a formula over the pre-state.
+    pub index: Formula,
+    /// The assumptions in force when the subscript is evaluated.
+    pub assumptions: Vec<Formula>,
+}
+
+impl Transition {
+    /// Gets the formulae relating the pre-state to the post-state of `vars`.
+    ///
+    /// Variables in `vars` that the commands do not assign keep their value.
+    #[must_use]
+    pub fn relation(&self, vars: &[var::Symbol]) -> Vec<Formula> {
+        let unchanged = vars
+            .iter()
+            .filter(|v| !self.updates.iter().any(|(u, _)| u == *v))
+            .map(|v| (v.clone(), Expr::Var(Var::Pre(v.clone()).into())));
+
+        let updates = self
+            .updates
+            .iter()
+            .cloned()
+            .chain(unchanged)
+            .map(|(v, value)| Expr::bop(Expr::Var(Var::Post(v).into()), Rel::Eq, value));
+
+        self.assumptions.iter().cloned().chain(updates).collect()
+    }
+
+    /// Gets the relation between the pre-state and the post-state of `vars` as one formula.
+    #[must_use]
+    pub fn formula(&self, vars: &[var::Symbol]) -> Formula {
+        Expr::conjunction(self.relation(vars))
+    }
+
+    /// Gets the current value of `var`.
+    fn value(&self, var: &var::Symbol) -> Formula {
+        self.updates.iter().find(|(u, _)| u == var).map_or_else(
+            || Expr::Var(Var::Pre(var.clone()).into()),
+            |(_, f)| f.clone(),
+        )
+    }
+
+    /// Evaluates `expr` in the current state, recording any subscripts it contains.
+    fn eval<M: Clone>(&mut self, expr: &Expr<M, var::Symbol>) -> Formula {
+        let result = formula(expr, Var::Pre).subst(&mut |v| match v.item {
+            Var::Pre(s) => self.value(&s),
+            other => Expr::Var(Tagged::with_default(other)),
+        });
+        self.record(&result);
+        result
+    }
+
+    /// Records every subscript in `formula`, innermost first.
+    fn record(&mut self, formula: &Formula) {
+        match formula {
+            Expr::Literal(_) | Expr::Var(_) => (),
+            Expr::Bop { lhs, rhs, .. } => {
+                self.record(lhs);
+                self.record(rhs);
+            }
+            Expr::Uop { expr, .. } => self.record(expr),
+            Expr::Index { array, index } => {
+                self.record(array);
+                self.record(index);
+                self.access(array.as_ref().clone(), index.as_ref().clone());
+            }
+            Expr::Update {
+                array,
+                index,
+                value,
+            } => {
+                self.record(array);
+                self.record(index);
+                self.record(value);
+            }
+        }
+    }
+
+    /// Records a subscript of `array` at `index`, unless we have already recorded it under fewer
+    /// assumptions.
+    ///
+    /// Both `array` and `index` are formulae over the pre-state.
+    fn access(&mut self, array: Formula, index: Formula) {
+        if self.accesses.iter().any(|a| {
+            a.array == array && a.index == index && self.assumptions.starts_with(&a.assumptions)
+        }) {
+            return;
+        }
+        self.accesses.push(Access {
+            array,
+            index,
+            assumptions: self.assumptions.clone(),
+        });
+    }
+
+    /// Assigns `value` to `var`.
+    fn assign(&mut self, var: var::Symbol, value: Formula) {
+        match self.updates.iter_mut().find(|(u, _)| *u == var) {
+            Some((_, f)) => *f = value,
+            None => self.updates.push((var, value)),
+        }
+    }
+
+    /// Executes a statement, found at `meta`, inside an atomic command.
+    fn stm<M: Clone>(&mut self, stm: &Stm<'_, M, var::Symbol>, meta: &M) -> Result<(), M> {
+        match stm {
+            Stm::Assign(a) => self.assign_stm(a, meta),
+            Stm::Atomic(stm::Atomic { body: l, .. }) | Stm::Block(l) => self.list(l),
+            Stm::Nop => Ok(()),
+            Stm::Call(_) => Err(Error::Unsupported {
+                what: "procedure calls inside atomic commands",
+                meta: meta.clone(),
+            }),
+            Stm::Primitive(p) => self.primitive(p, meta),
+            Stm::Ite(i) => self.ite(i),
+            Stm::Repeat(_) | Stm::While(_) => Err(Error::Unsupported {
+                what: "loops inside atomic commands",
+                meta: meta.clone(),
+            }),
+        }
+    }
+
+    /// Executes a conditional inside an atomic command.
+    fn ite<M: Clone>(&mut self, ite: &stm::Ite<'_, M, var::Symbol>) -> Result<(), M> {
+        let cond = match &ite.cond.item {
+            Condition::Deterministic(c) => Some(self.eval(c)),
+            Condition::Nondeterministic => None,
+        };
+
+        let mut true_branch = self.clone();
+        true_branch.assumptions.extend(cond.clone());
+        true_branch.stm(&ite.true_branch.item, &ite.true_branch.meta)?;
+
+        // Intermediate variables must be fresh across both branches.
+        let mut false_branch = self.clone();
+        false_branch.intermediates = true_branch.intermediates;
+        false_branch.assumptions.extend(cond.map(Expr::not));
+        false_branch.stm(&ite.false_branch.item, &ite.false_branch.meta)?;
+
+        self.merge(&[true_branch, false_branch]);
+        Ok(())
+    }
+
+    /// Merges `branches`, which were executed from the current state, into the current state.
+    fn merge(&mut self, branches: &[Transition; 2]) {
+        let start = self.assumptions.len();
+        let mut paths = branches.each_ref().map(|b| b.assumptions[start..].to_vec());
+
+        let mut vars: Vec<&var::Symbol> = vec![];
+        for (var, _) in branches.iter().flat_map(|b| &b.updates) {
+            if !vars.contains(&var) {
+                vars.push(var);
+            }
+        }
+
+        self.intermediates = branches[1].intermediates;
+        for var in vars {
+            let values = branches.each_ref().map(|b| b.value(var));
+            if values[0] == values[1] {
+                self.assign(var.clone(), values[0].clone());
+                continue;
+            }
+            let chosen = self.intermediate(var);
+            for (path, value) in paths.iter_mut().zip(values) {
+                path.push(Expr::bop(chosen.clone(), Rel::Eq, value));
+            }
+            self.assign(var.clone(), chosen);
+        }
+
+        let (accesses, errors) = (self.accesses.len(), self.errors.len());
+        for branch in branches {
+            self.accesses
+                .extend_from_slice(&branch.accesses[accesses..]);
+            self.errors.extend_from_slice(&branch.errors[errors..]);
+        }
+
+        // If either path is empty, it always holds, and so does their disjunction.
+        if paths.iter().all(|p| !p.is_empty()) {
+            let [t, f] = paths.map(Expr::conjunction);
+            self.assumptions.push(Expr::bop(t, bop::Bool::Or, f));
+        }
+    }
+
+    fn assign_stm<M: Clone>(
+        &mut self,
+        assign: &stm::Assign<M, var::Symbol>,
+        meta: &M,
+    ) -> Result<(), M> {
+        // Discarded expressions have no side effects (yet).
+        let Some(lvalue) = &assign.lvalue else {
+            return Ok(());
+        };
+        let value = self.eval(&assign.rvalue.item);
+        self.assign_lvalue(&lvalue.item, value, meta)
+    }
+
+    /// Assigns `value` to `lvalue`, which is either a variable or an element of an array.
+    fn assign_lvalue<M: Clone>(
+        &mut self,
+        lvalue: &Expr<M, var::Symbol>,
+        value: Formula,
+        meta: &M,
+    ) -> Result<(), M> {
+        let (var, value) = self.lvalue_update(lvalue, value, meta)?;
+        self.assign(var, value);
+        Ok(())
+    }
+
+    /// Works out the effect of assigning `value` to `lvalue`, without performing it.
+    ///
+    /// Returns the variable underlying `lvalue`, and the value that variable would take.
+    fn lvalue_update<M: Clone>(
+        &mut self,
+        lvalue: &Expr<M, var::Symbol>,
+        value: Formula,
+        meta: &M,
+    ) -> Result<(var::Symbol, Formula), M> {
+        match lvalue {
+            Expr::Var(v) => Ok((v.item.clone(), value)),
+            Expr::Index { array, index } => {
+                let array_value = self.eval(array);
+                let index_value = self.eval(index);
+                self.access(array_value.clone(), index_value.clone());
+                self.lvalue_update(array, Expr::update(array_value, index_value, value), meta)
+            }
+            _ => Err(Error::BadLvalue { meta: meta.clone() }),
+        }
+    }
+
+    /// Executes a primitive call.
+    ///
+    /// The type checker has already checked the number and types of the arguments.
+    fn primitive<M: Clone>(
+        &mut self,
+        call: &stm::Primitive<M, var::Symbol>,
+        meta: &M,
+    ) -> Result<(), M> {
+        let args: Vec<_> = call.args.iter().map(|a| &a.item).collect();
+        match (call.primitive.item, args.as_slice()) {
+            (Primitive::Cas, [x, expected, new, ok]) => {
+                let old = self.eval(x);
+                let expected = self.eval(expected);
+                let new = self.eval(new);
+                let success = Expr::bop(old, Rel::Eq, expected);
+
+                let (var, updated) = self.lvalue_update(x, new, meta)?;
+                let current = self.value(&var);
+                let chosen = self.intermediate(&var);
+                self.assumptions.push(Expr::bop(
+                    Expr::bop(
+                        success.clone(),
+                        bop::Bool::Implies,
+                        Expr::bop(chosen.clone(), Rel::Eq, updated),
+                    ),
+                    bop::Bool::And,
+                    Expr::bop(
+                        Expr::not(success.clone()),
+                        bop::Bool::Implies,
+                        Expr::bop(chosen.clone(), Rel::Eq, current),
+                    ),
+                ));
+                self.assign(var, chosen);
+                self.assign_lvalue(ok, success, meta)
+            }
+            (Primitive::Dec, [x]) => {
+                let old = self.eval(x);
+                self.assign_lvalue(x, Expr::bop(old, bop::Arith::Sub, Expr::i64(1)), meta)
+            }
+            (Primitive::Error, []) => {
+                self.errors.push(self.assumptions.clone());
+                self.assumptions.push(Expr::bool(false));
+                Ok(())
+            }
+            (Primitive::Exchange, [x, new, old]) => {
+                let value = self.eval(x);
+                let new = self.eval(new);
+                self.assign_lvalue(x, new, meta)?;
+                self.assign_lvalue(old, value, meta)
+            }
+            (Primitive::FetchAdd, [x, delta, old]) => {
+                let value = self.eval(x);
+                let delta = self.eval(delta);
+                self.assign_lvalue(x, Expr::bop(value.clone(), bop::Arith::Add, delta), meta)?;
+                self.assign_lvalue(old, value, meta)
+            }
+            (Primitive::Inc, [x]) => {
+                let old = self.eval(x);
+                self.assign_lvalue(x, Expr::bop(old, bop::Arith::Add, Expr::i64(1)), meta)
+            }
+            (Primitive::TestAndSet, [x, old]) => {
+                let value = self.eval(x);
+                self.assign_lvalue(x, Expr::bool(true), meta)?;
+                self.assign_lvalue(old, value, meta)
+            }
+            _ => Err(Error::Unsupported {
+                what: "primitive calls with the wrong number of arguments",
+                meta: meta.clone(),
+            }),
+        }
+    }
+
+    /// Introduces a fresh intermediate value for `var`.
+    fn intermediate(&mut self, var: &var::Symbol) -> Formula {
+        let result = Expr::Var(Var::Inter(var.clone(), self.intermediates).into());
+        self.intermediates += 1;
+        result
+    }
+
+    fn list<M: Clone>(&mut self, list: &stm::List<'_, M, var::Symbol>) -> Result<(), M> {
+        for triple in list {
+            if let Some(a) = triple.item.pre.as_ref().or(triple.item.post.as_ref()) {
+                return Err(Error::AssertionInAtomic {
+                    meta: a.meta.clone(),
+                });
+            }
+            self.stm(&triple.item.stm.item, &triple.item.stm.meta)?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the transition relation of `commands`.
+///
+/// # Errors
+///
+/// Fails if the commands contain view assertions inside atomic blocks, assignments to things
+/// other than variables and array elements, or constructs the generator does not yet support.
+pub fn transition<M: Clone>(commands: &[Command<'_, M>]) -> Result<Transition, M> {
+    let mut result = Transition::default();
+    for command in commands {
+        match command {
+            Command::Assume(e) => {
+                let assumption = result.eval(e);
+                result.assumptions.push(assumption);
+            }
+            Command::Atomic(stm) => result.stm(&stm.item, &stm.meta)?,
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Computes the transition relation of the first atomic block in the script `body`, which
+    /// declares integers `x` and `y` and Boolean `z`.
+    fn relation(body: &str) -> Transition {
+        let source = format!(
+            "program T; var x : integer; var y : integer; var z : boolean; procedure P; begin {body} end; begin end."
+        );
+        let ast = crate::parser::parse(&source).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let stm = &program.procedures[0].body[0].item.stm;
+        assert!(
+            matches!(stm.item, Stm::Atomic(_)),
+            "expected an atomic block"
+        );
+        transition(&[Command::Atomic(stm.clone())]).unwrap()
+    }
+
+    fn vars(names: &[&str]) -> Vec<var::Symbol> {
+        names.iter().map(|n| var::Symbol::global(*n)).collect()
+    }
+
+    /// Tests sequential composition.
+    #[test]
+    fn atomic_sequence() {
+        let t = relation("<| x := x + 1; y := x; x := y * 2 |>");
+        assert_eq!(
+            "x!after = (x + 1) * 2 and y!after = x + 1",
+            t.formula(&vars(&["x", "y"])).to_string()
+        );
+    }
+
+    /// Tests that deterministic conditionals merge their branches.
+    #[test]
+    fn atomic_ite() {
+        let t = relation("<| if x = 0 then x := 1 else y := 2; z := true |>");
+        assert_eq!(
+            "(x = 0 and x!mid0 = 1 and y!mid1 = y or not (x = 0) and x!mid0 = x and y!mid1 = 2) and x!after = x!mid0 and y!after = y!mid1 and z!after = true",
+            t.formula(&vars(&["x", "y", "z"])).to_string()
+        );
+    }
+
+    /// Tests that nondeterministic conditionals merge their branches without a condition.
+    #[test]
+    fn atomic_nondeterministic() {
+        let t = relation("<| if * then x := 1 |>");
+        assert_eq!(
+            "(x!mid0 = 1 or x!mid0 = x) and x!after = x!mid0",
+            t.formula(&vars(&["x"])).to_string()
+        );
+    }
+}
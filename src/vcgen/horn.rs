@@ -0,0 +1,271 @@
+//! Emission of verification conditions as constrained Horn clauses.
+//!
+//! Indefinite constraints (`constraint V ?;`) leave the definitions of their patterns to be
+//! inferred.  Each such pattern becomes an uninterpreted predicate over its pattern variables and
+//! the global variables (see [globals](super::globals)), and each verification condition becomes
+//! one or more Horn clauses over those predicates, so that a CHC solver can search for definitions
+//! that make every condition hold.
+//!
+//! Conditions mention unknown instances under conditions from pattern matching (see
+//! [`Unknown`]).  An assumed instance `c => P(a)` is not Horn, so we split the condition into one
+//! clause in which `c` is false and one in which both `c` and `P(a)` hold.  The conclusion and
+//! each required instance give a clause apiece.
+//!
+//! The script is in the SMT-LIB `HORN` logic, and ends with `check-sat`: the conditions hold for
+//! some definitions if, and only if, the script is satisfiable.
+
+use std::{fmt::Write as _, io};
+
+use super::{
+    super::language::{ast::constraint, cfg, expr::Constant},
+    reify, smt2, Condition, Formula, Goal, Unknown, Var,
+};
+
+/// An uninterpreted predicate standing for the definition of an indefinite constraint pattern.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Predicate {
+    /// The index of the constraint.
+    pub constraint: usize,
+    /// The index of the pattern in the constraint.
+    pub pattern: usize,
+    /// The parameters of the predicate: the variables of the pattern, in order of first
+    /// appearance, followed by the global variables.
+    pub params: Vec<cfg::var::Symbol>,
+}
+
+impl Predicate {
+    /// Gets the SMT-LIB name of this predicate.
+    ///
+    /// This is the name of the goal view of its pattern, with a `!pred` suffix so that it can't
+    /// clash with that of a variable.
+    #[must_use]
+    pub fn name(&self) -> String {
+        predicate_name(self.constraint, self.pattern)
+    }
+}
+
+/// Gets the SMT-LIB name of the predicate for the pattern with index `pattern` in the constraint
+/// with index `constraint`.
+///
+/// PVC identifiers can't contain `!`, so the suffix keeps the name apart from those of variables.
+fn predicate_name(constraint: usize, pattern: usize) -> String {
+    let goal = Goal::Pattern {
+        constraint,
+        pattern,
+    };
+    format!("{goal}!pred")
+}
+
+/// Gets a predicate for every pattern of every indefinite constraint in `program`.
+#[must_use]
+pub fn predicates<M>(program: &cfg::Program<'_, M>) -> Vec<Predicate> {
+    let globals = super::globals(program);
+    let mut predicates = vec![];
+
+    for (c, decl) in program.constraints.iter().enumerate() {
+        if !matches!(decl.item.body.item, constraint::Constraint::Indefinite) {
+            continue;
+        }
+        for (p, pattern) in decl.item.views.iter().enumerate() {
+            let mut params = reify::binders(&pattern.item);
+            params.extend(globals.iter().cloned());
+            predicates.push(Predicate {
+                constraint: c,
+                pattern: p,
+                params,
+            });
+        }
+    }
+
+    predicates
+}
+
+/// Writes `conditions`, which belong to `program`, to `out` as a system of Horn clauses.
+///
+/// # Errors
+///
+/// Fails if writing to `out` fails, or if a condition uses a variable whose type or an operator
+/// that has no SMT-LIB counterpart yet.
+pub fn write<M>(
+    program: &cfg::Program<'_, M>,
+    conditions: &[Condition<M>],
+    out: &mut impl io::Write,
+) -> smt2::Result<()> {
+    let mut script = String::new();
+    writeln!(script, "(set-logic HORN)")?;
+
+    for predicate in predicates(program) {
+        let sorts = (predicate.params.iter())
+            .map(|p| smt2::sort(program, &Var::Goal(p.clone())))
+            .collect::<smt2::Result<Vec<_>>>()?;
+        writeln!(
+            script,
+            "(declare-fun {} ({}) Bool)",
+            predicate.name(),
+            sorts.join(" ")
+        )?;
+    }
+
+    for condition in conditions {
+        clauses(&mut script, program, condition)?;
+    }
+    writeln!(script, "(check-sat)")?;
+
+    out.write_all(script.as_bytes())?;
+    Ok(())
+}
+
+/// Writes the clauses for `condition` to `script`.
+fn clauses<M>(
+    script: &mut String,
+    program: &cfg::Program<'_, M>,
+    condition: &Condition<M>,
+) -> smt2::Result<()> {
+    let mut heads: Vec<(Vec<String>, String)> = vec![];
+    if !is_true(&condition.conclusion) {
+        let negated = format!("(not {})", smt2::term(&condition.conclusion)?);
+        heads.push((vec![negated], "false".to_owned()));
+    }
+    for unknown in &condition.required {
+        heads.push((guard(&unknown.condition)?, application(unknown)?));
+    }
+    if heads.is_empty() {
+        return Ok(());
+    }
+
+    let mut vars: Vec<&Var> = vec![];
+    condition.for_each_var(&mut |v| {
+        if !vars.contains(&v) {
+            vars.push(v);
+        }
+    });
+    let binders = vars
+        .into_iter()
+        .map(|v| Ok(format!("({} {})", smt2::symbol(v), smt2::sort(program, v)?)))
+        .collect::<smt2::Result<Vec<_>>>()?;
+
+    let mut hypotheses = vec![];
+    for hypothesis in &condition.hypotheses {
+        if !is_true(hypothesis) {
+            hypotheses.push(smt2::term(hypothesis)?);
+        }
+    }
+
+    writeln!(script, "; {}", condition.name())?;
+    for case in cases(&condition.assumed)? {
+        for (extra, head) in &heads {
+            let body: Vec<&String> = hypotheses.iter().chain(&case).chain(extra).collect();
+            let body = match body.as_slice() {
+                [] => "true".to_owned(),
+                [one] => (*one).clone(),
+                many => format!(
+                    "(and {})",
+                    many.iter()
+                        .map(|s| s.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                ),
+            };
+            let clause = format!("(=> {body} {head})");
+            if binders.is_empty() {
+                writeln!(script, "(assert {clause})")?;
+            } else {
+                writeln!(script, "(assert (forall ({}) {clause}))", binders.join(" "))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Gets the cases into which the assumed instances `assumed` split a clause, as the extra terms
+/// each case adds to the body.
+///
+/// Each instance either doesn't apply, or applies and holds.
+fn cases(assumed: &[Unknown]) -> smt2::Result<Vec<Vec<String>>> {
+    let mut cases = vec![vec![]];
+    for unknown in assumed {
+        let holds = application(unknown)?;
+        let mut next = vec![];
+        for case in cases {
+            if !is_true(&unknown.condition) {
+                let mut absent = case.clone();
+                absent.push(format!("(not {})", smt2::term(&unknown.condition)?));
+                next.push(absent);
+            }
+            let mut present = case;
+            present.extend(guard(&unknown.condition)?);
+            present.push(holds.clone());
+            next.push(present);
+        }
+        cases = next;
+    }
+    Ok(cases)
+}
+
+/// Renders `condition` as the terms it adds to a clause body.
+fn guard(condition: &Formula) -> smt2::Result<Vec<String>> {
+    Ok(if is_true(condition) {
+        vec![]
+    } else {
+        vec![smt2::term(condition)?]
+    })
+}
+
+/// Renders the application of the predicate of `unknown` to its arguments.
+fn application(unknown: &Unknown) -> smt2::Result<String> {
+    let name = predicate_name(unknown.constraint, unknown.pattern);
+    if unknown.args.is_empty() {
+        return Ok(name);
+    }
+    let args = (unknown.args.iter())
+        .map(smt2::term)
+        .collect::<smt2::Result<Vec<_>>>()?;
+    Ok(format!("({name} {})", args.join(" ")))
+}
+
+fn is_true(formula: &Formula) -> bool {
+    formula.as_literal() == Some(&Constant::Bool(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that indefinite patterns become predicates over their variables and the globals, and
+    /// that assumed instances split clauses.
+    #[test]
+    fn write_counter() {
+        let ast = crate::parser::parse(
+            "program Count;
+var x : integer;
+view V(n : integer);
+procedure P;
+begin
+  { [x > 0] V(1) } <| x := x + 1 |> { V(1) }
+end;
+constraint V(n) ?;
+begin end.",
+        )
+        .unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let conds = super::super::generate(&program).unwrap();
+
+        let mut out = vec![];
+        write(&program, &conds, &mut out).unwrap();
+        assert_eq!(
+            "(set-logic HORN)
+(declare-fun c0p0!pred (Int Int) Bool)
+; P_0_emp
+(assert (forall ((x!after Int) (x Int)) (=> (and (= x!after (+ x 1)) (not (> x 0))) (c0p0!pred 1 x!after))))
+(assert (forall ((x!after Int) (x Int)) (=> (and (= x!after (+ x 1)) (> x 0) (c0p0!pred 1 x)) (c0p0!pred 1 x!after))))
+; P_0_c0p0
+(assert (forall ((x!after Int) (x Int) (constraint0.n!goal Int)) (=> (and (= x!after (+ x 1)) (not (> x 0)) (c0p0!pred constraint0.n!goal x)) (c0p0!pred 1 x!after))))
+(assert (forall ((x!after Int) (x Int) (constraint0.n!goal Int)) (=> (and (= x!after (+ x 1)) (not (> x 0)) (c0p0!pred constraint0.n!goal x)) (c0p0!pred constraint0.n!goal x!after))))
+(assert (forall ((x!after Int) (x Int) (constraint0.n!goal Int)) (=> (and (= x!after (+ x 1)) (> x 0) (c0p0!pred 1 x) (c0p0!pred constraint0.n!goal x)) (c0p0!pred 1 x!after))))
+(assert (forall ((x!after Int) (x Int) (constraint0.n!goal Int)) (=> (and (= x!after (+ x 1)) (> x 0) (c0p0!pred 1 x) (c0p0!pred constraint0.n!goal x)) (c0p0!pred constraint0.n!goal x!after))))
+(check-sat)
+",
+            String::from_utf8(out).unwrap()
+        );
+    }
+}
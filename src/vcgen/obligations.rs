@@ -0,0 +1,188 @@
+//! Semantic well-formedness obligations on constraints.
+//!
+//! Some requirements on constraints can't be checked syntactically (see
+//! [`wellformed`](super::super::wellformed)), and so become side-conditions:
+//!
+//! - the definition of `emp` must be `true`, as `emp` is the unit of view composition: the body of
+//!   every constraint over the empty pattern must hold;
+//! - every iterated constraint must be well-formed (see below);
+//! - an exact definition supersedes the other constraints over the same pattern when reifying
+//!   conclusions (see [reify]), so it must imply each of them.
+//!
+//! A constraint pattern atom whose iterator is a variable or a wildcard, as in
+//! `constraint Arc[n] entails ...`, takes every occurrence of its view atom (see [reify]).
+//! Reification therefore instantiates the constraint once, for the total number of occurrences,
+//! rather than once for each smaller number.  This is only sound if the constraint is:
+//!
+//! - _downward closed_: its body for `n + 1` occurrences must imply its body for `n`;
+//! - true in its _base case_: the rest of its pattern, reified on its own, must imply its body for
+//!   zero occurrences.
+//!
+//! Each such atom gives one side-condition for each requirement; a wildcard iterator needs no
+//! downward closure, as the body cannot depend on it.
+//!
+//! Indefinite constraints have no definition yet, and so their side-conditions are over unknown
+//! instances, which only mean something to Horn clause inference (see [horn](super::horn)).
+
+use std::collections::HashMap;
+
+use super::{
+    super::language::{
+        cfg::{self, var, BlockRef},
+        expr::{
+            bop::{Arith, Rel},
+            Expr,
+        },
+        tagged::Tagged,
+    },
+    reify::{self, Demand, Pattern, Position},
+    view, Condition, Formula, Goal, Origin, Result, Unknown, Var,
+};
+
+/// Generates the side-conditions for every constraint in `program`.
+///
+/// Pattern variables become goal variables, and other variables refer to the pre-state.
+///
+/// # Errors
+///
+/// Fails if a constraint uses a pattern that the generator does not yet support.
+pub fn generate<M: Clone>(program: &cfg::Program<'_, M>) -> Result<Vec<Condition<M>>, M> {
+    let mut conds = vec![];
+
+    for (c, decl) in program.constraints.iter().enumerate() {
+        let body = reify::body(decl);
+
+        for (p, pattern) in decl.item.views.iter().enumerate() {
+            let condition = |goal, hypotheses, assumed, (conclusion, required)| Condition {
+                origin: Origin::Constraint,
+                goal,
+                meta: decl.meta.clone(),
+                pre: pattern.meta.clone(),
+                post: decl.item.body.meta.clone(),
+                hypotheses,
+                conclusion,
+                assumed,
+                required,
+            };
+
+            if let (Some(body), Some((d, q))) =
+                (&body, reify::definition(&program.constraints, c, p))
+            {
+                let definition = &program.constraints[d];
+                let renaming = reify::renaming(&definition.item.views[q].item, &pattern.item)
+                    .unwrap_or_default();
+                let bindings = (renaming.into_iter())
+                    .map(|(from, to)| (from, Expr::Var(Tagged::with_default(Var::Goal(to)))))
+                    .collect();
+                let hypothesis = reify::body(definition)
+                    .map(|d| reify::substitute(&bindings, d.as_ref(), state));
+                let goal = Goal::Definition {
+                    constraint: c,
+                    pattern: p,
+                };
+                let conclusion = reify::substitute(&HashMap::new(), body, state);
+                conds.push(condition(
+                    goal,
+                    hypothesis.into_iter().collect(),
+                    vec![],
+                    (conclusion, vec![]),
+                ));
+            }
+
+            if pattern.item.contents.is_empty() {
+                let goal = Goal::EmpDefinition {
+                    constraint: c,
+                    pattern: p,
+                };
+                let conclusion = instance(program, c, p, &HashMap::new());
+                conds.push(condition(goal, vec![], vec![], conclusion));
+                continue;
+            }
+
+            let prepared = Pattern::new(&pattern.item)?;
+            for (a, atom) in prepared.atoms.iter().enumerate() {
+                let Demand::Rest(var) = &atom.demand else {
+                    continue;
+                };
+                let instance = |count: Formula| {
+                    let bindings = var.iter().map(|v| (v.clone(), count.clone())).collect();
+                    instance(program, c, p, &bindings)
+                };
+
+                if let Some(var) = var {
+                    let n = Expr::Var(Tagged::with_default(Var::Goal(var.clone())));
+                    let goal = Goal::Downclosure {
+                        constraint: c,
+                        pattern: p,
+                        atom: a,
+                    };
+                    let (successor, assumed) =
+                        instance(Expr::bop(n.clone(), Arith::Add, Formula::i64(1)));
+                    let hypotheses = vec![
+                        Expr::bop(Formula::i64(0), Rel::LessEq, n.clone()),
+                        successor,
+                    ];
+                    conds.push(condition(goal, hypotheses, assumed, instance(n)));
+                }
+
+                let rest = view::goal_view(&prepared, Some(a))?;
+                let goal = Goal::Base {
+                    constraint: c,
+                    pattern: p,
+                    atom: a,
+                };
+                let mut hypotheses = vec![rest.reify(program, Var::Pre, Position::Hypothesis)?];
+                hypotheses.extend(rest.goal_bounds());
+                let assumed = rest.unknowns(program, Var::Pre)?;
+                conds.push(condition(
+                    goal,
+                    hypotheses,
+                    assumed,
+                    instance(Formula::i64(0)),
+                ));
+            }
+        }
+    }
+
+    Ok(conds)
+}
+
+/// Instantiates the body of the constraint with index `constraint` over its pattern with index
+/// `pattern`, substituting `bindings` for pattern variables and taking any others as goal
+/// variables.
+///
+/// An indefinite constraint has no body, and so instead gives an unknown instance, alongside a
+/// trivial formula.
+fn instance<M: Clone>(
+    program: &cfg::Program<'_, M>,
+    constraint: usize,
+    pattern: usize,
+    bindings: &HashMap<var::Symbol, Formula>,
+) -> (Formula, Vec<Unknown>) {
+    let decl = &program.constraints[constraint];
+    if let Some(body) = reify::body(decl) {
+        return (reify::substitute(bindings, &body, state), vec![]);
+    }
+
+    let var = |v: var::Symbol| Expr::Var(Tagged::with_default(state(v)));
+    let args = (reify::binders(&decl.item.views[pattern].item).into_iter())
+        .map(|v| bindings.get(&v).cloned().unwrap_or_else(|| var(v)))
+        .chain(super::globals(program).into_iter().map(var))
+        .collect();
+    let unknown = Unknown {
+        constraint,
+        pattern,
+        condition: Formula::bool(true),
+        args,
+    };
+    (Formula::bool(true), vec![unknown])
+}
+
+/// Interprets pattern variables as goal variables, and other variables in the pre-state.
+fn state(var: var::Symbol) -> Var {
+    if matches!(var.block, BlockRef::Constraint(_)) {
+        Var::Goal(var)
+    } else {
+        Var::Pre(var)
+    }
+}
@@ -0,0 +1,471 @@
+//! Proof outlines.
+//!
+//! A proof outline is a graph whose nodes are the view assertions of a procedure body, and whose
+//! edges are the (possibly empty) sequences of commands that take the procedure from one assertion
+//! to another.  Each edge contains at most one atomic command, along with any assumptions made
+//! on the way (for instance, by the conditions of if-then-else statements).
+//!
+//! Lowering walks the body with a _frontier_ of paths, each starting at an assertion and carrying
+//! the commands seen since.  Reaching an assertion closes every path in the frontier with an edge
+//! to that assertion, and leaves a single empty path starting there.  A procedure's pre-condition
+//! and post-condition, if it has them, are the first and last assertions of its body.
+//!
+//! Loops close the paths entering them at their invariant, which is then both the source of the
+//! paths through the loop body and the destination of the back-edges closing those paths.
+//!
+//! Calls to declared procedures are assert-assume pairs, and don't themselves become commands.
+//! Instead, a call closes every path in the frontier at the callee's pre-condition, then starts a
+//! single empty path at the callee's post-condition, in each case with the arguments of the call
+//! substituted for the callee's parameters; post-conditions therefore can't mention parameters
+//! their procedures assign to.  Calls don't frame: whatever the caller held before the call must be
+//! passed to the callee in its pre-condition, and the caller holds only the callee's post-condition
+//! afterwards.
+
+use super::{
+    super::language::{
+        ast::{call, ite, stm, view},
+        cfg::{self, var::Symbol},
+        expr::Expr,
+        tagged::Tagged,
+        var::Scope,
+    },
+    Error, Result,
+};
+
+/// A proof outline.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Outline<'inp, M> {
+    /// The assertions of the outline.
+    pub nodes: Vec<Node<'inp, M>>,
+    /// The edges of the outline.
+    pub edges: Vec<Edge<'inp, M>>,
+}
+
+/// A node in a proof outline.
+pub type Node<'inp, M> = Tagged<M, view::Assertion<'inp, M, Symbol>>;
+
+/// An edge in a proof outline.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Edge<'inp, M> {
+    /// The index of the source assertion.
+    pub src: usize,
+    /// The index of the destination assertion.
+    pub dst: usize,
+    /// The commands along the edge, in order.
+    pub commands: Vec<Command<'inp, M>>,
+    /// Metadata for the edge, pointing to its atomic command if it has one, or else its last
+    /// command or destination assertion.
+    pub meta: M,
+}
+
+/// A command on an edge of a proof outline.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Command<'inp, M> {
+    /// Assumes that an expression holds.
+    Assume(Expr<M, Symbol>),
+    /// Executes a statement atomically.
+    Atomic(Tagged<M, stm::Stm<'inp, M, Symbol>>),
+}
+
+/// Lowers the body of `proc`, a procedure in `program`, into a proof outline.
+///
+/// # Errors
+///
+/// Fails if the body contains commands not surrounded by assertions, atomic commands not
+/// separated by assertions, calls whose arguments mention shared variables, or calls to
+/// procedures whose post-conditions can't be instantiated with their arguments.
+pub fn lower<'inp, M: Clone>(
+    program: &cfg::Program<'inp, M>,
+    proc: &cfg::Procedure<'inp, M>,
+) -> Result<Outline<'inp, M>, M> {
+    let mut lowerer = Lowerer {
+        program,
+        outline: Outline {
+            nodes: vec![],
+            edges: vec![],
+        },
+    };
+    let mut frontier = vec![Path::default()];
+    if let Some(requires) = &proc.requires {
+        frontier = lowerer.assertion(requires, frontier)?;
+    }
+    frontier = lowerer.list(&proc.body, frontier)?;
+    match &proc.ensures {
+        Some(ensures) => {
+            lowerer.assertion(ensures, frontier)?;
+        }
+        None => finish(frontier)?,
+    }
+    Ok(lowerer.outline)
+}
+
+/// A partial path through the outline.
+#[derive(Clone)]
+struct Path<'inp, M> {
+    /// The assertion at which the path starts, if we have seen one yet.
+    src: Option<usize>,
+    /// The commands seen so far.
+    commands: Vec<Command<'inp, M>>,
+    /// Metadata for the most significant command seen so far.
+    meta: Option<M>,
+    /// Whether the path contains an atomic command.
+    atomic: bool,
+}
+
+/// The default path starts nowhere and has no commands.
+impl<M> Default for Path<'_, M> {
+    fn default() -> Self {
+        Self {
+            src: None,
+            commands: vec![],
+            meta: None,
+            atomic: false,
+        }
+    }
+}
+
+/// The set of paths currently being lowered.
+type Frontier<'inp, M> = Vec<Path<'inp, M>>;
+
+/// State for lowering a body.
+struct Lowerer<'a, 'inp, M> {
+    /// The program containing the body, used to look up the specifications of callees.
+    program: &'a cfg::Program<'inp, M>,
+    outline: Outline<'inp, M>,
+}
+
+impl<'inp, M: Clone> Lowerer<'_, 'inp, M> {
+    fn list(
+        &mut self,
+        list: &stm::List<'inp, M, Symbol>,
+        mut frontier: Frontier<'inp, M>,
+    ) -> Result<Frontier<'inp, M>, M> {
+        for triple in list {
+            frontier = self.triple(&triple.item, frontier)?;
+        }
+        Ok(frontier)
+    }
+
+    fn triple(
+        &mut self,
+        triple: &stm::Triple<'inp, M, Symbol>,
+        mut frontier: Frontier<'inp, M>,
+    ) -> Result<Frontier<'inp, M>, M> {
+        if let Some(pre) = &triple.pre {
+            frontier = self.assertion(pre, frontier)?;
+        }
+        frontier = self.stm(&triple.stm, frontier)?;
+        if let Some(post) = &triple.post {
+            frontier = self.assertion(post, frontier)?;
+        }
+        Ok(frontier)
+    }
+
+    /// Closes every path in `frontier` at `assertion`.
+    fn assertion(
+        &mut self,
+        assertion: &Node<'inp, M>,
+        frontier: Frontier<'inp, M>,
+    ) -> Result<Frontier<'inp, M>, M> {
+        let dst = self.node(assertion);
+        self.close(frontier, dst)
+    }
+
+    /// Adds `assertion` to the outline as a new node, returning its index.
+    fn node(&mut self, assertion: &Node<'inp, M>) -> usize {
+        self.outline.nodes.push(assertion.clone());
+        self.outline.nodes.len() - 1
+    }
+
+    /// Closes every path in `frontier` at the node with index `dst`, leaving a single empty path
+    /// starting there.
+    fn close(&mut self, frontier: Frontier<'inp, M>, dst: usize) -> Result<Frontier<'inp, M>, M> {
+        for path in frontier {
+            match path.src {
+                Some(src) => self.outline.edges.push(Edge {
+                    src,
+                    dst,
+                    commands: path.commands,
+                    meta: path
+                        .meta
+                        .unwrap_or_else(|| self.outline.nodes[dst].meta.clone()),
+                }),
+                // The first assertion in a body starts the first path; we can only get here with
+                // commands if there were commands before that assertion.
+                None if path.commands.is_empty() => (),
+                None => {
+                    return Err(Error::MissingAssertion {
+                        meta: path
+                            .meta
+                            .unwrap_or_else(|| self.outline.nodes[dst].meta.clone()),
+                    })
+                }
+            }
+        }
+
+        Ok(start(dst))
+    }
+
+    fn stm(
+        &mut self,
+        stm: &Tagged<M, stm::Stm<'inp, M, Symbol>>,
+        frontier: Frontier<'inp, M>,
+    ) -> Result<Frontier<'inp, M>, M> {
+        match &stm.item {
+            stm::Stm::Block(list) => self.list(list, frontier),
+            stm::Stm::Ite(i) => self.ite(i, frontier),
+            stm::Stm::Nop => Ok(frontier),
+            stm::Stm::Repeat(r) => self.repeat(r, frontier),
+            stm::Stm::While(w) => self.while_loop(w, frontier),
+            stm::Stm::Call(c) => match self.program.procedure(egg::Symbol::new(&c.name.item)) {
+                Some(callee) => self.call(c, callee, &stm.meta, frontier),
+                // The binder turns calls to anything else into primitive calls, so this only
+                // happens in programs built by other means; the transition relation rejects it.
+                None => frontier.into_iter().map(|path| path.atomic(stm)).collect(),
+            },
+            // Everything else is a single atomic command.
+            _ => frontier.into_iter().map(|path| path.atomic(stm)).collect(),
+        }
+    }
+
+    /// Lowers `call`, found at `meta`, to the procedure `callee`.
+    fn call(
+        &mut self,
+        call: &call::Call<'inp, M, Symbol>,
+        callee: &cfg::Procedure<'inp, M>,
+        meta: &M,
+        frontier: Frontier<'inp, M>,
+    ) -> Result<Frontier<'inp, M>, M> {
+        // Arguments are substituted into the post-condition too, so they must mean the same
+        // thing after the call as before it; other threads may change shared variables meanwhile.
+        for arg in &call.args {
+            let mut shared = false;
+            arg.item.for_each_var(&mut |v| {
+                shared |= self
+                    .program
+                    .variables
+                    .get_exact(v)
+                    .is_some_and(|r| r.scope == Scope::Shared);
+            });
+            if shared {
+                return Err(Error::Unsupported {
+                    what: "shared variables in call arguments",
+                    meta: arg.meta.clone(),
+                });
+            }
+        }
+
+        if let Some(ensures) = &callee.ensures {
+            check_ensures(callee, &ensures.item)?;
+        }
+
+        let instantiate = |spec: &stm::TripleAssertion<'inp, M, Symbol>| {
+            let spec = spec.as_ref().map_or(view::Assertion::Emp, |s| {
+                s.item.clone().subst(&mut |v| {
+                    let param = callee.parameters.iter().position(|p| *p == v.item);
+                    match param.and_then(|i| call.args.get(i)) {
+                        Some(arg) => arg.item.clone(),
+                        None => Expr::Var(v),
+                    }
+                })
+            });
+            Tagged::new(meta.clone(), spec)
+        };
+
+        self.assertion(&instantiate(&callee.requires), frontier)?;
+        Ok(start(self.node(&instantiate(&callee.ensures))))
+    }
+
+    /// Lowers a while loop.
+    ///
+    /// The loop closes every path in `frontier` at its invariant, then runs its body from there
+    /// under the loop condition, and closes every path out of the body back at the invariant.
+    /// Paths leave the loop from the invariant, under the negated condition.
+    fn while_loop(
+        &mut self,
+        w: &stm::While<'inp, M, Symbol>,
+        frontier: Frontier<'inp, M>,
+    ) -> Result<Frontier<'inp, M>, M> {
+        let invariant = self.node(&w.invariant);
+        let entry = self.close(frontier, invariant)?;
+        let (body, exit) = split(entry, &w.cond);
+        let body = self.stm(&w.body.clone().map(|b| *b), body)?;
+        self.close(body, invariant)?;
+        Ok(exit)
+    }
+
+    /// Lowers a repeat-until loop.
+    ///
+    /// The loop closes every path in `frontier` at its invariant, then runs its body from there.
+    /// Paths out of the body leave the loop under the loop condition, and otherwise go back to
+    /// the invariant.
+    fn repeat(
+        &mut self,
+        r: &stm::Repeat<'inp, M, Symbol>,
+        frontier: Frontier<'inp, M>,
+    ) -> Result<Frontier<'inp, M>, M> {
+        let invariant = self.node(&r.invariant);
+        let entry = self.close(frontier, invariant)?;
+        let body = self.list(&r.body, entry)?;
+        let (exit, again) = split(body, &r.cond);
+        self.close(again, invariant)?;
+        Ok(exit)
+    }
+
+    fn ite(
+        &mut self,
+        ite: &stm::Ite<'inp, M, Symbol>,
+        frontier: Frontier<'inp, M>,
+    ) -> Result<Frontier<'inp, M>, M> {
+        let (true_paths, false_paths) = split(frontier, &ite.cond);
+        let mut frontier = self.stm(&ite.true_branch.clone().map(|b| *b), true_paths)?;
+        frontier.extend(self.stm(&ite.false_branch.clone().map(|b| *b), false_paths)?);
+        Ok(frontier)
+    }
+}
+
+impl<'inp, M: Clone> Path<'inp, M> {
+    /// Adds the atomic command `stm` to this path.
+    fn atomic(mut self, stm: &Tagged<M, stm::Stm<'inp, M, Symbol>>) -> Result<Self, M> {
+        if self.atomic {
+            return Err(Error::AdjacentAtomics {
+                meta: stm.meta.clone(),
+            });
+        }
+        self.atomic = true;
+        self.meta = Some(stm.meta.clone());
+        self.commands.push(Command::Atomic(stm.clone()));
+        Ok(self)
+    }
+}
+
+/// Checks that `ensures`, the post-condition of `callee`, means the same thing when instantiated
+/// with the arguments of a call.
+///
+/// We substitute the arguments for the parameters, so the post-condition can't mention parameters
+/// the callee assigns to, whose final values may differ from the arguments.  Nor can it mention the
+/// callee's local variables, which aren't in scope at the call.  The binder doesn't let
+/// post-conditions see local variables, so the latter only happens in programs built by other
+/// means.
+fn check_ensures<'inp, M: Clone>(
+    callee: &cfg::Procedure<'inp, M>,
+    ensures: &view::Assertion<'inp, M, Symbol>,
+) -> Result<(), M> {
+    let mut assigned = vec![];
+    assignments(&callee.body, &mut assigned);
+
+    let mut result = Ok(());
+    ensures.for_each_tagged_var(&mut |v| {
+        if result.is_err()
+            || !matches!(&v.item.block, cfg::BlockRef::Proc(p, _) if *p == callee.name)
+        {
+            return;
+        }
+        let what = if !callee.parameters.contains(&v.item) {
+            "post-conditions mentioning local variables"
+        } else if assigned.contains(&&v.item) {
+            "post-conditions mentioning parameters the procedure assigns to"
+        } else {
+            return;
+        };
+        result = Err(Error::Unsupported {
+            what,
+            meta: v.meta.clone(),
+        });
+    });
+    result
+}
+
+/// Collects every variable that `list` assigns to, directly or through array subscripts, into
+/// `assigned`.
+fn assignments<'a, M>(list: &'a stm::List<'_, M, Symbol>, assigned: &mut Vec<&'a Symbol>) {
+    for triple in list {
+        assignments_stm(&triple.item.stm.item, assigned);
+    }
+}
+
+/// Collects every variable that `stm` assigns to into `assigned`.
+fn assignments_stm<'a, M>(stm: &'a stm::Stm<'_, M, Symbol>, assigned: &mut Vec<&'a Symbol>) {
+    match stm {
+        stm::Stm::Assign(a) => assigned.extend(a.lvalue.as_ref().and_then(|l| root(&l.item))),
+        stm::Stm::Atomic(stm::Atomic { body: l, .. }) | stm::Stm::Block(l) => {
+            assignments(l, assigned);
+        }
+        stm::Stm::Call(_) | stm::Stm::Nop => (),
+        stm::Stm::Ite(i) => {
+            assignments_stm(&i.true_branch.item, assigned);
+            assignments_stm(&i.false_branch.item, assigned);
+        }
+        stm::Stm::Primitive(p) => {
+            let params = p.primitive.item.parameters();
+            for (param, arg) in params.iter().zip(&p.args) {
+                if param.assigned {
+                    assigned.extend(root(&arg.item));
+                }
+            }
+        }
+        stm::Stm::Repeat(r) => assignments(&r.body, assigned),
+        stm::Stm::While(w) => assignments_stm(&w.body.item, assigned),
+    }
+}
+
+/// Gets the variable that assigning to `lvalue` changes, if any.
+///
+/// Assigning through a pointer changes what it points to, not the pointer itself.
+fn root<M>(lvalue: &Expr<M, Symbol>) -> Option<&Symbol> {
+    match lvalue {
+        Expr::Var(v) => Some(&v.item),
+        Expr::Index { array, .. } => root(array),
+        _ => None,
+    }
+}
+
+/// Starts a single empty path at the node with index `src`.
+fn start<'inp, M>(src: usize) -> Frontier<'inp, M> {
+    vec![Path {
+        src: Some(src),
+        ..Path::default()
+    }]
+}
+
+/// Splits `frontier` into the paths on which `cond` holds and those on which it doesn't.
+fn split<'inp, M: Clone>(
+    frontier: Frontier<'inp, M>,
+    cond: &Tagged<M, ite::Condition<M, Symbol>>,
+) -> (Frontier<'inp, M>, Frontier<'inp, M>) {
+    match &cond.item {
+        ite::Condition::Nondeterministic => (frontier.clone(), frontier),
+        ite::Condition::Deterministic(c) => (
+            assume(frontier.clone(), c, &cond.meta),
+            assume(frontier, &Expr::not(c.clone()), &cond.meta),
+        ),
+    }
+}
+
+/// Adds an assumption of `cond` to every path in `frontier`.
+fn assume<'inp, M: Clone>(
+    frontier: Frontier<'inp, M>,
+    cond: &Expr<M, Symbol>,
+    meta: &M,
+) -> Frontier<'inp, M> {
+    frontier
+        .into_iter()
+        .map(|mut path| {
+            path.commands.push(Command::Assume(cond.clone()));
+            if !path.atomic {
+                path.meta = Some(meta.clone());
+            }
+            path
+        })
+        .collect()
+}
+
+/// Checks that the frontier at the end of a body has no dangling commands.
+fn finish<M>(frontier: Frontier<'_, M>) -> Result<(), M> {
+    match frontier.into_iter().find(|p| !p.commands.is_empty()) {
+        Some(Path {
+            meta: Some(meta), ..
+        }) => Err(Error::MissingAssertion { meta }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,750 @@
+//! Reification of views through constraints.
+//!
+//! Reification turns a flattened view into the formula it denotes: for every constraint, and every
+//! way of matching one of its patterns against a sub-multiset of the view's atoms, we instantiate
+//! the constraint's body and make it conditional on the match.
+//!
+//! Each atom of a flattened view is present some number of times, given by its iterator.  One
+//! atom of the view can therefore match several atoms of a pattern, as long as it is present often
+//! enough for all of them.  A pattern atom whose iterator is a variable or a wildcard, as in
+//! `constraint Arc[n] entails ...`, takes every occurrence of its view atom that the rest of the
+//! pattern leaves over, binding the variable to their number.  Atoms of the view that differ
+//! syntactically may still be equal, so we split such matches into cases over which other atoms
+//! are equal to the matched one, adding up their occurrences.  Symbolic iterators may be
+//! negative, so we also require the number to be non-negative.  Taking every occurrence at once is
+//! only sound for well-formed constraints (see [`super::obligations`]).
+//!
+//! Wildcard arguments match anything.
+//!
+//! Each form of constraint body reifies differently:
+//!
+//! - `entails e` and `= e` instantiate `e`;
+//! - `fails` instantiates `false`, so that the view can never hold;
+//! - `?` has no definition until one is inferred, and so instantiates nothing, as if it were
+//!   `entails true`.
+//!
+//! An exact definition `= e` gives the whole meaning of its pattern.  When reifying a view that
+//! must be established, rather than assumed, it therefore supersedes every other constraint over
+//! the same pattern, up to renaming of pattern variables; a side-condition checks that it implies
+//! each of them (see [`super::obligations`]).
+
+use std::{borrow::Cow, collections::HashMap};
+
+use egg::Symbol;
+use num_bigint::{BigInt, Sign};
+use num_traits::Zero;
+
+use super::{
+    super::language::{
+        ast::{constraint, view::pattern},
+        cfg::{self, var, BlockRef},
+        expr::{
+            bop::{Arith, Rel},
+            map::HasMeta,
+            Constant, Expr,
+        },
+        tagged::Tagged,
+    },
+    view::{implies, View},
+    Error, Formula, Result, Unknown, Var,
+};
+
+/// Where a reified view appears in a condition.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Position {
+    /// The view is assumed, as is the pre-condition of a command.
+    Hypothesis,
+    /// The view must be established, as must the post-condition of a command.
+    Conclusion,
+}
+
+/// Reifies `view` through `constraints`, for use at `position`.
+///
+/// Variables in constraint bodies that are not bound by the constraint's pattern are interpreted
+/// using `state`.
+///
+/// # Errors
+///
+/// Fails if a constraint uses a pattern that the generator does not yet support.
+pub fn reify<M: Clone>(
+    view: &View,
+    constraints: &[Tagged<M, cfg::Constraint<M>>],
+    state: fn(var::Symbol) -> Var,
+    position: Position,
+) -> Result<Formula, M> {
+    let mut conjuncts = view.local.clone();
+
+    for (c, decl) in constraints.iter().enumerate() {
+        let Some(body) = body(decl) else {
+            continue;
+        };
+        for (p, pattern) in decl.item.views.iter().enumerate() {
+            if position == Position::Conclusion && definition(constraints, c, p).is_some() {
+                continue;
+            }
+            let pattern = Pattern::new(&pattern.item)?;
+            pattern.matches(view, 0, &mut Match::default(), &mut |m| {
+                m.instantiate(view, state, &mut |conditions, bindings| {
+                    let conjunct = implies(conditions, substitute(bindings, &body, state));
+                    // Symmetric patterns match the same atoms more than once.
+                    if !conjuncts.contains(&conjunct) {
+                        conjuncts.push(conjunct);
+                    }
+                });
+            });
+        }
+    }
+
+    Ok(Formula::conjunction(conjuncts))
+}
+
+/// Finds the instances of indefinite constraints in `view`.
+///
+/// Each instance binds the variables of its pattern, in order of appearance, followed by the
+/// variables in `globals`, which are interpreted using `state`.
+///
+/// # Errors
+///
+/// Fails if a constraint uses a pattern that the generator does not yet support.
+pub fn unknowns<M: Clone>(
+    view: &View,
+    constraints: &[Tagged<M, cfg::Constraint<M>>],
+    globals: &[var::Symbol],
+    state: fn(var::Symbol) -> Var,
+) -> Result<Vec<Unknown>, M> {
+    let mut unknowns = vec![];
+
+    for (c, decl) in constraints.iter().enumerate() {
+        if !matches!(decl.item.body.item, constraint::Constraint::Indefinite) {
+            continue;
+        }
+        for (p, pattern) in decl.item.views.iter().enumerate() {
+            let vars = binders(&pattern.item);
+            let prepared = Pattern::new(&pattern.item)?;
+            prepared.matches(view, 0, &mut Match::default(), &mut |m| {
+                m.instantiate(view, state, &mut |conditions, bindings| {
+                    let args = (vars.iter())
+                        .map(|v| bindings[v].clone())
+                        .chain(
+                            globals
+                                .iter()
+                                .map(|g| Expr::Var(Tagged::new((), state(g.clone())))),
+                        )
+                        .collect();
+                    let unknown = Unknown {
+                        constraint: c,
+                        pattern: p,
+                        condition: conditions.clone(),
+                        args,
+                    };
+                    if !unknowns.contains(&unknown) {
+                        unknowns.push(unknown);
+                    }
+                });
+            });
+        }
+    }
+
+    Ok(unknowns)
+}
+
+/// Gets the body of `decl` as an expression, or `None` if it is indefinite.
+pub(super) fn body<M: Clone>(
+    decl: &Tagged<M, cfg::Constraint<M>>,
+) -> Option<Cow<'_, Expr<M, var::Symbol>>> {
+    let body = &decl.item.body;
+    match &body.item {
+        constraint::Constraint::Indefinite => None,
+        constraint::Constraint::Fails => Some(Cow::Owned(Expr::Literal(Tagged::new(
+            body.meta.clone(),
+            Constant::Bool(false),
+        )))),
+        other => other.expr().map(|e| Cow::Borrowed(&e.item)),
+    }
+}
+
+/// Gets the exact definition that supersedes the pattern with index `pattern` of the constraint
+/// with index `constraint`, as the indices of its constraint and pattern.
+///
+/// The first exact definition over a pattern supersedes every other constraint with a body over
+/// the same pattern, including any later exact definitions.
+pub(super) fn definition<M: Clone>(
+    constraints: &[Tagged<M, cfg::Constraint<M>>],
+    constraint: usize,
+    pattern: usize,
+) -> Option<(usize, usize)> {
+    let decl = &constraints[constraint];
+    if matches!(decl.item.body.item, constraint::Constraint::Indefinite) {
+        return None;
+    }
+    let target = &decl.item.views[pattern].item;
+
+    (constraints.iter().enumerate())
+        .filter(|(_, d)| matches!(d.item.body.item, constraint::Constraint::Exact(_)))
+        .flat_map(|(d, decl)| (decl.item.views.iter().enumerate()).map(move |(q, p)| (d, q, p)))
+        .find(|(_, _, p)| renaming(&p.item, target).is_some())
+        .map(|(d, q, _)| (d, q))
+        .filter(|found| *found != (constraint, pattern))
+}
+
+/// Gets the renaming of pattern variables that makes pattern `from` equal to pattern `to`, ignoring
+/// metadata, if there is one.
+pub(super) fn renaming<M: Clone>(
+    from: &pattern::Pattern<M, var::Symbol>,
+    to: &pattern::Pattern<M, var::Symbol>,
+) -> Option<HashMap<var::Symbol, var::Symbol>> {
+    let (sources, targets) = (binders(from), binders(to));
+    if sources.len() != targets.len() {
+        return None;
+    }
+    let renaming: HashMap<_, _> = sources.into_iter().zip(targets).collect();
+    (erase(from, &renaming) == erase(to, &HashMap::new())).then_some(renaming)
+}
+
+/// Gets the variables bound by `pattern`, in order of first appearance.
+pub(super) fn binders<M>(pattern: &pattern::Pattern<M, var::Symbol>) -> Vec<var::Symbol> {
+    let mut vars = vec![];
+    for atom in &pattern.contents {
+        for arg in atom.item.args.iter().chain([&atom.item.iterator]) {
+            match &arg.item {
+                pattern::Argument::Expr(Expr::Var(v))
+                    if matches!(v.item.block, BlockRef::Constraint(_))
+                        && !vars.contains(&v.item) =>
+                {
+                    vars.push(v.item.clone());
+                }
+                _ => (),
+            }
+        }
+    }
+    vars
+}
+
+/// An atom of a pattern with its metadata erased: its name, its arguments, and its iterator, with
+/// `None` standing for a wildcard.
+type ErasedAtom = (
+    Symbol,
+    Vec<Option<Expr<(), var::Symbol>>>,
+    Option<Expr<(), var::Symbol>>,
+);
+
+/// Erases the metadata of `pattern`, renaming its variables with `renaming`.
+fn erase<M: Clone>(
+    pattern: &pattern::Pattern<M, var::Symbol>,
+    renaming: &HashMap<var::Symbol, var::Symbol>,
+) -> Vec<ErasedAtom> {
+    let arg = |arg: &Tagged<M, pattern::Argument<M, var::Symbol>>| match &arg.item {
+        pattern::Argument::Wildcard => None,
+        pattern::Argument::Expr(e) => Some(
+            e.clone()
+                .map_meta(|_| ())
+                .subst(&mut |v| Expr::Var(v.map(|s| renaming.get(&s).cloned().unwrap_or(s)))),
+        ),
+    };
+    (pattern.contents.iter())
+        .map(|atom| {
+            (
+                atom.item.name.item.name,
+                atom.item.args.iter().map(arg).collect(),
+                arg(&atom.item.iterator),
+            )
+        })
+        .collect()
+}
+
+/// A constraint pattern, prepared for matching.
+pub(super) struct Pattern<'a, M> {
+    pub(super) atoms: Vec<PatternAtom<'a, M>>,
+}
+
+/// An atom in a prepared constraint pattern.
+pub(super) struct PatternAtom<'a, M> {
+    pub(super) name: Symbol,
+    /// The arguments of the atom, with `None` standing for a wildcard.
+    ///
+    /// Arguments carry their metadata for error reporting.
+    pub(super) args: Vec<(Option<&'a Expr<M, var::Symbol>>, M)>,
+    /// How many occurrences of a view atom this atom matches.
+    pub(super) demand: Demand,
+    /// Metadata for the iterator, for error reporting.
+    pub(super) iterator: M,
+}
+
+/// How many occurrences of a view atom a pattern atom matches.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(super) enum Demand {
+    /// A fixed number of occurrences.
+    Count(BigInt),
+    /// Every occurrence left over by the rest of the pattern, binding the given variable, if any,
+    /// to their number.
+    Rest(Option<var::Symbol>),
+}
+
+impl<'a, M: Clone> Pattern<'a, M> {
+    /// Prepares `pattern` for matching.
+    pub(super) fn new(pattern: &'a pattern::Pattern<M, var::Symbol>) -> Result<Self, M> {
+        let atoms: Vec<_> = pattern
+            .contents
+            .iter()
+            .map(|a| PatternAtom::new(&a.item))
+            .collect::<Result<_, M>>()?;
+
+        // We can't split the occurrences of a view atom between several variables.
+        for (i, atom) in atoms.iter().enumerate() {
+            let rest = |a: &PatternAtom<M>| matches!(a.demand, Demand::Rest(_));
+            if rest(atom) && atoms[..i].iter().any(|a| a.name == atom.name && rest(a)) {
+                return Err(Error::Unsupported {
+                    what: "several iterated atoms of the same view in one pattern",
+                    meta: atom.iterator.clone(),
+                });
+            }
+        }
+        Ok(Self { atoms })
+    }
+}
+
+impl<'a, M: Clone> PatternAtom<'a, M> {
+    /// Prepares `atom` for matching.
+    fn new(atom: &'a pattern::Atom<M, var::Symbol>) -> Result<Self, M> {
+        let unsupported = |what| Error::Unsupported {
+            what,
+            meta: atom.iterator.meta.clone(),
+        };
+        let demand = match &atom.iterator.item {
+            pattern::Argument::Wildcard => Demand::Rest(None),
+            pattern::Argument::Expr(Expr::Var(v))
+                if matches!(v.item.block, BlockRef::Constraint(_)) =>
+            {
+                Demand::Rest(Some(v.item.clone()))
+            }
+            pattern::Argument::Expr(e) => match e.as_literal() {
+                Some(Constant::Int(i)) if i.sign() != Sign::Minus => Demand::Count(i.clone()),
+                Some(Constant::Int(_)) => return Err(unsupported("negative iterators")),
+                _ => {
+                    return Err(unsupported(
+                        "iterator patterns other than literals, variables, and wildcards",
+                    ))
+                }
+            },
+        };
+
+        Ok(Self {
+            name: atom.name.item.name,
+            args: atom
+                .args
+                .iter()
+                .map(|arg| {
+                    let expr = match &arg.item {
+                        pattern::Argument::Expr(e) => Some(e),
+                        pattern::Argument::Wildcard => None,
+                    };
+                    (expr, arg.meta.clone())
+                })
+                .collect(),
+            demand,
+            iterator: atom.iterator.meta.clone(),
+        })
+    }
+}
+
+impl<'a, M> Pattern<'a, M> {
+    /// Calls `f` on every way of matching the atoms of this pattern from index `index` onwards
+    /// to atoms of `view`.
+    ///
+    /// Matches may use view atoms more often than they are present; [`Match::instantiate`]
+    /// discards or guards against these.
+    fn matches(
+        &'a self,
+        view: &View,
+        index: usize,
+        matching: &mut Match<'a, M>,
+        f: &mut impl FnMut(&Match<'a, M>),
+    ) {
+        let Some(pattern) = self.atoms.get(index) else {
+            f(matching);
+            return;
+        };
+
+        for (i, candidate) in view.atoms.iter().enumerate() {
+            let candidate = &candidate.item.item;
+            if candidate.name != pattern.name || candidate.args.len() != pattern.args.len() {
+                continue;
+            }
+
+            let mut attempt = matching.clone();
+            attempt.uses.push((i, &pattern.demand));
+            for ((arg, _), actual) in pattern.args.iter().zip(&candidate.args) {
+                match arg {
+                    None => (),
+                    Some(Expr::Var(v)) if matches!(v.item.block, BlockRef::Constraint(_)) => {
+                        attempt.bindings.insert(v.item.clone(), actual.clone());
+                    }
+                    Some(e) => attempt.equalities.push((e, actual.clone())),
+                }
+            }
+
+            self.matches(view, index + 1, &mut attempt, f);
+        }
+    }
+}
+
+/// A (partial) match of a constraint pattern against a view.
+struct Match<'a, M> {
+    /// The formulae bound to each pattern variable.
+    bindings: HashMap<var::Symbol, Formula>,
+    /// The index of the view atom matched by each pattern atom so far, with the pattern atom's
+    /// demand on it.
+    uses: Vec<(usize, &'a Demand)>,
+    /// Pattern arguments that are not variables, with the formulae they must equal.
+    equalities: Vec<(&'a Expr<M, var::Symbol>, Formula)>,
+}
+
+impl<M> Default for Match<'_, M> {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            uses: vec![],
+            equalities: vec![],
+        }
+    }
+}
+
+impl<M> Clone for Match<'_, M> {
+    fn clone(&self) -> Self {
+        Self {
+            bindings: self.bindings.clone(),
+            uses: self.uses.clone(),
+            equalities: self.equalities.clone(),
+        }
+    }
+}
+
+impl<M: Clone> Match<'_, M> {
+    /// Instantiates this match against `view`, calling `f` on the conditions and the variable
+    /// bindings of each instance.
+    ///
+    /// The conditions are that every matched atom of `view` is present, and present often enough
+    /// for every pattern atom it matched.  Instances that can never hold, because they use an atom
+    /// with a literal iterator too often, are discarded.
+    ///
+    /// A pattern atom with a rest demand takes the occurrences of every atom of `view` equal to
+    /// the one it matched, so the match has one instance for each choice of which other atoms of
+    /// `view` are equal.  Only later atoms may be chosen: choosing an earlier one gives the same
+    /// instance as the match on that atom.
+    fn instantiate(
+        &self,
+        view: &View,
+        state: fn(var::Symbol) -> Var,
+        f: &mut impl FnMut(&Formula, &HashMap<var::Symbol, Formula>),
+    ) {
+        let rests: Vec<(usize, Option<&var::Symbol>)> = (self.uses.iter())
+            .filter_map(|(i, demand)| match demand {
+                Demand::Rest(var) => Some((*i, var.as_ref())),
+                Demand::Count(_) => None,
+            })
+            .collect();
+
+        let mut choices: Vec<Vec<Vec<usize>>> = vec![vec![]];
+        for (i, _) in &rests {
+            let later: Vec<usize> = similar(view, *i).filter(|j| j > i).collect();
+            choices = (choices.into_iter())
+                .flat_map(|choice| {
+                    subsets(&later).map(move |chosen| {
+                        let mut choice = choice.clone();
+                        choice.push(chosen);
+                        choice
+                    })
+                })
+                .collect();
+        }
+
+        for choice in choices {
+            let groups: Vec<_> = (rests.iter().zip(choice))
+                .map(|((i, var), later)| {
+                    let mut group = vec![*i];
+                    group.extend(later);
+                    (group, *var)
+                })
+                .collect();
+            if let Some((conditions, bindings)) = self.instance(view, state, &groups) {
+                f(&conditions, &bindings);
+            }
+        }
+    }
+
+    /// Instantiates this match against `view`, where each of `groups` lists atoms of `view` that
+    /// are equal, together with the variable, if any, of the rest demand on them.
+    ///
+    /// The instance consists of its conditions and its bindings of pattern variables.
+    fn instance(
+        &self,
+        view: &View,
+        state: fn(var::Symbol) -> Var,
+        groups: &[(Vec<usize>, Option<&var::Symbol>)],
+    ) -> Option<(Formula, HashMap<var::Symbol, Formula>)> {
+        let mut bindings = self.bindings.clone();
+        let mut conditions = vec![];
+
+        let mut atoms: Vec<usize> = vec![];
+        for (i, _) in &self.uses {
+            if !atoms.contains(i) {
+                atoms.push(*i);
+            }
+        }
+        for i in atoms {
+            let atom = &view.atoms[i];
+            conditions.push(atom.guard.clone());
+            if !groups.iter().any(|(group, _)| group.contains(&i)) {
+                let fixed = self.fixed(&[i]);
+                condition(&mut conditions, at_most(&fixed, &atom.item.iterator))?;
+            }
+        }
+
+        for (group, var) in groups {
+            let first = group[0];
+            for j in similar(view, first) {
+                let equal = equal(view, first, j);
+                if group.contains(&j) {
+                    condition(&mut conditions, equal)?;
+                } else {
+                    condition(&mut conditions, negate(equal))?;
+                }
+            }
+
+            // Symbolic iterators may be negative, in which case their atoms are absent.
+            let count = sum(group.iter().map(|j| &view.atoms[*j].item.iterator));
+            let fixed = self.fixed(group);
+            condition(&mut conditions, at_least(&count, &fixed))?;
+            if let Some(var) = var {
+                bindings.insert((*var).clone(), minus(&count, &fixed));
+            }
+        }
+
+        let conditions = conditions
+            .into_iter()
+            .chain(self.equalities.iter().map(|(e, actual)| {
+                Expr::bop(substitute(&bindings, e, state), Rel::Eq, actual.clone())
+            }));
+        Some((Formula::conjunction(conditions), bindings))
+    }
+
+    /// Gets the number of occurrences that fixed demands take from the atoms of the view at
+    /// `indices`.
+    fn fixed(&self, indices: &[usize]) -> BigInt {
+        (self.uses.iter())
+            .filter(|(i, _)| indices.contains(i))
+            .filter_map(|(_, demand)| match demand {
+                Demand::Count(n) => Some(n),
+                Demand::Rest(_) => None,
+            })
+            .sum()
+    }
+}
+
+/// Adds `formula` to `conditions`, returning `None` if it is literally false.
+fn condition(conditions: &mut Vec<Formula>, formula: Formula) -> Option<()> {
+    match formula.as_literal() {
+        Some(Constant::Bool(false)) => return None,
+        Some(Constant::Bool(true)) => (),
+        _ => conditions.push(formula),
+    }
+    Some(())
+}
+
+/// Gets the indices of the atoms of `view`, other than the one at `index`, that could be equal to
+/// it.
+fn similar(view: &View, index: usize) -> impl Iterator<Item = usize> + '_ {
+    let atom = &view.atoms[index].item.item;
+    (view.atoms.iter().enumerate())
+        .filter(move |(j, other)| {
+            *j != index
+                && other.item.item.name == atom.name
+                && other.item.item.args.len() == atom.args.len()
+        })
+        .map(|(j, _)| j)
+}
+
+/// Constructs the formula stating that the atom of `view` at index `j` is present, and equal to
+/// the one at index `i`.
+fn equal(view: &View, i: usize, j: usize) -> Formula {
+    let args = (view.atoms[i].item.item.args.iter())
+        .zip(&view.atoms[j].item.item.args)
+        .filter(|(x, y)| x != y)
+        .map(|(x, y)| Expr::bop(x.clone(), Rel::Eq, y.clone()));
+    Formula::conjunction(std::iter::once(view.atoms[j].guard.clone()).chain(args))
+}
+
+/// Negates `formula`, evaluating it if it is a literal.
+fn negate(formula: Formula) -> Formula {
+    match formula.as_literal() {
+        Some(Constant::Bool(b)) => Formula::bool(!b),
+        _ => Expr::not(formula),
+    }
+}
+
+/// Gets every subset of `items`, preserving order.
+fn subsets(items: &[usize]) -> impl Iterator<Item = Vec<usize>> + '_ {
+    (0..1_usize << items.len()).map(|mask| {
+        (items.iter().enumerate())
+            .filter(|(bit, _)| mask & (1 << bit) != 0)
+            .map(|(_, item)| *item)
+            .collect()
+    })
+}
+
+/// Substitutes `bindings` into `expr`, interpreting other variables with `state`.
+pub(super) fn substitute<M: Clone>(
+    bindings: &HashMap<var::Symbol, Formula>,
+    expr: &Expr<M, var::Symbol>,
+    state: fn(var::Symbol) -> Var,
+) -> Formula {
+    expr.clone()
+        .map_meta(|_| ())
+        .subst(&mut |v| match bindings.get(&v.item) {
+            Some(f) => f.clone(),
+            None => Expr::Var(v.map(state)),
+        })
+}
+
+/// Constructs the formula `fixed <= count`, evaluating it if `count` is a literal.
+fn at_most(fixed: &BigInt, count: &Formula) -> Formula {
+    match count.as_literal() {
+        Some(Constant::Int(n)) => Formula::bool(fixed <= n),
+        _ if fixed.is_zero() => Formula::bool(true),
+        _ => Expr::bop(Formula::int(fixed.clone()), Rel::LessEq, count.clone()),
+    }
+}
+
+/// Constructs the formula `fixed <= count`, evaluating it if `count` is a literal.
+///
+/// Unlike [`at_most`], this doesn't assume that `count` is non-negative.
+fn at_least(count: &Formula, fixed: &BigInt) -> Formula {
+    match count.as_literal() {
+        Some(Constant::Int(n)) => Formula::bool(fixed <= n),
+        _ => Expr::bop(Formula::int(fixed.clone()), Rel::LessEq, count.clone()),
+    }
+}
+
+/// Adds up `counts`, evaluating the sum of any literals.
+fn sum<'a>(counts: impl Iterator<Item = &'a Formula>) -> Formula {
+    let mut literal = BigInt::zero();
+    let mut symbolic: Option<Formula> = None;
+    for count in counts {
+        match count.as_literal() {
+            Some(Constant::Int(n)) => literal += n,
+            _ => {
+                symbolic = Some(match symbolic {
+                    Some(s) => Expr::bop(s, Arith::Add, count.clone()),
+                    None => count.clone(),
+                });
+            }
+        }
+    }
+    match symbolic {
+        Some(s) if literal.is_zero() => s,
+        Some(s) => Expr::bop(s, Arith::Add, Formula::int(literal)),
+        None => Formula::int(literal),
+    }
+}
+
+/// Constructs the formula `count - fixed`, evaluating it if `count` is a literal.
+fn minus(count: &Formula, fixed: &BigInt) -> Formula {
+    match count.as_literal() {
+        Some(Constant::Int(n)) => Formula::int(n - fixed),
+        _ if fixed.is_zero() => count.clone(),
+        _ => Expr::bop(count.clone(), Arith::Sub, Formula::int(fixed.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::view::flatten, *};
+
+    /// Reifies the view assertion `view` over pre-state variables at `position`, in a program with
+    /// the given views and constraints, after applying `tweak` to its flattened form.
+    fn reified(
+        decls: &str,
+        view: &str,
+        position: Position,
+        tweak: impl FnOnce(&mut View),
+    ) -> String {
+        let source = format!(
+            "program T; var x : integer; {decls} procedure P requires {{ {view} }}; begin nop end; begin end."
+        );
+        let ast = crate::parser::parse(&source).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        let requires = &program.procedures[0].requires.as_ref().unwrap().item;
+        let mut view = flatten(requires, Var::Pre).unwrap();
+        tweak(&mut view);
+        reify(&view, &program.constraints, Var::Pre, position)
+            .unwrap()
+            .to_string()
+    }
+
+    /// Tests that exact definitions supersede other constraints over the same pattern only in
+    /// conclusions, and that indefinite and fails constraints instantiate nothing and `false`.
+    #[test]
+    fn reify_forms() {
+        let decls =
+            "view V(n : integer); W; constraint V(a) entails a > 0; constraint V(b) = b = x; \
+                     constraint W ?; constraint W * W fails;";
+        let view = "V(1) * W[2]";
+        assert_eq!(
+            "1 > 0 and 1 = x and false",
+            reified(decls, view, Position::Hypothesis, |_| ())
+        );
+        assert_eq!(
+            "1 = x and false",
+            reified(decls, view, Position::Conclusion, |_| ())
+        );
+    }
+
+    /// Tests that wildcard arguments match anything.
+    #[test]
+    fn reify_wildcard() {
+        let decls = "view V(n : integer); constraint V(_) entails x > 0;";
+        assert_eq!(
+            "x > 0",
+            reified(decls, "V(1) * V(2)", Position::Hypothesis, |_| ())
+        );
+    }
+
+    /// Tests that iterator variables bind to the number of occurrences of a view atom.
+    #[test]
+    fn reify_iterator() {
+        let decls = "view Arc; constraint Arc[n] entails n <= x;";
+        assert_eq!(
+            "2 <= x",
+            reified(decls, "Arc[2] * Arc[0]", Position::Hypothesis, |_| ())
+        );
+    }
+
+    /// Tests that one view atom can match several pattern atoms if present often enough, and that
+    /// the iterator is checked when it isn't a literal.
+    #[test]
+    fn reify_reuse() {
+        let decls = "view V(n : integer); constraint V(a) * V(b) entails a = b;";
+        assert_eq!(
+            "x = x",
+            reified(decls, "V(x)[2]", Position::Hypothesis, |_| ())
+        );
+        assert_eq!("true", reified(decls, "V(x)", Position::Hypothesis, |_| ()));
+        assert_eq!(
+            "2 <= x implies x = x",
+            reified(decls, "V(x)", Position::Hypothesis, |v| {
+                v.atoms[0].item.iterator = Expr::Var(Var::Pre(var::Symbol::global("x")).into());
+            })
+        );
+    }
+
+    /// Tests that iterator variables count the occurrences of every atom that could be equal to
+    /// the one they matched, and that symbolic counts must be non-negative.
+    #[test]
+    fn reify_sum() {
+        let decls = "view Arc; constraint Arc[n] entails n <= x;";
+        assert_eq!(
+            "(not (x > 0) and 0 <= x implies x <= x) and (x > 0 and 0 <= x + 1 implies x + 1 <= x)",
+            reified(decls, "Arc[x] * [x > 0] Arc", Position::Hypothesis, |_| ())
+        );
+    }
+}
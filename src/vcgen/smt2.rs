@@ -0,0 +1,296 @@
+//! Emission of verification conditions in SMT-LIB 2 format.
+//!
+//! Each condition becomes a self-contained script that declares every variable the condition
+//! mentions, asserts the hypotheses, asserts the negated conclusion, and checks satisfiability.
+//! The condition holds if, and only if, the script is unsatisfiable.
+//!
+//! Instances of indefinite constraints are taken to hold, and so do not appear; to infer their
+//! definitions, emit the conditions as Horn clauses instead (see [horn](super::horn)).
+//!
+//! Arrays map onto the SMT-LIB theory of arrays, with integer indices; array bounds are not part
+//! of the sort, and are instead enforced by in-bounds obligations.
+
+use std::{fmt::Write as _, io};
+
+use super::{
+    super::language::{
+        cfg,
+        expr::{
+            bop::{Arith, Bool, Rel},
+            Bop, Constant, Expr, Uop,
+        },
+        typing,
+    },
+    Condition, Formula, Var,
+};
+
+/// Writes `condition` to `out` as an SMT-LIB 2 script.
+///
+/// Variable sorts come from the symbol tables of `program`.
+///
+/// # Errors
+///
+/// Fails if writing to `out` fails, or if the condition uses a variable whose type or an operator
+/// that has no SMT-LIB counterpart yet.
+pub fn write<M>(
+    program: &cfg::Program<'_, M>,
+    condition: &Condition<M>,
+    out: &mut impl io::Write,
+) -> Result<()> {
+    let mut script = String::new();
+    writeln!(script, "; {}", condition.name())?;
+    writeln!(script, "(set-logic ALL)")?;
+
+    let mut vars: Vec<&Var> = vec![];
+    condition.for_each_var(&mut |v| {
+        if !vars.contains(&v) {
+            vars.push(v);
+        }
+    });
+    for var in vars {
+        writeln!(
+            script,
+            "(declare-const {} {})",
+            symbol(var),
+            sort(program, var)?
+        )?;
+    }
+
+    for hypothesis in &condition.hypotheses {
+        writeln!(script, "(assert {})", term(hypothesis)?)?;
+    }
+    writeln!(script, "(assert (not {}))", term(&condition.conclusion)?)?;
+    writeln!(script, "(check-sat)")?;
+
+    out.write_all(script.as_bytes())?;
+    Ok(())
+}
+
+/// Gets the SMT-LIB sort of `var`.
+pub(super) fn sort<M>(program: &cfg::Program<'_, M>, var: &Var) -> Result<String> {
+    let unsupported = || Error::UnsupportedSort {
+        var: var.to_string(),
+    };
+    let record = program
+        .variables
+        .get_exact(var.symbol())
+        .ok_or_else(unsupported)?;
+    let ty = program.types.get(&record.ty).ok_or_else(unsupported)?;
+    type_sort(program, &ty.ty).ok_or_else(unsupported)
+}
+
+/// Gets the SMT-LIB sort of values of type `ty`, if it has one.
+///
+/// Named types take the sort of their definitions in `program`.
+fn type_sort<M>(
+    program: &cfg::Program<'_, M>,
+    ty: &typing::Type<M, egg::Symbol>,
+) -> Option<String> {
+    match ty.base() {
+        typing::Type::Prim(typing::Prim::Int) => Some("Int".to_owned()),
+        typing::Type::Prim(typing::Prim::Bool) => Some("Bool".to_owned()),
+        typing::Type::Array(a) => Some(format!("(Array Int {})", type_sort(program, a.base())?)),
+        typing::Type::Named(n) => type_sort(program, &program.types.get(&n.item)?.ty),
+        _ => None,
+    }
+}
+
+/// Renders `formula` as an SMT-LIB term.
+///
+/// # Errors
+///
+/// Fails if the formula contains an operator with no SMT-LIB counterpart.
+pub fn term(formula: &Formula) -> Result<String> {
+    let mut out = String::new();
+    write_term(&mut out, formula)?;
+    Ok(out)
+}
+
+fn write_term(out: &mut String, formula: &Formula) -> Result<()> {
+    match formula {
+        Expr::Literal(l) => match &l.item {
+            Constant::Bool(b) => write!(out, "{b}")?,
+            Constant::Int(i) if i.sign() == num_bigint::Sign::Minus => write!(out, "(- {})", -i)?,
+            Constant::Int(i) => write!(out, "{i}")?,
+        },
+        Expr::Var(v) => out.push_str(&symbol(&v.item)),
+        Expr::Bop { op, lhs, rhs } => {
+            write!(out, "({} ", bop(*op))?;
+            write_term(out, lhs)?;
+            out.push(' ');
+            write_term(out, rhs)?;
+            out.push(')');
+        }
+        Expr::Uop {
+            op: Uop::Plus,
+            expr,
+        } => write_term(out, expr)?,
+        Expr::Uop { op, expr } => {
+            let op = match op {
+                Uop::Minus => "-",
+                Uop::Not => "not",
+                _ => return Err(Error::UnsupportedOperator(op.to_string())),
+            };
+            write!(out, "({op} ")?;
+            write_term(out, expr)?;
+            out.push(')');
+        }
+        Expr::Index { array, index } => {
+            out.push_str("(select ");
+            write_term(out, array)?;
+            out.push(' ');
+            write_term(out, index)?;
+            out.push(')');
+        }
+        Expr::Update {
+            array,
+            index,
+            value,
+        } => {
+            out.push_str("(store ");
+            write_term(out, array)?;
+            out.push(' ');
+            write_term(out, index)?;
+            out.push(' ');
+            write_term(out, value)?;
+            out.push(')');
+        }
+    }
+    Ok(())
+}
+
+/// Gets the SMT-LIB name of a binary operator.
+fn bop(op: Bop) -> &'static str {
+    match op {
+        Bop::Arith(Arith::Add) => "+",
+        Bop::Arith(Arith::Sub) => "-",
+        Bop::Arith(Arith::Mul) => "*",
+        Bop::Arith(Arith::Div) => "div",
+        Bop::Arith(Arith::Modulus) => "mod",
+        Bop::Bool(Bool::And) => "and",
+        Bop::Bool(Bool::Or) => "or",
+        Bop::Bool(Bool::Implies) => "=>",
+        Bop::Bool(Bool::Iff) | Bop::Rel(Rel::Eq) => "=",
+        Bop::Rel(Rel::NotEq) => "distinct",
+        Bop::Rel(Rel::Less) => "<",
+        Bop::Rel(Rel::LessEq) => "<=",
+        Bop::Rel(Rel::Greater) => ">",
+        Bop::Rel(Rel::GreaterEq) => ">=",
+    }
+}
+
+/// Renders `var` as an SMT-LIB symbol, quoting it if it is not a simple symbol.
+pub(super) fn symbol(var: &Var) -> String {
+    let name = name(var);
+    if is_simple_symbol(&name) {
+        name
+    } else {
+        format!("|{name}|")
+    }
+}
+
+/// Gets the unquoted name of the SMT-LIB symbol for `var`.
+///
+/// Quoting a symbol doesn't change which symbol it is, so quoting alone can't stop a variable
+/// named after a theory symbol, such as `select`, from clashing with it.  Instead, we append `!`,
+/// which can't appear in PVC identifiers, to such names.
+#[must_use]
+pub fn name(var: &Var) -> String {
+    let name = var.to_string();
+    if is_theory_symbol(&name) {
+        name + "!"
+    } else {
+        name
+    }
+}
+
+/// Checks whether `name` is a simple symbol in the sense of SMT-LIB 2, and not a reserved word.
+fn is_simple_symbol(name: &str) -> bool {
+    const SPECIAL: &str = "~!@$%^&*_-+=<>.?/";
+    const RESERVED: [&str; 13] = [
+        "as",
+        "let",
+        "exists",
+        "forall",
+        "match",
+        "par",
+        "!",
+        "_",
+        "BINARY",
+        "DECIMAL",
+        "HEXADECIMAL",
+        "NUMERAL",
+        "STRING",
+    ];
+
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    (first.is_ascii_alphabetic() || SPECIAL.contains(first))
+        && chars.all(|c| c.is_ascii_alphanumeric() || SPECIAL.contains(c))
+        && !RESERVED.contains(&name)
+}
+
+/// Checks whether `name` is a sort or function symbol of one of the theories we use.
+fn is_theory_symbol(name: &str) -> bool {
+    const THEORY: [&str; 17] = [
+        "Bool", "true", "false", "not", "and", "or", "xor", "distinct", "ite", "Int", "div", "mod",
+        "abs", "Array", "select", "store", "const",
+    ];
+
+    THEORY.contains(&name)
+}
+
+/// Errors that can occur when emitting SMT-LIB.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Writing the script failed.
+    #[error("couldn't write SMT-LIB script")]
+    Io(#[from] io::Error),
+    /// Formatting the script failed.
+    #[error("couldn't format SMT-LIB script")]
+    Fmt(#[from] std::fmt::Error),
+    /// A variable has a type with no SMT-LIB sort.
+    #[error("variable `{var}` has a type with no SMT-LIB sort")]
+    UnsupportedSort { var: String },
+    /// An operator has no SMT-LIB counterpart.
+    #[error("operator `{0}` has no SMT-LIB counterpart")]
+    UnsupportedOperator(String),
+}
+
+/// Shorthand for results over [Error].
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that the emitter quotes symbols that are not simple, and renames those that clash
+    /// with theory symbols.
+    #[test]
+    fn symbol_quoting() {
+        let global = cfg::var::Symbol::global("count");
+        assert_eq!("count", symbol(&Var::Pre(global.clone())));
+        assert_eq!("count!after", symbol(&Var::Post(global)));
+        assert_eq!("|let|", symbol(&Var::Pre(cfg::var::Symbol::global("let"))));
+        assert_eq!("|1x|", symbol(&Var::Pre(cfg::var::Symbol::global("1x"))));
+        for theory in [
+            "select", "store", "distinct", "ite", "Int", "Bool", "true", "false", "not", "and",
+            "or", "div",
+        ] {
+            let global = cfg::var::Symbol::global(theory);
+            assert_eq!(format!("{theory}!"), symbol(&Var::Pre(global.clone())));
+            assert_eq!(format!("{theory}!after"), symbol(&Var::Post(global)));
+        }
+    }
+
+    /// Tests that negative literals and inequality are rendered correctly.
+    #[test]
+    fn term_negative_not_eq() {
+        let x: Formula = Expr::Var(Var::Pre(cfg::var::Symbol::global("x")).into());
+        let formula = Expr::bop(x, Rel::NotEq, Expr::i64(-3));
+        assert_eq!("(distinct x (- 3))", term(&formula).unwrap());
+    }
+}
@@ -0,0 +1,295 @@
+//! Flattening of views.
+//!
+//! Flattening turns a view assertion into its normal form (see [normal]), a list of guarded,
+//! iterated atoms and a local assertion, over formulas.  Reification (see [reify]) then turns a
+//! flattened view into a formula.
+
+use egg::Symbol;
+use num_bigint::{BigInt, Sign};
+
+use super::{
+    super::language::{
+        ast::{
+            constraint,
+            view::{self, normal},
+        },
+        cfg::{self, var},
+        expr::{bop::Rel, map::HasMeta, Constant, Expr},
+        tagged::Tagged,
+    },
+    reify::{self, Demand, Pattern, Position},
+    Error, Formula, Goal, Result, Unknown, Var,
+};
+
+/// A flattened view atom.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Atom {
+    /// The name of the view.
+    pub name: Symbol,
+    /// The arguments of the atom.
+    pub args: Vec<Formula>,
+}
+
+/// Something present a number of times.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Iterated<T> {
+    /// The item.
+    pub item: T,
+    /// The number of times the item is present.
+    pub iterator: Formula,
+}
+
+/// Something present under a guard.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Guarded<T> {
+    /// The guard.
+    pub guard: Formula,
+    /// The item.
+    pub item: T,
+}
+
+/// A flattened view.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct View {
+    /// The guarded, iterated atoms of the view.
+    pub atoms: Vec<Guarded<Iterated<Atom>>>,
+    /// The local assertions of the view, already implied by their guards.
+    pub local: Vec<Formula>,
+    /// Conditions that the iterators of the view are non-negative as written, already implied by
+    /// their guards.
+    ///
+    /// Flattening sums and multiplies iterators, which only preserves the meaning of the view if
+    /// these conditions hold; each edge checks them of its destination view.
+    pub iterators: Vec<Formula>,
+}
+
+impl View {
+    /// Joins `other` onto this view.
+    pub fn join(&mut self, other: Self) {
+        self.atoms.extend(other.atoms);
+        self.local.extend(other.local);
+        self.iterators.extend(other.iterators);
+    }
+
+    /// Reifies this view through the constraints of `program`, for use at `position`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a constraint uses a pattern that the generator does not yet support.
+    pub fn reify<M: Clone>(
+        &self,
+        program: &cfg::Program<'_, M>,
+        state: fn(var::Symbol) -> Var,
+        position: Position,
+    ) -> Result<Formula, M> {
+        reify::reify(self, &program.constraints, state, position)
+    }
+
+    /// Finds the instances of the indefinite constraints of `program` in this view.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a constraint uses a pattern that the generator does not yet support.
+    pub fn unknowns<M: Clone>(
+        &self,
+        program: &cfg::Program<'_, M>,
+        state: fn(var::Symbol) -> Var,
+    ) -> Result<Vec<Unknown>, M> {
+        reify::unknowns(self, &program.constraints, &super::globals(program), state)
+    }
+
+    /// Gets hypotheses that the goal variables iterating the atoms of this view are non-negative.
+    ///
+    /// Goal views count occurrences with goal variables (see [goals]); without these hypotheses, a
+    /// negative count would cancel out atoms of the view it joins.
+    #[must_use]
+    pub fn goal_bounds(&self) -> Vec<Formula> {
+        let mut vars = vec![];
+        for atom in &self.atoms {
+            if let Expr::Var(v) = &atom.item.iterator {
+                if matches!(v.item, Var::Goal(_)) && !vars.contains(&&atom.item.iterator) {
+                    vars.push(&atom.item.iterator);
+                }
+            }
+        }
+        vars.into_iter()
+            .map(|v| Expr::bop(Formula::i64(0), Rel::LessEq, v.clone()))
+            .collect()
+    }
+}
+
+/// Flattens `assertion` into a view, interpreting its variables using `state`.
+///
+/// Flattening normalises the assertion (see [normal]) and then converts it into formulas.
+///
+/// # Errors
+///
+/// Fails if the assertion contains negative iterators or non-deterministic conditionals, which the
+/// generator does not yet support.
+pub fn flatten<M: Clone + Default>(
+    assertion: &view::Assertion<'_, M, var::Symbol>,
+    state: fn(var::Symbol) -> Var,
+) -> Result<View, M> {
+    let normal = normal::normalise(assertion).map_err(|e| match e {
+        normal::Error::Nondeterministic { loc } => Error::Unsupported {
+            what: "non-deterministic conditional views",
+            meta: loc,
+        },
+    })?;
+
+    let atoms = normal
+        .atoms
+        .into_iter()
+        .map(|a| {
+            Ok(Guarded {
+                guard: formula(&a.guard, state),
+                item: Iterated {
+                    item: Atom {
+                        name: Symbol::new(&a.item.item.item.name.item),
+                        args: (a.item.item.item.args.iter())
+                            .map(|e| formula(&e.item, state))
+                            .collect(),
+                    },
+                    iterator: iterator(&a.item.iterator.item, &a.item.iterator.meta, state)?,
+                },
+            })
+        })
+        .collect::<Result<_, M>>()?;
+
+    let local = formula(&normal.local, state);
+    let local = if local.as_literal() == Some(&Constant::Bool(true)) {
+        vec![]
+    } else {
+        vec![local]
+    };
+
+    let iterators = (normal.iterators.iter())
+        .map(|i| {
+            let bound = Expr::bop(Formula::i64(0), Rel::LessEq, formula(&i.item.item, state));
+            implies(&formula(&i.guard, state), bound)
+        })
+        .collect();
+
+    Ok(View {
+        atoms,
+        local,
+        iterators,
+    })
+}
+
+/// Computes the goal views of `program`.
+///
+/// The goal views are the empty view, and a fresh instance of each constraint pattern, in which
+/// every pattern variable, including every iterator variable, becomes a goal variable.  Patterns
+/// of `fails` constraints give no goal view, as no other thread can hold them.
+///
+/// # Errors
+///
+/// Fails if a constraint uses a pattern that the generator does not yet support.
+pub fn goals<M: Clone>(program: &cfg::Program<'_, M>) -> Result<Vec<(Goal, View)>, M> {
+    let mut goals = vec![(Goal::Emp, View::default())];
+
+    for (c, decl) in program.constraints.iter().enumerate() {
+        if matches!(decl.item.body.item, constraint::Constraint::Fails) {
+            continue;
+        }
+        for (p, pattern) in decl.item.views.iter().enumerate() {
+            let goal = Goal::Pattern {
+                constraint: c,
+                pattern: p,
+            };
+            goals.push((goal, goal_view(&Pattern::new(&pattern.item)?, None)?));
+        }
+    }
+
+    Ok(goals)
+}
+
+/// Instantiates `pattern` as a view over goal variables, leaving out the atom at index `skip`, if
+/// any.
+///
+/// # Errors
+///
+/// Fails if the pattern has wildcard arguments, which have no instance.
+pub(super) fn goal_view<M: Clone>(
+    pattern: &Pattern<'_, M>,
+    skip: Option<usize>,
+) -> Result<View, M> {
+    let atoms = (pattern.atoms.iter().enumerate())
+        .filter(|(i, _)| Some(*i) != skip)
+        .map(|(_, atom)| {
+            let iterator = match &atom.demand {
+                Demand::Count(count) => Formula::int(count.clone()),
+                Demand::Rest(Some(var)) => Expr::Var(Tagged::with_default(Var::Goal(var.clone()))),
+                // The constraint doesn't depend on how many occurrences a wildcard iterator takes,
+                // so one occurrence stands for any positive number.
+                Demand::Rest(None) => Formula::int(BigInt::from(1)),
+            };
+            let args = (atom.args.iter())
+                .map(|(arg, meta)| {
+                    arg.ok_or_else(|| Error::Unsupported {
+                        what: "wildcards in constraint patterns",
+                        meta: meta.clone(),
+                    })
+                    .map(|a| formula(a, Var::Goal))
+                })
+                .collect::<Result<_, M>>()?;
+            Ok(Guarded {
+                guard: Formula::bool(true),
+                item: Iterated {
+                    item: Atom {
+                        name: atom.name,
+                        args,
+                    },
+                    iterator,
+                },
+            })
+        })
+        .collect::<Result<_, M>>()?;
+
+    Ok(View {
+        atoms,
+        ..View::default()
+    })
+}
+
+/// Converts a program expression into a formula, interpreting its variables using `state`.
+pub(super) fn formula<M>(expr: &Expr<M, var::Symbol>, state: fn(var::Symbol) -> Var) -> Formula
+where
+    M: Clone,
+{
+    expr.clone()
+        .map_meta(|_| ())
+        .subst(&mut |v| Expr::Var(v.map(state)))
+}
+
+/// Makes `expr` conditional on `guard`, dropping a trivially true guard.
+pub(super) fn implies(guard: &Formula, expr: Formula) -> Formula {
+    if guard.as_literal() == Some(&Constant::Bool(true)) {
+        expr
+    } else {
+        Expr::bop(
+            guard.clone(),
+            crate::language::expr::bop::Bool::Implies,
+            expr,
+        )
+    }
+}
+
+/// Converts an iterator into a formula, interpreting its variables using `state`.
+///
+/// Symbolic iterators must be non-negative; flattening records conditions that check this (see
+/// [`View::iterators`]).
+fn iterator<M: Clone>(
+    expr: &Expr<M, var::Symbol>,
+    meta: &M,
+    state: fn(var::Symbol) -> Var,
+) -> Result<Formula, M> {
+    match expr.as_literal() {
+        Some(Constant::Int(i)) if i.sign() == Sign::Minus => Err(Error::Unsupported {
+            what: "negative iterators",
+            meta: meta.clone(),
+        }),
+        _ => Ok(formula(expr, state)),
+    }
+}
@@ -0,0 +1,290 @@
+//! Well-formedness checker for the constraints of bound PVC programs.
+//!
+//! The binder accepts any constraint whose expressions resolve, and the type checker only checks
+//! the types of the expressions inside it.  This checker rejects the rest of the ill-formed
+//! constraints, checking that:
+//!
+//! - every view atom in a pattern refers to a declared view, with the right number of arguments;
+//! - every pattern variable that a constraint mentions is bound by every one of its patterns.
+//!
+//! It also warns about views that are declared, but never constrained, and so mean nothing.
+//!
+//! Some well-formedness requirements, such as the downward closure of iterated constraints, are
+//! semantic, and so become verification conditions instead (see
+//! [`vcgen::obligations`](super::vcgen::obligations)).
+//!
+//! Like the type checker, this checker reports every problem it finds.
+
+use std::collections::HashSet;
+
+use egg::Symbol;
+
+use super::{
+    binder::{Bound, Meta},
+    diagnostic::{self, code, Diagnostic, FileId, Severity, ToDiagnostic},
+    language::{
+        ast::view::pattern,
+        cfg::{self, BlockRef},
+        expr::Expr,
+        tagged::Tagged,
+    },
+};
+
+/// A problem with the constraints of a program.
+#[derive(Debug, Clone, thiserror::Error, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Error<M> {
+    /// A pattern atom refers to a view that has not been declared.
+    #[error("unknown view `{name}`")]
+    UnknownView { name: Symbol, loc: M },
+    /// A pattern atom has the wrong number of arguments.
+    #[error("`{name}` takes {want} argument(s), but {got} were supplied")]
+    Arity {
+        name: Symbol,
+        want: usize,
+        got: usize,
+        want_loc: M,
+        got_loc: M,
+    },
+    /// A constraint mentions a pattern variable that one of its patterns does not bind.
+    #[error("pattern variable `{name}` is not bound by every pattern of its constraint")]
+    Unbound { name: Symbol, loc: M, pattern: M },
+    /// A view is never constrained.
+    #[error("view `{name}` is never constrained")]
+    Unconstrained { name: Symbol, loc: M },
+}
+
+impl<M> Error<M> {
+    /// Gets the severity of this problem.
+    ///
+    /// Unconstrained views are suspicious, but still have a meaning, so they only give warnings.
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::Unconstrained { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl ToDiagnostic for Error<Option<pest::Span<'_>>> {
+    fn to_diagnostic(&self, file: FileId) -> Diagnostic {
+        let diagnostic = Diagnostic::new(self.severity(), self.to_string());
+        match self {
+            Self::UnknownView { loc, .. } => diagnostic
+                .with_code(code::UNKNOWN_VIEW)
+                .with_label_opt(diagnostic::label(
+                    file,
+                    loc,
+                    true,
+                    "not declared in any view declaration",
+                )),
+            Self::Arity {
+                want,
+                got,
+                want_loc,
+                got_loc,
+                ..
+            } => diagnostic
+                .with_code(code::ARITY_MISMATCH)
+                .with_label_opt(diagnostic::label(
+                    file,
+                    got_loc,
+                    true,
+                    format!("{got} argument(s) supplied"),
+                ))
+                .with_label_opt(diagnostic::label(
+                    file,
+                    want_loc,
+                    false,
+                    format!("declared with {want} parameter(s) here"),
+                )),
+            Self::Unbound { loc, pattern, .. } => diagnostic
+                .with_code(code::UNBOUND_PATTERN_VAR)
+                .with_label_opt(diagnostic::label(file, loc, true, "used here"))
+                .with_label_opt(diagnostic::label(
+                    file,
+                    pattern,
+                    false,
+                    "not bound by this pattern",
+                )),
+            Self::Unconstrained { loc, .. } => diagnostic
+                .with_code(code::UNCONSTRAINED_VIEW)
+                .with_label_opt(diagnostic::label(file, loc, true, "declared here")),
+        }
+    }
+}
+
+/// Checks the constraints of `program`, returning every problem found.
+///
+/// An empty result means that the constraints of `program` are well-formed.
+#[must_use]
+pub fn check<'inp>(program: &Bound<'inp>) -> Vec<Error<Meta<'inp>>> {
+    let mut errors = vec![];
+    let mut constrained = HashSet::new();
+
+    for decl in &program.constraints {
+        for pattern in &decl.item.views {
+            for atom in &pattern.item.contents {
+                let name = atom.item.name.item.name;
+                constrained.insert(name);
+                check_atom(program, atom, &mut errors);
+            }
+        }
+        check_bound(decl, &mut errors);
+    }
+
+    // The view table is unordered, so we order its views by where they appear in the script.
+    let mut unconstrained: Vec<_> = (program.views.iter())
+        .filter(|(name, _)| !constrained.contains(*name))
+        .map(|(name, view)| (*name, view.origin.meta().copied().flatten()))
+        .collect();
+    unconstrained.sort_by_key(|(_, loc)| loc.map(|l| l.start()));
+    errors.extend(
+        unconstrained
+            .into_iter()
+            .map(|(name, loc)| Error::Unconstrained { name, loc }),
+    );
+
+    errors
+}
+
+/// Checks that `atom` refers to a declared view of `program`, with the right number of arguments.
+fn check_atom<'inp>(
+    program: &Bound<'inp>,
+    atom: &Tagged<Meta<'inp>, pattern::Atom<Meta<'inp>, cfg::var::Symbol>>,
+    errors: &mut Vec<Error<Meta<'inp>>>,
+) {
+    let name = atom.item.name.item.name;
+    let Some(view) = program.views.get(&name) else {
+        errors.push(Error::UnknownView {
+            name,
+            loc: atom.item.name.meta,
+        });
+        return;
+    };
+    if view.parameters.len() != atom.item.args.len() {
+        errors.push(Error::Arity {
+            name,
+            want: view.parameters.len(),
+            got: atom.item.args.len(),
+            want_loc: view.origin.meta().copied().flatten(),
+            got_loc: atom.meta,
+        });
+    }
+}
+
+/// Checks that every pattern variable mentioned in `decl` is bound by each of its patterns.
+///
+/// We report each variable once per pattern, at its first mention.
+fn check_bound<'inp>(
+    decl: &Tagged<Meta<'inp>, cfg::Constraint<Meta<'inp>>>,
+    errors: &mut Vec<Error<Meta<'inp>>>,
+) {
+    let mut mentions: Vec<Tagged<Meta<'inp>, cfg::var::Symbol>> = vec![];
+    let mut mention = |expr: &Expr<Meta<'inp>, cfg::var::Symbol>| {
+        expr.for_each_tagged_var(&mut |v| {
+            if matches!(v.item.block, BlockRef::Constraint(_))
+                && !mentions.iter().any(|m| m.item == v.item)
+            {
+                mentions.push(v.clone());
+            }
+        });
+    };
+    // Bare variables in patterns bind rather than mention, so only other expressions count.
+    for pattern in &decl.item.views {
+        for atom in &pattern.item.contents {
+            for arg in atom.item.args.iter().chain([&atom.item.iterator]) {
+                match &arg.item {
+                    pattern::Argument::Expr(Expr::Var(_)) | pattern::Argument::Wildcard => (),
+                    pattern::Argument::Expr(e) => mention(e),
+                }
+            }
+        }
+    }
+    if let Some(body) = decl.item.body.item.expr() {
+        mention(&body.item);
+    }
+
+    for pattern in &decl.item.views {
+        let bound = binders(&pattern.item);
+        for var in &mentions {
+            if !bound.contains(&&var.item) {
+                errors.push(Error::Unbound {
+                    name: var.item.name,
+                    loc: var.meta,
+                    pattern: pattern.meta,
+                });
+            }
+        }
+    }
+}
+
+/// Gets the variables bound by `pattern`.
+fn binders<M>(pattern: &pattern::Pattern<M, cfg::var::Symbol>) -> Vec<&cfg::var::Symbol> {
+    (pattern.contents.iter())
+        .flat_map(|atom| atom.item.args.iter().chain([&atom.item.iterator]))
+        .filter_map(|arg| match &arg.item {
+            pattern::Argument::Expr(Expr::Var(v))
+                if matches!(v.item.block, BlockRef::Constraint(_)) =>
+            {
+                Some(&v.item)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARC: &str = include_str!("../examples/arc.pvc");
+
+    /// Checks the constraints of the program with the given declarations.
+    fn checked(decls: &str) -> Vec<String> {
+        let source = format!("program T; var x : integer; {decls} begin end.");
+        let ast = crate::parser::parse(&source).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        check(&program).iter().map(ToString::to_string).collect()
+    }
+
+    /// Tests that the ARC example has well-formed constraints.
+    #[test]
+    fn check_arc() {
+        let ast = crate::parser::parse(ARC).unwrap();
+        let program = crate::binder::bind(&ast.item).unwrap();
+        assert_eq!(Vec::<Error<Meta>>::new(), check(&program));
+    }
+
+    /// Tests that patterns must match the view declarations.
+    #[test]
+    fn check_prototypes() {
+        assert_eq!(
+            vec![
+                "unknown view `W`",
+                "`V` takes 1 argument(s), but 2 were supplied"
+            ],
+            checked("view V(n : integer); constraint W * V(x, 1) entails true;")
+        );
+    }
+
+    /// Tests that every pattern of a constraint must bind the variables it mentions.
+    #[test]
+    fn check_unbound() {
+        assert_eq!(
+            vec![
+                "pattern variable `b` is not bound by every pattern of its constraint",
+                "pattern variable `a` is not bound by every pattern of its constraint",
+            ],
+            checked("view V(n : integer); constraint V(a), V(b) entails a = b;")
+        );
+    }
+
+    /// Tests that views without constraints are warned about.
+    #[test]
+    fn check_unconstrained() {
+        let errors = checked("view V; W; constraint V entails true;");
+        assert_eq!(vec!["view `W` is never constrained"], errors);
+    }
+}